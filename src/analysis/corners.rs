@@ -0,0 +1,307 @@
+use crate::*;
+
+/// Interest point found by a corner detector
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Keypoint {
+    /// Position
+    pub point: Point,
+
+    /// Detector response, larger values are stronger corners
+    pub score: f64,
+}
+
+/// Keep the points that have the highest score within `radius` pixels, ordered by score. On
+/// plateaus the first point in raster order is kept.
+fn suppress(scores: &[f32], width: usize, radius: usize) -> Vec<Keypoint> {
+    let height = scores.len() / width.max(1);
+    let r = radius as isize;
+    let mut keypoints = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let score = scores[i];
+            if score <= 0.0 {
+                continue;
+            }
+
+            let is_max = (-r..=r).all(|dy| {
+                (-r..=r).all(|dx| {
+                    let (nx, ny) = (x as isize + dx, y as isize + dy);
+                    if nx < 0 || ny < 0 || nx as usize >= width || ny as usize >= height {
+                        return true;
+                    }
+                    let j = ny as usize * width + nx as usize;
+                    score > scores[j] || (score == scores[j] && j >= i)
+                })
+            });
+            if is_max {
+                keypoints.push(Keypoint {
+                    point: Point::new(x, y),
+                    score: score as f64,
+                });
+            }
+        }
+    }
+    keypoints.sort_by(|a, b| b.score.total_cmp(&a.score));
+    keypoints
+}
+
+/// Harris corner detector
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Harris {
+    /// Sensitivity parameter, typically between 0.04 and 0.06
+    pub k: f64,
+
+    /// Standard deviation of the window used to sum gradients
+    pub sigma: f64,
+
+    /// Minimum response, as a fraction of the strongest response in the image
+    pub threshold: f64,
+
+    /// Radius of the non-maximum suppression window
+    pub radius: usize,
+}
+
+impl Default for Harris {
+    fn default() -> Harris {
+        Harris {
+            k: 0.04,
+            sigma: 1.0,
+            threshold: 0.01,
+            radius: 2,
+        }
+    }
+}
+
+impl Harris {
+    /// Create a new detector with the given relative threshold
+    pub fn new(threshold: f64) -> Harris {
+        Harris {
+            threshold,
+            ..Default::default()
+        }
+    }
+
+    /// Set the sensitivity parameter
+    pub fn with_k(mut self, k: f64) -> Harris {
+        self.k = k;
+        self
+    }
+
+    /// Set the window standard deviation
+    pub fn with_sigma(mut self, sigma: f64) -> Harris {
+        self.sigma = sigma;
+        self
+    }
+
+    /// Set the non-maximum suppression radius
+    pub fn with_radius(mut self, radius: usize) -> Harris {
+        self.radius = radius;
+        self
+    }
+
+    /// Compute the corner response of the luminance of an image, positive values indicate
+    /// corners and negative values indicate edges
+    pub fn response<T: Type, C: Color>(&self, image: &Image<T, C>) -> Image<f32, Gray> {
+        let gray: Image<f32, Gray> = image.convert();
+        let (w, h) = (gray.width(), gray.height());
+        let data = gray.data();
+        let at = |x: usize, y: usize| data[y * w + x];
+
+        // Products of Sobel derivatives
+        let mut xx = Image::<f32, Gray>::new(gray.size());
+        let mut yy = Image::<f32, Gray>::new(gray.size());
+        let mut xy = Image::<f32, Gray>::new(gray.size());
+        for y in 0..h {
+            let (y0, y1) = (y.saturating_sub(1), (y + 1).min(h - 1));
+            for x in 0..w {
+                let (x0, x1) = (x.saturating_sub(1), (x + 1).min(w - 1));
+                let dx = (at(x1, y0) + 2.0 * at(x1, y) + at(x1, y1)
+                    - at(x0, y0)
+                    - 2.0 * at(x0, y)
+                    - at(x0, y1))
+                    / 8.0;
+                let dy = (at(x0, y1) + 2.0 * at(x, y1) + at(x1, y1)
+                    - at(x0, y0)
+                    - 2.0 * at(x, y0)
+                    - at(x1, y0))
+                    / 8.0;
+                let i = y * w + x;
+                xx.data_mut()[i] = dx * dx;
+                yy.data_mut()[i] = dy * dy;
+                xy.data_mut()[i] = dx * dy;
+            }
+        }
+
+        let blur = |image: Image<f32, Gray>| {
+            image.run::<f32, Gray>(filter::gaussian_blur(self.sigma), None)
+        };
+        let (xx, yy, xy) = (blur(xx), blur(yy), blur(xy));
+
+        let mut response = Image::new(gray.size());
+        let k = self.k as f32;
+        for (i, r) in response.data_mut().iter_mut().enumerate() {
+            let (a, b, c) = (xx.data()[i], yy.data()[i], xy.data()[i]);
+            *r = a * b - c * c - k * (a + b) * (a + b);
+        }
+        response
+    }
+
+    /// Find corners, ordered by response
+    pub fn detect<T: Type, C: Color>(&self, image: &Image<T, C>) -> Vec<Keypoint> {
+        let response = self.response(image);
+        let max = response.data().iter().fold(0f32, |a, b| a.max(*b));
+        let min = (max as f64 * self.threshold) as f32;
+        let scores: Vec<f32> = response
+            .data()
+            .iter()
+            .map(|r| if *r > 0.0 && *r >= min { *r } else { 0.0 })
+            .collect();
+        suppress(&scores, response.width(), self.radius)
+    }
+}
+
+// Bresenham circle of radius 3 used by FAST, in clockwise order starting at the top
+const FAST_CIRCLE: [(isize, isize); 16] = [
+    (0, -3),
+    (1, -3),
+    (2, -2),
+    (3, -1),
+    (3, 0),
+    (3, 1),
+    (2, 2),
+    (1, 3),
+    (0, 3),
+    (-1, 3),
+    (-2, 2),
+    (-3, 1),
+    (-3, 0),
+    (-3, -1),
+    (-2, -2),
+    (-1, -3),
+];
+
+/// FAST (features from accelerated segment test) corner detector. A pixel is a corner when a
+/// contiguous arc of pixels on a circle of radius 3 around it are all brighter or all darker
+/// than the center by more than the threshold.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fast {
+    /// Minimum normalized intensity difference
+    pub threshold: f64,
+
+    /// Minimum number of contiguous pixels out of 16, typically 9 or 12
+    pub arc: usize,
+
+    /// Radius of the non-maximum suppression window, 0 to keep every corner
+    pub radius: usize,
+}
+
+impl Default for Fast {
+    fn default() -> Fast {
+        Fast {
+            threshold: 0.08,
+            arc: 9,
+            radius: 1,
+        }
+    }
+}
+
+impl Fast {
+    /// Create a new detector with the given intensity threshold
+    pub fn new(threshold: f64) -> Fast {
+        Fast {
+            threshold,
+            ..Default::default()
+        }
+    }
+
+    /// Set the minimum arc length
+    pub fn with_arc(mut self, arc: usize) -> Fast {
+        self.arc = arc;
+        self
+    }
+
+    /// Set the non-maximum suppression radius
+    pub fn with_radius(mut self, radius: usize) -> Fast {
+        self.radius = radius;
+        self
+    }
+
+    /// Score of a single pixel, the sum of the differences above the threshold along the
+    /// circle, or 0 when the pixel is not a corner
+    fn score(&self, ring: &[f32; 16], center: f32) -> f32 {
+        let t = self.threshold as f32;
+        let arc = self.arc.clamp(1, 16);
+        let mut best = 0.0f32;
+        for sign in [1.0f32, -1.0] {
+            let diff = ring.map(|p| (p - center) * sign - t);
+            let mut run = 0;
+            let mut longest = 0;
+            for d in diff.iter().chain(diff.iter()) {
+                run = if *d > 0.0 { run + 1 } else { 0 };
+                longest = longest.max(run);
+            }
+            if longest >= arc {
+                best = best.max(diff.iter().filter(|d| **d > 0.0).sum());
+            }
+        }
+        best
+    }
+
+    /// Find corners, ordered by score
+    pub fn detect<T: Type, C: Color>(&self, image: &Image<T, C>) -> Vec<Keypoint> {
+        let gray: Image<f32, Gray> = image.convert();
+        let (w, h) = (gray.width(), gray.height());
+        let data = gray.data();
+
+        let mut scores = vec![0f32; w * h];
+        for y in 3..h.saturating_sub(3) {
+            for x in 3..w.saturating_sub(3) {
+                let ring = FAST_CIRCLE.map(|(dx, dy)| {
+                    data[(y as isize + dy) as usize * w + (x as isize + dx) as usize]
+                });
+                scores[y * w + x] = self.score(&ring, data[y * w + x]);
+            }
+        }
+        suppress(&scores, w, self.radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use analysis::{Fast, Harris, Keypoint};
+
+    fn near_corner(k: &Keypoint, corners: &[(f64, f64)]) -> bool {
+        corners.iter().any(|(x, y)| {
+            (k.point.x as f64 - x).abs() <= 2.0 && (k.point.y as f64 - y).abs() <= 2.0
+        })
+    }
+
+    #[test]
+    fn test_corners() {
+        let mut image = Image::<f32, Gray>::new((48, 40));
+        image.fill_rect(
+            Region::new(Point::new(12, 10), Size::new(20, 16)),
+            &Pixel::from(vec![1.0]),
+        );
+        let corners = [(12.0, 10.0), (31.0, 10.0), (12.0, 25.0), (31.0, 25.0)];
+
+        let harris = Harris::default().detect(&image);
+        assert_eq!(harris.len(), 4);
+        assert!(harris.iter().all(|k| near_corner(k, &corners)));
+        assert!(harris[0].score >= harris[3].score);
+
+        let fast = Fast::default().detect(&image);
+        assert_eq!(fast.len(), 4);
+        assert!(fast.iter().all(|k| near_corner(k, &corners)));
+
+        // Flat images have no corners
+        let flat = Image::<f32, Gray>::new((16, 16));
+        assert!(Harris::default().detect(&flat).is_empty());
+        assert!(Fast::default().detect(&flat).is_empty());
+    }
+}
@@ -0,0 +1,287 @@
+use crate::*;
+
+/// Complex number used by the FFT implementation
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    pub fn conj(self) -> Complex {
+        Complex::new(self.re, -self.im)
+    }
+
+    pub fn norm(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn scale(self, x: f64) -> Complex {
+        Complex::new(self.re * x, self.im * x)
+    }
+}
+
+impl std::ops::Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl std::ops::Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl std::ops::Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+/// In-place radix-2 FFT, the length of `data` must be a power of two. The inverse transform is
+/// scaled by 1/n.
+pub(crate) fn fft(data: &mut [Complex], inverse: bool) {
+    let n = data.len();
+    if n <= 1 {
+        return;
+    }
+    debug_assert!(n.is_power_of_two());
+
+    // Bit reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let angle = sign * 2.0 * std::f64::consts::PI / len as f64;
+        let w = Complex::new(angle.cos(), angle.sin());
+        for chunk in data.chunks_exact_mut(len) {
+            let mut wk = Complex::new(1.0, 0.0);
+            let (a, b) = chunk.split_at_mut(len / 2);
+            for (x, y) in a.iter_mut().zip(b.iter_mut()) {
+                let t = *y * wk;
+                *y = *x - t;
+                *x = *x + t;
+                wk = wk * w;
+            }
+        }
+        len <<= 1;
+    }
+
+    if inverse {
+        let s = 1.0 / n as f64;
+        data.iter_mut().for_each(|x| *x = x.scale(s));
+    }
+}
+
+/// 2-dimensional FFT of a row-major `width` x `height` buffer, both dimensions must be powers of
+/// two
+pub(crate) fn fft2(data: &mut [Complex], width: usize, height: usize, inverse: bool) {
+    for row in data.chunks_exact_mut(width) {
+        fft(row, inverse);
+    }
+
+    let mut column = vec![Complex::default(); height];
+    for x in 0..width {
+        for (y, c) in column.iter_mut().enumerate() {
+            *c = data[y * width + x];
+        }
+        fft(&mut column, inverse);
+        for (y, c) in column.iter().enumerate() {
+            data[y * width + x] = *c;
+        }
+    }
+}
+
+/// Copy the luminance of an image into a zero-padded complex buffer, a Hann window is applied to
+/// reduce edge effects
+fn windowed<T: Type, C: Color>(image: &Image<T, C>, width: usize, height: usize) -> Vec<Complex> {
+    let gray: Image<f64, Gray> = image.convert();
+    let (w, h) = (gray.width(), gray.height());
+    let hann = |i: usize, n: usize| {
+        if n <= 1 {
+            1.0
+        } else {
+            0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / (n - 1) as f64).cos()
+        }
+    };
+
+    let mut data = vec![Complex::default(); width * height];
+    for (y, row) in gray.data().chunks_exact(w.max(1)).enumerate().take(h) {
+        let wy = hann(y, h);
+        for (x, v) in row.iter().enumerate() {
+            data[y * width + x] = Complex::new(v * wy * hann(x, w), 0.0);
+        }
+    }
+    data
+}
+
+/// Estimate the translation of `b` relative to `a` using phase correlation
+///
+/// Returns `(dx, dy, confidence)`, where `(dx, dy)` is the sub-pixel offset such that
+/// `b(x + dx, y + dy) ≈ a(x, y)` and `confidence` is the height of the correlation peak, in the
+/// range 0..1. Images of different sizes are zero-padded to a common, power-of-two size.
+pub fn phase_correlate<T: Type, C: Color, U: Type, D: Color>(
+    a: &Image<T, C>,
+    b: &Image<U, D>,
+) -> (f64, f64, f64) {
+    let width = a.width().max(b.width()).next_power_of_two();
+    let height = a.height().max(b.height()).next_power_of_two();
+
+    let mut fa = windowed(a, width, height);
+    let mut fb = windowed(b, width, height);
+    fft2(&mut fa, width, height, false);
+    fft2(&mut fb, width, height, false);
+
+    // Normalized cross-power spectrum
+    for (x, y) in fb.iter_mut().zip(&fa) {
+        let r = *x * y.conj();
+        let n = r.norm();
+        *x = if n > 1e-12 {
+            r.scale(1.0 / n)
+        } else {
+            Complex::default()
+        };
+    }
+    fft2(&mut fb, width, height, true);
+
+    let value = |x: isize, y: isize| {
+        let x = x.rem_euclid(width as isize) as usize;
+        let y = y.rem_euclid(height as isize) as usize;
+        fb[y * width + x].re
+    };
+
+    let (index, peak) = fb
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, c.re))
+        .fold((0, f64::MIN), |acc, x| if x.1 > acc.1 { x } else { acc });
+    let (px, py) = ((index % width) as isize, (index / width) as isize);
+
+    // Parabolic interpolation around the peak for sub-pixel accuracy
+    let offset = |l: f64, c: f64, r: f64| {
+        let d = l - 2.0 * c + r;
+        if d.abs() < 1e-12 {
+            0.0
+        } else {
+            (0.5 * (l - r) / d).clamp(-0.5, 0.5)
+        }
+    };
+    let sx = offset(value(px - 1, py), peak, value(px + 1, py));
+    let sy = offset(value(px, py - 1), peak, value(px, py + 1));
+
+    // Peaks past the midpoint wrap around to negative offsets
+    let wrap = |p: isize, n: usize| {
+        if p > n as isize / 2 {
+            p - n as isize
+        } else {
+            p
+        }
+    };
+
+    (
+        wrap(px, width) as f64 + sx,
+        wrap(py, height) as f64 + sy,
+        peak.clamp(0.0, 1.0),
+    )
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Shift the image so it lines up with `reference`, using the translation estimated by
+    /// `phase_correlate` and sampled with sub-pixel accuracy. Returns the aligned image, the
+    /// `(dx, dy)` offset that was removed and the confidence of the estimate. Areas that are
+    /// shifted in from outside the image are left empty.
+    pub fn align_to<U: Type, D: Color>(
+        &self,
+        reference: &Image<U, D>,
+        interpolation: Interpolation,
+    ) -> (Image<T, C>, (f64, f64), f64) {
+        let (dx, dy, confidence) = phase_correlate(reference, self);
+        let mut aligned = self.new_like();
+        aligned.each_pixel_mut(|pt, px| {
+            px.copy_from(&self.sample(pt.x as f64 + dx, pt.y as f64 + dy, interpolation));
+        });
+        (aligned, (dx, dy), confidence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(dx: f64, dy: f64) -> Image<f32, Gray> {
+        let mut image = Image::<f32, Gray>::new((64, 64));
+        image.for_each(|pt, mut px| {
+            let (x, y) = (pt.x as f64 - dx, pt.y as f64 - dy);
+            let blob = |cx: f64, cy: f64, r: f64| {
+                (-((x - cx).powi(2) + (y - cy).powi(2)) / (2.0 * r * r)).exp()
+            };
+            px[0] = (blob(20.0, 24.0, 4.0) + blob(40.0, 30.0, 6.0) + 0.5 * blob(30.0, 45.0, 3.0))
+                as f32;
+        });
+        image
+    }
+
+    #[test]
+    fn test_fft_roundtrip() {
+        let input: Vec<Complex> = (0..16).map(|i| Complex::new(i as f64, 0.0)).collect();
+        let mut data = input.clone();
+        fft(&mut data, false);
+        assert!((data[0].re - 120.0).abs() < 1e-9);
+        fft(&mut data, true);
+        for (a, b) in data.iter().zip(&input) {
+            assert!((*a - *b).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_phase_correlate() {
+        let a = pattern(0.0, 0.0);
+        let b = pattern(5.0, -3.0);
+        let (dx, dy, confidence) = analysis::phase_correlate(&a, &b);
+        assert!((dx - 5.0).abs() < 0.25, "dx = {}", dx);
+        assert!((dy + 3.0).abs() < 0.25, "dy = {}", dy);
+        assert!(confidence > 0.1);
+    }
+
+    #[test]
+    fn test_align_to() {
+        let a = pattern(0.0, 0.0);
+        let b = pattern(-4.5, 2.0);
+        let (aligned, (dx, dy), confidence) = b.align_to(&a, Interpolation::Bicubic);
+        assert!((dx + 4.5).abs() < 0.25 && (dy - 2.0).abs() < 0.25);
+        assert!(confidence > 0.1);
+
+        // Away from the borders the aligned image matches the reference
+        for y in 8..56 {
+            for x in 8..56 {
+                assert!((aligned.get_f((x, y), 0) - a.get_f((x, y), 0)).abs() < 0.05);
+            }
+        }
+    }
+}
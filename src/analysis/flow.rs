@@ -0,0 +1,391 @@
+use crate::*;
+
+/// Dense motion field, each pixel stores the `(dx, dy)` displacement of that pixel from one
+/// frame to the next
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FlowField {
+    size: Size,
+    data: Vec<(f32, f32)>,
+}
+
+impl FlowField {
+    /// Create a new flow field with no motion
+    pub fn new(size: impl Into<Size>) -> FlowField {
+        let size = size.into();
+        FlowField {
+            size,
+            data: vec![(0.0, 0.0); size.width * size.height],
+        }
+    }
+
+    /// Create a new flow field with the same displacement at every pixel
+    pub fn uniform(size: impl Into<Size>, dx: f64, dy: f64) -> FlowField {
+        let mut flow = FlowField::new(size);
+        flow.data
+            .iter_mut()
+            .for_each(|v| *v = (dx as f32, dy as f32));
+        flow
+    }
+
+    /// Field size
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// Get the displacement at the given point
+    pub fn get(&self, pt: impl Into<Point>) -> (f64, f64) {
+        let pt = pt.into();
+        let (dx, dy) = self.data[pt.y * self.size.width + pt.x];
+        (dx as f64, dy as f64)
+    }
+
+    /// Set the displacement at the given point
+    pub fn set(&mut self, pt: impl Into<Point>, dx: f64, dy: f64) {
+        let pt = pt.into();
+        self.data[pt.y * self.size.width + pt.x] = (dx as f32, dy as f32);
+    }
+
+    /// Get the displacement nearest to a floating point position, coordinates are clamped to the
+    /// field bounds
+    pub fn sample(&self, x: f64, y: f64) -> (f64, f64) {
+        if self.data.is_empty() {
+            return (0.0, 0.0);
+        }
+
+        let x = x.round().clamp(0.0, (self.size.width - 1) as f64) as usize;
+        let y = y.round().clamp(0.0, (self.size.height - 1) as f64) as usize;
+        self.get((x, y))
+    }
+
+    /// Raw displacement data in raster order
+    pub fn data(&self) -> &[(f32, f32)] {
+        &self.data
+    }
+
+    /// Mutable raw displacement data in raster order
+    pub fn data_mut(&mut self) -> &mut [(f32, f32)] {
+        &mut self.data
+    }
+}
+
+/// Single channel image used by `LucasKanade`, with its derivatives
+struct Plane {
+    width: usize,
+    height: usize,
+    data: Vec<f32>,
+    dx: Vec<f32>,
+    dy: Vec<f32>,
+}
+
+impl Plane {
+    fn new(image: &Image<f32, Gray>) -> Plane {
+        let (width, height) = (image.width(), image.height());
+        let data = image.data().to_vec();
+        let at = |x: usize, y: usize| data[y * width + x];
+        let mut dx = vec![0.0; data.len()];
+        let mut dy = vec![0.0; data.len()];
+        for y in 0..height {
+            let (y0, y1) = (y.saturating_sub(1), (y + 1).min(height - 1));
+            for x in 0..width {
+                let (x0, x1) = (x.saturating_sub(1), (x + 1).min(width - 1));
+                dx[y * width + x] = (at(x1, y) - at(x0, y)) / (x1 - x0).max(1) as f32;
+                dy[y * width + x] = (at(x, y1) - at(x, y0)) / (y1 - y0).max(1) as f32;
+            }
+        }
+        Plane {
+            width,
+            height,
+            data,
+            dx,
+            dy,
+        }
+    }
+
+    /// Bilinear sample of `values`, coordinates are clamped to the plane bounds
+    fn sample(&self, values: &[f32], x: f64, y: f64) -> f64 {
+        let x = x.clamp(0.0, (self.width - 1) as f64);
+        let y = y.clamp(0.0, (self.height - 1) as f64);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+        let at = |x: usize, y: usize| values[y * self.width + x] as f64;
+        let top = at(x0, y0) * (1.0 - fx) + at(x1, y0) * fx;
+        let bottom = at(x0, y1) * (1.0 - fx) + at(x1, y1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+/// Pyramidal Lucas–Kanade optical flow. Motion is estimated by matching a small window around
+/// each point, starting at the coarsest pyramid level so displacements larger than the window
+/// can be found.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LucasKanade {
+    /// Radius of the matching window
+    pub radius: usize,
+
+    /// Number of pyramid levels above full resolution
+    pub levels: usize,
+
+    /// Maximum number of iterations at each level
+    pub iterations: usize,
+
+    /// Iterations stop once the update is smaller than this, in pixels
+    pub epsilon: f64,
+
+    /// Minimum eigenvalue of the window's gradient matrix, divided by the number of pixels in
+    /// the window. Points with less texture than this are not tracked.
+    pub min_eigenvalue: f64,
+}
+
+impl Default for LucasKanade {
+    fn default() -> LucasKanade {
+        LucasKanade {
+            radius: 7,
+            levels: 3,
+            iterations: 20,
+            epsilon: 0.01,
+            min_eigenvalue: 1e-4,
+        }
+    }
+}
+
+impl LucasKanade {
+    /// Create a new tracker with the default settings
+    pub fn new() -> LucasKanade {
+        LucasKanade::default()
+    }
+
+    /// Set the window radius
+    pub fn with_radius(mut self, radius: usize) -> LucasKanade {
+        self.radius = radius;
+        self
+    }
+
+    /// Set the number of pyramid levels
+    pub fn with_levels(mut self, levels: usize) -> LucasKanade {
+        self.levels = levels;
+        self
+    }
+
+    /// Set the maximum number of iterations per level
+    pub fn with_iterations(mut self, iterations: usize) -> LucasKanade {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Set the minimum eigenvalue
+    pub fn with_min_eigenvalue(mut self, min_eigenvalue: f64) -> LucasKanade {
+        self.min_eigenvalue = min_eigenvalue;
+        self
+    }
+
+    /// Luminance pyramid, starting at full resolution. Levels that would be smaller than the
+    /// matching window are skipped.
+    fn pyramid<T: Type, C: Color>(&self, image: &Image<T, C>) -> Vec<Plane> {
+        let mut gray: Image<f32, Gray> = image.convert();
+        let mut levels = vec![Plane::new(&gray)];
+        let min = 2 * self.radius + 1;
+        for _ in 0..self.levels {
+            let size = Size::new(gray.width() / 2, gray.height() / 2);
+            if size.width < min || size.height < min {
+                break;
+            }
+            gray = gray.downscale(size);
+            levels.push(Plane::new(&gray));
+        }
+        levels
+    }
+
+    /// Displacement of a single point between two pyramids, in full resolution pixels
+    fn track_point(&self, prev: &[Plane], next: &[Plane], x: f64, y: f64) -> Option<(f64, f64)> {
+        let r = self.radius as isize;
+        let count = ((2 * r + 1) * (2 * r + 1)) as f64;
+        let (mut gx, mut gy) = (0.0, 0.0);
+
+        for level in (0..prev.len()).rev() {
+            let (a, b) = (&prev[level], &next[level]);
+            let (sx, sy) = (
+                a.width as f64 / prev[0].width as f64,
+                a.height as f64 / prev[0].height as f64,
+            );
+            // Pixel centers are at +0.5 in each level
+            let (px, py) = ((x + 0.5) * sx - 0.5, (y + 0.5) * sy - 0.5);
+
+            // Template and gradient matrix
+            let mut window = Vec::with_capacity(count as usize);
+            let (mut gxx, mut gxy, mut gyy) = (0.0, 0.0, 0.0);
+            for j in -r..=r {
+                for i in -r..=r {
+                    let (wx, wy) = (px + i as f64, py + j as f64);
+                    let ix = a.sample(&a.dx, wx, wy);
+                    let iy = a.sample(&a.dy, wx, wy);
+                    window.push((wx, wy, a.sample(&a.data, wx, wy), ix, iy));
+                    gxx += ix * ix;
+                    gxy += ix * iy;
+                    gyy += iy * iy;
+                }
+            }
+
+            let half = (gxx + gyy) / 2.0;
+            let eigenvalue = half - (((gxx - gyy) / 2.0).powi(2) + gxy * gxy).sqrt();
+            if eigenvalue / count < self.min_eigenvalue {
+                if level == 0 {
+                    return None;
+                }
+                continue;
+            }
+            let det = gxx * gyy - gxy * gxy;
+
+            let (mut vx, mut vy) = (gx * sx, gy * sy);
+            for _ in 0..self.iterations {
+                let (mut bx, mut by) = (0.0, 0.0);
+                for (wx, wy, value, ix, iy) in &window {
+                    let diff = value - b.sample(&b.data, wx + vx, wy + vy);
+                    bx += diff * ix;
+                    by += diff * iy;
+                }
+                let ex = (gyy * bx - gxy * by) / det;
+                let ey = (gxx * by - gxy * bx) / det;
+                vx += ex;
+                vy += ey;
+                if ex * ex + ey * ey < self.epsilon * self.epsilon {
+                    break;
+                }
+            }
+            (gx, gy) = (vx / sx, vy / sy);
+        }
+
+        let (nx, ny) = (x + gx, y + gy);
+        let (width, height) = (prev[0].width as f64, prev[0].height as f64);
+        if !nx.is_finite()
+            || !ny.is_finite()
+            || nx < 0.0
+            || ny < 0.0
+            || nx > width - 1.0
+            || ny > height - 1.0
+        {
+            return None;
+        }
+        Some((gx, gy))
+    }
+
+    /// Track points from `prev` to `next`, such as the keypoints found by `Harris` or `Fast`.
+    /// Returns the new position of each point, or `None` when a point has too little texture to
+    /// be tracked or moves outside of the image.
+    pub fn track<T: Type, C: Color>(
+        &self,
+        prev: &Image<T, C>,
+        next: &Image<T, C>,
+        points: &[(f64, f64)],
+    ) -> Vec<Option<(f64, f64)>> {
+        assert_eq!(prev.size(), next.size(), "frame sizes must match");
+        if prev.width() == 0 || prev.height() == 0 {
+            return vec![None; points.len()];
+        }
+
+        let (a, b) = (self.pyramid(prev), self.pyramid(next));
+        points
+            .iter()
+            .map(|(x, y)| {
+                self.track_point(&a, &b, *x, *y)
+                    .map(|(dx, dy)| (x + dx, y + dy))
+            })
+            .collect()
+    }
+
+    /// Estimate dense motion from `prev` to `next` by tracking the center of each `step` x `step`
+    /// block and filling the block with its displacement. Blocks that can't be tracked have no
+    /// motion. The result can be used with `Warp` or `MotionCompensatedAverage`.
+    pub fn dense<T: Type, C: Color>(
+        &self,
+        prev: &Image<T, C>,
+        next: &Image<T, C>,
+        step: usize,
+    ) -> FlowField {
+        assert_eq!(prev.size(), next.size(), "frame sizes must match");
+        let mut flow = FlowField::new(prev.size());
+        if prev.width() == 0 || prev.height() == 0 {
+            return flow;
+        }
+
+        let step = step.max(1);
+        let (a, b) = (self.pyramid(prev), self.pyramid(next));
+        for by in (0..prev.height()).step_by(step) {
+            for bx in (0..prev.width()).step_by(step) {
+                let (x1, y1) = (
+                    (bx + step).min(prev.width()),
+                    (by + step).min(prev.height()),
+                );
+                let (cx, cy) = ((bx + x1 - 1) as f64 / 2.0, (by + y1 - 1) as f64 / 2.0);
+                let (dx, dy) = self.track_point(&a, &b, cx, cy).unwrap_or((0.0, 0.0));
+                for y in by..y1 {
+                    for x in bx..x1 {
+                        flow.set((x, y), dx, dy);
+                    }
+                }
+            }
+        }
+        flow
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use analysis::{Harris, LucasKanade};
+
+    fn scene(dx: f64, dy: f64) -> Image<f32, Gray> {
+        let mut rng = Seed(7).rng();
+        let blobs: Vec<_> = (0..60)
+            .map(|_| {
+                let (x, y) = (rng.range(0.0, 96.0), rng.range(0.0, 96.0));
+                (x, y, rng.range(2.0, 4.0), rng.range(0.2, 0.8))
+            })
+            .collect();
+
+        let mut image = Image::<f32, Gray>::new((96, 96));
+        image.for_each(|pt, mut px| {
+            let (x, y) = (pt.x as f64 - dx, pt.y as f64 - dy);
+            px[0] = blobs
+                .iter()
+                .map(|(cx, cy, r, a)| {
+                    a * (-((x - cx).powi(2) + (y - cy).powi(2)) / (2.0 * r * r)).exp()
+                })
+                .sum::<f64>() as f32;
+        });
+        image
+    }
+
+    #[test]
+    fn test_lucas_kanade() {
+        let prev = scene(0.0, 0.0);
+        let tracker = LucasKanade::default();
+
+        for (dx, dy) in [(1.3, -0.6), (9.5, 6.0)] {
+            let next = scene(dx, dy);
+            let points: Vec<(f64, f64)> = Harris::default()
+                .detect(&prev)
+                .iter()
+                .map(|k| (k.point.x as f64, k.point.y as f64))
+                .filter(|(x, y)| (20.0..76.0).contains(x) && (20.0..76.0).contains(y))
+                .collect();
+            assert!(points.len() >= 5);
+
+            let tracked = tracker.track(&prev, &next, &points);
+            for ((x, y), p) in points.iter().zip(&tracked) {
+                let (nx, ny) = p.expect("point was lost");
+                assert!((nx - x - dx).abs() < 0.1 && (ny - y - dy).abs() < 0.1);
+            }
+
+            let flow = tracker.dense(&prev, &next, 8);
+            let (fx, fy) = flow.get((48, 48));
+            assert!((fx - dx).abs() < 0.1 && (fy - dy).abs() < 0.1);
+        }
+
+        // Flat regions can't be tracked
+        let flat = Image::<f32, Gray>::new((32, 32));
+        assert_eq!(tracker.track(&flat, &flat, &[(16.0, 16.0)]), vec![None]);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::*;
+
+/// Image gradients
+#[derive(Clone)]
+pub struct Gradients {
+    /// Gradient magnitude
+    pub magnitude: Image<f32, Gray>,
+
+    /// Gradient orientation in radians, in the range -π..π, measured from the x axis with y
+    /// pointing down
+    pub orientation: Image<f32, Gray>,
+}
+
+/// Compute the gradient magnitude and orientation of the luminance of an image using centered
+/// differences, edge pixels are clamped
+pub fn gradients<T: Type, C: Color>(image: &Image<T, C>) -> Gradients {
+    let gray: Image<f32, Gray> = image.convert();
+    let (w, h) = (gray.width(), gray.height());
+    let data = gray.data();
+    let at = |x: usize, y: usize| data[y * w + x] as f64;
+
+    let mut magnitude = Image::new(gray.size());
+    let mut orientation = Image::new(gray.size());
+    for y in 0..h {
+        for x in 0..w {
+            let dx = at((x + 1).min(w - 1), y) - at(x.saturating_sub(1), y);
+            let dy = at(x, (y + 1).min(h - 1)) - at(x, y.saturating_sub(1));
+            magnitude.data_mut()[y * w + x] = (dx * dx + dy * dy).sqrt() as f32;
+            orientation.data_mut()[y * w + x] = dy.atan2(dx) as f32;
+        }
+    }
+
+    Gradients {
+        magnitude,
+        orientation,
+    }
+}
+
+/// Histogram of oriented gradients descriptor extractor
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hog {
+    /// Cell width and height in pixels
+    pub cell_size: usize,
+
+    /// Block width and height in cells
+    pub block_size: usize,
+
+    /// Number of orientation bins per cell
+    pub bins: usize,
+
+    /// Use signed orientations (0..360 degrees) instead of unsigned (0..180 degrees)
+    pub signed: bool,
+}
+
+impl Default for Hog {
+    fn default() -> Hog {
+        Hog {
+            cell_size: 8,
+            block_size: 2,
+            bins: 9,
+            signed: false,
+        }
+    }
+}
+
+impl Hog {
+    /// Create a new `Hog` extractor, `cell_size`, `block_size` and `bins` must all be non-zero
+    pub fn new(cell_size: usize, block_size: usize, bins: usize) -> Result<Hog, Error> {
+        if cell_size == 0 || block_size == 0 || bins == 0 {
+            return Err(Error::Message(format!(
+                "invalid HOG parameters: cell_size={}, block_size={}, bins={}",
+                cell_size, block_size, bins
+            )));
+        }
+
+        Ok(Hog {
+            cell_size,
+            block_size,
+            bins,
+            signed: false,
+        })
+    }
+
+    /// Number of cells in each direction for an image of the given size
+    pub fn cells(&self, size: Size) -> Size {
+        Size::new(
+            size.width.checked_div(self.cell_size).unwrap_or(0),
+            size.height.checked_div(self.cell_size).unwrap_or(0),
+        )
+    }
+
+    /// Length of the descriptor for an image of the given size
+    pub fn descriptor_len(&self, size: Size) -> usize {
+        let cells = self.cells(size);
+        let bx = (cells.width + 1).saturating_sub(self.block_size);
+        let by = (cells.height + 1).saturating_sub(self.block_size);
+        bx * by * self.block_size * self.block_size * self.bins
+    }
+
+    /// Compute per-cell orientation histograms, the result contains `bins` values for each cell in
+    /// raster order
+    pub fn cell_histograms(&self, gradients: &Gradients) -> Vec<f32> {
+        if self.bins == 0 {
+            return Vec::new();
+        }
+
+        let cells = self.cells(gradients.magnitude.size());
+        let range = if self.signed {
+            2.0 * std::f64::consts::PI
+        } else {
+            std::f64::consts::PI
+        };
+        let bin_width = range / self.bins as f64;
+
+        let mut hist = vec![0.0; cells.width * cells.height * self.bins];
+        let width = gradients.magnitude.width();
+        let magnitude = gradients.magnitude.data();
+        let orientation = gradients.orientation.data();
+
+        for y in 0..cells.height * self.cell_size {
+            for x in 0..cells.width * self.cell_size {
+                let m = magnitude[y * width + x];
+                let mut angle = orientation[y * width + x] as f64;
+                if angle < 0.0 {
+                    angle += range;
+                }
+                if !self.signed && angle >= range {
+                    angle -= range;
+                }
+
+                // Split the vote between the two closest bins
+                let b = angle / bin_width - 0.5;
+                let b0 = b.floor();
+                let t = (b - b0) as f32;
+                let b0 = (b0 as isize).rem_euclid(self.bins as isize) as usize;
+                let b1 = (b0 + 1) % self.bins;
+
+                let cell = (y / self.cell_size) * cells.width + x / self.cell_size;
+                hist[cell * self.bins + b0] += m * (1.0 - t);
+                hist[cell * self.bins + b1] += m * t;
+            }
+        }
+
+        hist
+    }
+
+    /// Compute the HOG descriptor of an image, blocks of cells are normalized using L2-Hys
+    pub fn compute<T: Type, C: Color>(&self, image: &Image<T, C>) -> Vec<f32> {
+        let gradients = gradients(image);
+        let hist = self.cell_histograms(&gradients);
+        let cells = self.cells(image.size());
+        let bx = (cells.width + 1).saturating_sub(self.block_size);
+        let by = (cells.height + 1).saturating_sub(self.block_size);
+
+        let mut descriptor = Vec::with_capacity(self.descriptor_len(image.size()));
+        let mut block = Vec::with_capacity(self.block_size * self.block_size * self.bins);
+        for y in 0..by {
+            for x in 0..bx {
+                block.clear();
+                for cy in y..y + self.block_size {
+                    for cx in x..x + self.block_size {
+                        let start = (cy * cells.width + cx) * self.bins;
+                        block.extend_from_slice(&hist[start..start + self.bins]);
+                    }
+                }
+
+                l2_normalize(&mut block);
+                block.iter_mut().for_each(|v| *v = v.min(0.2));
+                l2_normalize(&mut block);
+                descriptor.extend_from_slice(&block);
+            }
+        }
+
+        descriptor
+    }
+}
+
+fn l2_normalize(v: &mut [f32]) {
+    let norm = (v.iter().map(|x| x * x).sum::<f32>() + 1e-6).sqrt();
+    v.iter_mut().for_each(|x| *x /= norm);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_gradients() {
+        let mut image = Image::<f32, Gray>::new((16, 16));
+        image.for_each(|pt, mut px| px[0] = pt.x as f32 / 16.0);
+
+        let g = analysis::gradients(&image);
+        assert!((g.magnitude.get_f((8, 8), 0) - 2.0 / 16.0).abs() < 1e-6);
+        assert!(g.orientation.get_f((8, 8), 0).abs() < 1e-6);
+
+        let hog = analysis::Hog::default();
+        let descriptor = hog.compute(&image);
+        assert_eq!(descriptor.len(), hog.descriptor_len(image.size()));
+        assert_eq!(descriptor.len(), 2 * 2 * 9);
+
+        // Horizontal gradient, all votes are split between the bins closest to 0 degrees
+        let hist = hog.cell_histograms(&g);
+        assert!(hist[0] > 0.0 && hist[8] > 0.0);
+        assert!(hist[1..8].iter().all(|x| *x == 0.0));
+    }
+
+    #[test]
+    fn test_hog_invalid() {
+        assert!(analysis::Hog::new(0, 2, 9).is_err());
+        assert!(analysis::Hog::new(8, 0, 9).is_err());
+        assert!(analysis::Hog::new(8, 2, 0).is_err());
+        assert!(analysis::Hog::new(8, 2, 9).is_ok());
+
+        // Fields are public, zero sizes set directly produce an empty descriptor instead of panicking
+        let image = Image::<f32, Gray>::new((16, 16));
+        for (cell_size, block_size, bins) in [(0, 2, 9), (8, 0, 9), (8, 2, 0)] {
+            let hog = analysis::Hog {
+                cell_size,
+                block_size,
+                bins,
+                signed: false,
+            };
+            assert_eq!(hog.descriptor_len(image.size()), 0);
+            assert!(hog.compute(&image).is_empty());
+        }
+    }
+}
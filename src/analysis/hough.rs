@@ -0,0 +1,258 @@
+use crate::*;
+
+/// Straight line detected by `hough_lines`, in normal form: x·cos(θ) + y·sin(θ) = ρ
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HoughLine {
+    /// Signed distance from the origin in pixels
+    pub rho: f64,
+
+    /// Angle of the line normal in radians, in the range 0..π, measured from the x axis with y
+    /// pointing down
+    pub theta: f64,
+
+    /// Number of edge pixels on the line
+    pub votes: usize,
+}
+
+impl HoughLine {
+    /// Direction of the line in degrees, in the range -90..90 where 0 is horizontal. For
+    /// document deskewing this is the rotation of the text lines.
+    pub fn angle(&self) -> f64 {
+        let angle = self.theta.to_degrees() - 90.0;
+        if angle < -90.0 {
+            angle + 180.0
+        } else {
+            angle
+        }
+    }
+
+    /// Distance from a point to the line
+    pub fn distance(&self, x: f64, y: f64) -> f64 {
+        (x * self.theta.cos() + y * self.theta.sin() - self.rho).abs()
+    }
+}
+
+/// Circle detected by `hough_circles`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HoughCircle {
+    /// Center position
+    pub center: Point,
+
+    /// Radius in pixels
+    pub radius: usize,
+
+    /// Number of edge pixels on the circle
+    pub votes: usize,
+}
+
+/// Positions of all set pixels in an edge map, pixels with a normalized value >= 0.5 are edges
+fn edge_points<T: Type>(edges: &Image<T, Gray>) -> Vec<(usize, usize)> {
+    let width = edges.width();
+    edges
+        .data()
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| x.to_norm() >= 0.5)
+        .map(|(i, _)| (i % width, i / width))
+        .collect()
+}
+
+/// Find the local maxima of an accumulator with at least `threshold` votes. `neighbors` returns
+/// the indices of the cells adjacent to a cell, on plateaus the first cell in order is kept.
+fn peaks(
+    acc: &[u32],
+    threshold: usize,
+    neighbors: impl Fn(usize) -> Vec<usize>,
+) -> Vec<(usize, usize)> {
+    let mut peaks: Vec<(usize, usize)> = acc
+        .iter()
+        .enumerate()
+        .filter(|(_, v)| **v as usize >= threshold.max(1))
+        .filter(|(i, v)| {
+            neighbors(*i)
+                .into_iter()
+                .all(|n| **v > acc[n] || (**v == acc[n] && n > *i))
+        })
+        .map(|(i, v)| (i, *v as usize))
+        .collect();
+    peaks.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    peaks
+}
+
+/// Detect straight lines in an edge map, such as the output of `filter::Canny`, using the Hough
+/// transform. Line angles are quantized into `angles` steps over 180 degrees and distances into
+/// steps of one pixel. Lines with at least `threshold` edge pixels are returned, ordered by the
+/// number of votes.
+pub fn hough_lines<T: Type>(
+    edges: &Image<T, Gray>,
+    angles: usize,
+    threshold: usize,
+) -> Vec<HoughLine> {
+    let angles = angles.max(1);
+    let (width, height) = (edges.width() as f64, edges.height() as f64);
+    let max_rho = (width * width + height * height).sqrt().ceil() as usize;
+    let bins = 2 * max_rho + 1;
+
+    let trig: Vec<(f64, f64)> = (0..angles)
+        .map(|i| (i as f64 * std::f64::consts::PI / angles as f64).sin_cos())
+        .collect();
+
+    let mut acc = vec![0u32; angles * bins];
+    for (x, y) in edge_points(edges) {
+        for (a, (sin, cos)) in trig.iter().enumerate() {
+            let rho = x as f64 * cos + y as f64 * sin;
+            let bin = (rho.round() as isize + max_rho as isize) as usize;
+            acc[a * bins + bin] += 1;
+        }
+    }
+
+    peaks(&acc, threshold, |i| {
+        let (a, r) = ((i / bins) as isize, (i % bins) as isize);
+        let mut n = Vec::with_capacity(8);
+        for da in -1..=1 {
+            for dr in -1..=1 {
+                let (na, nr) = (a + da, r + dr);
+                if (da, dr) != (0, 0)
+                    && na >= 0
+                    && nr >= 0
+                    && (na as usize) < angles
+                    && (nr as usize) < bins
+                {
+                    n.push(na as usize * bins + nr as usize);
+                }
+            }
+        }
+        n
+    })
+    .into_iter()
+    .map(|(i, votes)| HoughLine {
+        rho: (i % bins) as f64 - max_rho as f64,
+        theta: (i / bins) as f64 * std::f64::consts::PI / angles as f64,
+        votes,
+    })
+    .collect()
+}
+
+/// Integer offsets of the pixels on a circle of the given radius
+fn circle_offsets(radius: usize) -> Vec<(isize, isize)> {
+    let steps = (8 * radius).max(8);
+    let mut offsets: Vec<(isize, isize)> = (0..steps)
+        .map(|i| {
+            let (sin, cos) = (i as f64 * std::f64::consts::TAU / steps as f64).sin_cos();
+            (
+                (cos * radius as f64).round() as isize,
+                (sin * radius as f64).round() as isize,
+            )
+        })
+        .collect();
+    offsets.sort_unstable();
+    offsets.dedup();
+    offsets
+}
+
+/// Detect circles with a radius in the given range in an edge map using the Hough transform.
+/// Circles with at least `threshold` edge pixels are returned, ordered by the number of votes;
+/// a complete circle of radius r has about 2πr edge pixels.
+pub fn hough_circles<T: Type>(
+    edges: &Image<T, Gray>,
+    radii: std::ops::RangeInclusive<usize>,
+    threshold: usize,
+) -> Vec<HoughCircle> {
+    let (width, height) = (edges.width(), edges.height());
+    let (min_radius, max_radius) = (*radii.start(), *radii.end());
+    if max_radius < min_radius {
+        return Vec::new();
+    }
+    let layers = max_radius - min_radius + 1;
+    let layer = width * height;
+
+    let points = edge_points(edges);
+    let mut acc = vec![0u32; layers * layer];
+    for (l, radius) in radii.enumerate() {
+        let offsets = circle_offsets(radius);
+        let acc = &mut acc[l * layer..(l + 1) * layer];
+        for (x, y) in &points {
+            for (dx, dy) in &offsets {
+                let (cx, cy) = (*x as isize - dx, *y as isize - dy);
+                if cx >= 0 && cy >= 0 && (cx as usize) < width && (cy as usize) < height {
+                    acc[cy as usize * width + cx as usize] += 1;
+                }
+            }
+        }
+    }
+
+    peaks(&acc, threshold, |i| {
+        let (l, y, x) = (
+            (i / layer) as isize,
+            ((i % layer) / width) as isize,
+            (i % width) as isize,
+        );
+        let mut n = Vec::with_capacity(26);
+        for dl in -1..=1 {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let (nl, ny, nx) = (l + dl, y + dy, x + dx);
+                    if (dl, dy, dx) != (0, 0, 0)
+                        && nl >= 0
+                        && ny >= 0
+                        && nx >= 0
+                        && (nl as usize) < layers
+                        && (ny as usize) < height
+                        && (nx as usize) < width
+                    {
+                        n.push(nl as usize * layer + ny as usize * width + nx as usize);
+                    }
+                }
+            }
+        }
+        n
+    })
+    .into_iter()
+    .map(|(i, votes)| HoughCircle {
+        center: Point::new(i % width, (i % layer) / width),
+        radius: min_radius + i / layer,
+        votes,
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_hough_lines() {
+        // A horizontal line, and a line rotated by 10 degrees
+        let mut edges = Image::<u8, Gray>::new((100, 80));
+        for x in 0..100 {
+            edges.set((x, 20), [255]);
+            let y = 50.0 + (x as f64 - 50.0) * 10f64.to_radians().tan();
+            edges.set((x, y.round() as usize), [255]);
+        }
+
+        let lines = analysis::hough_lines(&edges, 180, 50);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].votes, 100);
+        assert_eq!(lines[0].angle(), 0.0);
+        assert_eq!(lines[0].rho, 20.0);
+        assert!((lines[1].angle() - 10.0).abs() <= 1.0);
+        assert!(lines[1].distance(50.0, 50.0) < 1.0);
+    }
+
+    #[test]
+    fn test_hough_circles() {
+        let mut edges = Image::<u8, Gray>::new((64, 64));
+        for i in 0..360 {
+            let (sin, cos) = (i as f64).to_radians().sin_cos();
+            let (x, y) = (30.0 + 12.0 * cos, 25.0 + 12.0 * sin);
+            edges.set((x.round() as usize, y.round() as usize), [255]);
+        }
+
+        let circles = analysis::hough_circles(&edges, 8..=16, 40);
+        assert_eq!(circles.len(), 1);
+        assert_eq!(circles[0].center, Point::new(30, 25));
+        assert_eq!(circles[0].radius, 12);
+    }
+}
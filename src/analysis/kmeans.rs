@@ -0,0 +1,255 @@
+use crate::*;
+
+use crate::color::{lab_to_rgb, rgb_to_lab};
+use crate::math::dist2;
+
+/// Color cluster found using k-means clustering
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColorCluster {
+    /// Cluster center
+    pub color: Pixel<Rgb>,
+
+    /// Fraction of sampled pixels assigned to this cluster, in the range 0..1
+    pub weight: f64,
+}
+
+/// K-means color clustering options
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KMeans {
+    /// Number of clusters
+    pub k: usize,
+
+    /// Maximum number of iterations
+    pub max_iterations: usize,
+
+    /// Cluster in CIE-L*a*b* instead of RGB, this is slower but gives perceptually better results
+    pub lab: bool,
+
+    /// Maximum number of pixels sampled from the image, 0 uses every pixel
+    pub max_samples: usize,
+
+    /// Random seed used to pick the initial cluster centers
+    pub seed: Seed,
+}
+
+impl KMeans {
+    /// Create new `KMeans` options with `k` clusters
+    pub fn new(k: usize) -> KMeans {
+        KMeans {
+            k,
+            max_iterations: 32,
+            lab: false,
+            max_samples: 65536,
+            seed: Seed::default(),
+        }
+    }
+
+    /// Enable or disable clustering in CIE-L*a*b*
+    pub fn with_lab(mut self, lab: bool) -> KMeans {
+        self.lab = lab;
+        self
+    }
+
+    /// Set the maximum number of iterations
+    pub fn with_max_iterations(mut self, n: usize) -> KMeans {
+        self.max_iterations = n;
+        self
+    }
+
+    /// Set the maximum number of sampled pixels
+    pub fn with_max_samples(mut self, n: usize) -> KMeans {
+        self.max_samples = n;
+        self
+    }
+
+    /// Set the random seed
+    pub fn with_seed(mut self, seed: impl Into<Seed>) -> KMeans {
+        self.seed = seed.into();
+        self
+    }
+
+    /// Cluster the colors of an image, the results are sorted by weight with the most common
+    /// color first
+    pub fn run<T: Type, C: Color>(&self, image: &Image<T, C>) -> Vec<ColorCluster> {
+        let samples: Vec<[f64; 3]> = analysis::sample_pixels(image, self.max_samples)
+            .iter()
+            .map(|px| {
+                if self.lab {
+                    rgb_to_lab(px)
+                } else {
+                    [px[0], px[1], px[2]]
+                }
+            })
+            .collect();
+
+        if samples.is_empty() || self.k == 0 {
+            return Vec::new();
+        }
+
+        let (centers, counts) = cluster(&samples, self.k, self.max_iterations, self.seed);
+
+        let mut clusters: Vec<ColorCluster> = centers
+            .into_iter()
+            .zip(counts)
+            .filter(|(_, n)| *n > 0)
+            .map(|(c, n)| {
+                let mut color = Pixel::new();
+                if self.lab {
+                    lab_to_rgb(c, &mut color);
+                } else {
+                    color.copy_from_slice(c);
+                }
+                ColorCluster {
+                    color,
+                    weight: n as f64 / samples.len() as f64,
+                }
+            })
+            .collect();
+
+        clusters.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+        clusters
+    }
+}
+
+impl Seeded for KMeans {
+    fn seed(&self) -> Seed {
+        self.seed
+    }
+
+    fn set_seed(&mut self, seed: Seed) {
+        self.seed = seed;
+    }
+}
+
+/// Find the `k` dominant colors of an image using k-means clustering in RGB
+pub fn kmeans_colors<T: Type, C: Color>(image: &Image<T, C>, k: usize) -> Vec<ColorCluster> {
+    KMeans::new(k).run(image)
+}
+
+fn nearest(x: &[f64; 3], centers: &[[f64; 3]]) -> (usize, f64) {
+    centers
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, dist2(x, c)))
+        .fold((0, f64::MAX), |acc, x| if x.1 < acc.1 { x } else { acc })
+}
+
+/// Lloyd's algorithm with k-means++ initialization, returns cluster centers and the number of
+/// samples assigned to each
+fn cluster(
+    samples: &[[f64; 3]],
+    k: usize,
+    max_iterations: usize,
+    seed: Seed,
+) -> (Vec<[f64; 3]>, Vec<usize>) {
+    let mut rng = seed.rng();
+    let mut centers = vec![samples[rng.below(samples.len())]];
+    let mut distances: Vec<f64> = samples.iter().map(|x| dist2(x, &centers[0])).collect();
+
+    while centers.len() < k {
+        let total: f64 = distances.iter().sum();
+        if total <= 0.0 {
+            // Fewer distinct colors than clusters
+            break;
+        }
+
+        let mut target = rng.next_f64() * total;
+        let mut index = samples.len() - 1;
+        for (i, d) in distances.iter().enumerate() {
+            target -= d;
+            if target <= 0.0 {
+                index = i;
+                break;
+            }
+        }
+
+        centers.push(samples[index]);
+        for (x, d) in samples.iter().zip(distances.iter_mut()) {
+            *d = d.min(dist2(x, &samples[index]));
+        }
+    }
+
+    let mut assignment = vec![usize::MAX; samples.len()];
+    let mut counts = vec![0; centers.len()];
+    for _ in 0..max_iterations.max(1) {
+        let mut changed = false;
+        for (x, a) in samples.iter().zip(assignment.iter_mut()) {
+            let (i, _) = nearest(x, &centers);
+            if *a != i {
+                *a = i;
+                changed = true;
+            }
+        }
+
+        let mut sums = vec![[0.0; 3]; centers.len()];
+        counts.iter_mut().for_each(|n| *n = 0);
+        for (x, a) in samples.iter().zip(&assignment) {
+            counts[*a] += 1;
+            for c in 0..3 {
+                sums[*a][c] += x[c];
+            }
+        }
+
+        for ((center, sum), n) in centers.iter_mut().zip(sums).zip(&counts) {
+            if *n > 0 {
+                let n = *n as f64;
+                *center = [sum[0] / n, sum[1] / n, sum[2] / n];
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    (centers, counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn two_colors() -> Image<f32, Rgb> {
+        let mut image = Image::<f32, Rgb>::new((40, 40));
+        image.for_each(|pt, mut px| {
+            if pt.x < 10 {
+                px.copy_from_slice([0.9, 0.1, 0.1]);
+            } else {
+                px.copy_from_slice([0.1, 0.2, 0.8]);
+            }
+        });
+        image
+    }
+
+    #[test]
+    fn test_kmeans_colors() {
+        let clusters = analysis::kmeans_colors(&two_colors(), 2);
+        assert_eq!(clusters.len(), 2);
+        assert!((clusters[0].weight - 0.75).abs() < 1e-6);
+        assert!((clusters[0].color[2] - 0.8).abs() < 1e-3);
+        assert!((clusters[1].color[0] - 0.9).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_kmeans_lab() {
+        let clusters = analysis::KMeans::new(3).with_lab(true).run(&two_colors());
+        assert_eq!(clusters.len(), 2);
+        assert!((clusters[0].weight - 0.75).abs() < 1e-6);
+        assert!((clusters[0].color[2] - 0.8).abs() < 1e-3);
+        assert!((clusters[1].color[1] - 0.1).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_average_dominant_color() {
+        let image = two_colors();
+        let avg = image.average_color();
+        assert!((avg[0] - 0.3).abs() < 1e-6);
+        assert!((avg[2] - 0.625).abs() < 1e-6);
+
+        let dominant = image.dominant_color();
+        assert!((dominant[0] - 0.1).abs() < 1e-3);
+        assert!((dominant[2] - 0.8).abs() < 1e-3);
+    }
+}
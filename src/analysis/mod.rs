@@ -0,0 +1,61 @@
+use crate::*;
+
+mod corners;
+pub(crate) mod fft;
+mod flow;
+mod gradient;
+mod hough;
+mod kmeans;
+mod stats;
+
+pub use corners::*;
+pub use fft::phase_correlate;
+pub use flow::*;
+pub use gradient::*;
+pub use hough::*;
+pub use kmeans::*;
+pub use stats::*;
+
+/// Collect up to `max` pixels from an image, evenly spaced in raster order, converted to `Rgb`
+pub(crate) fn sample_pixels<T: Type, C: Color>(image: &Image<T, C>, max: usize) -> Vec<Pixel<Rgb>> {
+    let count = image.width() * image.height();
+    let step = if max == 0 || count <= max {
+        1
+    } else {
+        count.div_ceil(max)
+    };
+
+    let mut px = image.new_pixel();
+    (0..count)
+        .step_by(step)
+        .map(|i| {
+            image.pixel_at((i % image.width(), i / image.width()), &mut px);
+            px.convert()
+        })
+        .collect()
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Get the mean value of every channel
+    pub fn average_color(&self) -> Pixel<C> {
+        let mut sum = vec![0.0; C::CHANNELS];
+        for px in self.data().chunks_exact(C::CHANNELS) {
+            for (s, x) in sum.iter_mut().zip(px) {
+                *s += x.to_norm();
+            }
+        }
+
+        let count = (self.width() * self.height()).max(1) as f64;
+        sum.iter_mut().for_each(|s| *s /= count);
+        Pixel::from(sum)
+    }
+
+    /// Get the most common color, this is the center of the largest cluster found using k-means
+    /// clustering in CIE-L*a*b*
+    pub fn dominant_color(&self) -> Pixel<C> {
+        match KMeans::new(5).with_lab(true).run(self).first() {
+            Some(cluster) => cluster.color.convert(),
+            None => self.new_pixel(),
+        }
+    }
+}
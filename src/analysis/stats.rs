@@ -0,0 +1,305 @@
+use crate::*;
+
+/// Summary statistics for a single channel, values are normalized
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChannelStats {
+    /// Minimum value
+    pub min: f64,
+
+    /// Maximum value
+    pub max: f64,
+
+    /// Mean value
+    pub mean: f64,
+
+    /// Standard deviation
+    pub std_dev: f64,
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Get the minimum, maximum, mean and standard deviation of every channel
+    pub fn stats(&self) -> Vec<ChannelStats> {
+        self.region_stats(Region::new(Point::zero(), self.size()))
+    }
+
+    /// Get the minimum, maximum, mean and standard deviation of every channel inside of `roi`,
+    /// the region is clipped to the image bounds
+    pub fn region_stats(&self, roi: Region) -> Vec<ChannelStats> {
+        let mut min = vec![f64::INFINITY; C::CHANNELS];
+        let mut max = vec![f64::NEG_INFINITY; C::CHANNELS];
+        let mut sum = vec![0.0; C::CHANNELS];
+        let mut sum_sq = vec![0.0; C::CHANNELS];
+        let mut count = 0usize;
+
+        if let Some(roi) = roi.intersection(&Region::new(Point::zero(), self.size())) {
+            let row_len = self.width() * C::CHANNELS;
+            for y in roi.origin.y..roi.origin.y + roi.height() {
+                let start = y * row_len + roi.origin.x * C::CHANNELS;
+                let row = &self.data()[start..start + roi.width() * C::CHANNELS];
+                for px in row.chunks_exact(C::CHANNELS) {
+                    for (c, x) in px.iter().enumerate() {
+                        let x = x.to_norm();
+                        min[c] = min[c].min(x);
+                        max[c] = max[c].max(x);
+                        sum[c] += x;
+                        sum_sq[c] += x * x;
+                    }
+                }
+                count += roi.width();
+            }
+        }
+
+        (0..C::CHANNELS)
+            .map(|c| {
+                if count == 0 {
+                    return ChannelStats {
+                        min: 0.0,
+                        max: 0.0,
+                        mean: 0.0,
+                        std_dev: 0.0,
+                    };
+                }
+
+                let mean = sum[c] / count as f64;
+                let variance = (sum_sq[c] / count as f64 - mean * mean).max(0.0);
+                ChannelStats {
+                    min: min[c],
+                    max: max[c],
+                    mean,
+                    std_dev: variance.sqrt(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Width and height of the tiles tracked by `ImageStats`
+const STATS_TILE: usize = 64;
+
+/// Running totals for a single tile
+#[derive(Debug, Clone, PartialEq)]
+struct TileStats {
+    min: Vec<f64>,
+    max: Vec<f64>,
+    sum: Vec<f64>,
+    sum_sq: Vec<f64>,
+    count: usize,
+    histogram: Vec<Histogram>,
+}
+
+impl TileStats {
+    fn new<T: Type, C: Color>(image: &Image<T, C>, roi: Region, bins: usize) -> TileStats {
+        let mut tile = TileStats {
+            min: vec![f64::INFINITY; C::CHANNELS],
+            max: vec![f64::NEG_INFINITY; C::CHANNELS],
+            sum: vec![0.0; C::CHANNELS],
+            sum_sq: vec![0.0; C::CHANNELS],
+            count: roi.width() * roi.height(),
+            histogram: vec![Histogram::new(bins); C::CHANNELS],
+        };
+
+        let row_len = image.width() * C::CHANNELS;
+        for y in roi.origin.y..roi.origin.y + roi.height() {
+            let start = y * row_len + roi.origin.x * C::CHANNELS;
+            let row = &image.data()[start..start + roi.width() * C::CHANNELS];
+            for px in row.chunks_exact(C::CHANNELS) {
+                for (c, x) in px.iter().enumerate() {
+                    tile.histogram[c].add_value(*x);
+                    let x = x.to_norm();
+                    tile.min[c] = tile.min[c].min(x);
+                    tile.max[c] = tile.max[c].max(x);
+                    tile.sum[c] += x;
+                    tile.sum_sq[c] += x * x;
+                }
+            }
+        }
+        tile
+    }
+}
+
+/// Statistics and histograms of a whole image, cached per tile so they can be updated
+/// incrementally when only part of the image changes. This keeps scopes responsive while editing
+/// large images, for example by passing the region returned by `RetouchBrush::take_dirty` to
+/// `ImageStats::update` after every dab.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageStats {
+    size: Size,
+    bins: usize,
+    columns: usize,
+    tiles: Vec<TileStats>,
+    histogram: Vec<Histogram>,
+}
+
+impl ImageStats {
+    /// Compute the statistics of an image, histograms use `bins` bins per channel
+    pub fn new<T: Type, C: Color>(image: &Image<T, C>, bins: usize) -> ImageStats {
+        let mut stats = ImageStats {
+            size: Size::zero(),
+            bins,
+            columns: 0,
+            tiles: Vec::new(),
+            histogram: Vec::new(),
+        };
+        stats.reset(image);
+        stats
+    }
+
+    /// Recompute everything, used when the image size changes
+    fn reset<T: Type, C: Color>(&mut self, image: &Image<T, C>) {
+        self.size = image.size();
+        self.columns = image.width().div_ceil(STATS_TILE);
+        let rows = image.height().div_ceil(STATS_TILE);
+        self.tiles = (0..self.columns * rows)
+            .map(|i| TileStats::new(image, self.tile_region(i), self.bins))
+            .collect();
+
+        self.histogram = vec![Histogram::new(self.bins); C::CHANNELS];
+        for tile in &self.tiles {
+            for (h, t) in self.histogram.iter_mut().zip(&tile.histogram) {
+                h.merge(t);
+            }
+        }
+    }
+
+    /// Area covered by the tile at `index`
+    fn tile_region(&self, index: usize) -> Region {
+        let (x, y) = (
+            index % self.columns * STATS_TILE,
+            index / self.columns * STATS_TILE,
+        );
+        Region::new(
+            Point::new(x, y),
+            Size::new(
+                STATS_TILE.min(self.size.width - x),
+                STATS_TILE.min(self.size.height - y),
+            ),
+        )
+    }
+
+    /// Update the statistics after the pixels inside `roi` changed, only the tiles overlapping
+    /// the region are recomputed. When the image size changed everything is recomputed.
+    pub fn update<T: Type, C: Color>(&mut self, image: &Image<T, C>, roi: Region) {
+        if image.size() != self.size || self.histogram.len() != C::CHANNELS {
+            self.reset(image);
+            return;
+        }
+
+        let roi = match roi.intersection(&Region::new(Point::zero(), self.size)) {
+            Some(roi) if !roi.is_empty() => roi,
+            _ => return,
+        };
+        let (x0, y0) = (roi.origin.x / STATS_TILE, roi.origin.y / STATS_TILE);
+        let (x1, y1) = (
+            (roi.max_x() - 1) / STATS_TILE,
+            (roi.max_y() - 1) / STATS_TILE,
+        );
+        for ty in y0..=y1 {
+            for tx in x0..=x1 {
+                let index = ty * self.columns + tx;
+                let tile = TileStats::new(image, self.tile_region(index), self.bins);
+                for (c, h) in self.histogram.iter_mut().enumerate() {
+                    h.remove(&self.tiles[index].histogram[c]);
+                    h.merge(&tile.histogram[c]);
+                }
+                self.tiles[index] = tile;
+            }
+        }
+    }
+
+    /// Image size
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// Histogram of every channel, see `Image::histogram`
+    pub fn histogram(&self) -> &[Histogram] {
+        &self.histogram
+    }
+
+    /// Minimum, maximum, mean and standard deviation of every channel, see `Image::stats`
+    pub fn stats(&self) -> Vec<ChannelStats> {
+        let count: usize = self.tiles.iter().map(|t| t.count).sum();
+        (0..self.histogram.len())
+            .map(|c| {
+                if count == 0 {
+                    return ChannelStats {
+                        min: 0.0,
+                        max: 0.0,
+                        mean: 0.0,
+                        std_dev: 0.0,
+                    };
+                }
+
+                let min = self
+                    .tiles
+                    .iter()
+                    .fold(f64::INFINITY, |a, t| a.min(t.min[c]));
+                let max = self
+                    .tiles
+                    .iter()
+                    .fold(f64::NEG_INFINITY, |a, t| a.max(t.max[c]));
+                let sum: f64 = self.tiles.iter().map(|t| t.sum[c]).sum();
+                let sum_sq: f64 = self.tiles.iter().map(|t| t.sum_sq[c]).sum();
+
+                let mean = sum / count as f64;
+                let variance = (sum_sq / count as f64 - mean * mean).max(0.0);
+                ChannelStats {
+                    min,
+                    max,
+                    mean,
+                    std_dev: variance.sqrt(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_region_stats() {
+        let mut image = Image::<f32, Gray>::new((10, 10));
+        image.for_each(|pt, mut px| px[0] = if pt.x < 5 { 0.25 } else { 0.75 });
+
+        let stats = image.stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].min, 0.25);
+        assert_eq!(stats[0].max, 0.75);
+        assert!((stats[0].mean - 0.5).abs() < 1e-9);
+        assert!((stats[0].std_dev - 0.25).abs() < 1e-9);
+
+        let stats = image.region_stats(Region::new(Point::new(6, 2), Size::new(10, 3)));
+        assert_eq!(stats[0].min, 0.75);
+        assert!(stats[0].std_dev.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_image_stats() {
+        let mut image = Image::<u8, Rgb>::new((150, 100));
+        image.for_each(|pt, mut px| {
+            px.copy_from_slice([(pt.x % 256) as u8, (pt.y * 2) as u8, 10]);
+        });
+        let mut cache = analysis::ImageStats::new(&image, 64);
+        assert_eq!(cache.histogram(), image.histogram(64).as_slice());
+
+        // Paint a small area spanning several tiles
+        let region = Region::new(Point::new(60, 50), Size::new(10, 20));
+        image.fill_rect(region, &Pixel::from(vec![1.0, 0.0, 0.5]));
+        cache.update(&image, region);
+
+        assert_eq!(cache.histogram(), image.histogram(64).as_slice());
+        for (a, b) in cache.stats().iter().zip(image.stats()) {
+            assert_eq!((a.min, a.max), (b.min, b.max));
+            assert!((a.mean - b.mean).abs() < 1e-9 && (a.std_dev - b.std_dev).abs() < 1e-9);
+        }
+
+        // Changing the size recomputes everything
+        let small = image.downscale((40, 30));
+        cache.update(&small, Region::new(Point::zero(), small.size()));
+        assert_eq!(cache.size(), Size::new(40, 30));
+        assert_eq!(cache.histogram(), small.histogram(64).as_slice());
+    }
+}
@@ -0,0 +1,728 @@
+use crate::*;
+
+/// Shape of an annotation, in image coordinates
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Shape {
+    /// Straight line
+    Line(Point, Point),
+
+    /// Line with an arrow head at the second point
+    Arrow(Point, Point),
+
+    /// Rectangle
+    Rect(Region),
+
+    /// Ellipse inscribed in a rectangle
+    Ellipse(Region),
+
+    /// Closed polygon
+    Polygon(Polygon),
+
+    /// Text, `position` is the left end of the baseline
+    Text {
+        /// Baseline start
+        position: Point,
+
+        /// Text content
+        text: String,
+
+        /// Font size in pixels
+        size: f64,
+    },
+
+    /// Dimension line between two points with a label at its center
+    Measurement {
+        /// Start of the measurement
+        start: Point,
+
+        /// End of the measurement
+        end: Point,
+
+        /// Label, usually the distance
+        label: String,
+    },
+}
+
+/// Shape drawn over an image, used to export markups
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Annotation {
+    /// Shape
+    pub shape: Shape,
+
+    /// Outline and text color
+    pub stroke: Pixel<Rgba>,
+
+    /// Fill color for closed shapes
+    pub fill: Option<Pixel<Rgba>>,
+
+    /// Outline width in pixels
+    pub line_width: f64,
+}
+
+impl Annotation {
+    /// Create a new annotation with a red, two pixel wide outline
+    pub fn new(shape: Shape) -> Annotation {
+        Annotation {
+            shape,
+            stroke: Pixel::from(vec![1.0, 0.0, 0.0, 1.0]),
+            fill: None,
+            line_width: 2.0,
+        }
+    }
+
+    /// Line annotation
+    pub fn line(a: impl Into<Point>, b: impl Into<Point>) -> Annotation {
+        Annotation::new(Shape::Line(a.into(), b.into()))
+    }
+
+    /// Arrow annotation, pointing at `b`
+    pub fn arrow(a: impl Into<Point>, b: impl Into<Point>) -> Annotation {
+        Annotation::new(Shape::Arrow(a.into(), b.into()))
+    }
+
+    /// Rectangle annotation
+    pub fn rect(region: Region) -> Annotation {
+        Annotation::new(Shape::Rect(region))
+    }
+
+    /// Ellipse annotation
+    pub fn ellipse(region: Region) -> Annotation {
+        Annotation::new(Shape::Ellipse(region))
+    }
+
+    /// Polygon annotation
+    pub fn polygon(polygon: Polygon) -> Annotation {
+        Annotation::new(Shape::Polygon(polygon))
+    }
+
+    /// Text annotation
+    pub fn text(position: impl Into<Point>, text: impl Into<String>, size: f64) -> Annotation {
+        Annotation::new(Shape::Text {
+            position: position.into(),
+            text: text.into(),
+            size,
+        })
+    }
+
+    /// Measurement annotation labeled with the distance in pixels, or in millimeters when `dpi`
+    /// is known
+    pub fn measurement(
+        start: impl Into<Point>,
+        end: impl Into<Point>,
+        dpi: Option<f64>,
+    ) -> Annotation {
+        let (start, end) = (start.into(), end.into());
+        let distance = crate::geom::distance(start, end);
+        let label = match dpi.filter(|dpi| *dpi > 0.0) {
+            Some(dpi) => format!("{:.1} mm", distance / dpi * 25.4),
+            None => format!("{:.1} px", distance),
+        };
+        Annotation::new(Shape::Measurement { start, end, label })
+    }
+
+    /// Set outline color
+    pub fn with_stroke(mut self, color: Pixel<Rgba>) -> Annotation {
+        self.stroke = color;
+        self
+    }
+
+    /// Set fill color
+    pub fn with_fill(mut self, color: Pixel<Rgba>) -> Annotation {
+        self.fill = Some(color);
+        self
+    }
+
+    /// Set outline width
+    pub fn with_line_width(mut self, width: f64) -> Annotation {
+        self.line_width = width.max(0.0);
+        self
+    }
+}
+
+#[cfg(feature = "window")]
+impl From<&crate::window::Measurement> for Annotation {
+    fn from(m: &crate::window::Measurement) -> Annotation {
+        let label = match m.millimeters() {
+            Some(mm) => format!("{:.1} mm", mm),
+            None => format!("{:.1} px", m.distance),
+        };
+        Annotation::new(Shape::Measurement {
+            start: m.start,
+            end: m.end,
+            label,
+        })
+    }
+}
+
+/// Length of arrow heads and measurement end ticks relative to the line width
+const HEAD_SIZE: f64 = 5.0;
+
+/// Font size used for measurement labels relative to the line width
+const LABEL_SIZE: f64 = 7.0;
+
+/// Unit vector from `a` to `b` and its perpendicular
+fn direction(a: Point, b: Point) -> ((f64, f64), (f64, f64)) {
+    let (dx, dy) = (b.x as f64 - a.x as f64, b.y as f64 - a.y as f64);
+    let length = dx.hypot(dy).max(f64::EPSILON);
+    let d = (dx / length, dy / length);
+    (d, (-d.1, d.0))
+}
+
+/// Lines used to draw an arrow head at `b`
+fn arrow_head(a: Point, b: Point, size: f64) -> [(f64, f64); 3] {
+    let (d, n) = direction(a, b);
+    let (bx, by) = (b.x as f64, b.y as f64);
+    [
+        (
+            bx - size * d.0 + size * 0.5 * n.0,
+            by - size * d.1 + size * 0.5 * n.1,
+        ),
+        (bx, by),
+        (
+            bx - size * d.0 - size * 0.5 * n.0,
+            by - size * d.1 - size * 0.5 * n.1,
+        ),
+    ]
+}
+
+/// End ticks of a measurement line
+fn ticks(start: Point, end: Point, size: f64) -> [[(f64, f64); 2]; 2] {
+    let (_, n) = direction(start, end);
+    let tick = |p: Point| {
+        let (x, y) = (p.x as f64, p.y as f64);
+        [
+            (x + n.0 * size * 0.5, y + n.1 * size * 0.5),
+            (x - n.0 * size * 0.5, y - n.1 * size * 0.5),
+        ]
+    };
+    [tick(start), tick(end)]
+}
+
+fn byte(x: f64) -> u8 {
+    (x.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn svg_color(color: &Pixel<Rgba>) -> String {
+    format!(
+        "rgb({},{},{})",
+        byte(color[0]),
+        byte(color[1]),
+        byte(color[2])
+    )
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(TABLE[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for b in data {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Wrap data in a zlib stream using uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut chunks = data.chunks(65535).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+        out.push(chunks.peek().is_none() as u8);
+        let len = chunk.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for x in data {
+        a = (a + *x as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    out.extend_from_slice(&(b << 16 | a).to_be_bytes());
+    out
+}
+
+/// Encode an image as an 8-bit RGBA PNG file
+fn encode_png<T: Type, C: Color>(image: &Image<T, C>) -> Vec<u8> {
+    let (width, height) = (image.width(), image.height());
+    let mut raw = Vec::with_capacity((width * 4 + 1) * height);
+    for y in 0..height {
+        raw.push(0);
+        for x in 0..width {
+            let px = image.get_pixel((x, y)).convert::<Rgba>();
+            raw.extend(px.iter().map(|x| byte(*x)));
+        }
+    }
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut chunk = |kind: &[u8], data: &[u8]| {
+        png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = png.len();
+        png.extend_from_slice(kind);
+        png.extend_from_slice(data);
+        let crc = crc32(&png[start..]);
+        png.extend_from_slice(&crc.to_be_bytes());
+    };
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    chunk(b"IHDR", &header);
+    chunk(b"IDAT", &zlib_stored(&raw));
+    chunk(b"IEND", &[]);
+    png
+}
+
+/// Render an image and its annotations as an SVG document, the image is embedded as a PNG
+pub fn to_svg<T: Type, C: Color>(image: &Image<T, C>, annotations: &[Annotation]) -> String {
+    let (width, height) = (image.width(), image.height());
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+        width, height
+    );
+    svg += &format!(
+        "  <image width=\"{}\" height=\"{}\" href=\"data:image/png;base64,{}\"/>\n",
+        width,
+        height,
+        base64(&encode_png(image))
+    );
+
+    for a in annotations {
+        let stroke = format!(
+            "stroke=\"{}\" stroke-opacity=\"{}\" stroke-width=\"{}\"",
+            svg_color(&a.stroke),
+            a.stroke[3],
+            a.line_width
+        );
+        let fill = match &a.fill {
+            Some(f) => format!("fill=\"{}\" fill-opacity=\"{}\"", svg_color(f), f[3]),
+            None => "fill=\"none\"".to_string(),
+        };
+        let text = |x: f64, y: f64, size: f64, anchor: &str, s: &str| {
+            format!(
+                "  <text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"{}\" text-anchor=\"{}\" fill=\"{}\" fill-opacity=\"{}\">{}</text>\n",
+                x,
+                y,
+                size,
+                anchor,
+                svg_color(&a.stroke),
+                a.stroke[3],
+                escape_xml(s)
+            )
+        };
+        let polyline = |points: &[(f64, f64)]| {
+            let points: Vec<String> = points.iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+            format!(
+                "  <polyline points=\"{}\" fill=\"none\" {}/>\n",
+                points.join(" "),
+                stroke
+            )
+        };
+
+        match &a.shape {
+            Shape::Line(p, q) => {
+                svg += &polyline(&[(p.x as f64, p.y as f64), (q.x as f64, q.y as f64)]);
+            }
+            Shape::Arrow(p, q) => {
+                svg += &polyline(&[(p.x as f64, p.y as f64), (q.x as f64, q.y as f64)]);
+                svg += &polyline(&arrow_head(*p, *q, a.line_width * HEAD_SIZE));
+            }
+            Shape::Rect(r) => {
+                svg += &format!(
+                    "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {} {}/>\n",
+                    r.origin.x, r.origin.y, r.size.width, r.size.height, fill, stroke
+                );
+            }
+            Shape::Ellipse(r) => {
+                svg += &format!(
+                    "  <ellipse cx=\"{}\" cy=\"{}\" rx=\"{}\" ry=\"{}\" {} {}/>\n",
+                    r.origin.x as f64 + r.size.width as f64 / 2.0,
+                    r.origin.y as f64 + r.size.height as f64 / 2.0,
+                    r.size.width as f64 / 2.0,
+                    r.size.height as f64 / 2.0,
+                    fill,
+                    stroke
+                );
+            }
+            Shape::Polygon(p) => {
+                let points: Vec<String> = p
+                    .points
+                    .iter()
+                    .map(|p| format!("{},{}", p.x, p.y))
+                    .collect();
+                svg += &format!(
+                    "  <polygon points=\"{}\" {} {}/>\n",
+                    points.join(" "),
+                    fill,
+                    stroke
+                );
+            }
+            Shape::Text {
+                position,
+                text: s,
+                size,
+            } => {
+                svg += &text(position.x as f64, position.y as f64, *size, "start", s);
+            }
+            Shape::Measurement { start, end, label } => {
+                svg += &polyline(&[
+                    (start.x as f64, start.y as f64),
+                    (end.x as f64, end.y as f64),
+                ]);
+                for tick in ticks(*start, *end, a.line_width * HEAD_SIZE) {
+                    svg += &polyline(&tick);
+                }
+                let (x, y) = (
+                    (start.x + end.x) as f64 / 2.0,
+                    (start.y + end.y) as f64 / 2.0 - a.line_width * 2.0,
+                );
+                svg += &text(x, y, a.line_width * LABEL_SIZE, "middle", label);
+            }
+        }
+    }
+
+    svg += "</svg>\n";
+    svg
+}
+
+fn escape_pdf(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_ascii())
+        .map(|c| match c {
+            '\\' | '(' | ')' => format!("\\{}", c),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Render an image and its annotations as a single page PDF document, one image pixel maps to
+/// one point on the page
+pub fn to_pdf<T: Type, C: Color>(image: &Image<T, C>, annotations: &[Annotation]) -> Vec<u8> {
+    let (width, height) = (image.width(), image.height());
+    let h = height as f64;
+
+    let mut pixels = Vec::with_capacity(width * height * 3);
+    image.each_pixel(|_, px| {
+        let px = px.convert::<Rgb>();
+        pixels.extend(px.iter().map(|x| byte(*x)));
+    });
+
+    // Page content, the PDF y axis points up
+    let mut content = format!("q {} 0 0 {} 0 0 cm /Im0 Do Q\n", width, height);
+    let mut states = String::new();
+    for (i, a) in annotations.iter().enumerate() {
+        let fill_alpha = a.fill.as_ref().map(|f| f[3]).unwrap_or(1.0);
+        states += &format!("/GS{} << /CA {} /ca {} >> ", i, a.stroke[3], fill_alpha);
+
+        let s = &a.stroke;
+        content += &format!(
+            "q /GS{} gs {} {} {} RG {} w 1 J 1 j\n",
+            i, s[0], s[1], s[2], a.line_width
+        );
+        if let Some(f) = &a.fill {
+            content += &format!("{} {} {} rg\n", f[0], f[1], f[2]);
+        }
+        let paint = if a.fill.is_some() { "B" } else { "S" };
+
+        let line = |points: &[(f64, f64)]| {
+            let mut ops = String::new();
+            for (j, (x, y)) in points.iter().enumerate() {
+                ops += &format!("{} {} {} ", x, h - y, if j == 0 { "m" } else { "l" });
+            }
+            ops + "S\n"
+        };
+        let text = |x: f64, y: f64, size: f64, s: &str| {
+            format!(
+                "BT /F1 {} Tf {} {} {} rg {} {} Td ({}) Tj ET\n",
+                size,
+                a.stroke[0],
+                a.stroke[1],
+                a.stroke[2],
+                x,
+                h - y,
+                escape_pdf(s)
+            )
+        };
+
+        match &a.shape {
+            Shape::Line(p, q) => {
+                content += &line(&[(p.x as f64, p.y as f64), (q.x as f64, q.y as f64)]);
+            }
+            Shape::Arrow(p, q) => {
+                content += &line(&[(p.x as f64, p.y as f64), (q.x as f64, q.y as f64)]);
+                content += &line(&arrow_head(*p, *q, a.line_width * HEAD_SIZE));
+            }
+            Shape::Rect(r) => {
+                content += &format!(
+                    "{} {} {} {} re {}\n",
+                    r.origin.x,
+                    h - r.origin.y as f64 - r.size.height as f64,
+                    r.size.width,
+                    r.size.height,
+                    paint
+                );
+            }
+            Shape::Ellipse(r) => {
+                // Four cubic Bezier arcs
+                const K: f64 = 0.552_284_749_8;
+                let (rx, ry) = (r.size.width as f64 / 2.0, r.size.height as f64 / 2.0);
+                let (cx, cy) = (r.origin.x as f64 + rx, h - r.origin.y as f64 - ry);
+                content += &format!("{} {} m\n", cx + rx, cy);
+                content += &format!(
+                    "{} {} {} {} {} {} c\n",
+                    cx + rx,
+                    cy + ry * K,
+                    cx + rx * K,
+                    cy + ry,
+                    cx,
+                    cy + ry
+                );
+                content += &format!(
+                    "{} {} {} {} {} {} c\n",
+                    cx - rx * K,
+                    cy + ry,
+                    cx - rx,
+                    cy + ry * K,
+                    cx - rx,
+                    cy
+                );
+                content += &format!(
+                    "{} {} {} {} {} {} c\n",
+                    cx - rx,
+                    cy - ry * K,
+                    cx - rx * K,
+                    cy - ry,
+                    cx,
+                    cy - ry
+                );
+                content += &format!(
+                    "{} {} {} {} {} {} c {}\n",
+                    cx + rx * K,
+                    cy - ry,
+                    cx + rx,
+                    cy - ry * K,
+                    cx + rx,
+                    cy,
+                    paint
+                );
+            }
+            Shape::Polygon(p) if !p.is_empty() => {
+                for (j, pt) in p.points.iter().enumerate() {
+                    content += &format!(
+                        "{} {} {} ",
+                        pt.x,
+                        h - pt.y as f64,
+                        if j == 0 { "m" } else { "l" }
+                    );
+                }
+                content += &format!("h {}\n", paint);
+            }
+            Shape::Polygon(_) => (),
+            Shape::Text {
+                position,
+                text: s,
+                size,
+            } => {
+                content += &text(position.x as f64, position.y as f64, *size, s);
+            }
+            Shape::Measurement { start, end, label } => {
+                content += &line(&[
+                    (start.x as f64, start.y as f64),
+                    (end.x as f64, end.y as f64),
+                ]);
+                for tick in ticks(*start, *end, a.line_width * HEAD_SIZE) {
+                    content += &line(&tick);
+                }
+
+                // Helvetica glyphs average about half the font size in width
+                let size = a.line_width * LABEL_SIZE;
+                let x = (start.x + end.x) as f64 / 2.0 - label.len() as f64 * size * 0.25;
+                let y = (start.y + end.y) as f64 / 2.0 - a.line_width * 2.0;
+                content += &text(x, y, size, label);
+            }
+        }
+        content += "Q\n";
+    }
+
+    let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::new();
+    let mut object = |pdf: &mut Vec<u8>, body: &[u8]| {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", offsets.len()).as_bytes());
+        pdf.extend_from_slice(body);
+        pdf.extend_from_slice(b"\nendobj\n");
+    };
+
+    object(&mut pdf, b"<< /Type /Catalog /Pages 2 0 R >>");
+    object(&mut pdf, b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>");
+    object(
+        &mut pdf,
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents 4 0 R /Resources << /XObject << /Im0 5 0 R >> /Font << /F1 6 0 R >> /ExtGState << {}>> >> >>",
+            width, height, states
+        )
+        .as_bytes(),
+    );
+
+    let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+    stream.extend_from_slice(content.as_bytes());
+    stream.extend_from_slice(b"\nendstream");
+    object(&mut pdf, &stream);
+
+    let mut stream = format!(
+        "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Length {} >>\nstream\n",
+        width,
+        height,
+        pixels.len()
+    )
+    .into_bytes();
+    stream.extend_from_slice(&pixels);
+    stream.extend_from_slice(b"\nendstream");
+    object(&mut pdf, &stream);
+
+    object(
+        &mut pdf,
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>",
+    );
+
+    let xref = pdf.len();
+    let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1);
+    for offset in &offsets {
+        trailer += &format!("{:010} 00000 n \n", offset);
+    }
+    trailer += &format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        offsets.len() + 1,
+        xref
+    );
+    pdf.extend_from_slice(trailer.as_bytes());
+    pdf
+}
+
+/// Write an image and its annotations to an SVG file
+pub fn save_svg<T: Type, C: Color>(
+    path: impl AsRef<std::path::Path>,
+    image: &Image<T, C>,
+    annotations: &[Annotation],
+) -> Result<(), Error> {
+    std::fs::write(path, to_svg(image, annotations))?;
+    Ok(())
+}
+
+/// Write an image and its annotations to a PDF file
+pub fn save_pdf<T: Type, C: Color>(
+    path: impl AsRef<std::path::Path>,
+    image: &Image<T, C>,
+    annotations: &[Annotation],
+) -> Result<(), Error> {
+    std::fs::write(path, to_pdf(image, annotations))?;
+    Ok(())
+}
+
+/// Write an image and its annotations to an SVG or PDF file, depending on the file extension
+pub fn save<T: Type, C: Color>(
+    path: impl AsRef<std::path::Path>,
+    image: &Image<T, C>,
+    annotations: &[Annotation],
+) -> Result<(), Error> {
+    let path = path.as_ref();
+    let ext = path
+        .extension()
+        .and_then(|x| x.to_str())
+        .map(|x| x.to_ascii_lowercase());
+    match ext.as_deref() {
+        Some("svg") => save_svg(path, image, annotations),
+        Some("pdf") => save_pdf(path, image, annotations),
+        _ => Err(Error::UnableToWriteImage(format!(
+            "{}: expected an svg or pdf extension",
+            path.display()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use annotate::Annotation;
+
+    #[test]
+    fn test_annotate_export() {
+        let mut image = Image::<u8, Rgb>::new((8, 4));
+        image.set((1, 1), [255u8, 128, 0]);
+        let annotations = vec![
+            Annotation::rect(Region::new(Point::new(1, 1), Size::new(4, 2)))
+                .with_fill(Pixel::from(vec![0.0, 0.0, 1.0, 0.5])),
+            Annotation::ellipse(Region::new(Point::new(0, 0), Size::new(8, 4))),
+            Annotation::arrow((0, 0), (7, 3)),
+            Annotation::text((1, 3), "a < b & (c)", 2.0),
+            Annotation::measurement((0, 2), (7, 2), Some(25.4)),
+        ];
+
+        let svg = annotate::to_svg(&image, &annotations);
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert!(svg.contains("data:image/png;base64,iVBORw0KGgo"));
+        assert!(svg.contains("a &lt; b &amp; (c)"));
+        assert!(svg.contains("7.0 mm"));
+        assert_eq!(svg.matches("<rect").count(), 1);
+
+        let pdf = annotate::to_pdf(&image, &annotations);
+        assert!(pdf.starts_with(b"%PDF-1.4"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/MediaBox [0 0 8 4]"));
+        assert!(text.contains("(a < b & \\(c\\)) Tj"));
+
+        // The xref table points at each object
+        let tail = String::from_utf8_lossy(&pdf[pdf.len() - 32..]).to_string();
+        let start: usize = tail
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|x| x.lines().next())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(pdf[start..].starts_with(b"xref"));
+        let xref = String::from_utf8_lossy(&pdf[start..]).to_string();
+        let first: usize = xref.lines().nth(3).unwrap()[..10].parse().unwrap();
+        assert!(pdf[first..].starts_with(b"1 0 obj"));
+    }
+}
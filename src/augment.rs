@@ -0,0 +1,303 @@
+use crate::*;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Random transform applied by `Augment`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Augmentation {
+    /// Crop a randomly positioned area of the given size, images that are already small enough
+    /// are left unchanged
+    Crop(Size),
+
+    /// Mirror the image horizontally with the given probability
+    FlipHorizontal(f64),
+
+    /// Mirror the image vertically with the given probability
+    FlipVertical(f64),
+
+    /// Rotate around the center by a random angle of up to the given number of degrees in
+    /// either direction, the image size is preserved and uncovered areas are left empty
+    Rotate(f64),
+
+    /// Multiply brightness, contrast and saturation by random factors within the given amount
+    /// of 1, and rotate hue by up to the given number of degrees
+    ColorJitter {
+        /// Maximum brightness change
+        brightness: f64,
+
+        /// Maximum contrast change
+        contrast: f64,
+
+        /// Maximum saturation change
+        saturation: f64,
+
+        /// Maximum hue rotation in degrees
+        hue: f64,
+    },
+
+    /// Clear the given number of randomly positioned rectangles
+    Cutout {
+        /// Size of each rectangle
+        size: Size,
+
+        /// Number of rectangles
+        count: usize,
+    },
+
+    /// Gaussian blur with a random standard deviation of up to the given value
+    Blur(f64),
+}
+
+impl Augmentation {
+    /// Apply the transform to an image using values drawn from `rng`
+    pub fn apply<T: Type, C: Color>(&self, image: Image<T, C>, rng: &mut Rng) -> Image<T, C> {
+        let (width, height) = (image.width(), image.height());
+        match *self {
+            Augmentation::Crop(size) => {
+                if size.width >= width && size.height >= height {
+                    return image;
+                }
+                let size = Size::new(size.width.min(width), size.height.min(height));
+                let origin = Point::new(
+                    rng.below(width - size.width + 1),
+                    rng.below(height - size.height + 1),
+                );
+                image.crop(Region::new(origin, size))
+            }
+            Augmentation::FlipHorizontal(p) => {
+                if rng.next_f64() >= p {
+                    return image;
+                }
+                remap(&image, |x, y| (width as f64 - 1.0 - x, y))
+            }
+            Augmentation::FlipVertical(p) => {
+                if rng.next_f64() >= p {
+                    return image;
+                }
+                remap(&image, |x, y| (x, height as f64 - 1.0 - y))
+            }
+            Augmentation::Rotate(max) => {
+                let angle = rng.range(-max, max).to_radians();
+                if angle == 0.0 {
+                    return image;
+                }
+                let (sin, cos) = angle.sin_cos();
+                let (cx, cy) = ((width as f64 - 1.0) / 2.0, (height as f64 - 1.0) / 2.0);
+                remap(&image, |x, y| {
+                    let (dx, dy) = (x - cx, y - cy);
+                    (cx + dx * cos + dy * sin, cy - dx * sin + dy * cos)
+                })
+            }
+            Augmentation::ColorJitter {
+                brightness,
+                contrast,
+                saturation,
+                hue,
+            } => {
+                let b = rng.range(1.0 - brightness, 1.0 + brightness).max(0.0);
+                let c = rng.range(1.0 - contrast, 1.0 + contrast).max(0.0);
+                let s = rng.range(1.0 - saturation, 1.0 + saturation).max(0.0);
+                let h = rng.range(-hue, hue);
+                image
+                    .run::<T, C>(filter::brightness(b), None)
+                    .run::<T, C>(filter::contrast(c), None)
+                    .run::<T, C>(filter::saturation(s), None)
+                    .run(filter::HueRotate(h), None)
+            }
+            Augmentation::Cutout { size, count } => {
+                let mut image = image;
+                for _ in 0..count {
+                    let size = Size::new(size.width.min(width), size.height.min(height));
+                    let origin = Point::new(
+                        rng.below(width - size.width + 1),
+                        rng.below(height - size.height + 1),
+                    );
+                    image.for_each_region(Region::new(origin, size), |_, mut px| {
+                        px.as_mut().fill(T::from_f64(0.0))
+                    });
+                }
+                image
+            }
+            Augmentation::Blur(max) => {
+                let sigma = rng.range(0.0, max);
+                if sigma < 0.1 {
+                    return image;
+                }
+                image.run::<T, C>(filter::gaussian_blur(sigma), None)
+            }
+        }
+    }
+}
+
+/// Create an image of the same size where each pixel is sampled from `image` at the position
+/// returned by `f`
+fn remap<T: Type, C: Color>(
+    image: &Image<T, C>,
+    f: impl Sync + Send + Fn(f64, f64) -> (f64, f64),
+) -> Image<T, C> {
+    let mut dest = image.new_like();
+    dest.each_pixel_mut(|pt, px| {
+        let (x, y) = f(pt.x as f64, pt.y as f64);
+        px.copy_from(&image.sample(x, y, Interpolation::Bilinear));
+    });
+    dest
+}
+
+/// Data augmentation pipeline, applies a sequence of random transforms to produce variations of
+/// training images. The random values only depend on the seed and the index of the image, so a
+/// dataset can be regenerated exactly and batches can be processed in parallel.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Augment {
+    /// Transforms, applied in order
+    pub steps: Vec<Augmentation>,
+
+    /// Random seed
+    pub seed: Seed,
+}
+
+impl Augment {
+    /// Create an empty pipeline
+    pub fn new() -> Augment {
+        Augment::default()
+    }
+
+    /// Set the random seed
+    pub fn with_seed(mut self, seed: impl Into<Seed>) -> Augment {
+        self.seed = seed.into();
+        self
+    }
+
+    /// Append a transform
+    pub fn then(mut self, step: Augmentation) -> Augment {
+        self.steps.push(step);
+        self
+    }
+
+    /// Append a random crop
+    pub fn crop(self, width: usize, height: usize) -> Augment {
+        self.then(Augmentation::Crop(Size::new(width, height)))
+    }
+
+    /// Append a random horizontal flip
+    pub fn flip_horizontal(self, probability: f64) -> Augment {
+        self.then(Augmentation::FlipHorizontal(probability))
+    }
+
+    /// Append a random vertical flip
+    pub fn flip_vertical(self, probability: f64) -> Augment {
+        self.then(Augmentation::FlipVertical(probability))
+    }
+
+    /// Append a random rotation
+    pub fn rotate(self, max_degrees: f64) -> Augment {
+        self.then(Augmentation::Rotate(max_degrees))
+    }
+
+    /// Append random color changes
+    pub fn color_jitter(
+        self,
+        brightness: f64,
+        contrast: f64,
+        saturation: f64,
+        hue: f64,
+    ) -> Augment {
+        self.then(Augmentation::ColorJitter {
+            brightness,
+            contrast,
+            saturation,
+            hue,
+        })
+    }
+
+    /// Append random cutouts
+    pub fn cutout(self, width: usize, height: usize, count: usize) -> Augment {
+        self.then(Augmentation::Cutout {
+            size: Size::new(width, height),
+            count,
+        })
+    }
+
+    /// Append a random blur
+    pub fn blur(self, max_sigma: f64) -> Augment {
+        self.then(Augmentation::Blur(max_sigma))
+    }
+
+    /// Apply every transform to an image, `index` selects the variation so different indices
+    /// produce independent results
+    pub fn apply<T: Type, C: Color>(&self, image: &Image<T, C>, index: u64) -> Image<T, C> {
+        let mut rng = self.seed.derive(index).rng();
+        self.steps
+            .iter()
+            .fold(image.clone(), |image, step| step.apply(image, &mut rng))
+    }
+
+    /// Apply the pipeline to a batch of images, the image at position `i` uses index `i`
+    pub fn apply_batch<T: Type, C: Color>(&self, images: &[Image<T, C>]) -> Vec<Image<T, C>> {
+        #[cfg(feature = "parallel")]
+        let iter = images.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let iter = images.iter();
+
+        iter.enumerate()
+            .map(|(i, image)| self.apply(image, i as u64))
+            .collect()
+    }
+}
+
+impl Seeded for Augment {
+    fn seed(&self) -> Seed {
+        self.seed
+    }
+
+    fn set_seed(&mut self, seed: Seed) {
+        self.seed = seed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use augment::{Augment, Augmentation};
+
+    #[test]
+    fn test_augment() {
+        let mut image = Image::<f32, Rgb>::new((32, 24));
+        image.for_each(|pt, mut px| {
+            px[0] = pt.x as f32 / 31.0;
+            px[1] = pt.y as f32 / 23.0;
+            px[2] = 0.5;
+        });
+
+        let augment = Augment::new()
+            .with_seed(7)
+            .crop(16, 16)
+            .flip_horizontal(0.5)
+            .rotate(10.0)
+            .color_jitter(0.2, 0.2, 0.2, 10.0)
+            .cutout(4, 4, 2)
+            .blur(1.0);
+
+        let batch = augment.apply_batch(&[image.clone(), image.clone(), image.clone()]);
+        assert!(batch.iter().all(|im| im.size() == Size::new(16, 16)));
+        assert!(batch[0] == augment.apply(&image, 0));
+        assert!(batch[0] != batch[1]);
+        assert!(augment.clone().with_seed(8).apply(&image, 0) != batch[0]);
+
+        // Flipping twice restores the image
+        let mut rng = Seed(1).rng();
+        let flip = Augmentation::FlipHorizontal(1.0);
+        let flipped = flip.apply(image.clone(), &mut rng);
+        assert!((flipped.get_f((0, 5), 0) - 1.0).abs() < 1e-6);
+        assert!(flip.apply(flipped, &mut rng) == image);
+
+        let cutout = Augmentation::Cutout {
+            size: Size::new(40, 40),
+            count: 1,
+        };
+        let cleared = cutout.apply(image.clone(), &mut rng);
+        assert!(cleared.data().iter().all(|x| *x == 0.0));
+    }
+}
@@ -0,0 +1,515 @@
+use image2::*;
+
+const USAGE: &str = "image2 - command line image processing
+
+Usage:
+    image2 [options] convert <input> <output>
+    image2 [options] resize <input> <output> <width>x<height>
+    image2 [options] crop <input> <output> <x>,<y>,<width>x<height>
+    image2 [options] filter <input> <output> <name> [args...]
+    image2 [options] info <input>
+    image2 [options] diff <a> <b> [output]
+    image2 [options] view <input>
+    image2 filters
+
+Options:
+    --color <gray|rgb|rgba>          Color used to process images (default: rgb)
+    --type <u8|u16|f16|f32|f64>      Output data type (default: f32)
+    -h, --help                       Show this message";
+
+type RunFilter<C> = fn(&Image<f32, C>, &Params) -> Result<Image<f32, C>, Error>;
+
+/// Filter available to the `filter` command
+struct FilterDef<C: Color> {
+    name: &'static str,
+    params: &'static str,
+    run: RunFilter<C>,
+}
+
+/// Registry of the filters available to the `filter` command, used both to look them up and to list
+/// them in `image2 filters`
+fn filters<C: Color>() -> Vec<FilterDef<C>> {
+    fn def<C: Color>(name: &'static str, params: &'static str, run: RunFilter<C>) -> FilterDef<C> {
+        FilterDef { name, params, run }
+    }
+
+    vec![
+        def("noop", "", |image, _| Ok(apply(image, filter::noop()))),
+        def("invert", "", |image, _| Ok(apply(image, filter::invert()))),
+        def("clamp", "", |image, _| Ok(apply(image, filter::clamp()))),
+        def("brightness", "<amount>", |image, p| {
+            Ok(apply(image, filter::brightness(p.arg(0)?)))
+        }),
+        def("saturation", "<amount>", |image, p| {
+            Ok(apply(image, filter::saturation(p.arg(0)?)))
+        }),
+        def("contrast", "<amount>", |image, p| {
+            Ok(apply(image, filter::contrast(p.arg(0)?)))
+        }),
+        def("exposure", "<stops>", |image, p| {
+            Ok(apply(image, filter::exposure(p.arg(0)?)))
+        }),
+        def("gamma-log", "[gamma]", |image, p| {
+            Ok(apply(image, filter::gamma_log(p.opt(0)?)))
+        }),
+        def("gamma-lin", "[gamma]", |image, p| {
+            Ok(apply(image, filter::gamma_lin(p.opt(0)?)))
+        }),
+        def(
+            "normalize",
+            "<min> <max> <new-min> <new-max>",
+            |image, p| {
+                let f = filter::normalize(p.arg(0)?, p.arg(1)?, p.arg(2)?, p.arg(3)?);
+                Ok(apply(image, f))
+            },
+        ),
+        def("rotate90", "", |image, _| {
+            let size = image.size();
+            let flipped = Size::new(size.height, size.width);
+            Ok(image.run(filter::rotate90(size, flipped), Some(Meta::new(flipped))))
+        }),
+        def("rotate180", "", |image, _| {
+            Ok(apply(image, filter::rotate180(image.size())))
+        }),
+        def("rotate270", "", |image, _| {
+            let size = image.size();
+            let flipped = Size::new(size.height, size.width);
+            Ok(image.run(filter::rotate270(size, flipped), Some(Meta::new(flipped))))
+        }),
+    ]
+}
+
+/// Arguments passed to a filter
+struct Params<'a> {
+    name: &'a str,
+    values: &'a [String],
+}
+
+impl<'a> Params<'a> {
+    fn arg(&self, i: usize) -> Result<f64, Error> {
+        match self.values.get(i) {
+            Some(x) => number(x),
+            None => Err(Error::Message(format!(
+                "missing argument {} for filter {}",
+                i + 1,
+                self.name
+            ))),
+        }
+    }
+
+    fn opt(&self, i: usize) -> Result<Option<f64>, Error> {
+        self.values.get(i).map(|x| number(x)).transpose()
+    }
+}
+
+struct Args {
+    color: String,
+    ty: String,
+    positional: Vec<String>,
+}
+
+impl Args {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Option<Args>, Error> {
+        let mut dest = Args {
+            color: "rgb".into(),
+            ty: "f32".into(),
+            positional: Vec::new(),
+        };
+
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--color" => dest.color = Self::value(&arg, args.next())?,
+                "--type" => dest.ty = Self::value(&arg, args.next())?,
+                _ => dest.positional.push(arg),
+            }
+        }
+
+        if dest.positional.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(dest))
+    }
+
+    fn value(name: &str, value: Option<String>) -> Result<String, Error> {
+        value.ok_or_else(|| Error::Message(format!("missing value for {}", name)))
+    }
+
+    fn get(&self, index: usize, name: &str) -> Result<&str, Error> {
+        self.positional
+            .get(index)
+            .map(|x| x.as_str())
+            .ok_or_else(|| Error::Message(format!("missing argument: {}", name)))
+    }
+}
+
+fn number(s: &str) -> Result<f64, Error> {
+    s.parse()
+        .map_err(|_| Error::Message(format!("invalid number: {}", s)))
+}
+
+fn integer(s: &str) -> Result<usize, Error> {
+    s.parse()
+        .map_err(|_| Error::Message(format!("invalid integer: {}", s)))
+}
+
+/// Parse `<width>x<height>`
+fn size(s: &str) -> Result<Size, Error> {
+    match s.split_once('x') {
+        Some((w, h)) => Ok(Size::new(integer(w)?, integer(h)?)),
+        None => Err(Error::Message(format!("invalid size: {}", s))),
+    }
+}
+
+/// Parse `<x>,<y>,<width>x<height>`
+fn region(s: &str) -> Result<Region, Error> {
+    let parts: Vec<&str> = s.splitn(3, ',').collect();
+    match parts.as_slice() {
+        [x, y, s] => Ok(Region::new(Point::new(integer(x)?, integer(y)?), size(s)?)),
+        _ => Err(Error::Message(format!("invalid region: {}", s))),
+    }
+}
+
+fn save<C: Color>(args: &Args, image: &Image<f32, C>, path: &str) -> Result<(), Error> {
+    match args.ty.as_str() {
+        "u8" => image.convert::<u8, C>().save(path),
+        "u16" => image.convert::<u16, C>().save(path),
+        "f16" => image.convert::<f16, C>().save(path),
+        "f32" => image.save(path),
+        "f64" => image.convert::<f64, C>().save(path),
+        t => Err(Error::Message(format!("unknown type: {}", t))),
+    }
+}
+
+fn apply<C: Color>(image: &Image<f32, C>, filter: impl Filter<f32, C>) -> Image<f32, C> {
+    image.run(filter, None)
+}
+
+fn run_filter<C: Color>(
+    image: &Image<f32, C>,
+    name: &str,
+    params: &[String],
+) -> Result<Image<f32, C>, Error> {
+    let filter = filters::<C>()
+        .into_iter()
+        .find(|f| f.name == name)
+        .ok_or_else(|| {
+            Error::Message(format!(
+                "unknown filter: {}, use `image2 filters` to list the available filters",
+                name
+            ))
+        })?;
+    (filter.run)(
+        image,
+        &Params {
+            name,
+            values: params,
+        },
+    )
+}
+
+/// Run the `convert`, `resize`, `crop` and `filter` commands on an image that has already been
+/// loaded
+fn process<C: Color>(args: &Args, image: Image<f32, C>) -> Result<Image<f32, C>, Error> {
+    let command = args.get(0, "command")?;
+    match command {
+        "convert" => Ok(image),
+        "resize" => {
+            let size = size(args.get(3, "size")?)?;
+            Ok(image.run(filter::Resize::new(size), Some(Meta::new(size))))
+        }
+        "crop" => {
+            let roi = region(args.get(3, "region")?)?;
+            let bounds = Region::new(Point::zero(), image.size());
+            if !bounds.contains_rect(&roi) {
+                return Err(Error::Message(format!(
+                    "crop region is outside of the image: {}x{}",
+                    image.width(),
+                    image.height()
+                )));
+            }
+            Ok(image.crop(roi))
+        }
+        "filter" => {
+            let name = args.get(3, "name")?;
+            run_filter(&image, name, &args.positional[4..])
+        }
+        _ => Err(Error::Message(format!("unknown command: {}", command))),
+    }
+}
+
+fn info<C: Color>(path: &str) -> Result<(), Error> {
+    let (image, source) = Image::<f32, C>::open_with_info(path)?;
+    println!("size: {}x{}", image.width(), image.height());
+    println!("channels: {}", source.channels.join(","));
+    println!("format: {:?}", source.base_type);
+    println!("bits per sample: {}", source.bits_per_sample);
+    if let Some(compression) = &source.compression {
+        println!("compression: {}", compression);
+    }
+
+    #[cfg(feature = "oiio")]
+    {
+        let input = io::oiio::ImageInput::open(path, None)?;
+        for (key, value) in input.spec().attrs() {
+            println!("{}: {:?}", key, value);
+        }
+    }
+
+    println!("hash: {}", image.hash());
+    println!("average: {:?}", image.average_color().as_ref());
+    Ok(())
+}
+
+/// Per-pixel absolute difference between two images of the same size
+struct Diff<C: Color> {
+    output: Image<f32, C>,
+    max: f32,
+    mean: f64,
+    mse: f64,
+}
+
+impl<C: Color> Diff<C> {
+    fn new(a: &Image<f32, C>, b: &Image<f32, C>) -> Option<Diff<C>> {
+        if a.size() != b.size() {
+            return None;
+        }
+
+        let mut output = a.clone();
+        output.for_each2(b, |_, mut dest, src| {
+            for (d, s) in dest.as_slice_mut().iter_mut().zip(src.as_slice()) {
+                *d = (*d - s).abs();
+            }
+        });
+
+        let n = output.data().len().max(1) as f64;
+        let max = output.data().iter().fold(0.0f32, |acc, x| acc.max(*x));
+        let mse = output
+            .data()
+            .iter()
+            .map(|x| (*x as f64) * (*x as f64))
+            .sum::<f64>()
+            / n;
+        let mean = output.data().iter().map(|x| *x as f64).sum::<f64>() / n;
+        Some(Diff {
+            output,
+            max,
+            mean,
+            mse,
+        })
+    }
+}
+
+fn diff<C: Color>(args: &Args) -> Result<bool, Error> {
+    let a = Image::<f32, C>::open(args.get(1, "a")?)?;
+    let b = Image::<f32, C>::open(args.get(2, "b")?)?;
+    let diff = match Diff::new(&a, &b) {
+        Some(diff) => diff,
+        None => {
+            println!(
+                "size mismatch: {}x{} != {}x{}",
+                a.width(),
+                a.height(),
+                b.width(),
+                b.height()
+            );
+            return Ok(false);
+        }
+    };
+
+    println!("max: {}", diff.max);
+    println!("mean: {}", diff.mean);
+    if diff.mse > 0.0 {
+        println!("psnr: {:.2}", 10.0 * (1.0 / diff.mse).log10());
+    } else {
+        println!("psnr: inf");
+    }
+    println!("hash distance: {}", a.hash().diff(&b.hash()));
+
+    if let Some(path) = args.positional.get(3) {
+        save(args, &diff.output, path)?;
+    }
+
+    Ok(diff.max == 0.0)
+}
+
+fn view(path: &str) -> Result<(), Error> {
+    #[cfg(feature = "window")]
+    {
+        window::show_progressive::<f32, Rgba, _>(path, 64, |_, _| Ok(()))?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "window"))]
+    {
+        let _ = path;
+        Err(Error::Message(
+            "view requires the `window` feature".to_string(),
+        ))
+    }
+}
+
+fn run<C: Color>(args: &Args) -> Result<bool, Error> {
+    let command = args.get(0, "command")?;
+    match command {
+        "convert" | "resize" | "crop" | "filter" => {
+            let image = Image::<f32, C>::open(args.get(1, "input")?)?;
+            let output = process(args, image)?;
+            save(args, &output, args.get(2, "output")?)?;
+        }
+        "info" => info::<C>(args.get(1, "input")?)?,
+        "diff" => return diff::<C>(args),
+        "view" => view(args.get(1, "input")?)?,
+        "filters" => {
+            for f in filters::<C>() {
+                println!("{} {}", f.name, f.params);
+            }
+        }
+        _ => return Err(Error::Message(format!("unknown command: {}", command))),
+    }
+
+    Ok(true)
+}
+
+fn main() {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let result = match args.color.as_str() {
+        "gray" => run::<Gray>(&args),
+        "rgb" => run::<Rgb>(&args),
+        "rgba" => run::<Rgba>(&args),
+        c => Err(Error::Message(format!("unknown color: {}", c))),
+    };
+
+    match result {
+        Ok(true) => (),
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Args {
+        Args::parse(s.split_whitespace().map(String::from))
+            .unwrap()
+            .unwrap()
+    }
+
+    fn gradient() -> Image<f32, Rgb> {
+        let mut image = Image::<f32, Rgb>::new((8, 4));
+        image.for_each(|pt, mut px| {
+            px.copy_from_slice([pt.x as f32 / 8.0, pt.y as f32 / 4.0, 0.5]);
+        });
+        image
+    }
+
+    #[test]
+    fn test_parse() {
+        let a = args("--color gray resize in.png out.png 4x2 --type u8");
+        assert_eq!(a.color, "gray");
+        assert_eq!(a.ty, "u8");
+        assert_eq!(a.positional, ["resize", "in.png", "out.png", "4x2"]);
+
+        assert!(Args::parse(["--help".to_string()].into_iter())
+            .unwrap()
+            .is_none());
+        assert!(Args::parse(std::iter::empty()).unwrap().is_none());
+        assert!(Args::parse(["--color".to_string()].into_iter()).is_err());
+
+        assert_eq!(size("4x2").unwrap(), Size::new(4, 2));
+        assert!(size("4").is_err());
+        assert!(size("4xa").is_err());
+        assert_eq!(
+            region("1,2,3x4").unwrap(),
+            Region::new(Point::new(1, 2), Size::new(3, 4))
+        );
+        assert!(region("1,2").is_err());
+    }
+
+    #[test]
+    fn test_filters() {
+        let image = gradient();
+        let filters = filters::<Rgb>();
+
+        // Every registered filter can be looked up by name
+        for f in &filters {
+            let params: Vec<String> = f
+                .params
+                .split_whitespace()
+                .filter(|p| p.starts_with('<'))
+                .map(|_| "1".to_string())
+                .collect();
+            assert!(run_filter(&image, f.name, &params).is_ok(), "{}", f.name);
+        }
+
+        let mut names: Vec<&str> = filters.iter().map(|f| f.name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), filters.len());
+
+        assert!(run_filter(&image, "unknown", &[]).is_err());
+        assert!(run_filter(&image, "brightness", &[]).is_err());
+        assert!(run_filter(&image, "brightness", &["a".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_process() {
+        let image = gradient();
+
+        let output = process(&args("convert in out"), image.clone()).unwrap();
+        assert!(Diff::new(&image, &output).unwrap().max == 0.0);
+
+        let output = process(&args("filter in out invert"), image.clone()).unwrap();
+        assert!((output.get_f((2, 1), 0) - 0.75).abs() < 1e-6);
+        assert!((output.get_f((2, 1), 1) - 0.75).abs() < 1e-6);
+
+        let output = process(&args("filter in out rotate90"), image.clone()).unwrap();
+        assert_eq!(output.size(), Size::new(4, 8));
+
+        let output = process(&args("crop in out 2,1,3x2"), image.clone()).unwrap();
+        assert_eq!(output.size(), Size::new(3, 2));
+        assert_eq!(output.get_f((0, 0), 0), image.get_f((2, 1), 0));
+        assert!(process(&args("crop in out 6,0,4x4"), image.clone()).is_err());
+
+        // Downscaling area-averages instead of point sampling
+        let mut checker = Image::<f32, Rgb>::new((8, 4));
+        checker.for_each(|pt, mut px| px.as_slice_mut().fill(((pt.x + pt.y) % 2) as f32));
+        let output = process(&args("resize in out 4x2"), checker).unwrap();
+        assert_eq!(output.size(), Size::new(4, 2));
+        output.each_pixel(|_, px| assert!((px[0] - 0.5).abs() < 1e-6));
+
+        assert!(process(&args("resize in out 4"), image.clone()).is_err());
+        assert!(process(&args("unknown in out"), image).is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        let a = gradient();
+        let mut b = a.clone();
+        b.set_f((1, 1), 2, 0.75);
+
+        let diff = Diff::new(&a, &b).unwrap();
+        assert!((diff.max - 0.25).abs() < 1e-6);
+        assert!((diff.output.get_f((1, 1), 2) - 0.25).abs() < 1e-6);
+        assert!(diff.mse > 0.0);
+        assert!(Diff::new(&a, &a.crop(Region::new(Point::zero(), Size::new(2, 2)))).is_none());
+    }
+}
@@ -0,0 +1,450 @@
+use crate::*;
+
+/// Binary image storing one bit per pixel, useful for large masks. Each row is padded to a
+/// whole number of 64-bit words so logical operations can process 64 pixels at a time.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BitImage {
+    size: Size,
+    stride: usize,
+    words: Vec<u64>,
+}
+
+impl BitImage {
+    /// Create a new image with every pixel unset
+    pub fn new(size: impl Into<Size>) -> BitImage {
+        let size = size.into();
+        let stride = size.width.div_ceil(64);
+        BitImage {
+            size,
+            stride,
+            words: vec![0; stride * size.height],
+        }
+    }
+
+    /// Create from the first channel of an image, pixels with a normalized value >= 0.5 are set
+    pub fn from_image<T: Type, C: Color>(image: &Image<T, C>) -> BitImage {
+        let mut bits = BitImage::new(image.size());
+        let channels = C::CHANNELS;
+        for (y, row) in image
+            .data()
+            .chunks_exact(image.width() * channels)
+            .enumerate()
+        {
+            for (x, px) in row.chunks_exact(channels).enumerate() {
+                if px[0].to_norm() >= 0.5 {
+                    bits.words[y * bits.stride + x / 64] |= 1 << (x % 64);
+                }
+            }
+        }
+        bits
+    }
+
+    /// Convert to a grayscale image, set pixels have the maximum value
+    pub fn to_image<T: Type>(&self) -> Image<T, Gray> {
+        let mut image = Image::new(self.size);
+        let width = self.size.width;
+        for (i, px) in image.data_mut().iter_mut().enumerate() {
+            if self.get((i % width, i / width)) {
+                *px = T::from_norm(1.0);
+            }
+        }
+        image
+    }
+
+    /// Image size
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// Image width
+    pub fn width(&self) -> usize {
+        self.size.width
+    }
+
+    /// Image height
+    pub fn height(&self) -> usize {
+        self.size.height
+    }
+
+    /// Number of 64-bit words in each row
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Packed pixel data in raster order, the least significant bit of each word is the leftmost
+    /// pixel and the padding bits at the end of each row are always unset
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Returns true when the pixel at `pt` is set, pixels outside the image are unset
+    pub fn get(&self, pt: impl Into<Point>) -> bool {
+        let pt = pt.into();
+        if pt.x >= self.size.width || pt.y >= self.size.height {
+            return false;
+        }
+        self.words[pt.y * self.stride + pt.x / 64] & (1 << (pt.x % 64)) != 0
+    }
+
+    /// Set or unset the pixel at `pt`
+    pub fn set(&mut self, pt: impl Into<Point>, value: bool) {
+        let pt = pt.into();
+        let word = &mut self.words[pt.y * self.stride + pt.x / 64];
+        if value {
+            *word |= 1 << (pt.x % 64);
+        } else {
+            *word &= !(1 << (pt.x % 64));
+        }
+    }
+
+    /// Set or unset every pixel
+    pub fn fill(&mut self, value: bool) {
+        self.words.fill(if value { u64::MAX } else { 0 });
+        if value {
+            self.clear_padding();
+        }
+    }
+
+    /// Number of pixels that are set
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Iterate over the positions of the pixels that are set in raster order
+    pub fn points(&self) -> impl '_ + Iterator<Item = Point> {
+        self.words.iter().enumerate().flat_map(move |(i, word)| {
+            let (y, x0) = (i / self.stride, (i % self.stride) * 64);
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(Point::new(x0 + bit, y))
+            })
+        })
+    }
+
+    /// Invert every pixel
+    pub fn invert(&mut self) {
+        self.words.iter_mut().for_each(|w| *w = !*w);
+        self.clear_padding();
+    }
+
+    fn clear_padding(&mut self) {
+        let rem = self.size.width % 64;
+        if rem == 0 {
+            return;
+        }
+        let mask = (1u64 << rem) - 1;
+        for row in self.words.chunks_exact_mut(self.stride) {
+            row[self.stride - 1] &= mask;
+        }
+    }
+
+    fn zip_with(&mut self, other: &BitImage, f: impl Fn(u64, u64) -> u64) {
+        assert_eq!(self.size, other.size, "BitImage sizes must match");
+        for (a, b) in self.words.iter_mut().zip(other.words.iter()) {
+            *a = f(*a, *b);
+        }
+    }
+}
+
+impl<T: Type, C: Color> From<&Image<T, C>> for BitImage {
+    fn from(image: &Image<T, C>) -> BitImage {
+        BitImage::from_image(image)
+    }
+}
+
+macro_rules! bit_op {
+    ($op:ident, $f:ident, $assign:ident, $assign_f:ident, $x:tt) => {
+        impl std::ops::$assign<&BitImage> for BitImage {
+            fn $assign_f(&mut self, other: &BitImage) {
+                self.zip_with(other, |a, b| a $x b);
+            }
+        }
+
+        impl std::ops::$op<&BitImage> for &BitImage {
+            type Output = BitImage;
+
+            fn $f(self, other: &BitImage) -> BitImage {
+                let mut dest = self.clone();
+                std::ops::$assign::$assign_f(&mut dest, other);
+                dest
+            }
+        }
+
+        impl std::ops::$op<&BitImage> for BitImage {
+            type Output = BitImage;
+
+            fn $f(mut self, other: &BitImage) -> BitImage {
+                std::ops::$assign::$assign_f(&mut self, other);
+                self
+            }
+        }
+    };
+}
+
+bit_op!(BitAnd, bitand, BitAndAssign, bitand_assign, &);
+bit_op!(BitOr, bitor, BitOrAssign, bitor_assign, |);
+bit_op!(BitXor, bitxor, BitXorAssign, bitxor_assign, ^);
+
+impl std::ops::Not for &BitImage {
+    type Output = BitImage;
+
+    fn not(self) -> BitImage {
+        let mut dest = self.clone();
+        dest.invert();
+        dest
+    }
+}
+
+impl std::ops::Not for BitImage {
+    type Output = BitImage;
+
+    fn not(mut self) -> BitImage {
+        self.invert();
+        self
+    }
+}
+
+/// Run-length encoded binary mask using the same layout as COCO annotations: pixels are ordered
+/// column by column and `counts` alternates between runs of unset and set pixels, starting with
+/// unset pixels.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rle {
+    /// Mask size
+    pub size: Size,
+
+    /// Run lengths
+    pub counts: Vec<u32>,
+}
+
+impl Rle {
+    /// Encode a mask
+    pub fn encode(mask: &BitImage) -> Rle {
+        let (width, height) = (mask.width(), mask.height());
+        let mut counts = Vec::new();
+        let (mut value, mut run) = (false, 0u32);
+        for x in 0..width {
+            for y in 0..height {
+                if mask.get((x, y)) != value {
+                    counts.push(run);
+                    value = !value;
+                    run = 0;
+                }
+                run += 1;
+            }
+        }
+        counts.push(run);
+        Rle {
+            size: mask.size(),
+            counts,
+        }
+    }
+
+    /// Encode the first channel of an image, pixels with a normalized value >= 0.5 are set
+    pub fn from_image<T: Type, C: Color>(image: &Image<T, C>) -> Rle {
+        Rle::encode(&BitImage::from_image(image))
+    }
+
+    /// Decode to a mask
+    pub fn decode(&self) -> BitImage {
+        let mut mask = BitImage::new(self.size);
+        let height = self.size.height.max(1);
+        for (start, end) in self.runs() {
+            for i in start..end.min(self.size.width * self.size.height) {
+                mask.set((i / height, i % height), true);
+            }
+        }
+        mask
+    }
+
+    /// Decode to a grayscale image, set pixels have the maximum value
+    pub fn to_image<T: Type>(&self) -> Image<T, Gray> {
+        self.decode().to_image()
+    }
+
+    /// Start and end offsets, in column-major order, of each run of set pixels
+    pub fn runs(&self) -> impl '_ + Iterator<Item = (usize, usize)> {
+        let mut offset = 0;
+        self.counts.iter().enumerate().filter_map(move |(i, n)| {
+            let start = offset;
+            offset += *n as usize;
+            (i % 2 == 1 && *n > 0).then_some((start, offset))
+        })
+    }
+
+    /// Number of set pixels
+    pub fn area(&self) -> usize {
+        self.runs().map(|(start, end)| end - start).sum()
+    }
+
+    /// Number of pixels set in both masks
+    pub fn intersection_area(&self, other: &Rle) -> usize {
+        let mut a = self.runs().peekable();
+        let mut b = other.runs().peekable();
+        let mut area = 0;
+        while let (Some((a0, a1)), Some((b0, b1))) = (a.peek().copied(), b.peek().copied()) {
+            area += a1.min(b1).saturating_sub(a0.max(b0));
+            if a1 <= b1 {
+                a.next();
+            } else {
+                b.next();
+            }
+        }
+        area
+    }
+
+    /// Intersection over union of two masks of the same size, 0 when both masks are empty
+    pub fn iou(&self, other: &Rle) -> f64 {
+        let intersection = self.intersection_area(other);
+        let union = self.area() + other.area() - intersection;
+        if union == 0 {
+            return 0.0;
+        }
+        intersection as f64 / union as f64
+    }
+
+    /// Encode the run lengths using the compressed string format used by the COCO API
+    pub fn to_coco_string(&self) -> String {
+        let mut s = String::new();
+        for (i, n) in self.counts.iter().enumerate() {
+            let mut x = *n as i64;
+            if i > 2 {
+                x -= self.counts[i - 2] as i64;
+            }
+            loop {
+                let mut c = x & 0x1f;
+                x >>= 5;
+                let more = if c & 0x10 != 0 { x != -1 } else { x != 0 };
+                if more {
+                    c |= 0x20;
+                }
+                s.push((c as u8 + 48) as char);
+                if !more {
+                    break;
+                }
+            }
+        }
+        s
+    }
+
+    /// Decode run lengths from the compressed string format used by the COCO API
+    pub fn from_coco_string(size: impl Into<Size>, s: &str) -> Result<Rle, Error> {
+        let size = size.into();
+        let invalid = || Error::Message(format!("Invalid RLE string: {s}"));
+        let mut bytes = s.bytes().peekable();
+        let mut counts: Vec<u32> = Vec::new();
+        while bytes.peek().is_some() {
+            let (mut x, mut k) = (0i64, 0);
+            loop {
+                let c = bytes.next().ok_or_else(invalid)?.wrapping_sub(48) as i64;
+                if k > 12 || c > 0x3f {
+                    return Err(invalid());
+                }
+                x |= (c & 0x1f) << (5 * k);
+                k += 1;
+                if c & 0x20 == 0 {
+                    if c & 0x10 != 0 {
+                        x |= -1 << (5 * k);
+                    }
+                    break;
+                }
+            }
+            if counts.len() > 2 {
+                x += counts[counts.len() - 2] as i64;
+            }
+            counts.push(u32::try_from(x).map_err(|_| invalid())?);
+        }
+
+        let total: usize = counts.iter().map(|n| *n as usize).sum();
+        if total != size.width * size.height {
+            return Err(invalid());
+        }
+        Ok(Rle { size, counts })
+    }
+}
+
+impl From<&BitImage> for Rle {
+    fn from(mask: &BitImage) -> Rle {
+        Rle::encode(mask)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_bit_image() {
+        let mut image = Image::<u8, Gray>::new((70, 3));
+        image.for_each(|pt, mut px| px[0] = if pt.x % 3 == 0 { 255 } else { 0 });
+
+        let a = BitImage::from_image(&image);
+        assert_eq!(a.stride(), 2);
+        assert_eq!(a.count(), 24 * 3);
+        assert!(a.get((69, 1)) && !a.get((68, 1)) && !a.get((70, 1)));
+        assert!(a.to_image::<u8>() == image);
+
+        let mut b = BitImage::new((70, 3));
+        b.set((69, 2), true);
+        b.set((1, 0), true);
+        assert_eq!(
+            b.points().collect::<Vec<_>>(),
+            vec![Point::new(1, 0), Point::new(69, 2)]
+        );
+
+        assert_eq!((&a & &b).count(), 1);
+        assert_eq!((&a | &b).count(), 24 * 3 + 1);
+        // One shared pixel is cleared and one new pixel is set
+        assert_eq!((&a ^ &b).count(), 24 * 3);
+
+        // Padding bits stay unset
+        let inverted = !&a;
+        assert_eq!(inverted.count(), 70 * 3 - 24 * 3);
+        assert!(!inverted.get((70, 0)));
+        assert!(!inverted == a);
+
+        b.fill(true);
+        assert_eq!(b.count(), 70 * 3);
+    }
+
+    #[test]
+    fn test_rle() {
+        let mut mask = BitImage::new((5, 4));
+        for pt in [(1, 0), (1, 1), (1, 2), (2, 2), (4, 3)] {
+            mask.set(pt, true);
+        }
+
+        let rle = Rle::encode(&mask);
+        assert_eq!(rle.counts, vec![4, 3, 3, 1, 8, 1]);
+        assert_eq!(rle.area(), 5);
+        assert!(rle.decode() == mask);
+        assert!(Rle::from_image(&mask.to_image::<u8>()) == rle);
+
+        let s = rle.to_coco_string();
+        assert!(Rle::from_coco_string((5, 4), &s).unwrap() == rle);
+        assert!(Rle::from_coco_string((5, 5), &s).is_err());
+
+        // Long runs and negative differences round trip
+        let rle = Rle {
+            size: Size::new(1000, 1000),
+            counts: vec![5000, 100000, 3, 894000, 997],
+        };
+        let decoded = Rle::from_coco_string(rle.size, &rle.to_coco_string()).unwrap();
+        assert_eq!(decoded, rle);
+
+        let mut other = BitImage::new((5, 4));
+        other.set((1, 1), true);
+        other.set((3, 3), true);
+        let other = Rle::encode(&other);
+        let rle = Rle::encode(&mask);
+        assert_eq!(rle.intersection_area(&other), 1);
+        assert!((rle.iou(&other) - 1.0 / 6.0).abs() < 1e-12);
+        assert_eq!(rle.iou(&rle), 1.0);
+    }
+}
@@ -5,9 +5,81 @@ use crate::*;
 /// `Channel` is an alias for `usize` used to identify channel values in function arguments
 pub type Channel = usize;
 
+/// Set of channels, used to restrict operations to specific channels. Only the first 64
+/// channels can be selected, higher channels are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Channels(u64);
+
+impl Default for Channels {
+    fn default() -> Channels {
+        Channels::all()
+    }
+}
+
+impl<const N: usize> From<[Channel; N]> for Channels {
+    fn from(c: [Channel; N]) -> Channels {
+        c.iter().fold(Channels::none(), |acc, c| acc.with(*c))
+    }
+}
+
+impl Channels {
+    /// Every channel
+    pub fn all() -> Channels {
+        Channels(u64::MAX)
+    }
+
+    /// No channels
+    pub fn none() -> Channels {
+        Channels(0)
+    }
+
+    /// A single channel
+    pub fn only(c: Channel) -> Channels {
+        Channels::none().with(c)
+    }
+
+    /// Every channel except alpha for the given color
+    pub fn color<C: Color>() -> Channels {
+        match C::ALPHA {
+            Some(a) => Channels::all().without(a),
+            None => Channels::all(),
+        }
+    }
+
+    /// Only the alpha channel of the given color, empty if the color has no alpha channel
+    pub fn alpha<C: Color>() -> Channels {
+        match C::ALPHA {
+            Some(a) => Channels::only(a),
+            None => Channels::none(),
+        }
+    }
+
+    /// Add a channel to the set
+    pub fn with(self, c: Channel) -> Channels {
+        if c >= 64 {
+            return self;
+        }
+        Channels(self.0 | (1 << c))
+    }
+
+    /// Remove a channel from the set
+    pub fn without(self, c: Channel) -> Channels {
+        if c >= 64 {
+            return self;
+        }
+        Channels(self.0 & !(1 << c))
+    }
+
+    /// Returns true when the set contains channel `c`
+    pub fn contains(&self, c: Channel) -> bool {
+        c < 64 && self.0 & (1 << c) != 0
+    }
+}
+
 /// `Color` trait is used to define color spaces
 pub trait Color:
-    Unpin + PartialEq + Eq + PartialOrd + Ord + Clone + Sync + Send + std::fmt::Debug
+    'static + Unpin + PartialEq + Eq + PartialOrd + Ord + Clone + Sync + Send + std::fmt::Debug
 {
     /// Color name
     const NAME: &'static str;
@@ -51,7 +123,7 @@ impl Color for Gray {
     }
 
     fn from_rgb(src: &Pixel<Rgb>, mut dest: &mut Pixel<Self>) {
-        dest[0] = src[0] * 0.21 + src[1] * 0.72 + src[2] * 0.7;
+        dest[0] = src[0] * 0.21 + src[1] * 0.72 + src[2] * 0.07;
     }
 }
 
@@ -298,9 +370,10 @@ impl Color for Yuv {
         let g = rgb[1];
         let b = rgb[2];
 
+        // BT.601 coefficients, U and V are zero for neutral colors
         pixel[0] = 0.299 * r + 0.587 * g + 0.114 * b;
-        pixel[1] = -0.147 * r + 0.289 + g + 0.436 * b;
-        pixel[2] = 0.615 * r + 0.515 * g + 0.1 * b;
+        pixel[1] = -0.14713 * r - 0.28886 * g + 0.436 * b;
+        pixel[2] = 0.615 * r - 0.51499 * g - 0.10001 * b;
     }
 
     fn to_rgb(px: &Pixel<Self>, mut rgb: &mut Pixel<Rgb>) {
@@ -368,3 +441,72 @@ impl Color for Cmyk {
         rgb[2] = 1.0 - y;
     }
 }
+
+// D65 reference white
+const LAB_WHITE: [f64; 3] = [0.95047, 1.0, 1.08883];
+
+/// Convert an `Rgb` pixel to CIE-L*a*b* values in their natural range: L in 0..100, a and b
+/// roughly in -128..127
+pub(crate) fn rgb_to_lab(rgb: &Pixel<Rgb>) -> [f64; 3] {
+    let mut xyz = Pixel::<Xyz>::new();
+    Xyz::from_rgb(rgb, &mut xyz);
+
+    let f = |t: f64| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+
+    let x = f(xyz[0] / LAB_WHITE[0]);
+    let y = f(xyz[1] / LAB_WHITE[1]);
+    let z = f(xyz[2] / LAB_WHITE[2]);
+    [116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z)]
+}
+
+/// Convert CIE-L*a*b* values in their natural range to an `Rgb` pixel
+pub(crate) fn lab_to_rgb(lab: [f64; 3], rgb: &mut Pixel<Rgb>) {
+    let y = (lab[0] + 16.0) / 116.0;
+    let x = lab[1] / 500.0 + y;
+    let z = y - lab[2] / 200.0;
+
+    let f = |t: f64| {
+        let t3 = t * t * t;
+        if t3 > 0.008856 {
+            t3
+        } else {
+            (t - 16.0 / 116.0) / 7.787
+        }
+    };
+
+    let xyz = Pixel::<Xyz>::from(vec![
+        f(x) * LAB_WHITE[0],
+        f(y) * LAB_WHITE[1],
+        f(z) * LAB_WHITE[2],
+    ]);
+    Xyz::to_rgb(&xyz, rgb);
+}
+
+color!(
+    Lab,
+    "Three-channel CIE-L*a*b*, stored normalized: L / 100, (a + 128) / 255 and (b + 128) / 255"
+);
+impl Color for Lab {
+    const NAME: &'static str = "lab";
+    const CHANNELS: Channel = 3;
+
+    fn from_rgb(rgb: &Pixel<Rgb>, mut pixel: &mut Pixel<Self>) {
+        let lab = rgb_to_lab(rgb);
+        pixel[0] = lab[0] / 100.0;
+        pixel[1] = (lab[1] + 128.0) / 255.0;
+        pixel[2] = (lab[2] + 128.0) / 255.0;
+    }
+
+    fn to_rgb(px: &Pixel<Self>, rgb: &mut Pixel<Rgb>) {
+        lab_to_rgb(
+            [px[0] * 100.0, px[1] * 255.0 - 128.0, px[2] * 255.0 - 128.0],
+            rgb,
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use crate::solver::PoissonSystem;
+use crate::*;
+
+impl<T: Type> Image<T, Gray> {
+    /// Colorize a grayscale image from sparse color hints, such as scribbles painted with a
+    /// brush. Chroma is propagated from the pixels where `mask` is at least 0.5 to the rest of
+    /// the image, neighboring pixels with similar intensity receive similar colors while
+    /// intensity edges stop the propagation (Levin et al. 2004). The luminance of the result is
+    /// taken from this image. `hints` and `mask` must be the same size as this image.
+    pub fn colorize<U: Type, C: Color, V: Type>(
+        &self,
+        hints: &Image<U, C>,
+        mask: &Image<V, Gray>,
+    ) -> Image<f32, Rgb> {
+        // Intensity difference at which the affinity between neighbors falls to ~60%
+        const SIGMA: f64 = 0.05;
+
+        let system =
+            PoissonSystem::new(self.size(), |pt| mask.get_f(pt, 0) < 0.5).with_weights(|a, b| {
+                let d = self.get_f(a, 0) - self.get_f(b, 0);
+                (-d * d / (2.0 * SIGMA * SIGMA)).exp().max(1e-4)
+            });
+
+        let chroma = |pt: Point| hints.get_pixel(pt).convert::<Yuv>();
+        let mut uv = vec![vec![0.0; self.width() * self.height()]; 2];
+        mask.each_pixel(|pt, px| {
+            if px[0] >= 0.5 {
+                let yuv = chroma(pt);
+                uv[0][pt.y * self.width() + pt.x] = yuv[1];
+                uv[1][pt.y * self.width() + pt.x] = yuv[2];
+            }
+        });
+
+        if !system.unknowns().is_empty() {
+            let guidance = vec![0.0; system.unknowns().len()];
+            let initial = vec![0.0; system.unknowns().len()];
+            for channel in uv.iter_mut() {
+                let result = system.solve(
+                    &guidance,
+                    |pt| channel[pt.y * self.width() + pt.x],
+                    &initial,
+                    self.width() * self.height(),
+                    1e-6,
+                );
+                for (pt, value) in system.unknowns().iter().zip(result) {
+                    channel[pt.y * self.width() + pt.x] = value;
+                }
+            }
+        }
+
+        let mut dest = self.new_like_with_type_and_color::<f32, Rgb>();
+        dest.each_pixel_mut(|pt, px| {
+            let index = pt.y * self.width() + pt.x;
+            let luma = self.get_f(pt, 0);
+            let yuv = Pixel::<Yuv>::from(vec![luma, uv[0][index], uv[1][index]]);
+            yuv.convert_to(px);
+        });
+        dest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_colorize() {
+        // Two regions separated by an intensity edge
+        let mut gray = Image::<f32, Gray>::new((16, 8));
+        gray.for_each(|pt, mut px| px[0] = if pt.x < 8 { 0.3 } else { 0.7 });
+
+        let mut hints = Image::<f32, Rgb>::new((16, 8));
+        let mut mask = Image::<u8, Gray>::new((16, 8));
+        for y in 3..5 {
+            hints.set((2, y), [1.0, 0.0, 0.0]);
+            mask.set((2, y), [255]);
+            hints.set((13, y), [0.0, 0.0, 1.0]);
+            mask.set((13, y), [255]);
+        }
+
+        let color = gray.colorize(&hints, &mask);
+        let left = color.get((6, 0));
+        assert!(left[0] > left[1] && left[0] > left[2]);
+        let right = color.get((9, 7));
+        assert!(right[2] > right[0] && right[2] > right[1]);
+
+        // Luminance comes from the gray image
+        let luma = color.get_pixel((9, 7)).convert::<Yuv>()[0];
+        assert!((luma - 0.7).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,46 @@
+use super::{nearest, principal_endpoints, BitWriter};
+
+/// 4x4 weight grid, 2-bit weights, single plane
+const BLOCK_MODE: u32 = 0x42;
+
+/// LDR RGBA, direct
+const COLOR_ENDPOINT_MODE: u32 = 12;
+
+/// Unquantized values of 2-bit weights
+const WEIGHTS: [i32; 4] = [0, 21, 43, 64];
+
+/// Encode a single-partition LDR ASTC 4x4 block. With 2-bit weights the remaining space is large
+/// enough to store every endpoint with 8 bits per channel.
+pub fn encode_block(block: &[[u8; 4]; 16], dest: &mut [u8]) {
+    let (a, b) = principal_endpoints(block, 4);
+    let mut e0 = a.map(|x| x.round() as i32);
+    let mut e1 = b.map(|x| x.round() as i32);
+
+    // Decoders apply blue contraction when the second endpoint is darker than the first
+    if e1[0] + e1[1] + e1[2] < e0[0] + e0[1] + e0[2] {
+        std::mem::swap(&mut e0, &mut e1);
+    }
+
+    let palette = WEIGHTS.map(|w| [0, 1, 2, 3].map(|c| (e0[c] * (64 - w) + e1[c] * w + 32) >> 6));
+    let mut weights = 0u32;
+    for (i, px) in block.iter().enumerate() {
+        weights |= (nearest(px, &palette, 4) as u32) << (i * 2);
+    }
+
+    let mut w = BitWriter::new(dest);
+    w.write(BLOCK_MODE, 11);
+    w.write(0, 2);
+    w.write(COLOR_ENDPOINT_MODE, 4);
+    for c in 0..4 {
+        w.write(e0[c] as u32, 8);
+        w.write(e1[c] as u32, 8);
+    }
+
+    // Weights are stored in reverse bit order starting from the end of the block
+    for i in 0..32 {
+        if weights >> i & 1 == 1 {
+            let n = 127 - i;
+            dest[n / 8] |= 1 << (n % 8);
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use super::{nearest, principal_endpoints, BitWriter};
+
+fn to_565(c: &[f64; 4]) -> u16 {
+    let r = (c[0] * 31.0 / 255.0).round() as u16;
+    let g = (c[1] * 63.0 / 255.0).round() as u16;
+    let b = (c[2] * 31.0 / 255.0).round() as u16;
+    r << 11 | g << 5 | b
+}
+
+fn from_565(c: u16) -> [i32; 4] {
+    let r = (c >> 11 & 31) as i32;
+    let g = (c >> 5 & 63) as i32;
+    let b = (c & 31) as i32;
+    [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2, 255]
+}
+
+/// Encode the color part of a BC1/BC3 block, always using the 4 color mode
+pub fn encode_bc1(block: &[[u8; 4]; 16], dest: &mut [u8]) {
+    let (a, b) = principal_endpoints(block, 3);
+    let (mut c0, mut c1) = (to_565(&b), to_565(&a));
+    if c0 < c1 {
+        std::mem::swap(&mut c0, &mut c1);
+    }
+
+    let mut indices = 0u32;
+    if c0 != c1 {
+        let (p0, p1) = (from_565(c0), from_565(c1));
+        let palette = [
+            p0,
+            p1,
+            [0, 1, 2, 3].map(|c| (2 * p0[c] + p1[c]) / 3),
+            [0, 1, 2, 3].map(|c| (p0[c] + 2 * p1[c]) / 3),
+        ];
+        for (i, px) in block.iter().enumerate() {
+            indices |= (nearest(px, &palette, 3) as u32) << (i * 2);
+        }
+    }
+
+    dest[0..2].copy_from_slice(&c0.to_le_bytes());
+    dest[2..4].copy_from_slice(&c1.to_le_bytes());
+    dest[4..8].copy_from_slice(&indices.to_le_bytes());
+}
+
+/// Encode a BC3 block, alpha is stored the same way as a BC4 block followed by a BC1 color block
+pub fn encode_bc3(block: &[[u8; 4]; 16], dest: &mut [u8]) {
+    let a0 = block.iter().map(|px| px[3]).max().unwrap_or(255);
+    let a1 = block.iter().map(|px| px[3]).min().unwrap_or(255);
+
+    let mut indices = 0u64;
+    if a0 > a1 {
+        // Index 0 and 1 are the endpoints, 2 to 7 interpolate between them
+        let (a0, a1) = (a0 as i32, a1 as i32);
+        let mut palette = vec![[a0, 0, 0, 0], [a1, 0, 0, 0]];
+        palette.extend((1..7).map(|i| [((7 - i) * a0 + i * a1) / 7, 0, 0, 0]));
+        for (i, px) in block.iter().enumerate() {
+            let index = nearest(&[px[3], 0, 0, 0], &palette, 1) as u64;
+            indices |= index << (i * 3);
+        }
+    }
+
+    dest[0] = a0;
+    dest[1] = a1;
+    dest[2..8].copy_from_slice(&indices.to_le_bytes()[..6]);
+    encode_bc1(block, &mut dest[8..16]);
+}
+
+const BC7_WEIGHTS: [i32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// Quantize an endpoint to 7 bits per channel plus a shared p-bit
+fn bc7_endpoint(c: &[f64; 4]) -> ([u32; 4], u32) {
+    let mut best = ([0; 4], 0, f64::INFINITY);
+    for p in 0..2 {
+        let mut q = [0; 4];
+        let mut err = 0.0;
+        for i in 0..4 {
+            q[i] = ((c[i] - p as f64) / 2.0).round().clamp(0.0, 127.0) as u32;
+            let v = (q[i] << 1 | p) as f64;
+            err += (v - c[i]) * (v - c[i]);
+        }
+        if err < best.2 {
+            best = (q, p, err);
+        }
+    }
+    (best.0, best.1)
+}
+
+/// Encode a BC7 block using mode 6, a single RGBA subset with 4-bit indices
+pub fn encode_bc7(block: &[[u8; 4]; 16], dest: &mut [u8]) {
+    let (a, b) = principal_endpoints(block, 4);
+    let mut e = [bc7_endpoint(&a), bc7_endpoint(&b)];
+
+    let expand = |(q, p): ([u32; 4], u32)| q.map(|x| (x << 1 | p) as i32);
+    let palette = |e: &[([u32; 4], u32); 2]| {
+        let (e0, e1) = (expand(e[0]), expand(e[1]));
+        BC7_WEIGHTS.map(|w| [0, 1, 2, 3].map(|c| (e0[c] * (64 - w) + e1[c] * w + 32) >> 6))
+    };
+
+    let mut indices: Vec<usize> = block
+        .iter()
+        .map(|px| nearest(px, &palette(&e), 4))
+        .collect();
+
+    // The most significant bit of the first index is implicitly zero
+    if indices[0] >= 8 {
+        e.swap(0, 1);
+        indices.iter_mut().for_each(|i| *i = 15 - *i);
+    }
+
+    let mut w = BitWriter::new(dest);
+    w.write(1 << 6, 7);
+    for c in 0..4 {
+        w.write(e[0].0[c], 7);
+        w.write(e[1].0[c], 7);
+    }
+    w.write(e[0].1, 1);
+    w.write(e[1].1, 1);
+    for (i, index) in indices.iter().enumerate() {
+        w.write(*index as u32, if i == 0 { 3 } else { 4 });
+    }
+}
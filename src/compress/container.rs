@@ -0,0 +1,222 @@
+use std::path::Path;
+
+use super::{CompressedImage, Format};
+use crate::*;
+
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xab, 0x4b, 0x54, 0x58, 0x20, 0x32, 0x30, 0xbb, 0x0d, 0x0a, 0x1a, 0x0a,
+];
+
+impl Format {
+    /// Vulkan format identifier used by KTX2
+    fn vk_format(&self, srgb: bool) -> u32 {
+        let format = match self {
+            Format::Bc1 => 131,
+            Format::Bc3 => 137,
+            Format::Bc7 => 145,
+            Format::Astc4x4 => 157,
+        };
+        format + srgb as u32
+    }
+
+    /// DXGI format identifier used by DDS, ASTC is not supported
+    fn dxgi_format(&self, srgb: bool) -> Option<u32> {
+        let format = match self {
+            Format::Bc1 => 71,
+            Format::Bc3 => 77,
+            Format::Bc7 => 98,
+            Format::Astc4x4 => return None,
+        };
+        Some(format + srgb as u32)
+    }
+
+    /// Khronos data format descriptor color model and samples as `(bit offset, bit length,
+    /// channel)`
+    fn dfd_model(&self) -> (u8, &'static [(u16, u8, u8)]) {
+        match self {
+            Format::Bc1 => (128, &[(0, 63, 0)]),
+            Format::Bc3 => (130, &[(0, 63, 15), (64, 63, 0)]),
+            Format::Bc7 => (134, &[(0, 127, 0)]),
+            Format::Astc4x4 => (162, &[(0, 127, 0)]),
+        }
+    }
+}
+
+/// Make sure every level uses the same format, and that each level is half the size of the
+/// previous one
+fn check_levels(levels: &[CompressedImage]) -> Result<&CompressedImage, Error> {
+    let base = match levels.first() {
+        Some(base) => base,
+        None => return Err(Error::Message("No image levels".into())),
+    };
+
+    for (i, level) in levels.iter().enumerate() {
+        let expected = Size::new(
+            (base.size.width >> i).max(1),
+            (base.size.height >> i).max(1),
+        );
+        if level.format != base.format || level.srgb != base.srgb || level.size != expected {
+            return Err(Error::Message(format!("Invalid image level: {}", i)));
+        }
+    }
+
+    Ok(base)
+}
+
+fn data_format_descriptor(image: &CompressedImage) -> Vec<u8> {
+    let (model, samples) = image.format.dfd_model();
+    let block_size = 24 + 16 * samples.len();
+
+    let mut dfd = Vec::with_capacity(4 + block_size);
+    dfd.extend(((4 + block_size) as u32).to_le_bytes());
+    dfd.extend(0u32.to_le_bytes());
+    dfd.extend(2u16.to_le_bytes());
+    dfd.extend((block_size as u16).to_le_bytes());
+
+    // Color model, BT.709 primaries, transfer function, straight alpha
+    dfd.extend([model, 1, if image.srgb { 2 } else { 1 }, 0]);
+
+    let block = image.format.block_size();
+    dfd.extend([block.width as u8 - 1, block.height as u8 - 1, 0, 0]);
+
+    let mut bytes = [0u8; 8];
+    bytes[0] = image.format.block_bytes() as u8;
+    dfd.extend(bytes);
+
+    for (offset, length, channel) in samples {
+        dfd.extend(offset.to_le_bytes());
+        dfd.extend([*length, *channel, 0, 0, 0, 0]);
+        dfd.extend(0u32.to_le_bytes());
+        dfd.extend(u32::MAX.to_le_bytes());
+    }
+
+    dfd
+}
+
+/// Encode a KTX2 file, `levels` contains the mip chain starting with the full size image
+pub fn encode_ktx2(levels: &[CompressedImage]) -> Result<Vec<u8>, Error> {
+    let base = check_levels(levels)?;
+    let dfd = data_format_descriptor(base);
+
+    let mut out = Vec::new();
+    out.extend(KTX2_IDENTIFIER);
+    for x in [
+        base.format.vk_format(base.srgb),
+        1,
+        base.size.width as u32,
+        base.size.height as u32,
+        0,
+        0,
+        1,
+        levels.len() as u32,
+        0,
+    ] {
+        out.extend(x.to_le_bytes());
+    }
+
+    let dfd_offset = 80 + 24 * levels.len();
+    out.extend((dfd_offset as u32).to_le_bytes());
+    out.extend((dfd.len() as u32).to_le_bytes());
+    out.extend([0u8; 24]);
+
+    // Level data follows the data format descriptor, aligned to the block size
+    let align = base.format.block_bytes();
+    let mut offset = (dfd_offset + dfd.len()).next_multiple_of(align);
+    let mut data_offsets = Vec::with_capacity(levels.len());
+    for level in levels {
+        let len = level.data.len() as u64;
+        out.extend((offset as u64).to_le_bytes());
+        out.extend(len.to_le_bytes());
+        out.extend(len.to_le_bytes());
+        data_offsets.push(offset);
+        offset = (offset + level.data.len()).next_multiple_of(align);
+    }
+
+    out.extend(dfd);
+    for (level, offset) in levels.iter().zip(data_offsets) {
+        out.resize(offset, 0);
+        out.extend(&level.data);
+    }
+
+    Ok(out)
+}
+
+/// Write a KTX2 file, `levels` contains the mip chain starting with the full size image
+pub fn write_ktx2(path: impl AsRef<Path>, levels: &[CompressedImage]) -> Result<(), Error> {
+    std::fs::write(path, encode_ktx2(levels)?)?;
+    Ok(())
+}
+
+/// Encode a DDS file, `levels` contains the mip chain starting with the full size image. BC1
+/// and BC3 use the legacy header, BC7 requires the DX10 extension header. ASTC can't be stored
+/// in DDS files.
+pub fn encode_dds(levels: &[CompressedImage]) -> Result<Vec<u8>, Error> {
+    let base = check_levels(levels)?;
+    let dxgi_format = match base.format.dxgi_format(base.srgb) {
+        Some(format) => format,
+        None => {
+            return Err(Error::Message(format!(
+                "{:?} is not supported in DDS files",
+                base.format
+            )))
+        }
+    };
+
+    let four_cc: &[u8; 4] = match (base.format, base.srgb) {
+        (Format::Bc1, false) => b"DXT1",
+        (Format::Bc3, false) => b"DXT5",
+        _ => b"DX10",
+    };
+
+    // DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT | DDSD_LINEARSIZE
+    let mut flags = 0x1 | 0x2 | 0x4 | 0x1000 | 0x80000;
+    // DDSCAPS_TEXTURE
+    let mut caps = 0x1000;
+    if levels.len() > 1 {
+        flags |= 0x20000;
+        caps |= 0x8 | 0x400000;
+    }
+
+    let mut out = Vec::new();
+    out.extend(b"DDS ");
+    for x in [
+        124,
+        flags,
+        base.size.height as u32,
+        base.size.width as u32,
+        base.data.len() as u32,
+        0,
+        levels.len() as u32,
+    ] {
+        out.extend(u32::to_le_bytes(x));
+    }
+    out.extend([0u8; 44]);
+
+    // Pixel format
+    out.extend(32u32.to_le_bytes());
+    out.extend(0x4u32.to_le_bytes());
+    out.extend(four_cc);
+    out.extend([0u8; 20]);
+
+    out.extend(u32::to_le_bytes(caps));
+    out.extend([0u8; 16]);
+
+    if four_cc == b"DX10" {
+        // Format, 2D texture, no flags, array size, unknown alpha mode
+        for x in [dxgi_format, 3, 0, 1, 0] {
+            out.extend(u32::to_le_bytes(x));
+        }
+    }
+
+    for level in levels {
+        out.extend(&level.data);
+    }
+
+    Ok(out)
+}
+
+/// Write a DDS file, see `encode_dds`
+pub fn write_dds(path: impl AsRef<Path>, levels: &[CompressedImage]) -> Result<(), Error> {
+    std::fs::write(path, encode_dds(levels)?)?;
+    Ok(())
+}
@@ -0,0 +1,350 @@
+use std::path::Path;
+
+use crate::*;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+mod astc;
+mod bc;
+mod container;
+
+pub use container::{encode_dds, encode_ktx2, write_dds, write_ktx2};
+
+/// GPU-compressed texture format, every format uses 4x4 pixel blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Format {
+    /// BC1 (DXT1), RGB at 4 bits per pixel, alpha is discarded
+    Bc1,
+
+    /// BC3 (DXT5), RGBA at 8 bits per pixel with separately encoded alpha
+    Bc3,
+
+    /// BC7 RGBA at 8 bits per pixel, higher quality than BC1/BC3
+    Bc7,
+
+    /// ASTC RGBA using 4x4 blocks, 8 bits per pixel
+    Astc4x4,
+}
+
+impl Format {
+    /// Size of a block in pixels
+    pub fn block_size(&self) -> Size {
+        Size::new(4, 4)
+    }
+
+    /// Number of bytes used to store a single block
+    pub fn block_bytes(&self) -> usize {
+        match self {
+            Format::Bc1 => 8,
+            Format::Bc3 | Format::Bc7 | Format::Astc4x4 => 16,
+        }
+    }
+
+    /// Returns true when the format stores an alpha channel
+    pub fn has_alpha(&self) -> bool {
+        !matches!(self, Format::Bc1)
+    }
+
+    fn encode_block(&self, block: &[[u8; 4]; 16], dest: &mut [u8]) {
+        match self {
+            Format::Bc1 => bc::encode_bc1(block, dest),
+            Format::Bc3 => bc::encode_bc3(block, dest),
+            Format::Bc7 => bc::encode_bc7(block, dest),
+            Format::Astc4x4 => astc::encode_block(block, dest),
+        }
+    }
+}
+
+/// Image encoded using a GPU-compressed texture format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedImage {
+    /// Texture format
+    pub format: Format,
+
+    /// Size in pixels
+    pub size: Size,
+
+    /// `true` when color values are sRGB encoded
+    pub srgb: bool,
+
+    /// Encoded blocks, in raster order
+    pub data: Vec<u8>,
+}
+
+impl CompressedImage {
+    /// Number of blocks in each direction
+    pub fn blocks(&self) -> Size {
+        let block = self.format.block_size();
+        Size::new(
+            self.size.width.div_ceil(block.width),
+            self.size.height.div_ceil(block.height),
+        )
+    }
+
+    /// Write a KTX2 file containing this image
+    pub fn write_ktx2(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        write_ktx2(path, std::slice::from_ref(self))
+    }
+
+    /// Write a DDS file containing this image
+    pub fn write_dds(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        write_dds(path, std::slice::from_ref(self))
+    }
+}
+
+/// Encode 8-bit RGBA data, edge blocks are padded by repeating the last row and column
+fn encode(data: &[u8], size: Size, format: Format, srgb: bool) -> CompressedImage {
+    let mut image = CompressedImage {
+        format,
+        size,
+        srgb,
+        data: Vec::new(),
+    };
+
+    let blocks = image.blocks();
+    let row_bytes = blocks.width * format.block_bytes();
+    image.data = vec![0; row_bytes * blocks.height];
+    if row_bytes == 0 {
+        return image;
+    }
+
+    #[cfg(feature = "parallel")]
+    let rows = image.data.par_chunks_mut(row_bytes);
+    #[cfg(not(feature = "parallel"))]
+    let rows = image.data.chunks_mut(row_bytes);
+
+    rows.enumerate().for_each(|(by, row)| {
+        let mut block = [[0u8; 4]; 16];
+        for (bx, dest) in row.chunks_mut(format.block_bytes()).enumerate() {
+            for (i, texel) in block.iter_mut().enumerate() {
+                let x = (bx * 4 + i % 4).min(size.width - 1);
+                let y = (by * 4 + i / 4).min(size.height - 1);
+                let index = (y * size.width + x) * 4;
+                texel.copy_from_slice(&data[index..index + 4]);
+            }
+            format.encode_block(&block, dest);
+        }
+    });
+
+    image
+}
+
+/// Convert to 8-bit RGBA with straight alpha, `D` determines the encoding of the color channels
+fn rgba8<T: Type, C: Color, D: Color>(image: &Image<T, C>) -> Vec<u8> {
+    let mut data = Vec::with_capacity(image.width() * image.height() * 4);
+    image.each_pixel(|_, px| {
+        let alpha = C::ALPHA.map(|a| px[a].clamp(0.0, 1.0)).unwrap_or(1.0);
+
+        // Color conversion multiplies by alpha
+        let mut rgb: Pixel<Rgb> = px.convert();
+        if alpha > 0.0 {
+            rgb.iter_mut().for_each(|x| *x /= alpha);
+        }
+
+        let color: Pixel<D> = rgb.convert();
+        for c in 0..3 {
+            data.push(u8::from_norm(color[c].clamp(0.0, 1.0)));
+        }
+        data.push(u8::from_norm(alpha));
+    });
+    data
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Encode using a GPU-compressed texture format, color values are converted to sRGB
+    pub fn compress(&self, format: Format) -> CompressedImage {
+        encode(&rgba8::<T, C, Srgb>(self), self.size(), format, true)
+    }
+
+    /// Encode using a GPU-compressed texture format without converting to sRGB, this should be
+    /// used for non-color data like normal maps
+    pub fn compress_linear(&self, format: Format) -> CompressedImage {
+        encode(&rgba8::<T, C, Rgb>(self), self.size(), format, false)
+    }
+}
+
+/// Writes bits into a block, least significant bit first
+struct BitWriter<'a> {
+    dest: &'a mut [u8],
+    offset: usize,
+}
+
+impl<'a> BitWriter<'a> {
+    fn new(dest: &'a mut [u8]) -> BitWriter<'a> {
+        dest.iter_mut().for_each(|x| *x = 0);
+        BitWriter { dest, offset: 0 }
+    }
+
+    fn write(&mut self, value: u32, bits: usize) {
+        for i in 0..bits {
+            if value >> i & 1 == 1 {
+                let n = self.offset + i;
+                self.dest[n / 8] |= 1 << (n % 8);
+            }
+        }
+        self.offset += bits;
+    }
+}
+
+/// Find the two endpoints of the line that best fits `block`, using the first `channels`
+/// channels
+fn principal_endpoints(block: &[[u8; 4]; 16], channels: usize) -> ([f64; 4], [f64; 4]) {
+    let mut mean = [0.0; 4];
+    for px in block {
+        for c in 0..channels {
+            mean[c] += px[c] as f64 / 16.0;
+        }
+    }
+
+    let mut cov = [[0.0; 4]; 4];
+    for px in block {
+        for i in 0..channels {
+            for j in 0..channels {
+                cov[i][j] += (px[i] as f64 - mean[i]) * (px[j] as f64 - mean[j]);
+            }
+        }
+    }
+
+    // Power iteration, starting from the channel with the largest variance
+    let mut axis = [0.0; 4];
+    let start = (0..channels)
+        .max_by(|a, b| cov[*a][*a].total_cmp(&cov[*b][*b]))
+        .unwrap_or(0);
+    axis[start] = 1.0;
+    for _ in 0..8 {
+        let mut next = [0.0; 4];
+        for i in 0..channels {
+            for j in 0..channels {
+                next[i] += cov[i][j] * axis[j];
+            }
+        }
+        let norm = next.iter().map(|x| x * x).sum::<f64>().sqrt();
+        if norm < 1e-9 {
+            break;
+        }
+        axis = next.map(|x| x / norm);
+    }
+
+    let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+    for px in block {
+        let t: f64 = (0..channels)
+            .map(|c| (px[c] as f64 - mean[c]) * axis[c])
+            .sum();
+        min = min.min(t);
+        max = max.max(t);
+    }
+
+    let point = |t: f64| {
+        let mut p = [0.0; 4];
+        for c in 0..channels {
+            p[c] = (mean[c] + axis[c] * t).clamp(0.0, 255.0);
+        }
+        p
+    };
+    (point(min), point(max))
+}
+
+/// Index of the palette entry closest to `px`
+fn nearest(px: &[u8; 4], palette: &[[i32; 4]], channels: usize) -> usize {
+    let mut best = (0, i32::MAX);
+    for (i, p) in palette.iter().enumerate() {
+        let d: i32 = (0..channels)
+            .map(|c| (px[c] as i32 - p[c]) * (px[c] as i32 - p[c]))
+            .sum();
+        if d < best.1 {
+            best = (i, d);
+        }
+    }
+    best.0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn gradient() -> Image<u8, Rgba> {
+        let mut image = Image::<u8, Rgba>::new((10, 6));
+        image.for_each(|pt, mut px| {
+            let t = ((pt.x + pt.y) * 15) as u8;
+            px.copy_from_slice([t, 128, 255 - t, 200]);
+        });
+        image
+    }
+
+    #[test]
+    fn test_compress_sizes() {
+        let image = gradient();
+        for (format, bytes) in [
+            (compress::Format::Bc1, 8),
+            (compress::Format::Bc3, 16),
+            (compress::Format::Bc7, 16),
+            (compress::Format::Astc4x4, 16),
+        ] {
+            let c = image.compress_linear(format);
+            assert_eq!(c.blocks(), Size::new(3, 2));
+            assert_eq!(c.data.len(), 6 * bytes);
+        }
+    }
+
+    #[test]
+    fn test_compress_bc1_solid() {
+        let mut image = Image::<u8, Rgba>::new((4, 4));
+        image.for_each(|_, mut px| px.copy_from_slice([255, 0, 0, 255]));
+        let c = image.compress_linear(compress::Format::Bc1);
+        assert_eq!(c.data, vec![0x00, 0xf8, 0x00, 0xf8, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_compress_bc7_roundtrip() {
+        let image = gradient();
+        let c = image.compress_linear(compress::Format::Bc7);
+
+        // Decode mode 6 blocks
+        let weights = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+        for (b, block) in c.data.chunks(16).enumerate() {
+            let bits = u128::from_le_bytes(block.try_into().unwrap());
+            assert_eq!(bits & 0x7f, 0x40);
+            let read = |offset: usize, n: usize| ((bits >> offset) & ((1 << n) - 1)) as i32;
+            let p = [read(63, 1), read(64, 1)];
+            let endpoint = |e: usize, c: usize| read(7 + c * 14 + e * 7, 7) << 1 | p[e];
+
+            for i in 0..16 {
+                let index = if i == 0 {
+                    read(65, 3)
+                } else {
+                    read(64 + i * 4, 4)
+                } as usize;
+                let (x, y) = ((b % 3) * 4 + i % 4, (b / 3) * 4 + i / 4);
+                if x >= 10 || y >= 6 {
+                    continue;
+                }
+                for ch in 0..4 {
+                    let (e0, e1) = (endpoint(0, ch), endpoint(1, ch));
+                    let w = weights[index];
+                    let v = (e0 * (64 - w) + e1 * w + 32) >> 6;
+                    let expected = image.get((x, y))[ch] as i32;
+                    assert!((v - expected).abs() <= 12, "{} {} {}", v, expected, ch);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_ktx2_dds_headers() {
+        let c = gradient().compress(compress::Format::Bc7);
+        let ktx = compress::encode_ktx2(std::slice::from_ref(&c)).unwrap();
+        assert_eq!(&ktx[..12], b"\xabKTX 20\xbb\r\n\x1a\n");
+        assert_eq!(u32::from_le_bytes(ktx[12..16].try_into().unwrap()), 146);
+        assert!(ktx.ends_with(&c.data));
+
+        let dds = compress::encode_dds(std::slice::from_ref(&c)).unwrap();
+        assert_eq!(&dds[..4], b"DDS ");
+        assert_eq!(&dds[84..88], b"DX10");
+        assert_eq!(dds.len(), 4 + 124 + 20 + c.data.len());
+
+        let astc = gradient().compress(compress::Format::Astc4x4);
+        assert!(compress::encode_dds(&[astc]).is_err());
+    }
+}
@@ -0,0 +1,535 @@
+use crate::*;
+
+// Moore neighborhood in clockwise order (with y pointing down), starting from west
+const NEIGHBORS: [(isize, isize); 8] = [
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+];
+
+/// Binary view of a mask image, any pixel with a first channel value >= 0.5 is considered to be
+/// foreground
+pub(crate) struct Mask {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<bool>,
+}
+
+impl Mask {
+    pub fn new<T: Type, C: Color>(image: &Image<T, C>) -> Mask {
+        let (width, height, channels) = image.shape();
+        let data = image
+            .data()
+            .chunks_exact(channels)
+            .map(|px| px[0].to_norm() >= 0.5)
+            .collect();
+        Mask {
+            width,
+            height,
+            data,
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, x: isize, y: isize) -> bool {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return false;
+        }
+        self.data[y as usize * self.width + x as usize]
+    }
+}
+
+/// Trace the outer border of the region containing `start` using Moore-neighbor tracing,
+/// `start` must be the first foreground pixel of the region in raster order
+fn trace(mask: &Mask, start: (isize, isize)) -> Vec<Point> {
+    let mut points = vec![Point::new(start.0 as usize, start.1 as usize)];
+    let mut current = start;
+
+    // Index of the neighbor used as the backtrack position, the pixel to the west of the start is
+    // always background
+    let mut back = 0;
+    let mut first = None;
+
+    loop {
+        let next = (1..=8)
+            .map(|i| (back + i) % 8)
+            .find(|dir| mask.get(current.0 + NEIGHBORS[*dir].0, current.1 + NEIGHBORS[*dir].1));
+
+        let dir = match next {
+            Some(dir) => dir,
+            // Isolated pixel
+            None => break,
+        };
+
+        // Jacob's stopping criterion: stop once the start pixel is left in the same direction
+        // as the first time
+        if current == start {
+            match first {
+                Some(f) if f == dir => break,
+                Some(_) => (),
+                None => first = Some(dir),
+            }
+        }
+
+        let prev = current;
+        current = (current.0 + NEIGHBORS[dir].0, current.1 + NEIGHBORS[dir].1);
+
+        // The new backtrack position is the background pixel examined just before `dir`,
+        // expressed relative to the new current pixel
+        let (bx, by) = NEIGHBORS[(dir + 7) % 8];
+        let b = (prev.0 + bx - current.0, prev.1 + by - current.1);
+        back = NEIGHBORS.iter().position(|n| *n == b).unwrap_or(0);
+
+        points.push(Point::new(current.0 as usize, current.1 as usize));
+    }
+
+    if points.len() > 1 && points.last() == points.first() {
+        points.pop();
+    }
+
+    points
+}
+
+/// Mark every pixel 8-connected to `start` as visited
+fn visit(mask: &Mask, visited: &mut [bool], start: (isize, isize)) {
+    let mut stack = vec![start];
+    visited[start.1 as usize * mask.width + start.0 as usize] = true;
+
+    while let Some((x, y)) = stack.pop() {
+        for (dx, dy) in NEIGHBORS {
+            let (nx, ny) = (x + dx, y + dy);
+            if !mask.get(nx, ny) {
+                continue;
+            }
+
+            let index = ny as usize * mask.width + nx as usize;
+            if !visited[index] {
+                visited[index] = true;
+                stack.push((nx, ny));
+            }
+        }
+    }
+}
+
+/// Find the outer contours of all 8-connected foreground regions in a binary mask. Pixels with a
+/// normalized value >= 0.5 are treated as foreground.
+///
+/// The returned polygons contain every border pixel in clockwise order, use
+/// `Polygon::simplify` to reduce the number of vertices.
+pub fn find_contours<T: Type>(mask: &Image<T, Gray>) -> Vec<Polygon> {
+    let mask = Mask::new(mask);
+    let mut visited = vec![false; mask.width * mask.height];
+    let mut contours = Vec::new();
+
+    for y in 0..mask.height {
+        for x in 0..mask.width {
+            let index = y * mask.width + x;
+            if !mask.data[index] || visited[index] {
+                continue;
+            }
+
+            let start = (x as isize, y as isize);
+            contours.push(Polygon::new(trace(&mask, start)));
+            visit(&mask, &mut visited, start);
+        }
+    }
+
+    contours
+}
+
+/// Find contours and simplify them using the Douglas-Peucker algorithm with the given tolerance in
+/// pixels
+pub fn find_polygons<T: Type>(mask: &Image<T, Gray>, epsilon: f64) -> Vec<Polygon> {
+    find_contours(mask)
+        .into_iter()
+        .map(|c| c.simplify(epsilon))
+        .collect()
+}
+
+/// Find the bounding `Region` of each foreground region in a binary mask
+pub fn find_regions<T: Type>(mask: &Image<T, Gray>) -> Vec<Region> {
+    find_contours(mask).iter().map(Polygon::bounds).collect()
+}
+
+/// Border found by `find_contour_tree`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Contour {
+    /// Border pixels in tracing order
+    pub polygon: Polygon,
+
+    /// True for the border of a hole, false for the outer border of a foreground region
+    pub hole: bool,
+
+    /// Index of the enclosing contour: the hole containing an outer border, or the outer border
+    /// surrounding a hole. `None` for regions that are not inside a hole.
+    pub parent: Option<usize>,
+}
+
+/// Follow a border starting at `start`, `from` is the neighboring background pixel that was
+/// found when the border was detected. Pixels are relabeled with `nbd` as in Suzuki and Abe's
+/// algorithm so each border is only followed once.
+fn follow(
+    f: &mut [i32],
+    width: isize,
+    start: (isize, isize),
+    from: (isize, isize),
+    nbd: i32,
+) -> Vec<Point> {
+    let at = |p: (isize, isize)| (p.1 * width + p.0) as usize;
+    let step = |p: (isize, isize), dir: usize| (p.0 + NEIGHBORS[dir].0, p.1 + NEIGHBORS[dir].1);
+    let dir_of = |p: (isize, isize), q: (isize, isize)| {
+        NEIGHBORS
+            .iter()
+            .position(|n| (p.0 + n.0, p.1 + n.1) == q)
+            .unwrap_or(0)
+    };
+    let point = |p: (isize, isize)| Point::new(p.0 as usize - 1, p.1 as usize - 1);
+
+    // Search clockwise for the last pixel of the border
+    let d = dir_of(start, from);
+    let last = match (0..8)
+        .map(|i| (d + i) % 8)
+        .find(|dir| f[at(step(start, *dir))] != 0)
+    {
+        Some(dir) => step(start, dir),
+        None => {
+            // Isolated pixel
+            f[at(start)] = -nbd;
+            return vec![point(start)];
+        }
+    };
+
+    let mut points = Vec::new();
+    let (mut prev, mut current) = (last, start);
+    loop {
+        points.push(point(current));
+
+        // Search counterclockwise for the next pixel, starting after the previous one
+        let d = dir_of(current, prev);
+        let mut east_checked = false;
+        let mut next = prev;
+        for i in 1..=8 {
+            let dir = (d + 8 - i) % 8;
+            let p = step(current, dir);
+            if f[at(p)] != 0 {
+                next = p;
+                break;
+            }
+            east_checked |= dir == 4;
+        }
+
+        // Pixels followed by background on the right are marked negative, so a new border is
+        // not started from them
+        let index = at(current);
+        if east_checked {
+            f[index] = -nbd;
+        } else if f[index] == 1 {
+            f[index] = nbd;
+        }
+
+        if next == start && current == last {
+            break;
+        }
+        prev = current;
+        current = next;
+    }
+
+    points
+}
+
+/// Find the outer borders and hole borders of all foreground regions in a binary mask, along
+/// with how they are nested, using Suzuki and Abe's border following algorithm. Foreground
+/// pixels are 8-connected and background pixels are 4-connected. Pixels with a normalized
+/// value >= 0.5 are treated as foreground.
+///
+/// Contours are returned in the order they are found, so a parent always comes before its
+/// children. Outer borders and hole borders are traversed in opposite directions.
+pub fn find_contour_tree<T: Type>(mask: &Image<T, Gray>) -> Vec<Contour> {
+    let mask = Mask::new(mask);
+
+    // Pad the mask with a background frame, which acts as the outermost hole with label 1
+    let (width, height) = (mask.width as isize + 2, mask.height as isize + 2);
+    let mut f = vec![0i32; (width * height) as usize];
+    for (y, row) in mask.data.chunks_exact(mask.width.max(1)).enumerate() {
+        for (x, fg) in row.iter().enumerate() {
+            f[(y + 1) * width as usize + x + 1] = *fg as i32;
+        }
+    }
+
+    let mut contours: Vec<Contour> = Vec::new();
+    for y in 1..height - 1 {
+        let mut lnbd = 1;
+        for x in 1..width - 1 {
+            let index = (y * width + x) as usize;
+            let value = f[index];
+            let start = if value == 1 && f[index - 1] == 0 {
+                Some((false, (x - 1, y)))
+            } else if value >= 1 && f[index + 1] == 0 {
+                if value > 1 {
+                    lnbd = value;
+                }
+                Some((true, (x + 1, y)))
+            } else {
+                None
+            };
+
+            if let Some((hole, from)) = start {
+                // The border found last on this row decides the parent, labels start at 2
+                let parent = match lnbd {
+                    1 => None,
+                    n => {
+                        let last = (n - 2) as usize;
+                        if contours[last].hole == hole {
+                            contours[last].parent
+                        } else {
+                            Some(last)
+                        }
+                    }
+                };
+                let nbd = contours.len() as i32 + 2;
+                let points = follow(&mut f, width, (x, y), from, nbd);
+                contours.push(Contour {
+                    polygon: Polygon::new(points),
+                    hole,
+                    parent,
+                });
+            }
+
+            let value = f[index];
+            if value != 0 && value != 1 {
+                lnbd = value.abs();
+            }
+        }
+    }
+
+    contours
+}
+
+/// Determines which neighboring pixels are considered connected
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Connectivity {
+    /// Pixels sharing an edge
+    Four,
+
+    /// Pixels sharing an edge or a corner
+    #[default]
+    Eight,
+}
+
+impl Connectivity {
+    /// Offsets of the connected neighbors
+    pub fn offsets(&self) -> &'static [(isize, isize)] {
+        match self {
+            Connectivity::Four => &[(-1, 0), (0, -1), (1, 0), (0, 1)],
+            Connectivity::Eight => &NEIGHBORS,
+        }
+    }
+}
+
+/// Statistics of a connected component
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Component {
+    /// Label of the component in the label image
+    pub label: u32,
+
+    /// Number of pixels
+    pub area: usize,
+
+    /// Bounding box
+    pub bounds: Region,
+
+    /// Mean position of the pixels
+    pub centroid: (f64, f64),
+}
+
+/// Label the connected foreground regions of a binary mask. Pixels with a normalized value >=
+/// 0.5 are treated as foreground. Returns a label image where background pixels are 0 and the
+/// pixels of each component are numbered from 1 in raster order of their first pixel, along with
+/// the statistics of each component, `components[i]` has the label `i + 1`.
+pub fn connected_components<T: Type>(
+    mask: &Image<T, Gray>,
+    connectivity: Connectivity,
+) -> (Image<u32, Gray>, Vec<Component>) {
+    let mask = Mask::new(mask);
+    let mut labels = Image::<u32, Gray>::new((mask.width, mask.height));
+    let mut components = Vec::new();
+    let mut stack = Vec::new();
+
+    for y in 0..mask.height {
+        for x in 0..mask.width {
+            if !mask.data[y * mask.width + x] || labels.data()[y * mask.width + x] != 0 {
+                continue;
+            }
+
+            let label = components.len() as u32 + 1;
+            let (mut min, mut max) = ((x, y), (x, y));
+            let (mut area, mut sx, mut sy) = (0, 0.0, 0.0);
+            labels.data_mut()[y * mask.width + x] = label;
+            stack.push((x, y));
+            while let Some((px, py)) = stack.pop() {
+                area += 1;
+                sx += px as f64;
+                sy += py as f64;
+                min = (min.0.min(px), min.1.min(py));
+                max = (max.0.max(px), max.1.max(py));
+
+                for (dx, dy) in connectivity.offsets() {
+                    let (nx, ny) = (px as isize + dx, py as isize + dy);
+                    if !mask.get(nx, ny) {
+                        continue;
+                    }
+                    let i = ny as usize * mask.width + nx as usize;
+                    if labels.data()[i] == 0 {
+                        labels.data_mut()[i] = label;
+                        stack.push((nx as usize, ny as usize));
+                    }
+                }
+            }
+
+            components.push(Component {
+                label,
+                area,
+                bounds: Region::new(
+                    Point::new(min.0, min.1),
+                    Size::new(max.0 - min.0 + 1, max.1 - min.1 + 1),
+                ),
+                centroid: (sx / area as f64, sy / area as f64),
+            });
+        }
+    }
+
+    (labels, components)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn square_mask() -> Image<u8, Gray> {
+        let mut mask = Image::<u8, Gray>::new((20, 20));
+        mask.for_each_region(
+            Region::new(Point::new(5, 4), Size::new(8, 6)),
+            |_, mut px| {
+                px[0] = 255;
+            },
+        );
+        mask.set((17, 17), [255]);
+        mask
+    }
+
+    #[test]
+    fn test_find_contours() {
+        let contours = contour::find_contours(&square_mask());
+        assert_eq!(contours.len(), 2);
+        assert_eq!(
+            contours[0].bounds(),
+            Region::new(Point::new(5, 4), Size::new(8, 6))
+        );
+        assert_eq!(contours[0].len(), 2 * 8 + 2 * 6 - 4);
+        assert_eq!(contours[1].points, vec![Point::new(17, 17)]);
+    }
+
+    #[test]
+    fn test_simplify() {
+        let polygons = contour::find_polygons(&square_mask(), 0.5);
+        assert_eq!(polygons[0].len(), 4);
+        assert_eq!(polygons[0].area(), 7.0 * 5.0);
+        assert_eq!(
+            polygons[0].bounds(),
+            Region::new(Point::new(5, 4), Size::new(8, 6))
+        );
+    }
+
+    #[test]
+    fn test_connected_components() {
+        let mut mask = square_mask();
+        mask.set((13, 10), [255]);
+
+        let (labels, components) =
+            contour::connected_components(&mask, contour::Connectivity::Eight);
+        assert_eq!(components.len(), 2);
+        assert_eq!(components[0].area, 8 * 6 + 1);
+        assert_eq!(
+            components[0].bounds,
+            Region::new(Point::new(5, 4), Size::new(9, 7))
+        );
+        assert_eq!(labels.get((6, 5))[0], 1);
+        assert_eq!(labels.get((17, 17))[0], 2);
+        assert_eq!(labels.get((0, 0))[0], 0);
+        assert_eq!(components[1].centroid, (17.0, 17.0));
+
+        // The diagonal pixel is separate with 4-connectivity
+        let (labels, components) =
+            contour::connected_components(&mask, contour::Connectivity::Four);
+        assert_eq!(components.len(), 3);
+        assert_eq!(labels.get((13, 10))[0], 2);
+        let c = &components[0];
+        assert_eq!(c.area, 48);
+        assert!((c.centroid.0 - 8.5).abs() < 1e-9 && (c.centroid.1 - 6.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_find_contour_tree() {
+        // A ring containing an island, next to a solid square
+        let mut mask = Image::<u8, Gray>::new((24, 16));
+        mask.fill_rect(
+            Region::new(Point::new(1, 1), Size::new(12, 12)),
+            &Pixel::from(vec![1.0]),
+        );
+        mask.fill_rect(
+            Region::new(Point::new(3, 3), Size::new(8, 8)),
+            &Pixel::from(vec![0.0]),
+        );
+        mask.fill_rect(
+            Region::new(Point::new(6, 6), Size::new(2, 2)),
+            &Pixel::from(vec![1.0]),
+        );
+        mask.fill_rect(
+            Region::new(Point::new(16, 2), Size::new(4, 4)),
+            &Pixel::from(vec![1.0]),
+        );
+
+        let contours = contour::find_contour_tree(&mask);
+        assert_eq!(contours.len(), 4);
+        let summary: Vec<_> = contours
+            .iter()
+            .map(|c| (c.hole, c.parent, c.polygon.bounds()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    false,
+                    None,
+                    Region::new(Point::new(1, 1), Size::new(12, 12))
+                ),
+                (false, None, Region::new(Point::new(16, 2), Size::new(4, 4))),
+                (
+                    true,
+                    Some(0),
+                    Region::new(Point::new(2, 2), Size::new(10, 10))
+                ),
+                (
+                    false,
+                    Some(2),
+                    Region::new(Point::new(6, 6), Size::new(2, 2))
+                ),
+            ]
+        );
+        assert_eq!(contours[0].polygon.len(), 4 * 11);
+        // Corners of the hole are cut diagonally
+        assert_eq!(contours[2].polygon.len(), 4 * 9 - 4);
+
+        // Outer borders match `find_contours`
+        let outer = contour::find_contours(&mask);
+        assert_eq!(outer[0].len(), contours[0].polygon.len());
+    }
+}
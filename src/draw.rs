@@ -0,0 +1,197 @@
+use crate::*;
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Draw a one pixel wide line from `a` to `b`
+    pub fn draw_line(&mut self, a: impl Into<Point>, b: impl Into<Point>, color: &Pixel<C>) {
+        for pt in geom::line_points(a.into(), b.into()) {
+            if self.in_bounds(pt) {
+                self.set_pixel(pt, color);
+            }
+        }
+    }
+
+    /// Draw the outline of a polygon
+    pub fn draw_polygon(&mut self, polygon: &Polygon, color: &Pixel<C>) {
+        if polygon.len() == 1 {
+            self.draw_line(polygon.points[0], polygon.points[0], color);
+            return;
+        }
+
+        for (a, b) in polygon.edges() {
+            self.draw_line(a, b, color);
+        }
+    }
+
+    /// Fill the inside of a polygon, including its outline, using the even-odd rule
+    pub fn fill_polygon(&mut self, polygon: &Polygon, color: &Pixel<C>) {
+        if polygon.is_empty() {
+            return;
+        }
+
+        let bounds = polygon.bounds();
+        let mut crossings = Vec::new();
+        for y in bounds.min_y()..bounds.max_y().min(self.height()) {
+            let yc = y as f64 + 0.5;
+            crossings.clear();
+            for (a, b) in polygon.edges() {
+                let (ay, by) = (a.y as f64, b.y as f64);
+                if (ay <= yc) != (by <= yc) {
+                    let t = (yc - ay) / (by - ay);
+                    crossings.push(a.x as f64 + t * (b.x as f64 - a.x as f64));
+                }
+            }
+            crossings.sort_by(|a, b| a.total_cmp(b));
+
+            for span in crossings.chunks_exact(2) {
+                let x0 = span[0].round().max(0.0) as usize;
+                let x1 = (span[1].round().max(0.0) as usize).min(self.width().saturating_sub(1));
+                for x in x0..=x1 {
+                    self.set_pixel((x, y), color);
+                }
+            }
+        }
+
+        self.draw_polygon(polygon, color);
+    }
+
+    /// Draw the outline of a region
+    pub fn draw_rect(&mut self, region: Region, color: &Pixel<C>) {
+        if region.is_empty() {
+            return;
+        }
+
+        self.draw_polygon(&Polygon::from(region), color)
+    }
+
+    /// Fill a region with a single color
+    pub fn fill_rect(&mut self, region: Region, color: &Pixel<C>) {
+        let region = match region.intersection(&Region::new(Point::zero(), self.size())) {
+            Some(r) => r,
+            None => return,
+        };
+
+        self.for_each_region(region, |_, px| color.copy_to_slice(px));
+    }
+
+    /// Find the pixels reached by a flood fill starting at `seed`, returns a mask where filled
+    /// pixels are set to the maximum value. Pixels are included when every channel differs from
+    /// the seed pixel by no more than `tolerance`, using normalized values, and they are
+    /// connected to the seed through other included pixels.
+    pub fn flood_fill_mask(
+        &self,
+        seed: impl Into<Point>,
+        tolerance: f64,
+        connectivity: contour::Connectivity,
+    ) -> Image<u8, Gray> {
+        let seed = seed.into();
+        let (width, height) = (self.width(), self.height());
+        let mut mask = Image::<u8, Gray>::new((width, height));
+        if !self.in_bounds(seed) {
+            return mask;
+        }
+
+        let target = self.get_pixel(seed);
+        let matches = |x: usize, y: usize| {
+            (0..C::CHANNELS).all(|c| (self.get_f((x, y), c) - target[c]).abs() <= tolerance)
+        };
+
+        let data = mask.data_mut();
+        data[seed.y * width + seed.x] = u8::MAX;
+        let mut stack = vec![(seed.x, seed.y)];
+        while let Some((x, y)) = stack.pop() {
+            for (dx, dy) in connectivity.offsets() {
+                let (nx, ny) = (x as isize + dx, y as isize + dy);
+                if nx < 0 || ny < 0 || nx as usize >= width || ny as usize >= height {
+                    continue;
+                }
+                let (nx, ny) = (nx as usize, ny as usize);
+                let i = ny * width + nx;
+                if data[i] == 0 && matches(nx, ny) {
+                    data[i] = u8::MAX;
+                    stack.push((nx, ny));
+                }
+            }
+        }
+        mask
+    }
+
+    /// Replace the pixels reached by a flood fill starting at `seed` with `color`, see
+    /// `Image::flood_fill_mask`. Returns the bounding box of the filled area, which can be used
+    /// to update only the affected part of a display.
+    pub fn flood_fill(
+        &mut self,
+        seed: impl Into<Point>,
+        color: &Pixel<C>,
+        tolerance: f64,
+        connectivity: contour::Connectivity,
+    ) -> Option<Region> {
+        let mask = self.flood_fill_mask(seed, tolerance, connectivity);
+        let mut filled: Option<(Point, Point)> = None;
+        self.for_each(|pt, px| {
+            if mask.get(pt)[0] > 0 {
+                color.copy_to_slice(px);
+            }
+        });
+        mask.each_pixel(|pt, px| {
+            if px[0] > 0.0 {
+                let (min, max) = filled.unwrap_or((pt, pt));
+                filled = Some((min.min(pt), max.max(pt)));
+            }
+        });
+        filled.map(|(min, max)| Region::new(min, Size::new(max.x - min.x + 1, max.y - min.y + 1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_draw_polygon() {
+        let mut image = Image::<u8, Gray>::new((32, 32));
+        let white = Pixel::from(vec![1.0]);
+        let polygon = Polygon::new(vec![
+            Point::new(4, 4),
+            Point::new(20, 4),
+            Point::new(20, 12),
+            Point::new(4, 12),
+        ]);
+        image.fill_polygon(&polygon, &white);
+
+        let contours = contour::find_contours(&image);
+        assert_eq!(contours.len(), 1);
+        assert_eq!(contours[0].bounds(), polygon.bounds());
+        assert_eq!(contours[0].simplify(0.5).len(), 4);
+    }
+
+    #[test]
+    fn test_flood_fill() {
+        // Two squares touching at a corner
+        let mut image = Image::<f32, Gray>::new((16, 16));
+        image.fill_rect(
+            Region::new(Point::new(2, 2), Size::new(4, 4)),
+            &Pixel::from(vec![0.5]),
+        );
+        image.fill_rect(
+            Region::new(Point::new(6, 6), Size::new(4, 4)),
+            &Pixel::from(vec![0.52]),
+        );
+
+        let mask = image.flood_fill_mask((3, 3), 0.05, contour::Connectivity::Four);
+        assert_eq!(mask.data().iter().filter(|x| **x > 0).count(), 16);
+        let mask = image.flood_fill_mask((3, 3), 0.05, contour::Connectivity::Eight);
+        assert_eq!(mask.data().iter().filter(|x| **x > 0).count(), 32);
+        let mask = image.flood_fill_mask((3, 3), 0.01, contour::Connectivity::Eight);
+        assert_eq!(mask.data().iter().filter(|x| **x > 0).count(), 16);
+
+        let red = Pixel::from(vec![1.0]);
+        let filled = image.flood_fill((7, 7), &red, 0.05, contour::Connectivity::Eight);
+        assert_eq!(filled, Some(Region::new(Point::new(2, 2), Size::new(8, 8))));
+        assert_eq!(image.get((2, 2))[0], 1.0);
+        assert_eq!(image.get((0, 0))[0], 0.0);
+        assert_eq!(
+            image.flood_fill((20, 20), &red, 0.0, contour::Connectivity::Four),
+            None
+        );
+    }
+}
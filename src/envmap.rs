@@ -0,0 +1,315 @@
+//! Equirectangular environment maps use longitude along the x axis and latitude along the y axis,
+//! the center of the image faces `-Z` and the top row is `+Y`. Cube map faces are ordered
+//! `+X`, `-X`, `+Y`, `-Y`, `+Z`, `-Z` using the OpenGL orientation.
+
+use std::f64::consts::PI;
+
+use crate::*;
+
+/// Unit direction vector
+pub type Direction = [f64; 3];
+
+fn dot(a: Direction, b: Direction) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: Direction, b: Direction) -> Direction {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(a: Direction) -> Direction {
+    let len = dot(a, a).sqrt();
+    if len > 0.0 {
+        a.map(|x| x / len)
+    } else {
+        a
+    }
+}
+
+/// Direction of the center of pixel `(x, y)` in an equirectangular map of the given size
+pub fn equirect_direction(x: f64, y: f64, size: Size) -> Direction {
+    let phi = ((x + 0.5) / size.width as f64 - 0.5) * 2.0 * PI;
+    let theta = (y + 0.5) / size.height as f64 * PI;
+    [
+        theta.sin() * phi.sin(),
+        theta.cos(),
+        -theta.sin() * phi.cos(),
+    ]
+}
+
+/// Pixel coordinates in an equirectangular map of the given size for a direction, the inverse of
+/// `equirect_direction`
+pub fn equirect_coords(dir: Direction, size: Size) -> (f64, f64) {
+    let dir = normalize(dir);
+    let phi = dir[0].atan2(-dir[2]);
+    let theta = dir[1].clamp(-1.0, 1.0).acos();
+    (
+        (phi / (2.0 * PI) + 0.5) * size.width as f64 - 0.5,
+        theta / PI * size.height as f64 - 0.5,
+    )
+}
+
+/// Direction of the center of pixel `(x, y)` on a cube map face
+pub fn cube_direction(face: usize, x: f64, y: f64, face_size: usize) -> Direction {
+    let s = 2.0 * (x + 0.5) / face_size as f64 - 1.0;
+    let t = 2.0 * (y + 0.5) / face_size as f64 - 1.0;
+    let dir = match face {
+        0 => [1.0, -t, -s],
+        1 => [-1.0, -t, s],
+        2 => [s, 1.0, t],
+        3 => [s, -1.0, -t],
+        4 => [s, -t, 1.0],
+        _ => [-s, -t, -1.0],
+    };
+    normalize(dir)
+}
+
+/// Bilinear sampling of an equirectangular map, wrapping horizontally
+fn sample(image: &Image<f32, Rgb>, dir: Direction) -> [f64; 3] {
+    let size = image.size();
+    let (x, y) = equirect_coords(dir, size);
+    let y = y.clamp(0.0, (size.height - 1) as f64);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+
+    let wrap = |x: f64| (x as isize).rem_euclid(size.width as isize) as usize;
+    let (xa, xb) = (wrap(x0), wrap(x0 + 1.0));
+    let (ya, yb) = (y0 as usize, (y0 as usize + 1).min(size.height - 1));
+
+    let mut out = [0.0; 3];
+    for (px, py, w) in [
+        (xa, ya, (1.0 - fx) * (1.0 - fy)),
+        (xb, ya, fx * (1.0 - fy)),
+        (xa, yb, (1.0 - fx) * fy),
+        (xb, yb, fx * fy),
+    ] {
+        let data = image.get((px, py));
+        (0..3).for_each(|c| out[c] += data[c] as f64 * w);
+    }
+    out
+}
+
+/// Sample a mip chain at a fractional level of detail
+fn sample_lod(levels: &[Image<f32, Rgb>], dir: Direction, lod: f64) -> [f64; 3] {
+    let lod = lod.clamp(0.0, (levels.len() - 1) as f64);
+    let (l0, t) = (lod.floor() as usize, lod.fract());
+    let a = sample(&levels[l0], dir);
+    if t == 0.0 || l0 + 1 >= levels.len() {
+        return a;
+    }
+    let b = sample(&levels[l0 + 1], dir);
+    [0, 1, 2].map(|c| a[c] * (1.0 - t) + b[c] * t)
+}
+
+/// Convert an equirectangular map to the six faces of a cube map
+pub fn to_cubemap<T: Type, C: Color>(env: &Image<T, C>, face_size: usize) -> Vec<Image<f32, Rgb>> {
+    let env: Image<f32, Rgb> = env.convert();
+    (0..6)
+        .map(|face| {
+            let mut dest = Image::new((face_size, face_size));
+            dest.for_each(|pt, mut px| {
+                let dir = cube_direction(face, pt.x as f64, pt.y as f64, face_size);
+                let color = sample(&env, dir);
+                (0..3).for_each(|c| px[c] = color[c] as f32);
+            });
+            dest
+        })
+        .collect()
+}
+
+/// Compute a diffuse irradiance map from an equirectangular environment map. Each output pixel
+/// contains the cosine-weighted average of the incoming light around its direction, divided by
+/// pi, so the diffuse color of a surface is `albedo * irradiance`. The environment is reduced to
+/// at most 64x32 pixels before convolution, irradiance has very little high frequency detail.
+pub fn irradiance<T: Type, C: Color>(env: &Image<T, C>, size: impl Into<Size>) -> Image<f32, Rgb> {
+    let env: Image<f32, Rgb> = env.convert();
+    let source = env
+        .mipmaps()
+        .into_iter()
+        .find(|x| x.width() <= 64 && x.height() <= 32)
+        .unwrap_or(env);
+
+    // Texel directions and solid angles
+    let src_size = source.size();
+    let d_phi = 2.0 * PI / src_size.width as f64;
+    let d_theta = PI / src_size.height as f64;
+    let mut texels = Vec::with_capacity(src_size.width * src_size.height);
+    for y in 0..src_size.height {
+        let theta = (y as f64 + 0.5) * d_theta;
+        let solid_angle = d_phi * d_theta * theta.sin();
+        for x in 0..src_size.width {
+            let dir = equirect_direction(x as f64, y as f64, src_size);
+            let data = source.get((x, y));
+            let color = [0, 1, 2].map(|c| data[c] as f64 * solid_angle);
+            texels.push((dir, color));
+        }
+    }
+
+    let size = size.into();
+    let mut dest = Image::new(size);
+    dest.for_each(|pt, mut px| {
+        let normal = equirect_direction(pt.x as f64, pt.y as f64, size);
+        let mut sum = [0.0; 3];
+        for (dir, color) in &texels {
+            let cos = dot(normal, *dir);
+            if cos > 0.0 {
+                (0..3).for_each(|c| sum[c] += color[c] * cos);
+            }
+        }
+        (0..3).for_each(|c| px[c] = (sum[c] / PI) as f32);
+    });
+    dest
+}
+
+/// Van der Corput sequence paired with a regular grid, used for low discrepancy sampling
+fn hammersley(i: usize, n: usize) -> (f64, f64) {
+    let radical = (i as u32).reverse_bits() as f64 / (1u64 << 32) as f64;
+    (i as f64 / n as f64, radical)
+}
+
+/// GGX normal distribution function
+fn ggx(n_dot_h: f64, alpha: f64) -> f64 {
+    let a2 = alpha * alpha;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+/// Generate prefiltered specular levels from an equirectangular environment map for use with
+/// split-sum image based lighting. Level `i` is half the size of level `i - 1` and is filtered
+/// with a GGX lobe of roughness `i / (levels - 1)`, level 0 is the unfiltered environment.
+/// `samples` is the number of importance samples used for each pixel.
+pub fn prefilter_specular<T: Type, C: Color>(
+    env: &Image<T, C>,
+    levels: usize,
+    samples: usize,
+) -> Vec<Image<f32, Rgb>> {
+    let env: Image<f32, Rgb> = env.convert();
+    let mips = env.mipmaps();
+    let base = env.size();
+    let texel_solid_angle = 4.0 * PI / (base.width * base.height).max(1) as f64;
+    let samples = samples.max(1);
+
+    let mut out = vec![env.clone()];
+    for level in 1..levels {
+        let roughness = level as f64 / (levels - 1) as f64;
+        let alpha = roughness * roughness;
+        let size = Size::new((base.width >> level).max(1), (base.height >> level).max(1));
+
+        let mut dest = Image::new(size);
+        dest.for_each(|pt, mut px| {
+            let n = equirect_direction(pt.x as f64, pt.y as f64, size);
+            let up = if n[1].abs() < 0.999 {
+                [0.0, 1.0, 0.0]
+            } else {
+                [1.0, 0.0, 0.0]
+            };
+            let tx = normalize(cross(up, n));
+            let ty = cross(n, tx);
+
+            let mut sum = [0.0; 3];
+            let mut weight = 0.0;
+            for i in 0..samples {
+                let (u, v) = hammersley(i, samples);
+                let phi = 2.0 * PI * u;
+                let cos_theta = ((1.0 - v) / (1.0 + (alpha * alpha - 1.0) * v)).sqrt();
+                let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                let (hx, hy) = (sin_theta * phi.cos(), sin_theta * phi.sin());
+                let h = [0, 1, 2].map(|c| tx[c] * hx + ty[c] * hy + n[c] * cos_theta);
+
+                // Reflect the view direction, which is assumed to equal the normal
+                let n_dot_h = dot(n, h);
+                let l = [0, 1, 2].map(|c| 2.0 * n_dot_h * h[c] - n[c]);
+                let n_dot_l = dot(n, l);
+                if n_dot_l <= 0.0 {
+                    continue;
+                }
+
+                // Sample a lower resolution level when samples are sparse to avoid aliasing
+                let pdf = ggx(n_dot_h, alpha) / 4.0;
+                let sample_solid_angle = 1.0 / (samples as f64 * pdf + 1e-9);
+                let lod = 0.5 * (sample_solid_angle / texel_solid_angle).log2() + 1.0;
+
+                let color = sample_lod(&mips, l, lod);
+                (0..3).for_each(|c| sum[c] += color[c] * n_dot_l);
+                weight += n_dot_l;
+            }
+
+            if weight > 0.0 {
+                (0..3).for_each(|c| px[c] = (sum[c] / weight) as f32);
+            }
+        });
+        out.push(dest);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_envmap_directions() {
+        let size = Size::new(64, 32);
+        for (x, y) in [(0.0, 3.0), (20.0, 10.0), (63.0, 31.0)] {
+            let dir = envmap::equirect_direction(x, y, size);
+            let (u, v) = envmap::equirect_coords(dir, size);
+            assert!((u - x).abs() < 1e-9 && (v - y).abs() < 1e-9);
+        }
+
+        let front = envmap::equirect_direction(31.5, 15.5, size);
+        assert!((front[2] + 1.0).abs() < 1e-9);
+        let right = envmap::cube_direction(0, 1.5, 1.5, 4);
+        assert!(right[0] > 0.99);
+    }
+
+    #[test]
+    fn test_envmap_constant() {
+        let mut env = Image::<f32, Rgb>::new((32, 16));
+        env.for_each(|_, mut px| px.copy_from_slice([2.0, 1.0, 0.5]));
+
+        let irradiance = envmap::irradiance(&env, (8, 4));
+        for pt in [(0, 0), (3, 1), (7, 3)] {
+            let px = irradiance.get(pt);
+            assert!((px[0] - 2.0).abs() < 0.02, "{:?}", px.as_slice());
+            assert!((px[2] - 0.5).abs() < 0.01);
+        }
+
+        let levels = envmap::prefilter_specular(&env, 4, 32);
+        let sizes: Vec<Size> = levels.iter().map(|x| x.size()).collect();
+        assert_eq!(
+            sizes,
+            vec![
+                Size::new(32, 16),
+                Size::new(16, 8),
+                Size::new(8, 4),
+                Size::new(4, 2)
+            ]
+        );
+        for level in &levels {
+            assert!((level.get((1, 1))[1] - 1.0).abs() < 1e-3);
+        }
+
+        let faces = envmap::to_cubemap(&env, 4);
+        assert_eq!(faces.len(), 6);
+        assert!((faces[3].get((2, 2))[0] - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_envmap_irradiance_direction() {
+        // Light only in the upper hemisphere
+        let mut env = Image::<f32, Rgb>::new((32, 16));
+        env.for_each(|pt, mut px| {
+            if pt.y < 8 {
+                px.copy_from_slice([1.0, 1.0, 1.0]);
+            }
+        });
+
+        let irradiance = envmap::irradiance(&env, (8, 4));
+        assert!(irradiance.get((0, 0))[0] > 0.9);
+        assert!(irradiance.get((0, 3))[0] < 0.1);
+    }
+}
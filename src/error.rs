@@ -29,6 +29,14 @@ pub enum Error {
     #[error("Multiple images not supported in image: {0}")]
     MultipleImagesNotSupported(String),
 
+    /// An image file doesn't contain the expected number of channels
+    #[error("Unexpected number of channels: expected {0}, found {1}")]
+    UnexpectedChannels(usize, usize),
+
+    /// An image file exceeds the configured decode limits
+    #[error("Decode limit exceeded: {0} is {1}, maximum is {2}")]
+    LimitExceeded(crate::io::Limit, usize, usize),
+
     /// Invalid image data type
     #[error("Invalid data type")]
     InvalidType,
@@ -0,0 +1,758 @@
+use crate::solver::PoissonSystem;
+use crate::*;
+
+/// Patch radius, patches are `2 * RADIUS + 1` pixels square
+const RADIUS: isize = 3;
+
+/// Smallest image size used by the coarsest pyramid level
+const MIN_SIZE: usize = 16;
+
+/// Maximum number of pyramid levels
+const MAX_LEVELS: usize = 6;
+
+/// Single pyramid level
+#[derive(Clone)]
+struct Layer {
+    width: usize,
+    height: usize,
+    channels: usize,
+    data: Vec<f64>,
+    hole: Vec<bool>,
+}
+
+impl Layer {
+    fn index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    fn value(&self, x: usize, y: usize, c: usize) -> f64 {
+        self.data[self.index(x, y) * self.channels + c]
+    }
+
+    /// Half size layer, a pixel is part of the hole if any of the pixels it covers are
+    fn downsample(&self) -> Layer {
+        let (width, height) = (self.width / 2, self.height / 2);
+        let mut out = Layer {
+            width,
+            height,
+            channels: self.channels,
+            data: vec![0.0; width * height * self.channels],
+            hole: vec![false; width * height],
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                let i = out.index(x, y);
+                let mut count = 0.0;
+                for (sx, sy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let (sx, sy) = (x * 2 + sx, y * 2 + sy);
+                    if self.hole[self.index(sx, sy)] {
+                        out.hole[i] = true;
+                        continue;
+                    }
+                    count += 1.0;
+                    for c in 0..self.channels {
+                        out.data[i * self.channels + c] += self.value(sx, sy, c);
+                    }
+                }
+                if count > 0.0 {
+                    for c in 0..self.channels {
+                        out.data[i * self.channels + c] /= count;
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Fill the hole by smooth interpolation of the surrounding pixels
+    fn fill_smooth(&mut self) {
+        let system = PoissonSystem::new(Size::new(self.width, self.height), |pt| {
+            self.hole[self.index(pt.x, pt.y)]
+        });
+        let n = system.unknowns().len();
+        if n == 0 || n == self.hole.len() {
+            return;
+        }
+
+        for c in 0..self.channels {
+            let result = system.solve(
+                &vec![0.0; n],
+                |pt| self.value(pt.x, pt.y, c),
+                &vec![0.0; n],
+                self.width * self.height,
+                1e-6,
+            );
+            for (pt, value) in system.unknowns().iter().zip(result) {
+                let i = self.index(pt.x, pt.y);
+                self.data[i * self.channels + c] = value;
+            }
+        }
+    }
+
+    /// Returns true when the patch centered at `(x, y)` fits in the image and contains no hole
+    /// pixels
+    fn is_source(&self, x: usize, y: usize) -> bool {
+        let (x, y, r) = (x as isize, y as isize, RADIUS);
+        if x < r || y < r || x + r >= self.width as isize || y + r >= self.height as isize {
+            return false;
+        }
+        (y - r..=y + r)
+            .all(|sy| (x - r..=x + r).all(|sx| !self.hole[self.index(sx as usize, sy as usize)]))
+    }
+
+    /// Sum of squared differences between the patch at `target` and the patch at `source`,
+    /// target pixels outside of the image are skipped
+    fn distance(&self, target: (usize, usize), source: (usize, usize)) -> f64 {
+        let mut sum = 0.0;
+        for dy in -RADIUS..=RADIUS {
+            let ty = target.1 as isize + dy;
+            if ty < 0 || ty >= self.height as isize {
+                continue;
+            }
+            let sy = (source.1 as isize + dy) as usize;
+            for dx in -RADIUS..=RADIUS {
+                let tx = target.0 as isize + dx;
+                if tx < 0 || tx >= self.width as isize {
+                    continue;
+                }
+                let sx = (source.0 as isize + dx) as usize;
+                let (ti, si) = (
+                    self.index(tx as usize, ty as usize) * self.channels,
+                    self.index(sx, sy) * self.channels,
+                );
+                for c in 0..self.channels {
+                    let d = self.data[ti + c] - self.data[si + c];
+                    sum += d * d;
+                }
+            }
+        }
+        sum
+    }
+}
+
+/// Nearest neighbor field from the patches overlapping the hole to patches outside of it
+struct Field {
+    targets: Vec<(usize, usize)>,
+    target_index: Vec<usize>,
+    sources: Vec<(usize, usize)>,
+    valid: Vec<bool>,
+    nnf: Vec<(usize, usize)>,
+    cost: Vec<f64>,
+}
+
+impl Field {
+    /// Returns `None` when there are no source patches
+    fn new(layer: &Layer) -> Option<Field> {
+        let (w, h) = (layer.width, layer.height);
+        let r = RADIUS as usize;
+        let mut valid = vec![false; w * h];
+        let mut sources = Vec::new();
+        let mut near_hole = vec![false; w * h];
+        for y in 0..h {
+            for x in 0..w {
+                if layer.is_source(x, y) {
+                    valid[layer.index(x, y)] = true;
+                    sources.push((x, y));
+                }
+                if layer.hole[layer.index(x, y)] {
+                    for ny in y.saturating_sub(r)..(y + r + 1).min(h) {
+                        for nx in x.saturating_sub(r)..(x + r + 1).min(w) {
+                            near_hole[ny * w + nx] = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        if sources.is_empty() {
+            return None;
+        }
+
+        let mut targets = Vec::new();
+        let mut target_index = vec![usize::MAX; w * h];
+        for (i, _) in near_hole.iter().enumerate().filter(|(_, x)| **x) {
+            target_index[i] = targets.len();
+            targets.push((i % w, i / w));
+        }
+
+        Some(Field {
+            nnf: vec![sources[0]; targets.len()],
+            cost: vec![f64::INFINITY; targets.len()],
+            targets,
+            target_index,
+            sources,
+            valid,
+        })
+    }
+
+    /// Replace the match for target `t` if `source` is a valid patch with a lower cost
+    fn try_source(&mut self, layer: &Layer, t: usize, source: (isize, isize)) {
+        let (w, h) = (layer.width as isize, layer.height as isize);
+        if source.0 < 0 || source.1 < 0 || source.0 >= w || source.1 >= h {
+            return;
+        }
+
+        let source = (source.0 as usize, source.1 as usize);
+        if !self.valid[layer.index(source.0, source.1)] || source == self.nnf[t] {
+            return;
+        }
+
+        let cost = layer.distance(self.targets[t], source);
+        if cost < self.cost[t] {
+            self.cost[t] = cost;
+            self.nnf[t] = source;
+        }
+    }
+
+    fn init_random(&mut self, layer: &Layer, rng: &mut Rng) {
+        for t in 0..self.targets.len() {
+            let s = self.sources[rng.below(self.sources.len())];
+            self.nnf[t] = s;
+            self.cost[t] = layer.distance(self.targets[t], s);
+        }
+    }
+
+    /// Initialize from the field of the previous, half size level, falling back to random
+    /// matches where the upsampled match isn't a valid patch
+    fn init_upsampled(
+        &mut self,
+        layer: &Layer,
+        coarse: &Field,
+        coarse_width: usize,
+        rng: &mut Rng,
+    ) {
+        self.init_random(layer, rng);
+        for t in 0..self.targets.len() {
+            let (x, y) = self.targets[t];
+            let ci = coarse
+                .target_index
+                .get((y / 2) * coarse_width + (x / 2).min(coarse_width - 1))
+                .copied()
+                .unwrap_or(usize::MAX);
+            if ci != usize::MAX {
+                let (sx, sy) = coarse.nnf[ci];
+                self.try_source(
+                    layer,
+                    t,
+                    ((sx * 2 + x % 2) as isize, (sy * 2 + y % 2) as isize),
+                );
+            }
+        }
+    }
+
+    /// Recompute the cost of every match after the layer data has changed
+    fn update_costs(&mut self, layer: &Layer) {
+        for t in 0..self.targets.len() {
+            self.cost[t] = layer.distance(self.targets[t], self.nnf[t]);
+        }
+    }
+
+    /// Improve matches by propagating good matches from neighbors and by random search
+    fn iterate(&mut self, layer: &Layer, rng: &mut Rng, reverse: bool) {
+        let n = self.targets.len();
+        let step: isize = if reverse { 1 } else { -1 };
+        let (w, h) = (layer.width as isize, layer.height as isize);
+
+        for k in 0..n {
+            let t = if reverse { n - 1 - k } else { k };
+            let (x, y) = (self.targets[t].0 as isize, self.targets[t].1 as isize);
+
+            // Propagation, shift the match of an already visited neighbor
+            for (nx, ny) in [(x + step, y), (x, y + step)] {
+                if nx < 0 || ny < 0 || nx >= w || ny >= h {
+                    continue;
+                }
+                let ni = self.target_index[(ny * w + nx) as usize];
+                if ni != usize::MAX {
+                    let (sx, sy) = self.nnf[ni];
+                    self.try_source(layer, t, (sx as isize - (nx - x), sy as isize - (ny - y)));
+                }
+            }
+
+            // Random search in exponentially shrinking windows around the current match
+            let mut radius = w.max(h);
+            while radius >= 1 {
+                let (sx, sy) = self.nnf[t];
+                let dx = rng.below(2 * radius as usize + 1) as isize - radius;
+                let dy = rng.below(2 * radius as usize + 1) as isize - radius;
+                self.try_source(layer, t, (sx as isize + dx, sy as isize + dy));
+                radius /= 2;
+            }
+        }
+    }
+
+    /// Reconstruct the hole by averaging the source pixels of every patch that covers it
+    fn vote(&self, layer: &Layer) -> Vec<f64> {
+        let channels = layer.channels;
+        let (w, h) = (layer.width as isize, layer.height as isize);
+        let mut data = layer.data.clone();
+        let mut sum = vec![0.0; channels];
+        for y in 0..layer.height {
+            for x in 0..layer.width {
+                if !layer.hole[layer.index(x, y)] {
+                    continue;
+                }
+
+                sum.iter_mut().for_each(|s| *s = 0.0);
+                let mut total = 0.0;
+                for dy in -RADIUS..=RADIUS {
+                    for dx in -RADIUS..=RADIUS {
+                        let (tx, ty) = (x as isize + dx, y as isize + dy);
+                        if tx < 0 || ty < 0 || tx >= w || ty >= h {
+                            continue;
+                        }
+                        let t = self.target_index[(ty * w + tx) as usize];
+                        if t == usize::MAX {
+                            continue;
+                        }
+                        let (sx, sy) = self.nnf[t];
+                        let (px, py) = ((sx as isize - dx) as usize, (sy as isize - dy) as usize);
+                        for (c, s) in sum.iter_mut().enumerate() {
+                            *s += layer.value(px, py, c);
+                        }
+                        total += 1.0;
+                    }
+                }
+
+                if total > 0.0 {
+                    let i = layer.index(x, y) * channels;
+                    for (d, s) in data[i..i + channels].iter_mut().zip(&sum) {
+                        *d = s / total;
+                    }
+                }
+            }
+        }
+        data
+    }
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Content-aware fill, replace the pixels where `mask` is at least 0.5 with content
+    /// synthesized from the rest of the image. Patches from outside of the masked region are
+    /// matched using PatchMatch and blended over several scales, which reproduces texture and
+    /// structure in large holes where diffusion-based inpainting would smear. `mask` must be the
+    /// same size as the image.
+    pub fn fill_region<U: Type>(&self, mask: &Image<U, Gray>) -> Image<T, C> {
+        self.fill_region_with_seed(mask, Seed::default())
+    }
+
+    /// Content-aware fill using the given seed for the random search, see `Image::fill_region`
+    pub fn fill_region_with_seed<U: Type>(
+        &self,
+        mask: &Image<U, Gray>,
+        seed: impl Into<Seed>,
+    ) -> Image<T, C> {
+        let mut base = Layer {
+            width: self.width(),
+            height: self.height(),
+            channels: C::CHANNELS,
+            data: Vec::with_capacity(self.width() * self.height() * C::CHANNELS),
+            hole: Vec::with_capacity(self.width() * self.height()),
+        };
+        self.each_pixel(|pt, px| {
+            base.data.extend(px.iter());
+            base.hole.push(mask.get_f(pt, 0) >= 0.5);
+        });
+
+        let mut dest = self.clone();
+        if !base.hole.iter().any(|x| *x) || base.hole.iter().all(|x| *x) {
+            return dest;
+        }
+
+        let mut pyramid = vec![base];
+        while pyramid.len() < MAX_LEVELS {
+            let last = &pyramid[pyramid.len() - 1];
+            if last.width / 2 < MIN_SIZE || last.height / 2 < MIN_SIZE {
+                break;
+            }
+            let next = last.downsample();
+            if next.hole.iter().all(|x| *x) {
+                break;
+            }
+            pyramid.push(next);
+        }
+
+        let mut rng = seed.into().rng();
+        let mut coarse: Option<(Layer, Option<Field>)> = None;
+        while let Some(mut layer) = pyramid.pop() {
+            // Seed the hole with the coarse result, or by smooth interpolation at the first level
+            match &coarse {
+                Some((prev, _)) => {
+                    for y in 0..layer.height {
+                        for x in 0..layer.width {
+                            let i = layer.index(x, y);
+                            if !layer.hole[i] {
+                                continue;
+                            }
+                            let (cx, cy) =
+                                ((x / 2).min(prev.width - 1), (y / 2).min(prev.height - 1));
+                            for c in 0..layer.channels {
+                                layer.data[i * layer.channels + c] = prev.value(cx, cy, c);
+                            }
+                        }
+                    }
+                }
+                None => layer.fill_smooth(),
+            }
+
+            let mut field = Field::new(&layer);
+            if let Some(field) = &mut field {
+                match &coarse {
+                    Some((prev, Some(prev_field))) => {
+                        field.init_upsampled(&layer, prev_field, prev.width, &mut rng)
+                    }
+                    _ => field.init_random(&layer, &mut rng),
+                }
+
+                let iterations = if pyramid.is_empty() { 2 } else { 4 };
+                for i in 0..iterations {
+                    if i > 0 {
+                        field.update_costs(&layer);
+                    }
+                    field.iterate(&layer, &mut rng, i % 2 == 1);
+                    field.iterate(&layer, &mut rng, i % 2 == 0);
+                    layer.data = field.vote(&layer);
+                }
+            } else if coarse.is_some() {
+                layer.fill_smooth();
+            }
+
+            coarse = Some((layer, field));
+        }
+
+        let (layer, _) = coarse.unwrap();
+        dest.each_pixel_mut(|pt, mut px| {
+            let i = layer.index(pt.x, pt.y);
+            if layer.hole[i] {
+                for c in 0..C::CHANNELS {
+                    px[c] = layer.data[i * layer.channels + c];
+                }
+            }
+        });
+        dest
+    }
+}
+
+/// Pixel on the front of the fast marching method used by `Image::inpaint`, ordered so the
+/// smallest distance is popped first from a `BinaryHeap`
+#[derive(PartialEq)]
+struct Front {
+    distance: f64,
+    index: usize,
+}
+
+impl Eq for Front {}
+
+impl PartialOrd for Front {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Front {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other
+            .distance
+            .total_cmp(&self.distance)
+            .then(other.index.cmp(&self.index))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Known,
+    Band,
+    Inside,
+}
+
+/// State of the fast marching method used by `Image::inpaint`
+struct Marching {
+    width: usize,
+    height: usize,
+    state: Vec<State>,
+    distance: Vec<f64>,
+}
+
+impl Marching {
+    fn neighbors(&self, i: usize) -> impl Iterator<Item = usize> {
+        let (x, y, width, height) = (i % self.width, i / self.width, self.width, self.height);
+        [
+            (x > 0).then(|| i - 1),
+            (x + 1 < width).then(|| i + 1),
+            (y > 0).then(|| i - width),
+            (y + 1 < height).then(|| i + width),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Distance at `i`, or `None` when it hasn't been reached yet
+    fn reached(&self, i: usize) -> Option<f64> {
+        (self.state[i] != State::Inside).then(|| self.distance[i])
+    }
+
+    /// Solve the eikonal equation |∇T| = 1 at `i` using the distances of its neighbors
+    fn solve(&self, i: usize) -> f64 {
+        let (x, y) = (i % self.width, i / self.width);
+        let at = |x: isize, y: isize| {
+            if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+                return None;
+            }
+            self.reached(y as usize * self.width + x as usize)
+        };
+
+        let (x, y) = (x as isize, y as isize);
+        let mut best = f64::MAX;
+        for dx in [-1, 1] {
+            for dy in [-1, 1] {
+                let solution = match (at(x + dx, y), at(x, y + dy)) {
+                    (Some(a), Some(b)) => {
+                        let r = 2.0 - (a - b) * (a - b);
+                        if r >= 0.0 {
+                            (a + b + r.sqrt()) / 2.0
+                        } else {
+                            1.0 + a.min(b)
+                        }
+                    }
+                    (Some(a), None) | (None, Some(a)) => 1.0 + a,
+                    (None, None) => continue,
+                };
+                best = best.min(solution);
+            }
+        }
+        best
+    }
+
+    /// Normalized gradient of the distance at `i`, which points away from the boundary
+    fn normal(&self, i: usize) -> (f64, f64) {
+        let (x, y) = (i % self.width, i / self.width);
+        let t = self.distance[i];
+        let diff = |before: Option<usize>, after: Option<usize>| match (
+            before.and_then(|j| self.reached(j)),
+            after.and_then(|j| self.reached(j)),
+        ) {
+            (Some(a), Some(b)) => (b - a) / 2.0,
+            (Some(a), None) => t - a,
+            (None, Some(b)) => b - t,
+            (None, None) => 0.0,
+        };
+        let gx = diff((x > 0).then(|| i - 1), (x + 1 < self.width).then(|| i + 1));
+        let gy = diff(
+            (y > 0).then(|| i - self.width),
+            (y + 1 < self.height).then(|| i + self.width),
+        );
+        let length = (gx * gx + gy * gy).sqrt();
+        if length > 0.0 {
+            (gx / length, gy / length)
+        } else {
+            (0.0, 0.0)
+        }
+    }
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Inpaint the pixels where `mask` is at least 0.5 using the fast marching method described
+    /// by Telea. Pixels are filled from the boundary inwards, each one a weighted average of the
+    /// known pixels within `radius`, extrapolated along their gradients so edges continue into
+    /// the hole. This is fast and works well for thin regions such as scratches, dust and
+    /// text, `Image::fill_region` is better suited to large holes in textured areas. `mask` must
+    /// be the same size as the image.
+    pub fn inpaint<U: Type>(&self, mask: &Image<U, Gray>, radius: f64) -> Image<T, C> {
+        let (width, height) = (self.width(), self.height());
+        let channels = C::CHANNELS;
+        let mut data: Vec<f64> = Vec::with_capacity(width * height * channels);
+        let mut marching = Marching {
+            width,
+            height,
+            state: Vec::with_capacity(width * height),
+            distance: vec![0.0; width * height],
+        };
+        self.each_pixel(|pt, px| {
+            data.extend(px.iter());
+            marching.state.push(if mask.get_f(pt, 0) >= 0.5 {
+                State::Inside
+            } else {
+                State::Known
+            });
+        });
+
+        let mut dest = self.clone();
+        if !marching.state.contains(&State::Known) || !marching.state.contains(&State::Inside) {
+            return dest;
+        }
+
+        // The initial front is made of the known pixels bordering the hole
+        let mut heap = std::collections::BinaryHeap::new();
+        for i in 0..width * height {
+            if marching.state[i] == State::Known
+                && marching
+                    .neighbors(i)
+                    .any(|j| marching.state[j] == State::Inside)
+            {
+                marching.state[i] = State::Band;
+                heap.push(Front {
+                    distance: 0.0,
+                    index: i,
+                });
+            }
+        }
+
+        let r = radius.max(1.0);
+        let reach = r.ceil() as isize;
+        let mut value = vec![0.0; channels];
+        while let Some(Front { distance, index }) = heap.pop() {
+            if marching.state[index] == State::Known || distance > marching.distance[index] {
+                continue;
+            }
+            marching.state[index] = State::Known;
+
+            let next: Vec<usize> = marching
+                .neighbors(index)
+                .filter(|j| marching.state[*j] == State::Inside)
+                .collect();
+            for i in next {
+                marching.state[i] = State::Band;
+                marching.distance[i] = marching.solve(i);
+
+                // Weighted average of the known pixels nearby
+                let (x, y) = ((i % width) as isize, (i / width) as isize);
+                let (nx, ny) = marching.normal(i);
+                let mut total = 0.0;
+                value.iter_mut().for_each(|v| *v = 0.0);
+                for dy in -reach..=reach {
+                    for dx in -reach..=reach {
+                        let (kx, ky) = (x + dx, y + dy);
+                        if kx < 0 || ky < 0 || kx as usize >= width || ky as usize >= height {
+                            continue;
+                        }
+                        let k = ky as usize * width + kx as usize;
+                        let length2 = (dx * dx + dy * dy) as f64;
+                        if marching.state[k] != State::Known || length2 > r * r || length2 == 0.0 {
+                            continue;
+                        }
+
+                        // Vector from the known pixel to the pixel being filled
+                        let (rx, ry) = (-dx as f64, -dy as f64);
+                        let length = length2.sqrt();
+                        let direction = ((rx * nx + ry * ny) / length).abs().max(1e-6);
+                        let level =
+                            1.0 / (1.0 + (marching.distance[k] - marching.distance[i]).abs());
+                        let w = direction * level / length2;
+
+                        let known = |j: usize| marching.state[j] == State::Known;
+                        for (c, v) in value.iter_mut().enumerate() {
+                            let at = |j: usize| data[j * channels + c];
+                            let (ku, kv) = (kx as usize, ky as usize);
+                            let gx = match (ku > 0 && known(k - 1), ku + 1 < width && known(k + 1))
+                            {
+                                (true, true) => (at(k + 1) - at(k - 1)) / 2.0,
+                                (true, false) => at(k) - at(k - 1),
+                                (false, true) => at(k + 1) - at(k),
+                                (false, false) => 0.0,
+                            };
+                            let gy = match (
+                                kv > 0 && known(k - width),
+                                kv + 1 < height && known(k + width),
+                            ) {
+                                (true, true) => (at(k + width) - at(k - width)) / 2.0,
+                                (true, false) => at(k) - at(k - width),
+                                (false, true) => at(k + width) - at(k),
+                                (false, false) => 0.0,
+                            };
+                            *v += w * (at(k) + gx * rx + gy * ry);
+                        }
+                        total += w;
+                    }
+                }
+
+                if total > 0.0 {
+                    for (c, v) in value.iter().enumerate() {
+                        data[i * channels + c] = v / total;
+                    }
+                }
+                heap.push(Front {
+                    distance: marching.distance[i],
+                    index: i,
+                });
+            }
+        }
+
+        dest.each_pixel_mut(|pt, mut px| {
+            let i = pt.y * width + pt.x;
+            for c in 0..channels {
+                px[c] = data[i * channels + c];
+            }
+        });
+        dest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_fill_region() {
+        // Horizontal stripes, two pixels wide
+        let mut image = Image::<f32, Gray>::new((48, 40));
+        image.for_each(|pt, mut px| px[0] = if pt.y % 4 < 2 { 1.0 } else { 0.0 });
+
+        let mut mask = Image::<u8, Gray>::new((48, 40));
+        mask.for_each(|pt, mut px| {
+            if (18..30).contains(&pt.x) && (14..26).contains(&pt.y) {
+                px[0] = 255;
+            }
+        });
+
+        let filled = image.fill_region(&mask);
+        let mut error = 0.0;
+        for y in 14..26 {
+            for x in 18..30 {
+                error += (filled.get((x, y))[0] - image.get((x, y))[0]).abs();
+            }
+        }
+        assert!(error / 144.0 < 0.1, "{}", error / 144.0);
+
+        // Pixels outside of the mask are unchanged
+        assert_eq!(filled.get((2, 2))[0], image.get((2, 2))[0]);
+
+        // Nothing to fill
+        let empty = Image::<u8, Gray>::new((48, 40));
+        assert!(image.fill_region(&empty) == image);
+    }
+
+    #[test]
+    fn test_inpaint() {
+        // A horizontal ramp with a vertical edge, crossed by a scratch
+        let mut image = Image::<f32, Rgb>::new((40, 30));
+        image.for_each(|pt, mut px| {
+            let v = pt.x as f32 / 39.0;
+            px.copy_from_slice([v, if pt.x < 20 { 0.2 } else { 0.8 }, 0.5]);
+        });
+
+        let mut mask = Image::<u8, Gray>::new((40, 30));
+        mask.for_each(|pt, mut px| {
+            if (13..16).contains(&pt.y) && (5..35).contains(&pt.x) {
+                px[0] = 255;
+            }
+        });
+
+        let filled = image.inpaint(&mask, 4.0);
+        let mut error = [0.0; 3];
+        for y in 13..16 {
+            for x in 5..35 {
+                for (c, e) in error.iter_mut().enumerate() {
+                    *e += (filled.get_f((x, y), c) - image.get_f((x, y), c)).abs() / 90.0;
+                }
+            }
+        }
+        assert!(error[0] < 0.01, "{:?}", error);
+        assert!(error[1] < 0.05, "{:?}", error);
+        assert!(error[2] < 1e-6, "{:?}", error);
+
+        // Pixels outside of the mask are unchanged
+        assert_eq!(filled.get_f((2, 2), 0), image.get_f((2, 2), 0));
+        assert!(image.inpaint(&Image::<u8, Gray>::new((40, 30)), 4.0) == image);
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct TileKey {
+    /// Hash of the caller-provided key, the filter, the inputs and the output size
+    eval: u64,
+    tile: (usize, usize, usize, usize),
+}
+
+/// Feeds formatted output straight into a hasher, so large `Debug` representations are hashed
+/// without being collected into a `String` first
+struct HashWriter<'a, H>(&'a mut H);
+
+impl<H: std::hash::Hasher> std::fmt::Write for HashWriter<'_, H> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.0.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Caches filter results one tile at a time, so re-running a filter only recomputes tiles whose
+/// inputs or filter parameters changed
+///
+/// Tiles are keyed by a hash of the input images, a caller-provided filter key, the filter
+/// parameters (using the `Debug` representation of the filter) and the tile position. Inputs are
+/// hashed using their exact pixel data, so even small edits invalidate the cached tiles.
+///
+/// The `Debug` output doesn't capture everything that affects a filter, closures passed to
+/// filters like `if_then_else` all print the same way, so the key should name the filter and
+/// change whenever it's rebuilt with different behaviour.
+pub struct TileCache<U: Type, D: Color> {
+    tile_size: usize,
+    capacity: usize,
+    bytes: usize,
+    tiles: HashMap<TileKey, Image<U, D>>,
+    order: VecDeque<TileKey>,
+    hits: usize,
+    misses: usize,
+}
+
+impl<U: Type, D: Color> TileCache<U, D> {
+    /// Create a new cache using square tiles of the given size
+    pub fn new(tile_size: usize) -> Self {
+        TileCache {
+            tile_size: tile_size.max(1),
+            capacity: 256 * 1024 * 1024,
+            bytes: 0,
+            tiles: HashMap::new(),
+            order: VecDeque::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Set the maximum number of bytes of pixel data kept in the cache, the oldest tiles are
+    /// evicted first. Defaults to 256MB.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self.evict();
+        self
+    }
+
+    /// Tile size
+    pub fn tile_size(&self) -> usize {
+        self.tile_size
+    }
+
+    /// Number of bytes of pixel data in the cache
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Number of cached tiles
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Returns true when no tiles are cached
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Number of tiles loaded from the cache
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Number of tiles that had to be computed
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Remove all cached tiles
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+        self.order.clear();
+        self.bytes = 0;
+    }
+
+    fn evict(&mut self) {
+        while self.bytes > self.capacity {
+            let tile = match self.order.pop_front() {
+                Some(key) => self.tiles.remove(&key),
+                None => break,
+            };
+            if let Some(tile) = tile {
+                self.bytes -= tile.buffer().len();
+            }
+        }
+    }
+
+    fn eval_key<T: Type, C: Color>(
+        key: &str,
+        filter: &impl Filter<T, C, U, D>,
+        input: &[&Image<T, C>],
+        output: &Image<U, D>,
+    ) -> u64 {
+        use std::fmt::Write;
+        use std::hash::Hasher;
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        hasher.write(key.as_bytes());
+        hasher.write_u8(0);
+        let _ = write!(HashWriter(&mut hasher), "{:?}", filter);
+        hasher.write(std::any::type_name::<(T, C)>().as_bytes());
+        for image in input {
+            hasher.write_usize(image.width());
+            hasher.write_usize(image.height());
+            hasher.write(image.buffer());
+        }
+        hasher.write_usize(output.width());
+        hasher.write_usize(output.height());
+        hasher.finish()
+    }
+
+    /// Evaluate a filter identified by `key`, reusing cached tiles when possible
+    pub fn eval<T: Type, C: Color>(
+        &mut self,
+        key: &str,
+        filter: &impl Filter<T, C, U, D>,
+        input: &[&Image<T, C>],
+        output: &mut Image<U, D>,
+    ) {
+        let roi = Region::new(Point::zero(), output.size());
+        self.eval_region(key, filter, input, output, roi)
+    }
+
+    /// Evaluate a filter identified by `key` for every tile that intersects `roi`, reusing cached
+    /// tiles when possible. The filter is only prepared once, and only if a tile is missing.
+    pub fn eval_region<T: Type, C: Color>(
+        &mut self,
+        key: &str,
+        filter: &impl Filter<T, C, U, D>,
+        input: &[&Image<T, C>],
+        output: &mut Image<U, D>,
+        roi: Region,
+    ) {
+        let bounds = Region::new(Point::zero(), output.size());
+        let roi = match roi.intersection(&bounds) {
+            Some(roi) => roi,
+            None => return,
+        };
+
+        let _stage = stage!("tile_cache", key = key, tile_size = self.tile_size);
+        #[cfg(feature = "trace")]
+        let (hits, misses) = (self.hits, self.misses);
+
+        let eval = Self::eval_key(key, filter, input, output);
+        let mut prepared = None;
+        let ts = self.tile_size;
+        for ty in roi.min_y() / ts..roi.max_y().div_ceil(ts) {
+            for tx in roi.min_x() / ts..roi.max_x().div_ceil(ts) {
+                let tile = Region::new(Point::new(tx * ts, ty * ts), Size::new(ts, ts));
+                let tile = match tile.intersection(&bounds) {
+                    Some(t) => t,
+                    None => continue,
+                };
+
+                let key = TileKey {
+                    eval,
+                    tile: (tile.min_x(), tile.min_y(), tile.width(), tile.height()),
+                };
+
+                if let Some(data) = self.tiles.get(&key) {
+                    self.hits += 1;
+                    output.copy_from_region(Point::zero(), data, tile);
+                    continue;
+                }
+
+                self.misses += 1;
+                let input = prepared
+                    .get_or_insert_with(|| Input::new(input).prepare(filter, output.size()));
+                output
+                    .for_each_region(tile, |pt, mut data| filter.compute_at(pt, input, &mut data));
+                let data = output.crop(tile);
+                self.bytes += data.buffer().len();
+                self.tiles.insert(key, data);
+                self.order.push_back(key);
+                self.evict();
+            }
+        }
+
+        trace_event!(
+            hits = self.hits - hits,
+            misses = self.misses - misses,
+            "tiles"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_tile_cache() {
+        let mut image = Image::<f32, Rgb>::new((40, 24));
+        image.for_each(|pt, mut px| px[0] = pt.x as f32 / 40.0);
+
+        let mut cache = TileCache::new(16);
+        let mut a = image.new_like();
+        cache.eval("brightness", &filter::brightness(0.5), &[&image], &mut a);
+        assert_eq!(cache.misses(), 6);
+        assert_eq!(cache.hits(), 0);
+
+        let mut b = image.new_like();
+        cache.eval("brightness", &filter::brightness(0.5), &[&image], &mut b);
+        assert_eq!(cache.hits(), 6);
+        assert!(a == b);
+
+        // Changing the parameters invalidates every tile
+        cache.eval("brightness", &filter::brightness(0.25), &[&image], &mut b);
+        assert_eq!(cache.misses(), 12);
+
+        // Changing the input only recomputes the tiles in the requested region
+        image.set((1, 1), [1.0, 1.0, 1.0]);
+        cache.eval_region(
+            "brightness",
+            &filter::brightness(0.25),
+            &[&image],
+            &mut b,
+            Region::new(Point::new(0, 0), Size::new(10, 10)),
+        );
+        assert_eq!(cache.misses(), 13);
+        assert_eq!(cache.len(), 13);
+
+        // The cache is limited by the size of the pixel data
+        let tile_bytes = 16 * 16 * 3 * std::mem::size_of::<f32>();
+        assert!(cache.bytes() <= 13 * tile_bytes);
+        let cache = cache.with_capacity(tile_bytes * 4);
+        assert!(cache.bytes() <= tile_bytes * 4);
+        assert!(cache.len() < 13 && !cache.is_empty());
+    }
+
+    #[test]
+    fn test_tile_cache_prepared() {
+        let mut image = Image::<f32, Rgb>::new((40, 24));
+        image.for_each(|pt, mut px| px[0] = if pt.x < 20 { 0.0 } else { 1.0 });
+
+        let mut expected = image.new_like();
+        filter::gaussian_blur(2.0).eval(&[&image], &mut expected);
+
+        let mut cache = TileCache::new(16);
+        let mut dest = image.new_like();
+        cache.eval("blur", &filter::gaussian_blur(2.0), &[&image], &mut dest);
+        assert!(dest == expected);
+
+        // The whole image is prepared once per call, not once per tile
+        let counter = CountPrepare(AtomicUsize::new(0));
+        cache.eval("count", &counter, &[&image], &mut dest);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+        cache.eval("count", &counter, &[&image], &mut dest);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+    }
+
+    struct CountPrepare(AtomicUsize);
+
+    impl std::fmt::Debug for CountPrepare {
+        fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+            fmt.write_str("CountPrepare")
+        }
+    }
+
+    impl Filter<f32, Rgb> for CountPrepare {
+        fn prepare(
+            &self,
+            _input: &Input<f32, Rgb>,
+        ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            None
+        }
+
+        fn compute_at(&self, pt: Point, input: &Input<f32, Rgb>, dest: &mut DataMut<f32, Rgb>) {
+            input.get_pixel(pt, None).copy_to_slice(dest);
+        }
+    }
+
+    #[test]
+    fn test_tile_cache_key() {
+        let image = Image::<f32, Rgb>::new((20, 20));
+
+        let mut cache = TileCache::new(16);
+        let mut a = image.new_like();
+        let filter = filter::if_then_else(|_, _| true, filter::invert(), filter::noop());
+        cache.eval("invert", &filter, &[&image], &mut a);
+
+        // Filters built from different closures print the same, the key tells them apart
+        let mut b = image.new_like();
+        let filter = filter::if_then_else(|_, _| false, filter::invert(), filter::noop());
+        cache.eval("noop", &filter, &[&image], &mut b);
+        assert_eq!(cache.hits(), 0);
+        assert!(a != b);
+    }
+}
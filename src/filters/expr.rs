@@ -0,0 +1,442 @@
+use crate::*;
+
+/// Values available to an expression while evaluating a single pixel
+struct Env {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    inputs: Vec<[f64; 4]>,
+}
+
+/// Compiled expression
+type Compiled = Box<dyn Fn(&Env) -> f64 + Send + Sync>;
+
+/// Comparison operator
+type Comparison = fn(f64, f64) -> bool;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(&'static str),
+}
+
+/// Operators, longer operators come first so they are matched before their prefixes
+const OPERATORS: [&str; 20] = [
+    "<=", ">=", "==", "!=", "&&", "||", "+", "-", "*", "/", "%", "^", "<", ">", "!", "?", ":", "(",
+    ")", ",",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+    while let Some(ch) = rest.chars().next() {
+        if ch.is_ascii_digit() || ch == '.' {
+            let end = rest
+                .char_indices()
+                .find(|(i, c)| {
+                    !(c.is_ascii_digit()
+                        || *c == '.'
+                        || ((*c == 'e' || *c == 'E') && *i > 0)
+                        || ((*c == '-' || *c == '+')
+                            && matches!(rest[..*i].chars().last(), Some('e' | 'E'))))
+                })
+                .map_or(rest.len(), |(i, _)| i);
+            let number = rest[..end]
+                .parse()
+                .map_err(|_| invalid(format!("invalid number {}", &rest[..end])))?;
+            tokens.push(Token::Number(number));
+            rest = &rest[end..];
+        } else if ch.is_alphabetic() || ch == '_' {
+            let end = rest
+                .char_indices()
+                .find(|(_, c)| !(c.is_alphanumeric() || *c == '_'))
+                .map_or(rest.len(), |(i, _)| i);
+            tokens.push(Token::Ident(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else {
+            return Err(invalid(format!("unexpected character {}", ch)));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+fn invalid(message: impl std::fmt::Display) -> Error {
+    Error::Message(format!("Invalid expression: {}", message))
+}
+
+/// Convert a boolean to 1 or 0
+fn truth(b: bool) -> f64 {
+    if b {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Recursive descent parser producing closures directly
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    inputs: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn accept(&mut self, op: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Op(o)) if *o == op) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), Error> {
+        if self.accept(op) {
+            Ok(())
+        } else {
+            Err(invalid(format!("expected {}", op)))
+        }
+    }
+
+    fn list(&mut self) -> Result<Vec<Compiled>, Error> {
+        let mut list = vec![self.expr()?];
+        while self.accept(",") {
+            list.push(self.expr()?);
+        }
+        if let Some(token) = self.peek() {
+            return Err(invalid(format!("unexpected {:?}", token)));
+        }
+        Ok(list)
+    }
+
+    fn expr(&mut self) -> Result<Compiled, Error> {
+        let cond = self.or()?;
+        if !self.accept("?") {
+            return Ok(cond);
+        }
+        let a = self.expr()?;
+        self.expect(":")?;
+        let b = self.expr()?;
+        Ok(Box::new(
+            move |env| {
+                if cond(env) != 0.0 {
+                    a(env)
+                } else {
+                    b(env)
+                }
+            },
+        ))
+    }
+
+    fn or(&mut self) -> Result<Compiled, Error> {
+        let mut lhs = self.and()?;
+        while self.accept("||") {
+            let rhs = self.and()?;
+            lhs = Box::new(move |env| truth(lhs(env) != 0.0 || rhs(env) != 0.0));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Compiled, Error> {
+        let mut lhs = self.comparison()?;
+        while self.accept("&&") {
+            let rhs = self.comparison()?;
+            lhs = Box::new(move |env| truth(lhs(env) != 0.0 && rhs(env) != 0.0));
+        }
+        Ok(lhs)
+    }
+
+    fn comparison(&mut self) -> Result<Compiled, Error> {
+        let lhs = self.sum()?;
+        let ops: [(&str, Comparison); 6] = [
+            ("<=", |a, b| a <= b),
+            (">=", |a, b| a >= b),
+            ("==", |a, b| a == b),
+            ("!=", |a, b| a != b),
+            ("<", |a, b| a < b),
+            (">", |a, b| a > b),
+        ];
+        for (op, f) in ops {
+            if self.accept(op) {
+                let rhs = self.sum()?;
+                return Ok(Box::new(move |env| truth(f(lhs(env), rhs(env)))));
+            }
+        }
+        Ok(lhs)
+    }
+
+    fn sum(&mut self) -> Result<Compiled, Error> {
+        let mut lhs = self.product()?;
+        loop {
+            if self.accept("+") {
+                let rhs = self.product()?;
+                lhs = Box::new(move |env| lhs(env) + rhs(env));
+            } else if self.accept("-") {
+                let rhs = self.product()?;
+                lhs = Box::new(move |env| lhs(env) - rhs(env));
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<Compiled, Error> {
+        let mut lhs = self.unary()?;
+        loop {
+            if self.accept("*") {
+                let rhs = self.unary()?;
+                lhs = Box::new(move |env| lhs(env) * rhs(env));
+            } else if self.accept("/") {
+                let rhs = self.unary()?;
+                lhs = Box::new(move |env| lhs(env) / rhs(env));
+            } else if self.accept("%") {
+                let rhs = self.unary()?;
+                lhs = Box::new(move |env| lhs(env).rem_euclid(rhs(env)));
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Compiled, Error> {
+        if self.accept("-") {
+            let x = self.unary()?;
+            return Ok(Box::new(move |env| -x(env)));
+        }
+        if self.accept("!") {
+            let x = self.unary()?;
+            return Ok(Box::new(move |env| truth(x(env) == 0.0)));
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Compiled, Error> {
+        let base = self.atom()?;
+        if self.accept("^") {
+            let exp = self.unary()?;
+            return Ok(Box::new(move |env| base(env).powf(exp(env))));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Compiled, Error> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| invalid("unexpected end of input"))?;
+        self.pos += 1;
+        match token {
+            Token::Number(n) => Ok(Box::new(move |_| n)),
+            Token::Op("(") => {
+                let x = self.expr()?;
+                self.expect(")")?;
+                Ok(x)
+            }
+            Token::Ident(name) if self.accept("(") => {
+                let mut args = Vec::new();
+                if !self.accept(")") {
+                    args.push(self.expr()?);
+                    while self.accept(",") {
+                        args.push(self.expr()?);
+                    }
+                    self.expect(")")?;
+                }
+                function(&name, args)
+            }
+            Token::Ident(name) => self.variable(&name),
+            Token::Op(op) => Err(invalid(format!("unexpected {}", op))),
+        }
+    }
+
+    fn variable(&mut self, name: &str) -> Result<Compiled, Error> {
+        let f: Compiled = match name {
+            "x" => Box::new(|env| env.x),
+            "y" => Box::new(|env| env.y),
+            "w" => Box::new(|env| env.width),
+            "h" => Box::new(|env| env.height),
+            "u" => Box::new(|env| env.x / (env.width - 1.0).max(1.0)),
+            "v" => Box::new(|env| env.y / (env.height - 1.0).max(1.0)),
+            "pi" => Box::new(|_| std::f64::consts::PI),
+            "e" => Box::new(|_| std::f64::consts::E),
+            _ => {
+                // Channels of the first input are `r`, `g`, `b` and `a`, other inputs are
+                // referenced by adding the input index, like `r1`
+                let mut chars = name.chars();
+                let channel = match chars.next() {
+                    Some('r') => 0,
+                    Some('g') => 1,
+                    Some('b') => 2,
+                    Some('a') => 3,
+                    _ => return Err(invalid(format!("unknown variable {}", name))),
+                };
+                let index = match chars.as_str() {
+                    "" => 0,
+                    n => n
+                        .parse::<usize>()
+                        .map_err(|_| invalid(format!("unknown variable {}", name)))?,
+                };
+                self.inputs = self.inputs.max(index + 1);
+                Box::new(move |env| env.inputs[index][channel])
+            }
+        };
+        Ok(f)
+    }
+}
+
+fn function(name: &str, args: Vec<Compiled>) -> Result<Compiled, Error> {
+    let unary: Option<fn(f64) -> f64> = match name {
+        "abs" => Some(f64::abs),
+        "sqrt" => Some(f64::sqrt),
+        "exp" => Some(f64::exp),
+        "ln" | "log" => Some(f64::ln),
+        "sin" => Some(f64::sin),
+        "cos" => Some(f64::cos),
+        "tan" => Some(f64::tan),
+        "floor" => Some(f64::floor),
+        "ceil" => Some(f64::ceil),
+        "round" => Some(f64::round),
+        _ => None,
+    };
+    let binary: Option<fn(f64, f64) -> f64> = match name {
+        "min" => Some(f64::min),
+        "max" => Some(f64::max),
+        "pow" => Some(f64::powf),
+        "atan2" => Some(f64::atan2),
+        "step" => Some(|edge, x| truth(x >= edge)),
+        _ => None,
+    };
+    let ternary: Option<fn(f64, f64, f64) -> f64> = match name {
+        "clamp" => Some(|x, lo, hi| x.max(lo).min(hi)),
+        "mix" => Some(|a, b, t| a + (b - a) * t),
+        _ => None,
+    };
+
+    let mut args = args.into_iter();
+    let f: Compiled = match (unary, binary, ternary, args.len()) {
+        (Some(f), _, _, 1) => {
+            let a = args.next().unwrap();
+            Box::new(move |env| f(a(env)))
+        }
+        (_, Some(f), _, 2) => {
+            let (a, b) = (args.next().unwrap(), args.next().unwrap());
+            Box::new(move |env| f(a(env), b(env)))
+        }
+        (_, _, Some(f), 3) => {
+            let (a, b, c) = (
+                args.next().unwrap(),
+                args.next().unwrap(),
+                args.next().unwrap(),
+            );
+            Box::new(move |env| f(a(env), b(env), c(env)))
+        }
+        (None, None, None, _) => return Err(invalid(format!("unknown function {}", name))),
+        (_, _, _, n) => {
+            return Err(invalid(format!(
+                "wrong number of arguments for {}: {}",
+                name, n
+            )))
+        }
+    };
+    Ok(f)
+}
+
+/// Filter defined by a list of comma separated expressions that are evaluated for every pixel,
+/// which makes it possible to experiment without recompiling. Expressions are parsed once and
+/// compiled into closures.
+///
+/// One expression produces a `Gray` pixel, three produce `Rgb` and four produce `Rgba`. The
+/// channels of each input are available as `r`, `g`, `b` and `a` after conversion to `Rgba`,
+/// add the input index to read other inputs, for example `r1`. The position is available as
+/// `x` and `y`, the output size as `w` and `h` and the normalized position as `u` and `v`.
+///
+/// Expressions support arithmetic, `^` for powers, comparisons, `&&`, `||`, `!`,
+/// `cond ? a : b`, the constants `pi` and `e`, and the functions `abs`, `sqrt`, `exp`, `ln`,
+/// `sin`, `cos`, `tan`, `floor`, `ceil`, `round`, `min`, `max`, `pow`, `atan2`, `step`,
+/// `clamp` and `mix`. Comparisons and logical operators produce 1 or 0.
+pub struct Expr {
+    source: String,
+    channels: Vec<Compiled>,
+    inputs: usize,
+}
+
+impl std::fmt::Debug for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Expr").field(&self.source).finish()
+    }
+}
+
+impl Expr {
+    /// Parse and compile an expression
+    pub fn new(source: impl Into<String>) -> Result<Expr, Error> {
+        let source = source.into();
+        let mut parser = Parser {
+            tokens: tokenize(&source)?,
+            pos: 0,
+            inputs: 0,
+        };
+        let channels = parser.list()?;
+        if ![1, 3, 4].contains(&channels.len()) {
+            return Err(invalid(format!(
+                "expected 1, 3 or 4 channels, got {}",
+                channels.len()
+            )));
+        }
+        Ok(Expr {
+            source,
+            channels,
+            inputs: parser.inputs,
+        })
+    }
+
+    /// Expression source
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Number of inputs referenced by the expression
+    pub fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    /// Evaluate the expression at `pt` for the given input pixels, returns one value per
+    /// expression
+    pub fn eval_at(&self, pt: Point, size: Size, inputs: &[Pixel<Rgba>]) -> Vec<f64> {
+        let env = Env {
+            x: pt.x as f64,
+            y: pt.y as f64,
+            width: size.width as f64,
+            height: size.height as f64,
+            inputs: (0..self.inputs)
+                .map(|i| match inputs.get(i) {
+                    Some(px) => [px[0], px[1], px[2], px[3]],
+                    None => [0.0; 4],
+                })
+                .collect(),
+        };
+        self.channels.iter().map(|f| f(&env)).collect()
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Expr {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let pixels: Vec<Pixel<Rgba>> = (0..self.inputs.min(input.len()))
+            .map(|i| input.sample_pixel(pt, i).convert())
+            .collect();
+        let size = input.size().unwrap_or_default();
+        let values = self.eval_at(pt, size, &pixels);
+        match values.len() {
+            1 => Pixel::<Gray>::from(values).convert_to_data(dest),
+            3 => Pixel::<Rgb>::from(values).convert_to_data(dest),
+            _ => Pixel::<Rgba>::from(values).convert_to_data(dest),
+        }
+    }
+}
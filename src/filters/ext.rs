@@ -14,7 +14,7 @@ pub trait FilterExt<T: Type, C: Color, U: Type, D: Color>: Sized + Filter<T, C,
         AsyncFilter {
             mode,
             filter: self,
-            input,
+            input: input.prepare(self, output.size()),
             output,
             x: 0,
             y: 0,
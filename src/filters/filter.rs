@@ -1,18 +1,170 @@
 use crate::*;
 
+use crate::math::{invert3, mat3_apply, mat3_mul};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Controls how `convert_with_alpha` handles alpha when converting between colors
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AlphaMode {
+    /// Color is multiplied by alpha and the output alpha is always opaque, even when both colors
+    /// have an alpha channel. This matches `Pixel::convert` and is used by `convert`.
+    #[default]
+    Premultiply,
+
+    /// Color is left unchanged when alpha is removed and alpha is opaque when it is added. When
+    /// both colors have an alpha channel it is copied unchanged.
+    Straight,
+
+    /// Color is left unchanged when alpha is removed and alpha is set to the given normalized
+    /// value when it is added. When both colors have an alpha channel it is copied unchanged.
+    Fill(f64),
+}
+
 /// Convert between colors
 #[derive(Clone, Copy, Default, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-struct Convert<T: Color>(std::marker::PhantomData<T>);
+struct Convert<T: Color>(AlphaMode, std::marker::PhantomData<T>);
 
 /// Create new color conversion filter
 pub fn convert<T: Type, C: Color, U: Type, D: Color>() -> impl Filter<T, C, U, D> {
-    Convert(std::marker::PhantomData)
+    Convert(AlphaMode::Premultiply, std::marker::PhantomData)
+}
+
+/// Create new color conversion filter using the given alpha policy
+pub fn convert_with_alpha<T: Type, C: Color, U: Type, D: Color>(
+    alpha: AlphaMode,
+) -> impl Filter<T, C, U, D> {
+    Convert(alpha, std::marker::PhantomData)
+}
+
+impl<D: Color> Convert<D> {
+    fn fill(&self) -> f64 {
+        match self.0 {
+            AlphaMode::Fill(x) => x,
+            _ => 1.0,
+        }
+    }
+
+    /// Convert a whole image without going through `Pixel`, for the most common pairs of colors.
+    /// Returns false when there is no fast path for the given colors.
+    fn convert_fast<T: Type, C: Color, U: Type>(
+        &self,
+        input: &Image<T, C>,
+        output: &mut Image<U, D>,
+    ) -> bool {
+        use std::any::TypeId;
+
+        let is = |a: TypeId, b: TypeId| TypeId::of::<C>() == a && TypeId::of::<D>() == b;
+        let (gray, rgb, rgba) = (
+            TypeId::of::<Gray>(),
+            TypeId::of::<Rgb>(),
+            TypeId::of::<Rgba>(),
+        );
+        let (srgb, srgba) = (TypeId::of::<Srgb>(), TypeId::of::<Srgba>());
+        let fill = self.fill();
+        let premultiply = self.0 == AlphaMode::Premultiply;
+
+        if premultiply && (is(rgba, rgba) || is(srgba, srgba)) {
+            convert_rows(input, output, |s, d| {
+                let a = s[3];
+                d.iter_mut().zip(s).for_each(|(d, s)| *d = s * a);
+                d[3] = 1.0;
+            });
+        } else if TypeId::of::<C>() == TypeId::of::<D>() && (!premultiply || C::ALPHA.is_none()) {
+            output.rows_mut().for_each(|(y, row)| {
+                let src = &input.data()[y * row.len()..(y + 1) * row.len()];
+                for (d, s) in row.iter_mut().zip(src) {
+                    *d = U::from_norm(s.to_norm());
+                }
+            });
+        } else if is(rgb, rgba) || is(srgb, srgba) {
+            convert_rows(input, output, |s, d| {
+                d[..3].copy_from_slice(s);
+                d[3] = fill;
+            });
+        } else if is(rgba, rgb) || is(srgba, srgb) {
+            convert_rows(input, output, |s, d| {
+                let a = if premultiply { s[3] } else { 1.0 };
+                d.iter_mut().zip(s).for_each(|(d, s)| *d = s * a);
+            });
+        } else if is(rgb, gray) {
+            convert_rows(input, output, |s, d| {
+                d[0] = s[0] * 0.21 + s[1] * 0.72 + s[2] * 0.07;
+            });
+        } else if is(gray, rgb) {
+            convert_rows(input, output, |s, d| d.fill(s[0]));
+        } else {
+            return false;
+        }
+        true
+    }
+}
+
+/// Apply `f` to every pixel using normalized values, one row at a time
+fn convert_rows<T: Type, C: Color, U: Type, D: Color>(
+    input: &Image<T, C>,
+    output: &mut Image<U, D>,
+    f: impl Fn(&[f64], &mut [f64]) + Sync,
+) {
+    let width = input.width();
+    output.rows_mut().for_each(|(y, row)| {
+        let src = &input.data()[y * width * C::CHANNELS..(y + 1) * width * C::CHANNELS];
+        let mut s = vec![0.0; C::CHANNELS];
+        let mut d = vec![0.0; D::CHANNELS];
+        for (src, dest) in src
+            .chunks_exact(C::CHANNELS)
+            .zip(row.chunks_exact_mut(D::CHANNELS))
+        {
+            s.iter_mut().zip(src).for_each(|(a, b)| *a = b.to_norm());
+            f(&s, &mut d);
+            dest.iter_mut()
+                .zip(&d)
+                .for_each(|(a, b)| *a = U::from_norm(*b));
+        }
+    });
 }
 
 impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Convert<D> {
     fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
-        input.get_pixel(pt, None).convert_to_data(dest);
+        let mut px = input.get_pixel(pt, None);
+        if self.0 == AlphaMode::Premultiply {
+            px.convert_to_data(dest);
+            return;
+        }
+
+        let alpha = C::ALPHA.map(|a| px[a]);
+        if let Some(a) = C::ALPHA {
+            px[a] = 1.0;
+        }
+
+        let mut px: Pixel<D> = px.convert();
+        if let Some(a) = D::ALPHA {
+            px[a] = alpha.unwrap_or(self.fill());
+        }
+        px.copy_to_slice(dest);
+    }
+
+    fn eval(&self, input: &[&Image<T, C>], output: &mut Image<U, D>) {
+        let _stage = stage!(
+            "eval",
+            filter = ?self,
+            width = output.width(),
+            rows = output.height()
+        );
+        if input.len() == 1
+            && input[0].size() == output.size()
+            && self.convert_fast(input[0], output)
+        {
+            return;
+        }
+
+        let input = Input::new(input).prepare::<U, D, _>(self, output.size());
+        output.for_each(|pt, mut data| {
+            self.compute_at(pt, &input, &mut data);
+        });
     }
 }
 
@@ -34,6 +186,27 @@ impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Saturation {
     }
 }
 
+/// Rotate hue by the given number of degrees. The rotation is applied to the a/b chroma plane of
+/// `Lab`, so lightness and chroma are preserved. Alpha is not affected.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HueRotate(pub f64);
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for HueRotate {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, D>) {
+        let (sin, cos) = self.0.to_radians().sin_cos();
+        map_linear_rgb(pt, input, data, |rgb| {
+            // Normalized `Lab` stores a and b offset by 128/255
+            let center = 128.0 / 255.0;
+            let mut lab: Pixel<Lab> = rgb.convert();
+            let (a, b) = (lab[1] - center, lab[2] - center);
+            lab[1] = a * cos - b * sin + center;
+            lab[2] = a * sin + b * cos + center;
+            lab.convert_to(rgb);
+        });
+    }
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct Brightness(f64);
@@ -51,20 +224,57 @@ impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Brightness {
     }
 }
 
-#[derive(Debug)]
+/// Adjust exposure in photographic stops, values are converted to linear light and multiplied by
+/// `2^stops`. Alpha is left unchanged.
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-struct Exposure(f64);
+pub struct Exposure {
+    /// Number of stops to increase or decrease exposure by
+    pub stops: f64,
+
+    /// When set, values above this knee are smoothly compressed so they approach 1.0 instead of
+    /// clipping
+    pub rolloff: Option<f64>,
+}
+
+impl Exposure {
+    /// Create a new exposure filter without highlight rolloff
+    pub fn new(stops: f64) -> Exposure {
+        Exposure {
+            stops,
+            rolloff: None,
+        }
+    }
+
+    /// Compress highlights above `knee`, which should be between 0 and 1
+    pub fn with_rolloff(mut self, knee: f64) -> Exposure {
+        self.rolloff = Some(knee.clamp(0.0, 1.0 - f64::EPSILON));
+        self
+    }
+
+    /// Apply exposure and rolloff to a single linear value
+    pub fn apply(&self, value: f64) -> f64 {
+        let x = value * 2f64.powf(self.stops);
+        match self.rolloff {
+            Some(knee) if x > knee => {
+                let range = 1.0 - knee;
+                knee + range * (1.0 - (-(x - knee) / range).exp())
+            }
+            _ => x,
+        }
+    }
+}
 
 /// Adjust image exposure, the argument is the number of stops to increase or decrease exposure by
 pub fn exposure<T: Type, C: Color, U: Type, D: Color>(stops: f64) -> impl Filter<T, C, U, D> {
-    Exposure(stops)
+    Exposure::new(stops)
 }
 
 impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Exposure {
     fn compute_at(&self, pt: Point, input: &Input<T, C>, data: &mut DataMut<U, D>) {
-        let mut px = input.get_pixel(pt, None);
-        px *= 2f64.powf(self.0);
-        px.convert_to_data(data);
+        map_linear_rgb(pt, input, data, |rgb| {
+            rgb.iter_mut().for_each(|x| *x = self.apply(*x))
+        });
     }
 }
 
@@ -133,15 +343,23 @@ impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Invert {
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct Blend;
 
-/// Average two images
+/// Average two images, the second image is stretched to cover the first if they differ in size
 pub fn blend<T: Type, C: Color, U: Type, D: Color>() -> impl Filter<T, C, U, D> {
     Blend
 }
 
 impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Blend {
+    fn sampling(&self, index: usize) -> Sampling {
+        if index == 0 {
+            Sampling::TopLeft
+        } else {
+            Sampling::Normalized
+        }
+    }
+
     fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
         let a = input.get_pixel(pt, None);
-        let b = input.get_pixel(pt, Some(1));
+        let b = input.sample_pixel(pt, 1);
         ((a + &b) / 2.).copy_to_slice(dest);
     }
 }
@@ -184,6 +402,192 @@ impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for GammaLin {
     }
 }
 
+/// Convert a pixel to linear RGB, apply `f` and convert the result to `D`. Alpha is copied
+/// through unchanged instead of being multiplied into the color
+fn map_linear_rgb<T: Type, C: Color, U: Type, D: Color>(
+    pt: Point,
+    input: &Input<T, C>,
+    dest: &mut DataMut<U, D>,
+    f: impl Fn(&mut Pixel<Rgb>),
+) {
+    let mut px = input.get_pixel(pt, None);
+
+    // Color conversion multiplies by alpha, so convert the opaque color instead
+    let alpha = C::ALPHA.map(|a| std::mem::replace(&mut px[a], 1.0));
+    let mut rgb: Pixel<Rgb> = px.convert();
+    f(&mut rgb);
+
+    let mut out: Pixel<D> = rgb.convert();
+    if let (Some(alpha), Some(a)) = (alpha, D::ALPHA) {
+        out[a] = alpha;
+    }
+    out.copy_to_slice(dest);
+}
+
+/// Decode a normalized sRGB value to linear light using the exact piecewise transfer function
+pub fn srgb_to_linear_value(x: f64) -> f64 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encode a normalized linear value using the exact piecewise sRGB transfer function
+pub fn linear_to_srgb_value(x: f64) -> f64 {
+    if x <= 0.0031308 {
+        x * 12.92
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Apply `f` to every channel except alpha, channel values are copied without any color
+/// conversion
+fn map_color_channels<T: Type, C: Color, U: Type, D: Color>(
+    pt: Point,
+    input: &Input<T, C>,
+    dest: &mut DataMut<U, D>,
+    f: impl Fn(f64) -> f64,
+) {
+    let mut px = input.get_pixel(pt, None);
+    for c in (0..C::CHANNELS).filter(|c| Some(*c) != C::ALPHA) {
+        px[c] = f(px[c]);
+    }
+    px.copy_to_slice(dest);
+}
+
+/// Decode sRGB encoded values to linear light using the exact sRGB transfer function, unlike
+/// color conversions the values are transformed in place regardless of the color type. Alpha is
+/// not affected.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SrgbToLinear;
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for SrgbToLinear {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        map_color_channels(pt, input, dest, |x| {
+            x.signum() * srgb_to_linear_value(x.abs())
+        })
+    }
+}
+
+/// Encode linear values using the exact sRGB transfer function, see `SrgbToLinear`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinearToSrgb;
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for LinearToSrgb {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        map_color_channels(pt, input, dest, |x| {
+            x.signum() * linear_to_srgb_value(x.abs())
+        })
+    }
+}
+
+/// Raise every channel except alpha to the power of `1 / gamma`, values greater than 1 brighten
+/// the image. Use `Gamma(2.2)` to encode and `Gamma(1.0 / 2.2)` to decode a pure power curve.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Gamma(pub f64);
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Gamma {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let exp = 1.0 / self.0;
+        map_color_channels(pt, input, dest, |x| x.signum() * x.abs().powf(exp))
+    }
+}
+
+/// Reduce every channel except alpha to the given number of evenly spaced levels, values are
+/// clamped to the 0-1 range. Unlike `OrderedDither` no noise is added, which produces flat bands
+/// of color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Posterize(pub usize);
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Posterize {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        map_color_channels(pt, input, dest, |x| quantize(x, self.0))
+    }
+}
+
+/// Invert channel values above the threshold, imitating a photographic print that was exposed
+/// to light during development. Alpha is not affected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Solarize(pub f64);
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Solarize {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        map_color_channels(pt, input, dest, |x| if x > self.0 { 1.0 - x } else { x })
+    }
+}
+
+/// Add gaussian noise with a standard deviation of `amount` to every channel except alpha. The
+/// noise only depends on the seed and the pixel position, so the output is reproducible.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Noise {
+    /// Standard deviation of the noise
+    pub amount: f64,
+
+    /// Add the same noise to every channel instead of independent noise per channel
+    pub monochrome: bool,
+
+    /// Random seed
+    pub seed: Seed,
+}
+
+impl Noise {
+    /// Create a new noise filter
+    pub fn new(amount: f64) -> Noise {
+        Noise {
+            amount,
+            monochrome: false,
+            seed: Seed::default(),
+        }
+    }
+
+    /// Enable or disable monochrome noise
+    pub fn with_monochrome(mut self, monochrome: bool) -> Noise {
+        self.monochrome = monochrome;
+        self
+    }
+
+    /// Set the random seed
+    pub fn with_seed(mut self, seed: impl Into<Seed>) -> Noise {
+        self.seed = seed.into();
+        self
+    }
+}
+
+impl Seeded for Noise {
+    fn seed(&self) -> Seed {
+        self.seed
+    }
+
+    fn set_seed(&mut self, seed: Seed) {
+        self.seed = seed;
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Noise {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let mut rng = self.seed.at(pt.x, pt.y);
+        let shared = rng.normal();
+        let mut px = input.get_pixel(pt, None);
+        for c in (0..C::CHANNELS).filter(|c| Some(*c) != C::ALPHA) {
+            let n = if self.monochrome {
+                shared
+            } else {
+                rng.normal()
+            };
+            px[c] += n * self.amount;
+        }
+        px.copy_to_slice(dest);
+    }
+}
+
 /// Conditional filter
 struct If<
     F: Fn(Point, &Input<T, C>) -> bool,
@@ -200,6 +604,26 @@ struct If<
     _t: std::marker::PhantomData<(T, C, U, D)>,
 }
 
+/// Output of each branch of an `If` filter that prepares data, evaluated up front
+struct Branches<U: Type, D: Color> {
+    then: Option<Image<U, D>>,
+    else_: Option<Image<U, D>>,
+}
+
+/// Evaluate `filter` over the whole output if it prepares any data. Its `compute_at` expects an
+/// input carrying that data, so the result is computed once instead of per pixel.
+fn eval_prepared<T: Type, C: Color, U: Type, D: Color>(
+    filter: &impl Filter<T, C, U, D>,
+    input: &Input<T, C>,
+) -> Option<Image<U, D>> {
+    let prepared = filter.prepare(input)?;
+    let size = input.size().unwrap_or_else(|| input.images()[0].size());
+    let input = input.clone().with_prepared(Some(prepared));
+    let mut output = Image::new(size);
+    output.for_each(|pt, mut data| filter.compute_at(pt, &input, &mut data));
+    Some(output)
+}
+
 /// Create new conditional filter
 pub fn if_then_else<
     F: Sync + Fn(Point, &Input<T, C>) -> bool,
@@ -259,11 +683,33 @@ impl<
         Schedule::Pixel
     }
 
+    fn prepare(
+        &self,
+        input: &Input<T, C>,
+    ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+        let then = eval_prepared(&self.then, input);
+        let else_ = eval_prepared(&self.else_, input);
+        if then.is_none() && else_.is_none() {
+            return None;
+        }
+
+        Some(std::sync::Arc::new(Branches { then, else_ }))
+    }
+
     fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
-        if (self.cond)(pt, input) {
-            self.then.compute_at(pt, input, dest)
-        } else {
-            self.else_.compute_at(pt, input, dest)
+        let cond = (self.cond)(pt, input);
+        let branches = input.prepared::<Branches<U, D>>();
+        let output = branches.and_then(|b| {
+            if cond {
+                b.then.as_ref()
+            } else {
+                b.else_.as_ref()
+            }
+        });
+        match output {
+            Some(image) if image.in_bounds(pt) => dest.copy_from_slice(image.get(pt)),
+            _ if cond => self.then.compute_at(pt, input, dest),
+            _ => self.else_.compute_at(pt, input, dest),
         }
     }
 }
@@ -402,3 +848,1942 @@ pub fn rotate270<T: Type, C: Color, U: Type, D: Color>(
         Point::new((width / 2.) as usize, (dheight / 2.) as usize),
     )
 }
+
+#[derive(Debug)]
+struct PerChannel<F> {
+    filter: F,
+    channels: Channels,
+}
+
+/// Restrict a filter to the selected channels, all other channels are copied from the input
+pub fn per_channel<T: Type, C: Color, U: Type, D: Color>(
+    filter: impl Filter<T, C, U, D>,
+    channels: impl Into<Channels>,
+) -> impl Filter<T, C, U, D> {
+    PerChannel {
+        filter,
+        channels: channels.into(),
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color, F: Filter<T, C, U, D>> Filter<T, C, U, D>
+    for PerChannel<F>
+{
+    fn schedule(&self) -> Schedule {
+        self.filter.schedule()
+    }
+
+    fn output_size(&self, input: &Input<T, C>, dest: &mut Image<U, D>) -> Size {
+        self.filter.output_size(input, dest)
+    }
+
+    fn sampling(&self, index: usize) -> Sampling {
+        self.filter.sampling(index)
+    }
+
+    fn prepare(
+        &self,
+        input: &Input<T, C>,
+    ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+        self.filter.prepare(input)
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        self.filter.compute_at(pt, input, dest);
+
+        let mut px = dest.to_pixel();
+        let original: Pixel<D> = input.get_pixel(pt, None).convert();
+        for c in 0..D::CHANNELS {
+            if !self.channels.contains(c) {
+                px[c] = original[c];
+            }
+        }
+        px.copy_to_slice(dest);
+    }
+}
+
+#[derive(Debug)]
+struct LumaChroma<L, Ch> {
+    luma: L,
+    chroma: Ch,
+}
+
+/// Convert to `Yuv` and run separate filters on the luma and chroma planes before converting
+/// back. Only the U and V channels of the chroma filter output are used.
+pub fn luma_chroma<T: Type, C: Color, U: Type, D: Color>(
+    luma: impl Filter<f32, Gray>,
+    chroma: impl Filter<f32, Yuv>,
+) -> impl Filter<T, C, U, D> {
+    LumaChroma { luma, chroma }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color, L: Filter<f32, Gray>, Ch: Filter<f32, Yuv>>
+    Filter<T, C, U, D> for LumaChroma<L, Ch>
+{
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn prepare(
+        &self,
+        input: &Input<T, C>,
+    ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+        let yuv: Image<f32, Yuv> = input.images()[0].convert();
+
+        let mut y = Image::<f32, Gray>::new(yuv.size());
+        y.for_each(|pt, mut px| px[0] = yuv.get(pt)[0]);
+
+        let mut luma = y.new_like();
+        self.luma.eval(&[&y], &mut luma);
+
+        let mut output = yuv.new_like();
+        self.chroma.eval(&[&yuv], &mut output);
+        output.for_each(|pt, mut px| px[0] = luma.get(pt)[0]);
+
+        Some(std::sync::Arc::new(output))
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        compute_prepared_yuv(pt, input, dest)
+    }
+}
+
+/// Write the pixel at `pt` from a prepared `Yuv` image
+fn compute_prepared_yuv<T: Type, C: Color, U: Type, D: Color>(
+    pt: Point,
+    input: &Input<T, C>,
+    dest: &mut DataMut<U, D>,
+) {
+    match input.prepared::<Image<f32, Yuv>>() {
+        Some(image) if image.in_bounds(pt) => image.get_pixel(pt).convert_to_data(dest),
+        _ => input.get_pixel(pt, None).convert_to_data(dest),
+    }
+}
+
+/// High-quality resize filter. Axes that are being shrunk are area-averaged, axes that are being
+/// enlarged are sampled using the selected interpolation kernel. `Interpolation::Nearest` uses
+/// point sampling in both directions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Resize {
+    /// Output size
+    pub size: Size,
+
+    /// Kernel used when enlarging
+    pub interpolation: Interpolation,
+}
+
+impl Resize {
+    /// Create a new `Resize` filter using the default interpolation method
+    pub fn new(size: impl Into<Size>) -> Resize {
+        Resize {
+            size: size.into(),
+            interpolation: Interpolation::default(),
+        }
+    }
+
+    /// Set the kernel used when enlarging
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Resize {
+        self.interpolation = interpolation;
+        self
+    }
+
+    // Input indices and weights contributing to each output index along one axis
+    fn weights(&self, from: usize, to: usize) -> Vec<Vec<(usize, f64)>> {
+        let scale = from as f64 / to.max(1) as f64;
+        let clamp = |i: isize| i.clamp(0, from as isize - 1) as usize;
+
+        (0..to)
+            .map(|i| {
+                if from == 0 {
+                    return Vec::new();
+                }
+
+                let center = (i as f64 + 0.5) * scale - 0.5;
+                let mut weights: Vec<(usize, f64)> = match self.interpolation {
+                    Interpolation::Nearest => vec![(clamp(center.round() as isize), 1.0)],
+                    _ if scale > 1.0 => {
+                        let (lo, hi) = (i as f64 * scale, (i + 1) as f64 * scale);
+                        let end = (hi.ceil() as usize).min(from);
+                        (lo.floor() as usize..end)
+                            .map(|j| (j, (hi.min(j as f64 + 1.0) - lo.max(j as f64)).max(0.0)))
+                            .collect()
+                    }
+                    interpolation => {
+                        let r = interpolation.radius();
+                        let start = (center - r).floor() as isize + 1;
+                        let end = (center + r).floor() as isize;
+                        (start..=end)
+                            .map(|j| (clamp(j), interpolation.weight(center - j as f64)))
+                            .collect()
+                    }
+                };
+
+                let total: f64 = weights.iter().map(|(_, w)| w).sum();
+                if total != 0.0 {
+                    weights.iter_mut().for_each(|(_, w)| *w /= total);
+                }
+                weights
+            })
+            .collect()
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Resize {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn output_size(&self, _input: &Input<T, C>, _dest: &mut Image<U, D>) -> Size {
+        self.size
+    }
+
+    fn prepare(
+        &self,
+        input: &Input<T, C>,
+    ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+        let image = input.images()[0];
+        let xweights = self.weights(image.width(), self.size.width);
+        let yweights = self.weights(image.height(), self.size.height);
+
+        // Separable: resize horizontally, then vertically
+        let mut tmp = Image::<f32, C>::new((self.size.width, image.height()));
+        tmp.for_each(|pt, data| {
+            let mut px = Pixel::<C>::new();
+            px.fill(0.0);
+            for (x, w) in &xweights[pt.x] {
+                px += &(image.get_pixel((*x, pt.y)) * *w);
+            }
+            px.copy_to_slice(data);
+        });
+
+        let mut output = Image::<f32, C>::new(self.size);
+        output.for_each(|pt, data| {
+            let mut px = Pixel::<C>::new();
+            px.fill(0.0);
+            for (y, w) in &yweights[pt.y] {
+                px += &(tmp.get_pixel((pt.x, *y)) * *w);
+            }
+            px.copy_to_slice(data);
+        });
+
+        Some(std::sync::Arc::new(output.data().to_vec()))
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let index = (pt.y * self.size.width + pt.x) * C::CHANNELS;
+        if let Some(data) = input.prepared::<Vec<f32>>() {
+            if let Some(data) = data.get(index..index + C::CHANNELS) {
+                Pixel::<C>::from_slice(data).convert_to_data(dest);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct GaussianBlur(f64);
+
+/// Gaussian blur with the given standard deviation. Large values of `sigma` use a recursive
+/// approximation, so the cost doesn't depend on the size of the blur.
+pub fn gaussian_blur<T: Type, C: Color, U: Type, D: Color>(sigma: f64) -> impl Filter<T, C, U, D> {
+    GaussianBlur(sigma)
+}
+
+impl GaussianBlur {
+    /// Values of `sigma` above this use the recursive approximation
+    const RECURSIVE: f64 = 8.0;
+
+    fn blur_line(&self, weights: &[f64], src: &[f64], dest: &mut [f64]) {
+        let n = src.len();
+        if self.0 > Self::RECURSIVE {
+            return recursive_gaussian(self.0, src, dest);
+        }
+
+        let r = (weights.len() / 2) as isize;
+        for (i, d) in dest.iter_mut().enumerate() {
+            *d = weights
+                .iter()
+                .enumerate()
+                .map(|(k, w)| {
+                    let j = (i as isize + k as isize - r).clamp(0, n as isize - 1);
+                    src[j as usize] * w
+                })
+                .sum();
+        }
+    }
+}
+
+/// Apply `f` to every row and then every column of `image`, each channel is processed
+/// separately. Returns normalized, interleaved pixel data.
+fn filter_lines<T: Type, C: Color>(
+    image: &Image<T, C>,
+    f: impl Sync + Fn(&[f64], &mut [f64]),
+) -> Vec<f64> {
+    let (width, height, channels) = (image.width(), image.height(), C::CHANNELS);
+    let mut data: Vec<f64> = image.data().iter().map(|x| x.to_norm()).collect();
+    filter_rows(&mut data, width, channels, &f);
+    let mut data = transpose(&data, width, height, channels);
+    filter_rows(&mut data, height, channels, &f);
+    transpose(&data, height, width, channels)
+}
+
+fn filter_rows(
+    data: &mut [f64],
+    width: usize,
+    channels: usize,
+    f: &(impl Sync + Fn(&[f64], &mut [f64])),
+) {
+    if width == 0 {
+        return;
+    }
+
+    #[cfg(feature = "parallel")]
+    let rows = data.par_chunks_mut(width * channels);
+    #[cfg(not(feature = "parallel"))]
+    let rows = data.chunks_mut(width * channels);
+
+    rows.for_each(|row| {
+        let mut src = vec![0.0; width];
+        let mut dest = vec![0.0; width];
+        for c in 0..channels {
+            for (x, s) in src.iter_mut().enumerate() {
+                *s = row[x * channels + c];
+            }
+            f(&src, &mut dest);
+            for (x, d) in dest.iter().enumerate() {
+                row[x * channels + c] = *d;
+            }
+        }
+    });
+}
+
+fn transpose(data: &[f64], width: usize, height: usize, channels: usize) -> Vec<f64> {
+    let mut output = vec![0.0; data.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let src = (y * width + x) * channels;
+            let dest = (x * height + y) * channels;
+            output[dest..dest + channels].copy_from_slice(&data[src..src + channels]);
+        }
+    }
+    output
+}
+
+/// Write the pixel at `pt` from data computed using `filter_lines`
+fn compute_prepared<T: Type, C: Color, U: Type, D: Color>(
+    pt: Point,
+    input: &Input<T, C>,
+    dest: &mut DataMut<U, D>,
+) {
+    let width = input.images()[0].width();
+    let index = (pt.y * width + pt.x) * C::CHANNELS;
+    match input.prepared::<Vec<f64>>() {
+        Some(data) if pt.x < width && index + C::CHANNELS <= data.len() => {
+            Pixel::<C>::from(&data[index..index + C::CHANNELS]).convert_to_data(dest)
+        }
+        _ => input.get_pixel(pt, None).convert_to_data(dest),
+    }
+}
+
+/// Young & van Vliet recursive gaussian filter
+fn recursive_gaussian(sigma: f64, src: &[f64], dest: &mut [f64]) {
+    let n = src.len();
+    if n == 0 {
+        return;
+    }
+
+    let q = if sigma >= 2.5 {
+        0.98711 * sigma - 0.96330
+    } else {
+        3.97156 - 4.14554 * (1.0 - 0.26891 * sigma).sqrt()
+    };
+    let (q2, q3) = (q * q, q * q * q);
+    let b0 = 1.57825 + 2.44413 * q + 1.4281 * q2 + 0.422205 * q3;
+    let b1 = (2.44413 * q + 2.85619 * q2 + 1.26661 * q3) / b0;
+    let b2 = -(1.4281 * q2 + 1.26661 * q3) / b0;
+    let b3 = 0.422205 * q3 / b0;
+    let b = 1.0 - (b1 + b2 + b3);
+
+    // Forward pass, the edge is extended using the first value
+    let (mut w1, mut w2, mut w3) = (src[0], src[0], src[0]);
+    for (d, x) in dest.iter_mut().zip(src) {
+        let w = b * x + b1 * w1 + b2 * w2 + b3 * w3;
+        *d = w;
+        w3 = w2;
+        w2 = w1;
+        w1 = w;
+    }
+
+    // Backward pass, initialized using the Triggs & Sdika boundary conditions so the end of the
+    // line behaves as if it was extended using the last value
+    let last = src[n - 1];
+    let w = |i: usize| {
+        if i < n {
+            dest[n - 1 - i] - last
+        } else {
+            src[0] - last
+        }
+    };
+    let u = [w(0), w(1), w(2)];
+    let m = [
+        [
+            -b3 * b1 + 1.0 - b3 * b3 - b2,
+            (b3 + b1) * (b2 + b3 * b1),
+            b3 * (b1 + b3 * b2),
+        ],
+        [
+            b1 + b3 * b2,
+            -(b2 - 1.0) * (b2 + b3 * b1),
+            -(b3 * b1 + b3 * b3 + b2 - 1.0) * b3,
+        ],
+        [
+            b3 * b1 + b2 + b1 * b1 - b2 * b2,
+            b1 * b2 + b3 * b2 * b2 - b1 * b3 * b3 - b3 * b3 * b3 - b3 * b2 + b3,
+            b3 * (b1 + b3 * b2),
+        ],
+    ];
+    let scale = b / ((1.0 + b1 - b2 + b3) * (1.0 - b1 - b2 - b3) * (1.0 + b2 + (b1 - b3) * b3));
+    let v = m.map(|row| scale * (row[0] * u[0] + row[1] * u[1] + row[2] * u[2]) + last);
+
+    let (mut y1, mut y2, mut y3) = (v[0], v[1], v[2]);
+    for d in dest.iter_mut().rev() {
+        let y = b * *d + b1 * y1 + b2 * y2 + b3 * y3;
+        *d = y;
+        y3 = y2;
+        y2 = y1;
+        y1 = y;
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for GaussianBlur {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn prepare(
+        &self,
+        input: &Input<T, C>,
+    ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+        let weights = kernel::gaussian(self.0);
+        let data = filter_lines(input.images()[0], |src, dest| {
+            self.blur_line(weights.horizontal(), src, dest)
+        });
+        Some(std::sync::Arc::new(data))
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        compute_prepared(pt, input, dest)
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct BoxBlur(usize);
+
+/// Box blur averaging a square of `2 * radius + 1` pixels, running sums are used so the cost
+/// doesn't depend on `radius`
+pub fn box_blur<T: Type, C: Color, U: Type, D: Color>(radius: usize) -> impl Filter<T, C, U, D> {
+    BoxBlur(radius)
+}
+
+impl BoxBlur {
+    fn blur_line(&self, src: &[f64], dest: &mut [f64]) {
+        let n = src.len() as isize;
+        if n == 0 {
+            return;
+        }
+
+        let r = self.0 as isize;
+        let at = |i: isize| src[i.clamp(0, n - 1) as usize];
+        let size = (2 * r + 1) as f64;
+        let mut sum: f64 = (-r..=r).map(at).sum();
+        for (i, d) in dest.iter_mut().enumerate() {
+            let i = i as isize;
+            *d = sum / size;
+            sum += at(i + r + 1) - at(i - r);
+        }
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for BoxBlur {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn prepare(
+        &self,
+        input: &Input<T, C>,
+    ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+        let data = filter_lines(input.images()[0], |src, dest| self.blur_line(src, dest));
+        Some(std::sync::Arc::new(data))
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        compute_prepared(pt, input, dest)
+    }
+}
+
+/// Level used by `Threshold`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ThresholdLevel {
+    /// Fixed, normalized level
+    Fixed(f64),
+
+    /// Compute the level from the image histogram using Otsu's method
+    Otsu,
+}
+
+/// Binarize an image, pixels with a luminance above the threshold level are set to 1 and all
+/// other pixels are set to 0
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Threshold {
+    /// Threshold level
+    pub level: ThresholdLevel,
+}
+
+impl Threshold {
+    /// Threshold using a fixed, normalized level
+    pub fn new(level: f64) -> Threshold {
+        Threshold {
+            level: ThresholdLevel::Fixed(level),
+        }
+    }
+
+    /// Threshold using a level computed with Otsu's method
+    pub fn otsu() -> Threshold {
+        Threshold {
+            level: ThresholdLevel::Otsu,
+        }
+    }
+
+    /// Compute the level that will be used for `image`
+    pub fn level<T: Type, C: Color>(&self, image: &Image<T, C>) -> f64 {
+        match self.level {
+            ThresholdLevel::Fixed(level) => level,
+            ThresholdLevel::Otsu => {
+                let mut hist = Histogram::new(256);
+                image.each_pixel(|_, px| hist.add_value(px.convert::<Gray>()[0].clamp(0.0, 1.0)));
+                hist.otsu()
+            }
+        }
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Threshold {
+    fn schedule(&self) -> Schedule {
+        match self.level {
+            ThresholdLevel::Fixed(_) => Schedule::Pixel,
+            ThresholdLevel::Otsu => Schedule::Image,
+        }
+    }
+
+    fn prepare(
+        &self,
+        input: &Input<T, C>,
+    ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+        Some(std::sync::Arc::new(self.level(input.images()[0])))
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let level = match (self.level, input.prepared::<f64>()) {
+            (ThresholdLevel::Fixed(level), _) => level,
+            (ThresholdLevel::Otsu, Some(level)) => *level,
+            (ThresholdLevel::Otsu, None) => 0.5,
+        };
+
+        let luma = input.get_pixel(pt, None).convert::<Gray>()[0];
+        let value = if luma > level { 1.0 } else { 0.0 };
+        Pixel::<Gray>::from(vec![value]).convert_to_data(dest);
+    }
+}
+
+/// Method used to compute the local level of an `AdaptiveThreshold`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AdaptiveMethod {
+    /// Mean of the neighborhood
+    Mean,
+
+    /// Gaussian-weighted mean of the neighborhood
+    Gaussian,
+}
+
+/// Binarize an image by comparing each pixel against the level of its local neighborhood, which
+/// works better than a global threshold for unevenly lit images. Pixels with a luminance above
+/// the local level minus `offset` are set to 1 and all other pixels are set to 0
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AdaptiveThreshold {
+    /// Method used to compute the local level
+    pub method: AdaptiveMethod,
+
+    /// Neighborhood radius, the neighborhood is `2 * radius + 1` pixels wide
+    pub radius: usize,
+
+    /// Value subtracted from the local level
+    pub offset: f64,
+}
+
+impl AdaptiveThreshold {
+    /// Create a new adaptive threshold filter
+    pub fn new(method: AdaptiveMethod, radius: usize, offset: f64) -> AdaptiveThreshold {
+        AdaptiveThreshold {
+            method,
+            radius,
+            offset,
+        }
+    }
+
+    /// Threshold against the mean of the neighborhood
+    pub fn mean(radius: usize, offset: f64) -> AdaptiveThreshold {
+        Self::new(AdaptiveMethod::Mean, radius, offset)
+    }
+
+    /// Threshold against the gaussian-weighted mean of the neighborhood
+    pub fn gaussian(radius: usize, offset: f64) -> AdaptiveThreshold {
+        Self::new(AdaptiveMethod::Gaussian, radius, offset)
+    }
+
+    /// Compute the local level of each pixel of a grayscale image
+    fn levels(&self, image: &Image<f32, Gray>) -> Vec<f64> {
+        match self.method {
+            AdaptiveMethod::Mean => {
+                let blur = BoxBlur(self.radius);
+                filter_lines(image, |src, dest| blur.blur_line(src, dest))
+            }
+            AdaptiveMethod::Gaussian => {
+                // Same relationship between window size and sigma as OpenCV
+                let sigma = (0.3 * (self.radius as f64 - 1.0) + 0.8).max(0.5);
+                let blur = GaussianBlur(sigma);
+                let weights = kernel::gaussian(sigma);
+                filter_lines(image, |src, dest| {
+                    blur.blur_line(weights.horizontal(), src, dest)
+                })
+            }
+        }
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for AdaptiveThreshold {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn prepare(
+        &self,
+        input: &Input<T, C>,
+    ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+        let gray = input.images()[0].convert::<f32, Gray>();
+        Some(std::sync::Arc::new(self.levels(&gray)))
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let width = input.images()[0].width();
+        let level = input
+            .prepared::<Vec<f64>>()
+            .and_then(|levels| levels.get(pt.y * width + pt.x).copied())
+            .unwrap_or(0.5);
+
+        let luma = input.get_pixel(pt, None).convert::<Gray>()[0];
+        let value = if luma > level - self.offset { 1.0 } else { 0.0 };
+        Pixel::<Gray>::from(vec![value]).convert_to_data(dest);
+    }
+}
+
+/// Canny edge detector, produces a binary edge map where edge pixels are set to 1 and all other
+/// pixels are set to 0. The luminance is smoothed with a gaussian blur, gradients are computed
+/// using the Sobel operator and thinned to single pixel lines, then pixels with a gradient
+/// magnitude above `high` are kept along with connected pixels above `low`. Magnitudes are
+/// normalized so a step from 0 to 1 has a magnitude of 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Canny {
+    /// Weak edge threshold
+    pub low: f64,
+
+    /// Strong edge threshold
+    pub high: f64,
+
+    /// Standard deviation of the gaussian blur applied before computing gradients, 0 disables
+    /// smoothing
+    pub sigma: f64,
+}
+
+impl Default for Canny {
+    fn default() -> Self {
+        Canny {
+            low: 0.1,
+            high: 0.2,
+            sigma: 1.4,
+        }
+    }
+}
+
+impl Canny {
+    /// Create a new edge detector with the default blur
+    pub fn new(low: f64, high: f64) -> Canny {
+        Canny {
+            low,
+            high,
+            ..Default::default()
+        }
+    }
+
+    /// Set blur standard deviation
+    pub fn with_sigma(mut self, sigma: f64) -> Canny {
+        self.sigma = sigma.max(0.0);
+        self
+    }
+
+    /// Compute the edge map of an image, `true` for edge pixels in raster order
+    pub fn edges<T: Type, C: Color>(&self, image: &Image<T, C>) -> Vec<bool> {
+        let gray = image.convert::<f32, Gray>();
+        let (width, height) = (gray.width(), gray.height());
+        let luma = if self.sigma > 0.0 {
+            let blur = GaussianBlur(self.sigma);
+            let weights = kernel::gaussian(self.sigma);
+            filter_lines(&gray, |src, dest| {
+                blur.blur_line(weights.horizontal(), src, dest)
+            })
+        } else {
+            gray.data().iter().map(|x| *x as f64).collect()
+        };
+
+        let at = |x: isize, y: isize| {
+            let x = x.clamp(0, width as isize - 1) as usize;
+            let y = y.clamp(0, height as isize - 1) as usize;
+            luma[y * width + x]
+        };
+
+        let mut magnitude = vec![0.0; width * height];
+        let mut direction = vec![0u8; width * height];
+        for y in 0..height as isize {
+            for x in 0..width as isize {
+                let gx = (at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1))
+                    - (at(x - 1, y - 1) + 2.0 * at(x - 1, y) + at(x - 1, y + 1));
+                let gy = (at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1))
+                    - (at(x - 1, y - 1) + 2.0 * at(x, y - 1) + at(x + 1, y - 1));
+                let i = y as usize * width + x as usize;
+                magnitude[i] = (gx * gx + gy * gy).sqrt() / 4.0;
+
+                // Quantize the gradient direction to horizontal, one of the diagonals or vertical
+                let angle = gy.atan2(gx).to_degrees().rem_euclid(180.0);
+                direction[i] = ((angle + 22.5) / 45.0) as u8 % 4;
+            }
+        }
+
+        // Keep pixels that are a local maximum across the edge
+        let offsets = [(1, 0), (1, 1), (0, 1), (-1, 1)];
+        let mag = |x: isize, y: isize| {
+            if x < 0 || y < 0 || x >= width as isize || y >= height as isize {
+                0.0
+            } else {
+                magnitude[y as usize * width + x as usize]
+            }
+        };
+        let mut edges = vec![false; width * height];
+        let mut stack = Vec::new();
+        let mut candidate = vec![false; width * height];
+        for y in 0..height as isize {
+            for x in 0..width as isize {
+                let i = y as usize * width + x as usize;
+                let m = magnitude[i];
+                if m < self.low || m <= 0.0 {
+                    continue;
+                }
+                let (dx, dy) = offsets[direction[i] as usize];
+                if m < mag(x + dx, y + dy) || m <= mag(x - dx, y - dy) {
+                    continue;
+                }
+                candidate[i] = true;
+                if m >= self.high {
+                    edges[i] = true;
+                    stack.push((x, y));
+                }
+            }
+        }
+
+        // Hysteresis, follow weak edges connected to strong edges
+        while let Some((x, y)) = stack.pop() {
+            for (dx, dy) in [
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ] {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                    continue;
+                }
+                let j = ny as usize * width + nx as usize;
+                if candidate[j] && !edges[j] {
+                    edges[j] = true;
+                    stack.push((nx, ny));
+                }
+            }
+        }
+        edges
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Canny {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn prepare(
+        &self,
+        input: &Input<T, C>,
+    ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+        Some(std::sync::Arc::new(self.edges(input.images()[0])))
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let width = input.images()[0].width();
+        let edge = input
+            .prepared::<Vec<bool>>()
+            .and_then(|edges| edges.get(pt.y * width + pt.x).copied())
+            .unwrap_or(false);
+        let value = if edge { 1.0 } else { 0.0 };
+        Pixel::<Gray>::from(vec![value]).convert_to_data(dest);
+    }
+}
+
+/// Marks pixels where the first channel of the input changes sign, producing a binary edge map
+/// from the output of `Kernel::laplacian_of_gaussian` or `Kernel::difference_of_gaussians`.
+/// Only the pixel closest to zero on each side of a crossing is marked so edges are thin, and
+/// crossings where the values on either side differ by less than `threshold` are ignored to
+/// suppress noise in flat areas. The input must be stored using a signed type such as `f32`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ZeroCrossing {
+    /// Minimum difference across a crossing
+    pub threshold: f64,
+}
+
+impl ZeroCrossing {
+    /// Create a new zero crossing detector
+    pub fn new(threshold: f64) -> ZeroCrossing {
+        ZeroCrossing { threshold }
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for ZeroCrossing {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let size = input.images()[0].size();
+        let at = |dx: isize, dy: isize| {
+            let x = (pt.x as isize + dx).clamp(0, size.width as isize - 1) as usize;
+            let y = (pt.y as isize + dy).clamp(0, size.height as isize - 1) as usize;
+            input.get_f((x, y), 0, None)
+        };
+
+        let p = at(0, 0);
+        let edge = [(1, 0), (0, 1), (1, 1), (1, -1)].iter().any(|(dx, dy)| {
+            let (a, b) = (at(-dx, -dy), at(*dx, *dy));
+            if p == 0.0 {
+                return a * b < 0.0 && (a - b).abs() > self.threshold;
+            }
+            [a, b]
+                .iter()
+                .any(|q| p * q < 0.0 && p.abs() <= q.abs() && (p - q).abs() > self.threshold)
+        });
+
+        let value = if edge { 1.0 } else { 0.0 };
+        Pixel::<Gray>::from(vec![value]).convert_to_data(dest);
+    }
+}
+
+/// Histogram bin of a normalized value
+fn histogram_bin(value: f64, bins: usize) -> usize {
+    (value.clamp(0.0, 1.0) * (bins - 1) as f64).round() as usize
+}
+
+/// Build a mapping from histogram bins to equalized values. When `clip_limit` is set, bins are
+/// limited to `clip_limit` times the average bin count and the excess is redistributed evenly.
+fn equalization_map(mut hist: Vec<f64>, clip_limit: Option<f64>) -> Vec<f64> {
+    let n = hist.len();
+    let total: f64 = hist.iter().sum();
+
+    if let Some(clip) = clip_limit {
+        let limit = (clip * total / n as f64).max(1.0);
+        let mut excess = 0.0;
+        for h in hist.iter_mut() {
+            if *h > limit {
+                excess += *h - limit;
+                *h = limit;
+            }
+        }
+        hist.iter_mut().for_each(|h| *h += excess / n as f64);
+    }
+
+    let mut cdf = Vec::with_capacity(n);
+    let mut sum = 0.0;
+    for h in &hist {
+        sum += h;
+        cdf.push(sum);
+    }
+
+    let min = cdf.iter().copied().find(|x| *x > 0.0).unwrap_or(0.0);
+    cdf.iter()
+        .enumerate()
+        .map(|(i, x)| {
+            if total > min {
+                ((x - min) / (total - min)).max(0.0)
+            } else {
+                i as f64 / (n - 1) as f64
+            }
+        })
+        .collect()
+}
+
+/// Convert `image` to `Yuv` and replace the luma plane using `f`, which is called with the luma
+/// values in raster order
+fn map_luma<T: Type, C: Color>(
+    image: &Image<T, C>,
+    f: impl FnOnce(&[f64]) -> Vec<f64>,
+) -> Image<f32, Yuv> {
+    let mut yuv: Image<f32, Yuv> = image.convert();
+    let luma: Vec<f64> = yuv
+        .data()
+        .chunks_exact(Yuv::CHANNELS)
+        .map(|px| px[0] as f64)
+        .collect();
+    let luma = f(&luma);
+    for (px, y) in yuv.data_mut().chunks_exact_mut(Yuv::CHANNELS).zip(luma) {
+        px[0] = y as f32;
+    }
+    yuv
+}
+
+/// Histogram equalization, spreads the luminance of an image over the whole range. Color images
+/// are equalized in the luma channel so hue is preserved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EqualizeHistogram {
+    /// Number of histogram bins
+    pub bins: usize,
+}
+
+impl Default for EqualizeHistogram {
+    fn default() -> EqualizeHistogram {
+        EqualizeHistogram { bins: 256 }
+    }
+}
+
+impl EqualizeHistogram {
+    /// Create a new histogram equalization filter with 256 bins
+    pub fn new() -> EqualizeHistogram {
+        EqualizeHistogram::default()
+    }
+
+    /// Set the number of histogram bins
+    pub fn with_bins(mut self, bins: usize) -> EqualizeHistogram {
+        self.bins = bins.max(2);
+        self
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for EqualizeHistogram {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn prepare(
+        &self,
+        input: &Input<T, C>,
+    ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+        let bins = self.bins.max(2);
+        let output = map_luma(input.images()[0], |luma| {
+            let mut hist = vec![0.0; bins];
+            luma.iter()
+                .for_each(|y| hist[histogram_bin(*y, bins)] += 1.0);
+            let map = equalization_map(hist, None);
+            luma.iter().map(|y| map[histogram_bin(*y, bins)]).collect()
+        });
+        Some(std::sync::Arc::new(output))
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        compute_prepared_yuv(pt, input, dest)
+    }
+}
+
+/// Contrast limited adaptive histogram equalization. The image is divided into a grid of tiles
+/// that are equalized separately, with each histogram clipped to limit noise amplification, and
+/// the results are blended using bilinear interpolation. Color images are equalized in the luma
+/// channel so hue is preserved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Clahe {
+    /// Number of tiles in each direction
+    pub tiles: Size,
+
+    /// Maximum bin count relative to the average bin count of a tile
+    pub clip_limit: f64,
+
+    /// Number of histogram bins
+    pub bins: usize,
+}
+
+impl Default for Clahe {
+    fn default() -> Clahe {
+        Clahe {
+            tiles: Size::new(8, 8),
+            clip_limit: 2.0,
+            bins: 256,
+        }
+    }
+}
+
+impl Clahe {
+    /// Create a new CLAHE filter using an 8x8 grid of tiles
+    pub fn new(clip_limit: f64) -> Clahe {
+        Clahe {
+            clip_limit,
+            ..Default::default()
+        }
+    }
+
+    /// Set the number of tiles in each direction
+    pub fn with_tiles(mut self, tiles: impl Into<Size>) -> Clahe {
+        self.tiles = tiles.into();
+        self
+    }
+
+    /// Set the number of histogram bins
+    pub fn with_bins(mut self, bins: usize) -> Clahe {
+        self.bins = bins.max(2);
+        self
+    }
+
+    fn equalize(&self, luma: &[f64], size: Size) -> Vec<f64> {
+        let bins = self.bins.max(2);
+        let (width, height) = (size.width, size.height);
+        if width == 0 || height == 0 {
+            return luma.to_vec();
+        }
+
+        let tile_width = width.div_ceil(self.tiles.width.clamp(1, width));
+        let tile_height = height.div_ceil(self.tiles.height.clamp(1, height));
+        let (tx, ty) = (width.div_ceil(tile_width), height.div_ceil(tile_height));
+
+        let mut maps = Vec::with_capacity(tx * ty);
+        for j in 0..ty {
+            for i in 0..tx {
+                let mut hist = vec![0.0; bins];
+                for y in j * tile_height..((j + 1) * tile_height).min(height) {
+                    for x in i * tile_width..((i + 1) * tile_width).min(width) {
+                        hist[histogram_bin(luma[y * width + x], bins)] += 1.0;
+                    }
+                }
+                maps.push(equalization_map(hist, Some(self.clip_limit)));
+            }
+        }
+
+        // Tile index and blend factor along one axis
+        let neighbors = |p: usize, tile: usize, count: usize| {
+            let f = ((p as f64 + 0.5) / tile as f64 - 0.5).max(0.0);
+            let a = (f.floor() as usize).min(count - 1);
+            let b = (a + 1).min(count - 1);
+            (a, b, (f - a as f64).clamp(0.0, 1.0))
+        };
+
+        let mut output = vec![0.0; luma.len()];
+        for y in 0..height {
+            let (j0, j1, fy) = neighbors(y, tile_height, ty);
+            for x in 0..width {
+                let (i0, i1, fx) = neighbors(x, tile_width, tx);
+                let bin = histogram_bin(luma[y * width + x], bins);
+                let map = |i: usize, j: usize| maps[j * tx + i][bin];
+                let top = map(i0, j0) * (1.0 - fx) + map(i1, j0) * fx;
+                let bottom = map(i0, j1) * (1.0 - fx) + map(i1, j1) * fx;
+                output[y * width + x] = top * (1.0 - fy) + bottom * fy;
+            }
+        }
+        output
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Clahe {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn prepare(
+        &self,
+        input: &Input<T, C>,
+    ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+        let image = input.images()[0];
+        let output = map_luma(image, |luma| self.equalize(luma, image.size()));
+        Some(std::sync::Arc::new(output))
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        compute_prepared_yuv(pt, input, dest)
+    }
+}
+
+/// Levels adjustment, input values between `in_black` and `in_white` are stretched to cover the
+/// range between `out_black` and `out_white` with `gamma` applied in between. All values are
+/// normalized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Levels {
+    /// Input value mapped to `out_black`
+    pub in_black: f64,
+
+    /// Input value mapped to `out_white`
+    pub in_white: f64,
+
+    /// Midtone gamma, values greater than 1 brighten the image
+    pub gamma: f64,
+
+    /// Output black point
+    pub out_black: f64,
+
+    /// Output white point
+    pub out_white: f64,
+
+    /// Channel to adjust, when `None` every channel except alpha is adjusted
+    pub channel: Option<Channel>,
+}
+
+impl Default for Levels {
+    fn default() -> Levels {
+        Levels {
+            in_black: 0.0,
+            in_white: 1.0,
+            gamma: 1.0,
+            out_black: 0.0,
+            out_white: 1.0,
+            channel: None,
+        }
+    }
+}
+
+impl Levels {
+    /// Create a levels adjustment for every channel except alpha
+    pub fn new(in_black: f64, in_white: f64, gamma: f64, out_black: f64, out_white: f64) -> Self {
+        Levels {
+            in_black,
+            in_white,
+            gamma,
+            out_black,
+            out_white,
+            channel: None,
+        }
+    }
+
+    /// Only adjust `channel`
+    pub fn with_channel(mut self, channel: Channel) -> Levels {
+        self.channel = Some(channel);
+        self
+    }
+
+    /// Stretch the image so that `clip` (between 0 and 0.5) of the values at each end of the
+    /// histogram are clipped, the histogram is computed over every channel except alpha
+    pub fn auto_levels<T: Type, C: Color>(image: &Image<T, C>, clip: f64) -> Levels {
+        let mut hist = Histogram::new(1024);
+        image.each_pixel(|_, px| {
+            for c in (0..C::CHANNELS).filter(|c| Some(*c) != C::ALPHA) {
+                hist.add_value(px[c].clamp(0.0, 1.0));
+            }
+        });
+        Self::from_histogram(&hist, clip)
+    }
+
+    /// Same as `auto_levels` using only the values from `channel`, the result only adjusts
+    /// `channel`
+    pub fn auto_levels_channel<T: Type, C: Color>(
+        image: &Image<T, C>,
+        channel: Channel,
+        clip: f64,
+    ) -> Levels {
+        let mut hist = Histogram::new(1024);
+        image.each_pixel(|_, px| hist.add_value(px[channel].clamp(0.0, 1.0)));
+        Self::from_histogram(&hist, clip).with_channel(channel)
+    }
+
+    fn from_histogram(hist: &Histogram, clip: f64) -> Levels {
+        let clip = clip.clamp(0.0, 0.5);
+        let in_black = hist.percentile(clip);
+        let in_white = hist.percentile(1.0 - clip);
+        Levels {
+            in_black,
+            in_white: in_white.max(in_black + f64::EPSILON),
+            ..Default::default()
+        }
+    }
+
+    /// Apply the adjustment to a single normalized value
+    pub fn apply(&self, value: f64) -> f64 {
+        let range = self.in_white - self.in_black;
+        let x = if range.abs() < f64::EPSILON {
+            (value >= self.in_white) as u8 as f64
+        } else {
+            ((value - self.in_black) / range).clamp(0.0, 1.0)
+        };
+        let x = if self.gamma > 0.0 {
+            x.powf(1.0 / self.gamma)
+        } else {
+            x
+        };
+        self.out_black + x * (self.out_white - self.out_black)
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Levels {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        match self.channel {
+            Some(c) if c < C::CHANNELS => px[c] = self.apply(px[c]),
+            Some(_) => (),
+            None => {
+                for c in (0..C::CHANNELS).filter(|c| Some(*c) != C::ALPHA) {
+                    px[c] = self.apply(px[c]);
+                }
+            }
+        }
+        px.convert_to_data(dest);
+    }
+}
+
+/// Tone curve defined by control points, values between the control points are interpolated
+/// using a monotone cubic spline so the curve never overshoots
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Curve {
+    points: Vec<(f64, f64)>,
+    tangents: Vec<f64>,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    lut: Vec<f64>,
+}
+
+impl Default for Curve {
+    fn default() -> Curve {
+        Curve::new([(0.0, 0.0), (1.0, 1.0)])
+    }
+}
+
+impl Curve {
+    /// Number of entries in the lookup table
+    const LUT_SIZE: usize = 4096;
+
+    /// Create a new curve from normalized `(input, output)` control points
+    pub fn new(points: impl Into<Vec<(f64, f64)>>) -> Curve {
+        let mut points = points.into();
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        points.dedup_by(|a, b| a.0 == b.0);
+
+        let n = points.len();
+        let secants: Vec<f64> = points
+            .windows(2)
+            .map(|p| (p[1].1 - p[0].1) / (p[1].0 - p[0].0))
+            .collect();
+
+        // Fritsch-Carlson tangents
+        let mut tangents = vec![0.0; n];
+        if n > 1 {
+            tangents[0] = secants[0];
+            tangents[n - 1] = secants[n - 2];
+            for k in 1..n - 1 {
+                if secants[k - 1] * secants[k] > 0.0 {
+                    tangents[k] = (secants[k - 1] + secants[k]) / 2.0;
+                }
+            }
+            for (k, d) in secants.iter().enumerate() {
+                if *d == 0.0 {
+                    tangents[k] = 0.0;
+                    tangents[k + 1] = 0.0;
+                    continue;
+                }
+                let (a, b) = (tangents[k] / d, tangents[k + 1] / d);
+                let h = a.hypot(b);
+                if h > 3.0 {
+                    tangents[k] = 3.0 / h * a * d;
+                    tangents[k + 1] = 3.0 / h * b * d;
+                }
+            }
+        }
+
+        let mut curve = Curve {
+            points,
+            tangents,
+            lut: Vec::new(),
+        };
+        curve.lut = (0..Self::LUT_SIZE)
+            .map(|i| curve.spline(i as f64 / (Self::LUT_SIZE - 1) as f64))
+            .collect();
+        curve
+    }
+
+    /// Control points
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    fn spline(&self, x: f64) -> f64 {
+        let p = &self.points;
+        match p.len() {
+            0 => return x,
+            1 => return p[0].1,
+            _ => (),
+        }
+
+        if x <= p[0].0 {
+            return p[0].1;
+        }
+        if x >= p[p.len() - 1].0 {
+            return p[p.len() - 1].1;
+        }
+
+        let k = p.partition_point(|q| q.0 <= x) - 1;
+        let ((x0, y0), (x1, y1)) = (p[k], p[k + 1]);
+        let h = x1 - x0;
+        let t = (x - x0) / h;
+        let (t2, t3) = (t * t, t * t * t);
+        (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+            + (t3 - 2.0 * t2 + t) * h * self.tangents[k]
+            + (-2.0 * t3 + 3.0 * t2) * y1
+            + (t3 - t2) * h * self.tangents[k + 1]
+    }
+
+    /// Evaluate the curve at `x` using the lookup table
+    pub fn eval(&self, x: f64) -> f64 {
+        if self.lut.is_empty() {
+            return self.spline(x);
+        }
+
+        let f = x.clamp(0.0, 1.0) * (self.lut.len() - 1) as f64;
+        let i = (f as usize).min(self.lut.len() - 2);
+        let t = f - i as f64;
+        self.lut[i] * (1.0 - t) + self.lut[i + 1] * t
+    }
+}
+
+/// Apply tone curves, per-channel curves are applied first followed by the master curve which
+/// affects every channel except alpha
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Curves {
+    /// Curve applied to every channel except alpha
+    pub master: Option<Curve>,
+
+    /// Curves applied to individual channels
+    pub channels: Vec<(Channel, Curve)>,
+}
+
+impl Curves {
+    /// Create a new `Curves` filter without any curves
+    pub fn new() -> Curves {
+        Curves::default()
+    }
+
+    /// Set the master curve from control points
+    pub fn with_master(mut self, points: impl Into<Vec<(f64, f64)>>) -> Curves {
+        self.master = Some(Curve::new(points));
+        self
+    }
+
+    /// Add a curve for `channel` from control points
+    pub fn with_channel(mut self, channel: Channel, points: impl Into<Vec<(f64, f64)>>) -> Curves {
+        self.channels.push((channel, Curve::new(points)));
+        self
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Curves {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        for (c, curve) in &self.channels {
+            if *c < C::CHANNELS {
+                px[*c] = curve.eval(px[*c]);
+            }
+        }
+
+        if let Some(master) = &self.master {
+            for c in (0..C::CHANNELS).filter(|c| Some(*c) != C::ALPHA) {
+                px[c] = master.eval(px[c]);
+            }
+        }
+        px.convert_to_data(dest);
+    }
+}
+
+/// Strategy used by `AutoWhiteBalance` to estimate the color of the illuminant
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WhiteBalanceMethod {
+    /// Assume the average color of the scene is neutral gray
+    GrayWorld,
+
+    /// Assume the brightest part of the scene is white, the brightest value of each channel is
+    /// taken at the given percentile (between 0 and 1) to ignore specular highlights and noise
+    WhitePatch(f64),
+}
+
+/// Remove color casts by estimating per-channel gains from the image and applying them in
+/// linear light. Gains are normalized so the strongest channel is left unchanged. Alpha is not
+/// affected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AutoWhiteBalance {
+    /// Estimation strategy
+    pub method: WhiteBalanceMethod,
+}
+
+impl AutoWhiteBalance {
+    /// Create a new filter using the given strategy
+    pub fn new(method: WhiteBalanceMethod) -> AutoWhiteBalance {
+        AutoWhiteBalance { method }
+    }
+
+    /// Balance using the gray-world assumption
+    pub fn gray_world() -> AutoWhiteBalance {
+        AutoWhiteBalance::new(WhiteBalanceMethod::GrayWorld)
+    }
+
+    /// Balance using the white-patch assumption with the given percentile
+    pub fn white_patch(percentile: f64) -> AutoWhiteBalance {
+        AutoWhiteBalance::new(WhiteBalanceMethod::WhitePatch(percentile))
+    }
+
+    /// Estimate the linear RGB gains that will be applied to `image`
+    pub fn gains<T: Type, C: Color>(&self, image: &Image<T, C>) -> [f64; 3] {
+        let estimate = match self.method {
+            WhiteBalanceMethod::GrayWorld => {
+                let mut sum = [0.0; 3];
+                image.each_pixel(|_, px| {
+                    let rgb = linear_rgb(px);
+                    (0..3).for_each(|c| sum[c] += rgb[c]);
+                });
+                sum
+            }
+            WhiteBalanceMethod::WhitePatch(p) => {
+                let mut hist = vec![Histogram::new(1024); 3];
+                image.each_pixel(|_, px| {
+                    let rgb = linear_rgb(px);
+                    (0..3).for_each(|c| hist[c].add_value(rgb[c].clamp(0.0, 1.0)));
+                });
+                [0, 1, 2].map(|c| hist[c].percentile(p))
+            }
+        };
+
+        let max = estimate.iter().cloned().fold(0.0, f64::max);
+        estimate.map(|x| if x > f64::EPSILON { max / x } else { 1.0 })
+    }
+}
+
+/// Linear RGB color of a pixel, without multiplying by alpha
+fn linear_rgb<C: Color>(px: &Pixel<C>) -> Pixel<Rgb> {
+    match C::ALPHA {
+        Some(a) => {
+            let mut px = px.clone();
+            px[a] = 1.0;
+            px.convert()
+        }
+        None => px.convert(),
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for AutoWhiteBalance {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn prepare(
+        &self,
+        input: &Input<T, C>,
+    ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+        Some(std::sync::Arc::new(self.gains(input.images()[0])))
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let gains = input.prepared::<[f64; 3]>().copied().unwrap_or([1.0; 3]);
+        map_linear_rgb(pt, input, dest, |rgb| {
+            rgb.iter_mut().zip(gains).for_each(|(x, g)| *x *= g)
+        });
+    }
+}
+
+/// Chromaticity of a Planckian radiator, valid from 1667K to 25000K
+fn planckian_xy(kelvin: f64) -> (f64, f64) {
+    let t = kelvin.clamp(1667.0, 25000.0);
+    let (t2, t3) = (t * t, t * t * t);
+    let x = if t <= 4000.0 {
+        -0.2661239e9 / t3 - 0.2343589e6 / t2 + 0.8776956e3 / t + 0.179910
+    } else {
+        -3.0258469e9 / t3 + 2.1070379e6 / t2 + 0.2226347e3 / t + 0.240390
+    };
+
+    let (x2, x3) = (x * x, x * x * x);
+    let y = if t <= 2222.0 {
+        -1.1063814 * x3 - 1.34811020 * x2 + 2.18555832 * x - 0.20219683
+    } else if t <= 4000.0 {
+        -0.9549476 * x3 - 1.37418593 * x2 + 2.09137015 * x - 0.16748867
+    } else {
+        3.0817580 * x3 - 5.87338670 * x2 + 3.75112997 * x - 0.37001483
+    };
+    (x, y)
+}
+
+/// XYZ white point for a color temperature, offset perpendicular to the Planckian locus by `duv`
+/// in the CIE 1960 UCS, positive values are greener
+fn white_point(kelvin: f64, duv: f64) -> [f64; 3] {
+    let uv = |(x, y): (f64, f64)| {
+        let d = -2.0 * x + 12.0 * y + 3.0;
+        (4.0 * x / d, 6.0 * y / d)
+    };
+
+    let (u, v) = uv(planckian_xy(kelvin));
+    let (u0, v0) = uv(planckian_xy(kelvin - 1.0));
+    let (u1, v1) = uv(planckian_xy(kelvin + 1.0));
+    let (du, dv) = (u1 - u0, v1 - v0);
+    let len = (du * du + dv * dv).sqrt().max(f64::EPSILON);
+    let (mut nu, mut nv) = (-dv / len, du / len);
+    if nv < 0.0 {
+        nu = -nu;
+        nv = -nv;
+    }
+
+    let (u, v) = (u + nu * duv, v + nv * duv);
+    let d = 2.0 * u - 8.0 * v + 4.0;
+    let (x, y) = (3.0 * u / d, 2.0 * v / d);
+    [x / y, 1.0, (1.0 - x - y) / y]
+}
+
+/// Linear sRGB to XYZ
+const RGB_TO_XYZ: [[f64; 3]; 3] = [
+    [0.4124564, 0.3575761, 0.1804375],
+    [0.2126729, 0.7151522, 0.0721750],
+    [0.0193339, 0.1191920, 0.9503041],
+];
+
+/// Bradford cone response
+const BRADFORD: [[f64; 3]; 3] = [
+    [0.8951, 0.2664, -0.1614],
+    [-0.7502, 1.7135, 0.0367],
+    [0.0389, -0.0685, 1.0296],
+];
+
+/// Correct the white balance of an image given the color temperature and tint of the light it
+/// was captured under. The illuminant is located on the Planckian locus and adapted to a 6504K
+/// reference white using the Bradford transform, so `WhiteBalance::new(6504.0, 0.0)` leaves the
+/// image unchanged. Lower temperatures make the image cooler, higher temperatures make it
+/// warmer. Positive tint values shift the image towards magenta and negative values towards
+/// green. Alpha is not affected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WhiteBalance {
+    /// Color temperature of the scene illuminant in Kelvin, between 1667 and 25000
+    pub temperature: f64,
+
+    /// Green/magenta tint, typically between -100 and 100
+    pub tint: f64,
+}
+
+impl Default for WhiteBalance {
+    fn default() -> Self {
+        WhiteBalance::new(WhiteBalance::REFERENCE, 0.0)
+    }
+}
+
+impl WhiteBalance {
+    /// Temperature of the reference white
+    pub const REFERENCE: f64 = 6504.0;
+
+    /// Create a new white balance filter
+    pub fn new(temperature: f64, tint: f64) -> WhiteBalance {
+        WhiteBalance { temperature, tint }
+    }
+
+    /// Matrix applied to linear RGB values
+    pub fn matrix(&self) -> [[f64; 3]; 3] {
+        let src = mat3_apply(&BRADFORD, white_point(self.temperature, self.tint / 3000.0));
+        let dest = mat3_apply(&BRADFORD, white_point(WhiteBalance::REFERENCE, 0.0));
+
+        let mut scale = [[0.0; 3]; 3];
+        (0..3).for_each(|i| scale[i][i] = dest[i] / src[i]);
+
+        let adapt = mat3_mul(&invert3(&BRADFORD), &mat3_mul(&scale, &BRADFORD));
+        mat3_mul(&invert3(&RGB_TO_XYZ), &mat3_mul(&adapt, &RGB_TO_XYZ))
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for WhiteBalance {
+    fn prepare(
+        &self,
+        _input: &Input<T, C>,
+    ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+        Some(std::sync::Arc::new(self.matrix()))
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let m = match input.prepared::<[[f64; 3]; 3]>() {
+            Some(m) => *m,
+            None => self.matrix(),
+        };
+        map_linear_rgb(pt, input, dest, |rgb| {
+            let out = mat3_apply(&m, [rgb[0], rgb[1], rgb[2]]);
+            rgb.copy_from_slice(out);
+        });
+    }
+}
+
+/// Apply an `N`x`N` matrix and offset to the first `N` channels of each pixel, any remaining
+/// channels (typically alpha) are copied unchanged. Values are transformed in place without any
+/// color conversion, so the preset matrices should be used with linear `Rgb` or `Rgba` images.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColorMatrix {
+    /// Number of rows and columns
+    pub size: usize,
+
+    /// Row-major matrix with `size * size` values
+    pub matrix: Vec<f64>,
+
+    /// Value added to each output channel, `size` values
+    pub offset: Vec<f64>,
+}
+
+impl ColorMatrix {
+    /// Create a new color matrix from `size * size` row-major values, returns `None` when the
+    /// number of values doesn't match
+    pub fn new(size: usize, matrix: impl Into<Vec<f64>>) -> Option<ColorMatrix> {
+        let matrix = matrix.into();
+        if matrix.len() != size * size {
+            return None;
+        }
+        Some(ColorMatrix {
+            size,
+            matrix,
+            offset: vec![0.0; size],
+        })
+    }
+
+    /// Identity matrix
+    pub fn identity(size: usize) -> ColorMatrix {
+        let mut matrix = vec![0.0; size * size];
+        (0..size).for_each(|i| matrix[i * size + i] = 1.0);
+        ColorMatrix {
+            size,
+            matrix,
+            offset: vec![0.0; size],
+        }
+    }
+
+    fn rgb(m: [[f64; 3]; 3]) -> ColorMatrix {
+        ColorMatrix {
+            size: 3,
+            matrix: m.concat(),
+            offset: vec![0.0; 3],
+        }
+    }
+
+    /// Set the offset added to each output channel
+    pub fn with_offset(mut self, offset: impl Into<Vec<f64>>) -> ColorMatrix {
+        let mut offset = offset.into();
+        offset.resize(self.size, 0.0);
+        self.offset = offset;
+        self
+    }
+
+    /// Apply `self` followed by `other`, the result is a single matrix. Matrices of different
+    /// sizes are extended with the identity.
+    pub fn compose(&self, other: &ColorMatrix) -> ColorMatrix {
+        let size = self.size.max(other.size);
+        let get = |m: &ColorMatrix, i: usize, j: usize| {
+            if i < m.size && j < m.size {
+                m.matrix[i * m.size + j]
+            } else if i == j {
+                1.0
+            } else {
+                0.0
+            }
+        };
+        let offset = |m: &ColorMatrix, i: usize| m.offset.get(i).copied().unwrap_or(0.0);
+
+        let mut out = ColorMatrix::identity(size);
+        for i in 0..size {
+            for j in 0..size {
+                out.matrix[i * size + j] =
+                    (0..size).map(|k| get(other, i, k) * get(self, k, j)).sum();
+            }
+            out.offset[i] = (0..size)
+                .map(|k| get(other, i, k) * offset(self, k))
+                .sum::<f64>()
+                + offset(other, i);
+        }
+        out
+    }
+
+    /// Transform the first `size` values of `px`
+    pub fn apply(&self, px: &mut [f64]) {
+        let n = self.size.min(px.len());
+        let out: Vec<f64> = (0..n)
+            .map(|i| {
+                let row = &self.matrix[i * self.size..i * self.size + n];
+                row.iter().zip(px.iter()).map(|(a, b)| a * b).sum::<f64>() + self.offset[i]
+            })
+            .collect();
+        px[..n].copy_from_slice(&out);
+    }
+
+    /// Sepia tone
+    pub fn sepia() -> ColorMatrix {
+        ColorMatrix::rgb([
+            [0.393, 0.769, 0.189],
+            [0.349, 0.686, 0.168],
+            [0.272, 0.534, 0.131],
+        ])
+    }
+
+    /// Grayscale using Rec. 709 luma weights
+    pub fn grayscale() -> ColorMatrix {
+        ColorMatrix::saturation(0.0)
+    }
+
+    /// Scale saturation around Rec. 709 luma, 0 is grayscale and 1 is the identity
+    pub fn saturation(amount: f64) -> ColorMatrix {
+        let luma = [0.2126, 0.7152, 0.0722];
+        let mut m = [[0.0; 3]; 3];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, x) in row.iter_mut().enumerate() {
+                *x = luma[j] * (1.0 - amount) + if i == j { amount } else { 0.0 };
+            }
+        }
+        ColorMatrix::rgb(m)
+    }
+
+    /// Mix channels, each output channel is a weighted sum of the red, green and blue inputs
+    pub fn channel_mixer(red: [f64; 3], green: [f64; 3], blue: [f64; 3]) -> ColorMatrix {
+        ColorMatrix::rgb([red, green, blue])
+    }
+
+    /// Simulate protanopia (Machado et al. 2009)
+    pub fn protanopia() -> ColorMatrix {
+        ColorMatrix::rgb([
+            [0.152286, 1.052583, -0.204868],
+            [0.114503, 0.786281, 0.099216],
+            [-0.003882, -0.048116, 1.051998],
+        ])
+    }
+
+    /// Simulate deuteranopia (Machado et al. 2009)
+    pub fn deuteranopia() -> ColorMatrix {
+        ColorMatrix::rgb([
+            [0.367322, 0.860646, -0.227968],
+            [0.280085, 0.672501, 0.047413],
+            [-0.011820, 0.042940, 0.968881],
+        ])
+    }
+
+    /// Simulate tritanopia (Machado et al. 2009)
+    pub fn tritanopia() -> ColorMatrix {
+        ColorMatrix::rgb([
+            [1.255528, -0.076749, -0.178779],
+            [-0.078411, 0.930809, 0.147602],
+            [0.004733, 0.691367, 0.303900],
+        ])
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for ColorMatrix {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        self.apply(px.as_mut());
+        px.copy_to_slice(dest);
+    }
+}
+
+/// Round a normalized value to the nearest of `levels` evenly spaced levels
+fn quantize(value: f64, levels: usize) -> f64 {
+    let max = (levels.max(2) - 1) as f64;
+    (value.clamp(0.0, 1.0) * max).round() / max
+}
+
+/// Number of levels needed to represent every value of `T`, capped at 65536
+fn type_levels<T: Type>() -> usize {
+    if T::is_float() {
+        return 65536;
+    }
+    ((T::MAX - T::MIN) as usize + 1).clamp(2, 65536)
+}
+
+/// Ordered dithering using a Bayer threshold matrix, reduces the number of levels per channel
+/// while preserving the average value of gradients. Each pixel is processed independently which
+/// makes the pattern stable between frames. Alpha is not affected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OrderedDither {
+    /// Number of output levels per channel
+    pub levels: usize,
+
+    /// Width and height of the threshold matrix, a power of two
+    pub matrix_size: usize,
+}
+
+impl Default for OrderedDither {
+    fn default() -> OrderedDither {
+        OrderedDither::new(256)
+    }
+}
+
+impl OrderedDither {
+    /// Create a new ordered dither filter with a 4x4 matrix
+    pub fn new(levels: usize) -> OrderedDither {
+        OrderedDither {
+            levels: levels.max(2),
+            matrix_size: 4,
+        }
+    }
+
+    /// Dither to the given number of bits per channel
+    pub fn with_bits(bits: usize) -> OrderedDither {
+        OrderedDither::new(1 << bits.clamp(1, 16))
+    }
+
+    /// Dither to the levels representable by `T`
+    pub fn for_type<T: Type>() -> OrderedDither {
+        OrderedDither::new(type_levels::<T>())
+    }
+
+    /// Set the matrix size, rounded up to a power of two between 2 and 16
+    pub fn with_matrix_size(mut self, size: usize) -> OrderedDither {
+        self.matrix_size = size.clamp(2, 16).next_power_of_two();
+        self
+    }
+
+    /// Threshold at `(x, y)`, between 0 and 1
+    pub fn threshold(&self, x: usize, y: usize) -> f64 {
+        let n = self.matrix_size.clamp(2, 16).next_power_of_two();
+        let bits = n.trailing_zeros() as usize;
+        let (a, b) = ((x ^ y) % n, y % n);
+        let mut index = 0;
+        for i in 0..bits {
+            let shift = 2 * (bits - 1 - i);
+            index |= ((a >> i) & 1) << (shift + 1) | ((b >> i) & 1) << shift;
+        }
+        (index as f64 + 0.5) / (n * n) as f64
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for OrderedDither {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let max = (self.levels.max(2) - 1) as f64;
+        let threshold = self.threshold(pt.x, pt.y);
+        map_color_channels(pt, input, dest, |x| {
+            ((x.clamp(0.0, 1.0) * max + threshold - 0.5).round() / max).clamp(0.0, 1.0)
+        })
+    }
+}
+
+/// Floyd-Steinberg error diffusion, reduces the number of levels per channel by spreading the
+/// rounding error of each pixel to its unprocessed neighbors. Produces less visible patterns than
+/// ordered dithering but requires the whole image. Alpha is not affected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FloydSteinberg {
+    /// Number of output levels per channel
+    pub levels: usize,
+
+    /// Alternate the scan direction on every row, which avoids directional artifacts
+    pub serpentine: bool,
+}
+
+impl Default for FloydSteinberg {
+    fn default() -> FloydSteinberg {
+        FloydSteinberg::new(256)
+    }
+}
+
+impl FloydSteinberg {
+    /// Create a new error diffusion filter using a serpentine scan
+    pub fn new(levels: usize) -> FloydSteinberg {
+        FloydSteinberg {
+            levels: levels.max(2),
+            serpentine: true,
+        }
+    }
+
+    /// Dither to the given number of bits per channel
+    pub fn with_bits(bits: usize) -> FloydSteinberg {
+        FloydSteinberg::new(1 << bits.clamp(1, 16))
+    }
+
+    /// Dither to the levels representable by `T`
+    pub fn for_type<T: Type>() -> FloydSteinberg {
+        FloydSteinberg::new(type_levels::<T>())
+    }
+
+    /// Enable or disable the serpentine scan
+    pub fn with_serpentine(mut self, serpentine: bool) -> FloydSteinberg {
+        self.serpentine = serpentine;
+        self
+    }
+
+    /// Dither raw channel values laid out row by row
+    fn diffuse<C: Color>(&self, data: &mut [f64], width: usize, height: usize) {
+        let channels = C::CHANNELS;
+        for c in (0..channels).filter(|c| Some(*c) != C::ALPHA) {
+            let mut error = vec![0.0; (width + 2) * 2];
+            for y in 0..height {
+                let reverse = self.serpentine && y % 2 == 1;
+                let (current, next) = error.split_at_mut(width + 2);
+                next.iter_mut().for_each(|e| *e = 0.0);
+                for i in 0..width {
+                    let x = if reverse { width - 1 - i } else { i };
+                    let index = (y * width + x) * channels + c;
+                    let value = data[index] + current[x + 1];
+                    let q = quantize(value, self.levels);
+                    let err = value - q;
+                    data[index] = q;
+
+                    // Error buffers are offset by one so both neighbors are always in range
+                    let (ahead, behind) = if reverse { (x, x + 2) } else { (x + 2, x) };
+                    current[ahead] += err * 7.0 / 16.0;
+                    next[behind] += err * 3.0 / 16.0;
+                    next[x + 1] += err * 5.0 / 16.0;
+                    next[ahead] += err / 16.0;
+                }
+                current.copy_from_slice(next);
+            }
+        }
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for FloydSteinberg {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn prepare(
+        &self,
+        input: &Input<T, C>,
+    ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+        let image = input.images()[0];
+        let mut data = Vec::with_capacity(image.width() * image.height() * C::CHANNELS);
+        image.each_pixel(|_, px| data.extend(px.iter()));
+        self.diffuse::<C>(&mut data, image.width(), image.height());
+        Some(std::sync::Arc::new(data))
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let width = input.images()[0].width();
+        let index = (pt.y * width + pt.x) * C::CHANNELS;
+        match input.prepared::<Vec<f64>>() {
+            Some(data) if pt.x < width && index + C::CHANNELS <= data.len() => {
+                Pixel::<C>::from(&data[index..index + C::CHANNELS]).copy_to_slice(dest)
+            }
+            _ => map_color_channels(pt, input, dest, |x| quantize(x, self.levels)),
+        }
+    }
+}
@@ -1,5 +1,22 @@
 use crate::*;
 
+/// Determines how output coordinates are mapped to input coordinates, this allows inputs with
+/// different dimensions to be used together
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Sampling {
+    /// Output coordinates are used directly, aligning the input with the top-left corner of the
+    /// output
+    #[default]
+    TopLeft,
+
+    /// Output coordinates are scaled so the input covers the whole output
+    Normalized,
+
+    /// Output coordinates are mapped to input coordinates using the given transform
+    Transform(Transform),
+}
+
 /// Filter input
 #[derive(Clone)]
 pub struct Input<'a, T: 'a + Type, C: 'a + Color> {
@@ -8,6 +25,15 @@ pub struct Input<'a, T: 'a + Type, C: 'a + Color> {
 
     /// Input pixel
     pub pixel: Option<(Point, Pixel<C>)>,
+
+    /// Sampling policy for each input image, `None` uses the policy declared by the filter
+    sampling: Vec<Option<Sampling>>,
+
+    /// Output size, used for `Sampling::Normalized`
+    size: Option<Size>,
+
+    /// Data computed by `Filter::prepare`
+    prepared: Option<std::sync::Arc<dyn std::any::Any + Send + Sync>>,
 }
 
 impl<'a, T: 'a + Type, C: 'a + Color> Input<'a, T, C> {
@@ -16,9 +42,116 @@ impl<'a, T: 'a + Type, C: 'a + Color> Input<'a, T, C> {
         Input {
             images: images.to_vec(),
             pixel: None,
+            sampling: Vec::new(),
+            size: None,
+            prepared: None,
         }
     }
 
+    /// Set the sampling policy for the input image at `index`
+    pub fn with_sampling(mut self, index: usize, sampling: Sampling) -> Self {
+        if self.sampling.len() <= index {
+            self.sampling.resize(index + 1, None);
+        }
+        self.sampling[index] = Some(sampling);
+        self
+    }
+
+    /// Set the output size
+    pub fn with_size(mut self, size: impl Into<Size>) -> Self {
+        self.size = Some(size.into());
+        self
+    }
+
+    /// Get the output size, if it has been set
+    pub fn size(&self) -> Option<Size> {
+        self.size
+    }
+
+    /// Replace the data computed by `Filter::prepare`
+    pub(crate) fn with_prepared(
+        mut self,
+        prepared: Option<std::sync::Arc<dyn std::any::Any + Send + Sync>>,
+    ) -> Self {
+        self.prepared = prepared;
+        self
+    }
+
+    /// Fill in the output size and any sampling policies that haven't been set explicitly using
+    /// the ones declared by `filter`, then run `Filter::prepare`
+    pub(crate) fn prepare<U: Type, D: Color, F: ?Sized + Filter<T, C, U, D>>(
+        mut self,
+        filter: &F,
+        size: Size,
+    ) -> Self {
+        self.size = Some(size);
+        self.sampling
+            .resize(self.images.len().max(self.sampling.len()), None);
+        for (i, s) in self.sampling.iter_mut().enumerate() {
+            if s.is_none() {
+                *s = Some(filter.sampling(i));
+            }
+        }
+        self.prepared = filter.prepare(&self);
+        self
+    }
+
+    /// Get data computed by `Filter::prepare`, returns `None` if nothing was prepared or if the
+    /// value isn't an `X`
+    pub fn prepared<X: 'static>(&self) -> Option<&X> {
+        self.prepared.as_ref().and_then(|x| x.downcast_ref())
+    }
+
+    /// Get the sampling policy for the input image at `index`
+    pub fn sampling(&self, index: usize) -> Sampling {
+        self.sampling
+            .get(index)
+            .copied()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    /// Map an output point to a point in the input image at `index` using its sampling policy,
+    /// the result is clamped to the bounds of the input image
+    pub fn map_point(&self, pt: impl Into<Point>, index: usize) -> Point {
+        let pt = pt.into();
+        let image = self.images[index];
+        let (x, y) = match self.sampling(index) {
+            Sampling::TopLeft => (pt.x as f64, pt.y as f64),
+            Sampling::Normalized => {
+                let size = self.size.unwrap_or_else(|| self.images[0].size());
+                (
+                    ((pt.x as f64 + 0.5) * image.width() as f64 / size.width.max(1) as f64).floor(),
+                    ((pt.y as f64 + 0.5) * image.height() as f64 / size.height.max(1) as f64)
+                        .floor(),
+                )
+            }
+            Sampling::Transform(t) => {
+                let p = t.transform_point(euclid::Point2D::new(pt.x as f64, pt.y as f64));
+                (p.x.round(), p.y.round())
+            }
+        };
+
+        Point::new(
+            x.clamp(0.0, image.width().saturating_sub(1) as f64) as usize,
+            y.clamp(0.0, image.height().saturating_sub(1) as f64) as usize,
+        )
+    }
+
+    /// Get the pixel from the input image at `index` that corresponds to the output point `pt`,
+    /// according to the sampling policy of that input
+    pub fn sample_pixel(&self, pt: impl Into<Point>, index: usize) -> Pixel<C> {
+        let pt = self.map_point(pt, index);
+        self.get_pixel(pt, if index == 0 { None } else { Some(index) })
+    }
+
+    /// Get the float value from the input image at `index` that corresponds to the output point
+    /// `pt`, according to the sampling policy of that input
+    pub fn sample_f(&self, pt: impl Into<Point>, c: Channel, index: usize) -> f64 {
+        let pt = self.map_point(pt, index);
+        self.get_f(pt, c, if index == 0 { None } else { Some(index) })
+    }
+
     /// Add chained pixel data
     pub fn with_pixel(mut self, point: Point, pixel: Pixel<C>) -> Self {
         self.pixel = Some((point, pixel));
@@ -80,3 +213,34 @@ impl<'a, T: 'a + Type, C: 'a + Color> Input<'a, T, C> {
         Pixel::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_sampling() {
+        let mut a = Image::<f32, Gray>::new((8, 8));
+        a.for_each(|_, mut px| px[0] = 1.0);
+
+        // Left half set, at a quarter of the resolution
+        let mut b = Image::<f32, Gray>::new((2, 2));
+        b.set((0, 0), [1.0]);
+        b.set((0, 1), [1.0]);
+
+        let mut dest = a.new_like();
+        filter::blend().eval(&[&a, &b], &mut dest);
+        assert_eq!(dest.get_f((3, 7), 0), 1.0);
+        assert_eq!(dest.get_f((4, 0), 0), 0.5);
+
+        let images = [&a, &b];
+        let input = Input::new(&images).with_size((8, 8));
+        assert_eq!(input.map_point((7, 7), 1), Point::new(1, 1));
+
+        let input = input
+            .with_sampling(1, Sampling::Transform(Transform::scale(0.25, 0.25)))
+            .with_sampling(0, Sampling::Normalized);
+        assert_eq!(input.map_point((7, 2), 1), Point::new(1, 1));
+        assert_eq!(input.map_point((7, 2), 0), Point::new(7, 2));
+    }
+}
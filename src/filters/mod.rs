@@ -4,6 +4,8 @@ use crate::*;
 use rayon::prelude::*;
 
 mod r#async;
+mod cache;
+mod expr;
 mod ext;
 mod input;
 mod pipeline;
@@ -11,10 +13,16 @@ mod pipeline;
 /// Image processing filters
 pub mod filter;
 
+/// Filters over sequences of frames
+pub mod temporal;
+
+pub use cache::TileCache;
+pub use expr::Expr;
 pub use ext::*;
-pub use input::Input;
+pub use input::{Input, Sampling};
 pub use pipeline::*;
 pub use r#async::*;
+pub use temporal::TemporalFilter;
 
 /// Filters are used to manipulate images in a generic, composable manner
 pub trait Filter<T: Type, C: Color, U: Type = T, D: Color = C>: std::fmt::Debug + Sync {
@@ -29,6 +37,23 @@ pub trait Filter<T: Type, C: Color, U: Type = T, D: Color = C>: std::fmt::Debug
         dest.size()
     }
 
+    /// Sampling policy used to map output coordinates to the input image at `index` when using
+    /// `Input::sample_pixel` or `Input::sample_f`
+    fn sampling(&self, _index: usize) -> Sampling {
+        Sampling::TopLeft
+    }
+
+    /// Called once before `compute_at` is used to evaluate the filter, this can be used by
+    /// filters that need to process the whole input up front. The returned value is available
+    /// from `compute_at` using `Input::prepared`. Filters that prepare data from the input image
+    /// should use `Schedule::Image`, so pipelines evaluate the filters before them first.
+    fn prepare(
+        &self,
+        _input: &Input<T, C>,
+    ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+        None
+    }
+
     /// Compute filter at the given point for the provided input
     ///
     /// - `pt`: Current output point
@@ -38,7 +63,15 @@ pub trait Filter<T: Type, C: Color, U: Type = T, D: Color = C>: std::fmt::Debug
 
     /// Evaluate a filter on part of an image
     fn eval_partial(&self, roi: Region, input: &[&Image<T, C>], output: &mut Image<U, D>) {
-        let input = Input::new(input);
+        let _stage = stage!(
+            "eval_partial",
+            filter = ?self,
+            x = roi.origin.x,
+            y = roi.origin.y,
+            width = roi.width(),
+            rows = roi.height()
+        );
+        let input = Input::new(input).prepare(self, output.size());
 
         let iter = output.iter_region_mut(roi);
         iter.for_each(|(pt, mut data)| {
@@ -48,10 +81,18 @@ pub trait Filter<T: Type, C: Color, U: Type = T, D: Color = C>: std::fmt::Debug
 
     /// Evaluate filter on part of an image using the same image for input and output
     fn eval_partial_in_place(&self, roi: Region, output: &mut Image<U, D>) {
+        let _stage = stage!(
+            "eval_partial_in_place",
+            filter = ?self,
+            x = roi.origin.x,
+            y = roi.origin.y,
+            width = roi.width(),
+            rows = roi.height()
+        );
         let input = output as *mut _ as *const _;
         let input = unsafe { &[&*input] };
 
-        let input = Input::new(input);
+        let input = Input::new(input).prepare(self, output.size());
 
         output.iter_region_mut(roi).for_each(|(pt, mut data)| {
             self.compute_at(pt, &input, &mut data);
@@ -60,7 +101,13 @@ pub trait Filter<T: Type, C: Color, U: Type = T, D: Color = C>: std::fmt::Debug
 
     /// Evaluate filter
     fn eval(&self, input: &[&Image<T, C>], output: &mut Image<U, D>) {
-        let input = Input::new(input);
+        let _stage = stage!(
+            "eval",
+            filter = ?self,
+            width = output.width(),
+            rows = output.height()
+        );
+        let input = Input::new(input).prepare(self, output.size());
 
         output.for_each(|pt, mut data| {
             self.compute_at(pt, &input, &mut data);
@@ -69,10 +116,16 @@ pub trait Filter<T: Type, C: Color, U: Type = T, D: Color = C>: std::fmt::Debug
 
     /// Evaluate filter using the same image for input and output
     fn eval_in_place(&self, output: &mut Image<U, D>) {
+        let _stage = stage!(
+            "eval_in_place",
+            filter = ?self,
+            width = output.width(),
+            rows = output.height()
+        );
         let input = output as *mut _ as *const _;
         let input = unsafe { &[&*input] };
 
-        let input = Input::new(input);
+        let input = Input::new(input).prepare(self, output.size());
 
         output.for_each(|pt, mut data| {
             self.compute_at(pt, &input, &mut data);
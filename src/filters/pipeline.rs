@@ -37,14 +37,21 @@ impl<T: Type, C: Color, U: Type, D: Color> Pipeline<T, C, U, D> {
         self
     }
 
+    /// Index of the last filter in each stage. Image filters start a new stage, so the filters
+    /// before them are evaluated first and `Filter::prepare` sees their output, and end it.
     fn image_schedule_list(&self) -> Vec<usize> {
         let mut dest = Vec::new();
         for (i, f) in self.filters.iter().enumerate() {
             if f.schedule() == Schedule::Image {
+                if i > 0 && dest.last() != Some(&(i - 1)) {
+                    dest.push(i - 1);
+                }
                 dest.push(i);
             }
         }
-        dest.push(self.filters.len() - 1);
+        if dest.last() != Some(&(self.filters.len() - 1)) {
+            dest.push(self.filters.len() - 1);
+        }
         dest
     }
 
@@ -59,20 +66,34 @@ impl<T: Type, C: Color, U: Type, D: Color> Pipeline<T, C, U, D> {
         image_schedule_filters: &[usize],
     ) {
         let current_filter = &self.filters[index];
+        let _stage = stage!(
+            "pipeline_stage",
+            stage = j,
+            filter = ?current_filter,
+            width = output.width(),
+            rows = output.height()
+        );
         if current_filter.schedule() == Schedule::Image {
             let output_size = current_filter.output_size(input, output);
             if output_size != tmpconv.size() {
                 *tmpconv = Image::new(output_size);
             }
         }
+        let start = if j == 0 {
+            0
+        } else {
+            image_schedule_filters[j - 1] + 1
+        };
+
+        // Each filter may declare its own sampling policy
+        let size = output.size();
+        let inputs: Vec<Input<T, C>> = self.filters[start..=index]
+            .iter()
+            .map(|f| input.clone().prepare(f.as_ref(), size))
+            .collect();
+
         output.iter_mut().for_each(|(pt, mut data)| {
-            for f in self.filters[if j == 0 {
-                0
-            } else {
-                image_schedule_filters[j - 1] + 1
-            }..=index]
-                .iter()
-            {
+            for (f, input) in self.filters[start..=index].iter().zip(&inputs) {
                 match f.schedule() {
                     Schedule::Pixel if j > 0 => {
                         let mut px = Pixel::new();
@@ -105,6 +126,11 @@ impl<T: Type, C: Color, U: Type, D: Color> Pipeline<T, C, U, D> {
     pub fn execute(&self, input: &[&Image<T, C>], output: &mut Image<U, D>) {
         let mut input = Input::new(input);
         let image_schedule_filters = self.image_schedule_list();
+        let _stage = stage!(
+            "pipeline",
+            filters = self.filters.len(),
+            stages = image_schedule_filters.len()
+        );
 
         let mut tmpconv = Image::<T, C>::new(output.size());
 
@@ -148,6 +174,11 @@ impl<T: Type, C: Color> Pipeline<T, C> {
         let input = unsafe { &[&*(output as *const _)] };
         let mut input = Input::new(input);
         let image_schedule_filters = self.image_schedule_list();
+        let _stage = stage!(
+            "pipeline",
+            filters = self.filters.len(),
+            stages = image_schedule_filters.len()
+        );
 
         let mut tmpconv = Image::<T, C>::new(output.size());
 
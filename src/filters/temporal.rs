@@ -0,0 +1,300 @@
+use crate::*;
+
+use crate::analysis::FlowField;
+
+/// Temporal filters compute each output frame from a sliding window of input frames, extending
+/// the `Filter` concept along the time axis
+pub trait TemporalFilter<T: Type, C: Color>: std::fmt::Debug + Sync {
+    /// Number of frames before and after the current frame used to compute the output
+    fn window(&self) -> (usize, usize);
+
+    /// Compute filter at the given point
+    ///
+    /// - `pt`: Current output point
+    /// - `frames`: Frames in the window, clamped at the start and end of the sequence
+    /// - `current`: Index of the current frame in `frames`
+    /// - `index`: Index of the current frame in the whole sequence
+    /// - `dest`: Single pixel output buffer
+    fn compute_at(
+        &self,
+        pt: Point,
+        frames: &[&Image<T, C>],
+        current: usize,
+        index: usize,
+        dest: &mut DataMut<T, C>,
+    );
+
+    /// Evaluate the filter for frame `index` of a sequence, returns an error if `index` is out of
+    /// range or the frames and `output` don't all have the same size
+    fn eval(
+        &self,
+        sequence: &[&Image<T, C>],
+        index: usize,
+        output: &mut Image<T, C>,
+    ) -> Result<(), Error> {
+        if index >= sequence.len() {
+            return Err(Error::Message(format!(
+                "frame index {} out of range for a sequence of {} frames",
+                index,
+                sequence.len()
+            )));
+        }
+
+        let size = output.size();
+        if let Some(i) = sequence.iter().position(|frame| frame.size() != size) {
+            return Err(Error::Message(format!(
+                "frame {} has size {:?}, expected {:?}",
+                i,
+                sequence[i].size(),
+                size
+            )));
+        }
+
+        let (before, after) = self.window();
+        let start = index.saturating_sub(before);
+        let end = index
+            .saturating_add(after)
+            .saturating_add(1)
+            .min(sequence.len());
+        let frames = &sequence[start..end];
+        let current = index - start;
+
+        output.for_each(|pt, mut data| {
+            self.compute_at(pt, frames, current, index, &mut data);
+        });
+        Ok(())
+    }
+
+    /// Evaluate the filter for every frame of a sequence
+    fn eval_sequence(&self, sequence: &[&Image<T, C>]) -> Result<Vec<Image<T, C>>, Error> {
+        (0..sequence.len())
+            .map(|i| {
+                let mut output = sequence[i].new_like();
+                self.eval(sequence, i, &mut output)?;
+                Ok(output)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Median {
+    radius: usize,
+}
+
+/// Per-channel median of the frames within `radius` frames of the current one
+pub fn median<T: Type, C: Color>(radius: usize) -> impl TemporalFilter<T, C> {
+    Median { radius }
+}
+
+impl<T: Type, C: Color> TemporalFilter<T, C> for Median {
+    fn window(&self) -> (usize, usize) {
+        (self.radius, self.radius)
+    }
+
+    fn compute_at(
+        &self,
+        pt: Point,
+        frames: &[&Image<T, C>],
+        _current: usize,
+        _index: usize,
+        dest: &mut DataMut<T, C>,
+    ) {
+        let mut values = vec![0.0; frames.len()];
+        let mut px = Pixel::<C>::new();
+        for c in 0..C::CHANNELS {
+            for (v, frame) in values.iter_mut().zip(frames) {
+                *v = frame.get_f(pt, c);
+            }
+            values.sort_by(|a, b| a.total_cmp(b));
+
+            let n = values.len();
+            px[c] = if n % 2 == 1 {
+                values[n / 2]
+            } else {
+                (values[n / 2 - 1] + values[n / 2]) / 2.0
+            };
+        }
+        px.copy_to_slice(dest);
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct ExponentialAverage {
+    alpha: f64,
+    frames: usize,
+}
+
+/// Exponential moving average over the current and `frames` previous frames, `alpha` is the
+/// weight of the current frame
+pub fn exponential_average<T: Type, C: Color>(
+    alpha: f64,
+    frames: usize,
+) -> impl TemporalFilter<T, C> {
+    ExponentialAverage { alpha, frames }
+}
+
+impl<T: Type, C: Color> TemporalFilter<T, C> for ExponentialAverage {
+    fn window(&self) -> (usize, usize) {
+        (self.frames, 0)
+    }
+
+    fn compute_at(
+        &self,
+        pt: Point,
+        frames: &[&Image<T, C>],
+        current: usize,
+        _index: usize,
+        dest: &mut DataMut<T, C>,
+    ) {
+        let mut px = Pixel::<C>::new();
+        px.fill(0.0);
+
+        let mut weight = self.alpha;
+        let mut total = 0.0;
+        for frame in frames[..=current].iter().rev() {
+            px += frame.get_pixel(pt) * weight;
+            total += weight;
+            weight *= 1.0 - self.alpha;
+        }
+
+        if total > 0.0 {
+            px /= total;
+        }
+        px.copy_to_slice(dest);
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct MotionCompensatedAverage {
+    radius: usize,
+    flow: Vec<FlowField>,
+}
+
+/// Average of the frames within `radius` frames of the current one, after aligning them using
+/// optical flow. `flow[i]` is the motion from frame `i` to frame `i + 1`; backward motion is
+/// approximated by negating the forward flow.
+pub fn motion_compensated_average<T: Type, C: Color>(
+    radius: usize,
+    flow: Vec<FlowField>,
+) -> impl TemporalFilter<T, C> {
+    MotionCompensatedAverage { radius, flow }
+}
+
+impl MotionCompensatedAverage {
+    fn clamp(x: f64, y: f64, size: Size) -> Point {
+        Point::new(
+            x.round().clamp(0.0, size.width.saturating_sub(1) as f64) as usize,
+            y.round().clamp(0.0, size.height.saturating_sub(1) as f64) as usize,
+        )
+    }
+}
+
+impl<T: Type, C: Color> TemporalFilter<T, C> for MotionCompensatedAverage {
+    fn window(&self) -> (usize, usize) {
+        (self.radius, self.radius)
+    }
+
+    fn compute_at(
+        &self,
+        pt: Point,
+        frames: &[&Image<T, C>],
+        current: usize,
+        index: usize,
+        dest: &mut DataMut<T, C>,
+    ) {
+        let mut px = frames[current].get_pixel(pt);
+        let size = frames[current].size();
+
+        // Follow the flow forward
+        let (mut x, mut y) = (pt.x as f64, pt.y as f64);
+        for (i, frame) in frames.iter().enumerate().skip(current + 1) {
+            let (dx, dy) = match self.flow.get(index + i - current - 1) {
+                Some(flow) => flow.sample(x, y),
+                None => (0.0, 0.0),
+            };
+            x += dx;
+            y += dy;
+            px += &frame.get_pixel(Self::clamp(x, y, size));
+        }
+
+        // Follow the flow backward
+        let (mut x, mut y) = (pt.x as f64, pt.y as f64);
+        for i in (0..current).rev() {
+            let (dx, dy) = match self.flow.get(index + i - current) {
+                Some(flow) => flow.sample(x, y),
+                None => (0.0, 0.0),
+            };
+            x -= dx;
+            y -= dy;
+            px += &frames[i].get_pixel(Self::clamp(x, y, size));
+        }
+
+        (px / frames.len() as f64).copy_to_slice(dest);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn frames() -> Vec<Image<f32, Gray>> {
+        (0..5)
+            .map(|i| {
+                let mut image = Image::<f32, Gray>::new((16, 8));
+                image.for_each(|pt, mut px| {
+                    // Bright column moving one pixel to the right each frame
+                    px[0] = if pt.x == 4 + i { 1.0 } else { 0.0 };
+                });
+                image
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_temporal() {
+        let frames = frames();
+        let sequence: Vec<&Image<f32, Gray>> = frames.iter().collect();
+
+        let median = temporal::median(1).eval_sequence(&sequence).unwrap();
+        assert_eq!(median.len(), 5);
+        assert_eq!(median[2].get_f((6, 0), 0), 0.0);
+
+        let mut ema = Image::<f32, Gray>::new((16, 8));
+        temporal::exponential_average(0.5, 1)
+            .eval(&sequence, 2, &mut ema)
+            .unwrap();
+        assert!((ema.get_f((6, 0), 0) - 2.0 / 3.0).abs() < 1e-6);
+        assert!((ema.get_f((5, 0), 0) - 1.0 / 3.0).abs() < 1e-6);
+
+        let flow = vec![analysis::FlowField::uniform((16, 8), 1.0, 0.0); 4];
+        let mut avg = Image::<f32, Gray>::new((16, 8));
+        temporal::motion_compensated_average(2, flow)
+            .eval(&sequence, 2, &mut avg)
+            .unwrap();
+        assert!((avg.get_f((6, 3), 0) - 1.0).abs() < 1e-6);
+        assert_eq!(avg.get_f((5, 3), 0), 0.0);
+    }
+
+    #[test]
+    fn test_temporal_invalid() {
+        let mut frames = frames();
+        let mut output = Image::<f32, Gray>::new((16, 8));
+
+        let sequence: Vec<&Image<f32, Gray>> = frames.iter().collect();
+        let median = temporal::median(1);
+        assert!(median.eval(&sequence, 5, &mut output).is_err());
+        assert!(median.eval(&[], 0, &mut output).is_err());
+
+        let mut small = Image::<f32, Gray>::new((8, 8));
+        assert!(median.eval(&sequence, 2, &mut small).is_err());
+
+        frames.push(Image::new((8, 8)));
+        let sequence: Vec<&Image<f32, Gray>> = frames.iter().collect();
+        assert!(median.eval(&sequence, 2, &mut output).is_err());
+        assert!(median.eval_sequence(&sequence).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use crate::*;
+
 /// (x, y) coordinates
 pub type Point = euclid::Point2D<usize, f64>;
 
@@ -6,3 +8,473 @@ pub type Size = euclid::Size2D<usize, f64>;
 
 /// Region of interest
 pub type Region = euclid::Rect<usize, f64>;
+
+/// Closed polygon made up of a list of vertices, the last vertex is implicitly connected to the
+/// first
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Polygon {
+    /// Polygon vertices
+    pub points: Vec<Point>,
+}
+
+impl From<Vec<Point>> for Polygon {
+    fn from(points: Vec<Point>) -> Polygon {
+        Polygon { points }
+    }
+}
+
+impl From<Polygon> for Vec<Point> {
+    fn from(polygon: Polygon) -> Vec<Point> {
+        polygon.points
+    }
+}
+
+impl From<Region> for Polygon {
+    fn from(r: Region) -> Polygon {
+        let (x0, y0) = (r.min_x(), r.min_y());
+        let (x1, y1) = (
+            r.max_x().saturating_sub(1).max(x0),
+            r.max_y().saturating_sub(1).max(y0),
+        );
+        Polygon::new(vec![
+            Point::new(x0, y0),
+            Point::new(x1, y0),
+            Point::new(x1, y1),
+            Point::new(x0, y1),
+        ])
+    }
+}
+
+impl Polygon {
+    /// Create a new polygon from a list of vertices
+    pub fn new(points: impl Into<Vec<Point>>) -> Polygon {
+        Polygon {
+            points: points.into(),
+        }
+    }
+
+    /// Number of vertices
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns true when the polygon has no vertices
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Iterate over the polygon edges as pairs of points
+    pub fn edges(&self) -> impl '_ + Iterator<Item = (Point, Point)> {
+        let n = self.points.len();
+        (0..n).map(move |i| (self.points[i], self.points[(i + 1) % n]))
+    }
+
+    /// Get the smallest `Region` containing every vertex
+    pub fn bounds(&self) -> Region {
+        if self.points.is_empty() {
+            return Region::default();
+        }
+
+        let mut min = self.points[0];
+        let mut max = self.points[0];
+        for pt in &self.points {
+            min = min.min(*pt);
+            max = max.max(*pt);
+        }
+
+        Region::new(min, Size::new(max.x - min.x + 1, max.y - min.y + 1))
+    }
+
+    /// Length of the closed outline
+    pub fn perimeter(&self) -> f64 {
+        if self.points.len() < 2 {
+            return 0.0;
+        }
+
+        self.edges().map(|(a, b)| distance(a, b)).sum()
+    }
+
+    /// Enclosed area, computed using the shoelace formula
+    pub fn area(&self) -> f64 {
+        let twice: f64 = self
+            .edges()
+            .map(|(a, b)| a.x as f64 * b.y as f64 - b.x as f64 * a.y as f64)
+            .sum();
+        twice.abs() / 2.0
+    }
+
+    /// Simplify the polygon using the Douglas-Peucker algorithm, vertices closer than `epsilon`
+    /// pixels to the simplified outline are removed
+    pub fn simplify(&self, epsilon: f64) -> Polygon {
+        let n = self.points.len();
+        if n < 4 {
+            return self.clone();
+        }
+
+        // Split the closed outline at the vertex furthest from the first one, then simplify both
+        // open halves
+        let first = self.points[0];
+        let (split, _) = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (i, distance(first, *p)))
+            .fold((0, 0.0), |acc, x| if x.1 > acc.1 { x } else { acc });
+
+        if split == 0 {
+            return Polygon::new(vec![first]);
+        }
+
+        let mut a = douglas_peucker(&self.points[..=split], epsilon);
+        let mut tail = self.points[split..].to_vec();
+        tail.push(first);
+        let b = douglas_peucker(&tail, epsilon);
+
+        a.pop();
+        a.extend_from_slice(&b[..b.len() - 1]);
+        Polygon::new(a)
+    }
+
+    /// Interior spans of row `y` using the even-odd rule at pixel centers, as inclusive pairs of
+    /// x coordinates
+    fn row_spans(&self, y: usize) -> Vec<(usize, usize)> {
+        let yc = y as f64 + 0.5;
+        let mut crossings: Vec<f64> = self
+            .edges()
+            .filter_map(|(a, b)| {
+                let (ay, by) = (a.y as f64, b.y as f64);
+                if (ay <= yc) == (by <= yc) {
+                    return None;
+                }
+                let t = (yc - ay) / (by - ay);
+                Some(a.x as f64 + t * (b.x as f64 - a.x as f64))
+            })
+            .collect();
+        crossings.sort_by(|a, b| a.total_cmp(b));
+        crossings
+            .chunks_exact(2)
+            .map(|span| (span[0].round() as usize, span[1].round() as usize))
+            .collect()
+    }
+
+    /// Returns true when the pixel at `pt` is covered by the polygon, these are the pixels
+    /// inside of the polygon or on its outline, the same pixels set by `Image::fill_polygon`
+    pub fn contains(&self, pt: impl Into<Point>) -> bool {
+        let pt = pt.into();
+        if self.is_empty() || !self.bounds().contains(pt) {
+            return false;
+        }
+        self.row_spans(pt.y)
+            .iter()
+            .any(|(x0, x1)| (*x0..=*x1).contains(&pt.x))
+            || self.outline().any(|p| p == pt)
+    }
+
+    /// Pixels on the outline, as drawn by `Image::draw_polygon`
+    fn outline(&self) -> impl '_ + Iterator<Item = Point> {
+        let n = self.points.len();
+        let edges: Box<dyn Iterator<Item = (Point, Point)>> = if n == 1 {
+            Box::new(std::iter::once((self.points[0], self.points[0])))
+        } else {
+            Box::new(self.edges())
+        };
+        edges.flat_map(|(a, b)| line_points(a, b))
+    }
+
+    /// Set the pixels covered by the polygon in `mask`, pixels outside of the mask are ignored
+    pub(crate) fn rasterize(&self, mask: &mut BitImage) {
+        if self.is_empty() {
+            return;
+        }
+
+        let (width, height) = (mask.width(), mask.height());
+        let bounds = self.bounds();
+        for y in bounds.min_y()..bounds.max_y().min(height) {
+            for (x0, x1) in self.row_spans(y) {
+                for x in x0..=x1.min(width.saturating_sub(1)) {
+                    mask.set((x, y), true);
+                }
+            }
+        }
+        for pt in self.outline() {
+            if pt.x < width && pt.y < height {
+                mask.set(pt, true);
+            }
+        }
+    }
+}
+
+/// Pixels on a one pixel wide line from `a` to `b`, using Bresenham's algorithm
+pub(crate) fn line_points(a: Point, b: Point) -> impl Iterator<Item = Point> {
+    let (mut x, mut y) = (a.x as isize, a.y as isize);
+    let (x1, y1) = (b.x as isize, b.y as isize);
+    let dx = (x1 - x).abs();
+    let dy = -(y1 - y).abs();
+    let sx = if x < x1 { 1 } else { -1 };
+    let sy = if y < y1 { 1 } else { -1 };
+    let mut err = dx + dy;
+    let mut done = false;
+
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let pt = Point::new(x as usize, y as usize);
+        if x == x1 && y == y1 {
+            done = true;
+        } else {
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+        Some(pt)
+    })
+}
+
+/// Region of interest with an arbitrary shape, built from rectangles and polygons combined using
+/// set operations. Coverage is evaluated per pixel.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Roi {
+    /// Axis-aligned rectangle
+    Rect(Region),
+
+    /// Polygon, covering the pixels inside of it and on its outline
+    Polygon(Polygon),
+
+    /// Pixels covered by either shape
+    Union(Box<Roi>, Box<Roi>),
+
+    /// Pixels covered by both shapes
+    Intersection(Box<Roi>, Box<Roi>),
+
+    /// Pixels covered by the first shape but not the second
+    Difference(Box<Roi>, Box<Roi>),
+}
+
+impl From<Region> for Roi {
+    fn from(region: Region) -> Roi {
+        Roi::Rect(region)
+    }
+}
+
+impl From<Polygon> for Roi {
+    fn from(polygon: Polygon) -> Roi {
+        Roi::Polygon(polygon)
+    }
+}
+
+impl Roi {
+    /// Pixels covered by `self` or `other`
+    pub fn union(self, other: impl Into<Roi>) -> Roi {
+        Roi::Union(Box::new(self), Box::new(other.into()))
+    }
+
+    /// Pixels covered by both `self` and `other`
+    pub fn intersection(self, other: impl Into<Roi>) -> Roi {
+        Roi::Intersection(Box::new(self), Box::new(other.into()))
+    }
+
+    /// Pixels covered by `self` but not `other`
+    pub fn subtract(self, other: impl Into<Roi>) -> Roi {
+        Roi::Difference(Box::new(self), Box::new(other.into()))
+    }
+
+    /// Returns true when the pixel at `pt` is covered
+    pub fn contains(&self, pt: impl Into<Point>) -> bool {
+        let pt = pt.into();
+        match self {
+            Roi::Rect(r) => r.contains(pt),
+            Roi::Polygon(p) => p.contains(pt),
+            Roi::Union(a, b) => a.contains(pt) || b.contains(pt),
+            Roi::Intersection(a, b) => a.contains(pt) && b.contains(pt),
+            Roi::Difference(a, b) => a.contains(pt) && !b.contains(pt),
+        }
+    }
+
+    /// Rectangle containing every covered pixel, it may be larger than necessary for shapes
+    /// built using set operations
+    pub fn bounds(&self) -> Region {
+        match self {
+            Roi::Rect(r) => *r,
+            Roi::Polygon(p) => p.bounds(),
+            Roi::Union(a, b) => a.bounds().union(&b.bounds()),
+            Roi::Intersection(a, b) => a.bounds().intersection(&b.bounds()).unwrap_or_default(),
+            Roi::Difference(a, _) => a.bounds(),
+        }
+    }
+
+    /// Create a mask of the given size with the covered pixels set
+    pub fn to_mask(&self, size: impl Into<Size>) -> BitImage {
+        let size = size.into();
+        match self {
+            Roi::Rect(r) => {
+                let mut mask = BitImage::new(size);
+                if let Some(r) = r.intersection(&Region::new(Point::zero(), size)) {
+                    for y in r.min_y()..r.max_y() {
+                        for x in r.min_x()..r.max_x() {
+                            mask.set((x, y), true);
+                        }
+                    }
+                }
+                mask
+            }
+            Roi::Polygon(p) => {
+                let mut mask = BitImage::new(size);
+                p.rasterize(&mut mask);
+                mask
+            }
+            Roi::Union(a, b) => a.to_mask(size) | &b.to_mask(size),
+            Roi::Intersection(a, b) => a.to_mask(size) & &b.to_mask(size),
+            Roi::Difference(a, b) => a.to_mask(size) & &!b.to_mask(size),
+        }
+    }
+
+    /// Iterate over the covered pixels in raster order
+    pub fn pixels(&self) -> impl Iterator<Item = Point> {
+        let bounds = self.bounds();
+        let mask = self.to_mask(Size::new(bounds.max_x(), bounds.max_y()));
+        mask.points().collect::<Vec<_>>().into_iter()
+    }
+
+    /// Number of covered pixels
+    pub fn area(&self) -> usize {
+        let bounds = self.bounds();
+        self.to_mask(Size::new(bounds.max_x(), bounds.max_y()))
+            .count()
+    }
+}
+
+/// Region of interest with a name, for example a labeled area in an annotation dataset
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NamedRoi {
+    /// Name
+    pub name: String,
+
+    /// Covered area
+    pub roi: Roi,
+}
+
+impl NamedRoi {
+    /// Create a new named region
+    pub fn new(name: impl Into<String>, roi: impl Into<Roi>) -> NamedRoi {
+        NamedRoi {
+            name: name.into(),
+            roi: roi.into(),
+        }
+    }
+}
+
+pub(crate) fn distance(a: Point, b: Point) -> f64 {
+    let dx = a.x as f64 - b.x as f64;
+    let dy = a.y as f64 - b.y as f64;
+    (dx * dx + dy * dy).sqrt()
+}
+
+pub(crate) fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let (px, py) = (p.x as f64, p.y as f64);
+    let (ax, ay) = (a.x as f64, a.y as f64);
+    let (bx, by) = (b.x as f64, b.y as f64);
+    let (dx, dy) = (bx - ax, by - ay);
+    let len2 = dx * dx + dy * dy;
+    if len2 == 0.0 {
+        return distance(p, a);
+    }
+
+    let t = (((px - ax) * dx + (py - ay) * dy) / len2).clamp(0.0, 1.0);
+    let (x, y) = (ax + t * dx, ay + t * dy);
+    ((px - x) * (px - x) + (py - y) * (py - y)).sqrt()
+}
+
+fn douglas_peucker(points: &[Point], epsilon: f64) -> Vec<Point> {
+    let n = points.len();
+    if n < 3 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; n];
+    keep[0] = true;
+    keep[n - 1] = true;
+
+    let mut stack = vec![(0, n - 1)];
+    while let Some((start, end)) = stack.pop() {
+        let mut max = 0.0;
+        let mut index = start;
+        for (i, p) in points.iter().enumerate().take(end).skip(start + 1) {
+            let d = segment_distance(*p, points[start], points[end]);
+            if d > max {
+                max = d;
+                index = i;
+            }
+        }
+
+        if max > epsilon {
+            keep[index] = true;
+            stack.push((start, index));
+            stack.push((index, end));
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(p, k)| if k { Some(*p) } else { None })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_roi() {
+        let triangle = Polygon::new(vec![Point::new(2, 2), Point::new(12, 2), Point::new(2, 12)]);
+
+        // Coverage matches `fill_polygon`
+        let mut image = Image::<u8, Gray>::new((16, 16));
+        image.fill_polygon(&triangle, &Pixel::from(vec![1.0]));
+        let roi = Roi::from(triangle.clone());
+        image.each_pixel(|pt, px| assert_eq!(px[0] > 0.0, roi.contains(pt), "{:?}", pt));
+        assert_eq!(
+            roi.pixels().count(),
+            image.data().iter().filter(|x| **x > 0).count()
+        );
+
+        let rect = Region::new(Point::new(0, 0), Size::new(6, 6));
+        let union = roi.clone().union(rect);
+        let intersection = roi.clone().intersection(rect);
+        let difference = roi.clone().subtract(rect);
+        assert_eq!(union.area() + intersection.area(), roi.area() + 36);
+        assert_eq!(difference.area(), roi.area() - intersection.area());
+        assert!(intersection.contains((3, 3)) && !intersection.contains((1, 1)));
+        assert!(union.contains((1, 1)) && !difference.contains((3, 3)));
+        assert_eq!(
+            intersection.bounds(),
+            Region::new(Point::new(2, 2), Size::new(4, 4))
+        );
+
+        let mask = difference.to_mask((8, 8));
+        assert_eq!(
+            mask.count(),
+            difference.pixels().filter(|p| p.x < 8 && p.y < 8).count()
+        );
+
+        let named = NamedRoi::new("corner", rect);
+        assert_eq!(named.roi.area(), 36);
+
+        let mut image = Image::<u8, Gray>::new((16, 16));
+        image.for_each_roi(&difference, |_, mut px| px[0] = 255);
+        assert_eq!(
+            image.data().iter().filter(|x| **x > 0).count(),
+            difference.area()
+        );
+    }
+}
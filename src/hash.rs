@@ -1,7 +1,18 @@
+use std::path::{Path, PathBuf};
+
 use crate::*;
 
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
 const HASH_SIZE: usize = 16;
 
+/// Size of the luminance image used by `Image::phash`
+const PHASH_SIZE: usize = 32;
+
+/// Number of low frequency DCT coefficients kept in each direction by `Image::phash`
+const PHASH_BITS: usize = 8;
+
 /// Hash is used for content-based hashing
 #[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord)]
 pub struct Hash(Vec<bool>);
@@ -94,3 +105,480 @@ impl<T: Type, C: Color> Image<T, C> {
         Hash(hash)
     }
 }
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Get perceptual image hash: the luminance is area averaged to 32x32, transformed using a
+    /// DCT, and each of the 8x8 lowest frequency coefficients is compared to their median. This
+    /// is more robust to scaling, compression and small edits than `Image::hash`.
+    pub fn phash(&self) -> Hash {
+        let mut luma = Image::<f32, Gray>::new(self.size());
+        let c = C::CHANNELS - C::ALPHA.is_some() as usize;
+        luma.for_each(|pt, mut px| {
+            px[0] = if c >= 3 {
+                self.get_f(pt, 0) * 0.21 + self.get_f(pt, 1) * 0.72 + self.get_f(pt, 2) * 0.07
+            } else {
+                self.get_f(pt, 0)
+            } as f32;
+        });
+        let small = luma.downscale((PHASH_SIZE, PHASH_SIZE));
+
+        // Separable DCT-II, only the lowest frequencies are needed
+        let basis: Vec<f64> = (0..PHASH_BITS * PHASH_SIZE)
+            .map(|i| {
+                let (k, x) = ((i / PHASH_SIZE) as f64, (i % PHASH_SIZE) as f64);
+                (std::f64::consts::PI * (2.0 * x + 1.0) * k / (2 * PHASH_SIZE) as f64).cos()
+            })
+            .collect();
+        let mut rows = vec![0.0; PHASH_SIZE * PHASH_BITS];
+        for y in 0..PHASH_SIZE {
+            for u in 0..PHASH_BITS {
+                rows[y * PHASH_BITS + u] = (0..PHASH_SIZE)
+                    .map(|x| small.get_f((x, y), 0) * basis[u * PHASH_SIZE + x])
+                    .sum();
+            }
+        }
+        let mut coefficients = vec![0.0; PHASH_BITS * PHASH_BITS];
+        for v in 0..PHASH_BITS {
+            for u in 0..PHASH_BITS {
+                coefficients[v * PHASH_BITS + u] = (0..PHASH_SIZE)
+                    .map(|y| rows[y * PHASH_BITS + u] * basis[v * PHASH_SIZE + y])
+                    .sum();
+            }
+        }
+        phash_from_dct(&coefficients)
+    }
+}
+
+/// Build a perceptual hash from the 8x8 lowest DCT coefficients in row-major order, the DC
+/// coefficient is excluded when computing the median
+fn phash_from_dct(coefficients: &[f64]) -> Hash {
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let median = sorted[sorted.len() / 2];
+    Hash(coefficients.iter().map(|c| *c > median).collect())
+}
+
+/// Area average of the luminance to the output size, matching `filter::Resize`
+#[cfg(feature = "opengl")]
+const GPU_DOWNSCALE: &str = "
+float weight(int i, int j, float scale) {
+    if (scale > 1.0) {
+        float lo = float(i) * scale;
+        return max(min(lo + scale, float(j + 1)) - max(lo, float(j)), 0.0);
+    }
+    float center = (float(i) + 0.5) * scale - 0.5;
+    return max(1.0 - abs(center - float(j)), 0.0);
+}
+
+ivec2 range(int i, float scale, float n) {
+    if (scale > 1.0) {
+        return ivec2(floor(float(i) * scale), min(ceil(float(i + 1) * scale), n) - 1.0);
+    }
+    float center = floor((float(i) + 0.5) * scale - 0.5);
+    return ivec2(center, center + 1.0);
+}
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 size = iChannelResolution0.xy;
+    vec2 scale = size / iResolution.xy;
+    ivec2 i = ivec2(fragCoord);
+    ivec2 xs = range(i.x, scale.x, size.x);
+    ivec2 ys = range(i.y, scale.y, size.y);
+
+    float sum = 0.0;
+    float total = 0.0;
+    for (int y = ys.x; y <= ys.y; y++) {
+        float wy = weight(i.y, y, scale.y);
+        for (int x = xs.x; x <= xs.y; x++) {
+            float w = wy * weight(i.x, x, scale.x);
+            ivec2 p = clamp(ivec2(x, y), ivec2(0), ivec2(size) - 1);
+            sum += w * dot(texelFetch(iChannel0, p, 0).rgb, vec3(0.21, 0.72, 0.07));
+            total += w;
+        }
+    }
+    fragColor = vec4(total > 0.0 ? sum / total : 0.0);
+}
+";
+
+/// Low frequency DCT-II coefficients of the downscaled luminance, one per output pixel
+#[cfg(feature = "opengl")]
+const GPU_DCT: &str = "
+const float PI = 3.14159265358979;
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    ivec2 k = ivec2(fragCoord);
+    int n = int(iChannelResolution0.x);
+    float sum = 0.0;
+    for (int y = 0; y < n; y++) {
+        float cy = cos(PI * float((2 * y + 1) * k.y) / float(2 * n));
+        for (int x = 0; x < n; x++) {
+            float cx = cos(PI * float((2 * x + 1) * k.x) / float(2 * n));
+            sum += texelFetch(iChannel0, ivec2(x, y), 0).r * cx * cy;
+        }
+    }
+    fragColor = vec4(sum);
+}
+";
+
+/// Number of images hashed between reads from the GPU, the coefficients of each image are
+/// packed into an 8x8 tile of a single texture
+#[cfg(feature = "opengl")]
+const GPU_BATCH: usize = 64;
+
+/// Computes `Image::phash` on the GPU, downscaling and transforming each image with shaders so
+/// only 64 coefficients per image are read back. Results usually match `Image::phash`, but may
+/// differ in a few bits because the GPU uses single precision.
+#[cfg(feature = "opengl")]
+pub struct GpuHasher {
+    downscale: texture::GlslFilter,
+    dct: texture::GlslFilter,
+    small: texture::ImageTexture<f32, Rgba>,
+    atlas: texture::ImageTexture<f32, Rgba>,
+}
+
+#[cfg(feature = "opengl")]
+impl GpuHasher {
+    /// Compile the shaders and allocate the intermediate textures
+    pub fn new(gl: &texture::glow::Context) -> Result<GpuHasher, Error> {
+        use texture::{GlslFilter, ToTexture};
+
+        let mut downscale = GlslFilter::new(gl, GPU_DOWNSCALE)?;
+        let mut dct = match GlslFilter::new(gl, GPU_DCT) {
+            Ok(dct) => dct,
+            Err(e) => {
+                downscale.destroy(gl);
+                return Err(e);
+            }
+        };
+
+        let tiles = (GPU_BATCH as f64).sqrt().ceil() as usize;
+        let atlas_size = (tiles * PHASH_BITS, tiles * PHASH_BITS);
+        let textures = match Image::<f32, Rgba>::new((PHASH_SIZE, PHASH_SIZE))
+            .create_image_texture(gl)
+        {
+            Ok(mut small) => match Image::<f32, Rgba>::new(atlas_size).create_image_texture(gl) {
+                Ok(atlas) => Ok((small, atlas)),
+                Err(e) => {
+                    small.destroy(gl);
+                    Err(e)
+                }
+            },
+            Err(e) => Err(e),
+        };
+
+        match textures {
+            Ok((small, atlas)) => Ok(GpuHasher {
+                downscale,
+                dct,
+                small,
+                atlas,
+            }),
+            Err(e) => {
+                downscale.destroy(gl);
+                dct.destroy(gl);
+                Err(e)
+            }
+        }
+    }
+
+    /// Hash a single image, see `hash_many`
+    pub fn hash<T: Type, C: Color>(
+        &self,
+        gl: &texture::glow::Context,
+        image: &Image<T, C>,
+    ) -> Result<Hash, Error>
+    where
+        Image<T, C>: texture::ToTexture<T, C>,
+    {
+        let mut hashes = self.hash_many(gl, std::slice::from_ref(image))?;
+        Ok(hashes.remove(0))
+    }
+
+    /// Hash many images, the input texture is reused between images of the same size and
+    /// results are read back once for every 64 images
+    pub fn hash_many<T: Type, C: Color>(
+        &self,
+        gl: &texture::glow::Context,
+        images: &[Image<T, C>],
+    ) -> Result<Vec<Hash>, Error>
+    where
+        Image<T, C>: texture::ToTexture<T, C>,
+    {
+        let mut input = None;
+        let hashes = self.hash_batches(gl, images, &mut input);
+        if let Some(mut input) = input {
+            input.destroy(gl);
+        }
+        hashes
+    }
+
+    fn hash_batches<T: Type, C: Color>(
+        &self,
+        gl: &texture::glow::Context,
+        images: &[Image<T, C>],
+        input: &mut Option<texture::ImageTexture<T, C>>,
+    ) -> Result<Vec<Hash>, Error>
+    where
+        Image<T, C>: texture::ToTexture<T, C>,
+    {
+        use texture::ToTexture;
+
+        let tiles = self.atlas.size.width / PHASH_BITS;
+        let tile = |i: usize| Point::new(i % tiles * PHASH_BITS, i / tiles * PHASH_BITS);
+        let mut atlas = Image::<f32, Rgba>::new(self.atlas.size);
+        let mut hashes = Vec::with_capacity(images.len());
+
+        for batch in images.chunks(GPU_BATCH) {
+            for (i, image) in batch.iter().enumerate() {
+                let texture = match input {
+                    Some(texture) => {
+                        texture.replace(gl, image)?;
+                        texture
+                    }
+                    None => input.insert(image.create_image_texture(gl)?),
+                };
+                self.downscale.render(gl, texture, &self.small, 0.0, &[])?;
+                let region = Region::new(tile(i), Size::new(PHASH_BITS, PHASH_BITS));
+                self.dct
+                    .render_region(gl, &self.small, &self.atlas, region, 0.0, &[])?;
+            }
+
+            self.atlas.read(gl, &mut atlas);
+            for i in 0..batch.len() {
+                let origin = tile(i);
+                let coefficients: Vec<f64> = (0..PHASH_BITS * PHASH_BITS)
+                    .map(|j| {
+                        let (u, v) = (j % PHASH_BITS, j / PHASH_BITS);
+                        atlas.get_f((origin.x + u, origin.y + v), 0)
+                    })
+                    .collect();
+                hashes.push(phash_from_dct(&coefficients));
+            }
+        }
+        Ok(hashes)
+    }
+
+    /// Delete the shaders and textures, `gl` should be the context they were created in
+    pub fn destroy(&mut self, gl: &texture::glow::Context) {
+        self.downscale.destroy(gl);
+        self.dct.destroy(gl);
+        self.small.destroy(gl);
+        self.atlas.destroy(gl);
+    }
+}
+
+/// Options used by `hash_dir_with`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HashOptions {
+    recursive: bool,
+    max_size: Option<Size>,
+    perceptual: bool,
+}
+
+impl HashOptions {
+    /// Create new `HashOptions`
+    pub fn new() -> HashOptions {
+        HashOptions::default()
+    }
+
+    /// Include files in subdirectories
+    pub fn recursive(mut self, recursive: bool) -> HashOptions {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Downscale images that are larger than `size` while loading, before the hash is computed
+    pub fn downscale(mut self, size: impl Into<Size>) -> HashOptions {
+        self.max_size = Some(size.into());
+        self
+    }
+
+    /// Use `Image::phash` instead of `Image::hash`
+    pub fn perceptual(mut self, perceptual: bool) -> HashOptions {
+        self.perceptual = perceptual;
+        self
+    }
+
+    fn collect_files(&self, path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+        for entry in std::fs::read_dir(path)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                if self.recursive {
+                    self.collect_files(&path, files)?;
+                }
+            } else {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    fn open_file<T: Type>(&self, path: &Path) -> Result<Image<T, Rgb>, Error> {
+        let mut options = io::OpenOptions::new();
+        if let Some(size) = self.max_size {
+            options = options.max_size(size);
+        }
+        Image::open_with(path, options)
+    }
+
+    fn hash_file(&self, path: &Path) -> Result<Hash, Error> {
+        let image: Image<f32, Rgb> = self.open_file(path)?;
+        if self.perceptual {
+            Ok(image.phash())
+        } else {
+            Ok(image.hash())
+        }
+    }
+}
+
+/// Hash every image in a directory, see `hash_dir_with`
+pub fn hash_dir(path: impl AsRef<Path>) -> Result<Vec<(PathBuf, Hash)>, Error> {
+    hash_dir_with(path, &HashOptions::new())
+}
+
+/// Hash every image in a directory, files are decoded and hashed in parallel when the `parallel`
+/// feature is enabled. Files that can't be decoded are skipped, the results are sorted by path.
+pub fn hash_dir_with(
+    path: impl AsRef<Path>,
+    options: &HashOptions,
+) -> Result<Vec<(PathBuf, Hash)>, Error> {
+    let mut files = Vec::new();
+    options.collect_files(path.as_ref(), &mut files)?;
+    files.sort();
+
+    #[cfg(feature = "parallel")]
+    let files = files.into_par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let files = files.into_iter();
+
+    Ok(files
+        .filter_map(|path| {
+            let hash = options.hash_file(&path).ok()?;
+            Some((path, hash))
+        })
+        .collect())
+}
+
+/// Hash every image in a directory using `GpuHasher`, producing the same hashes as
+/// `hash_dir_with` using `HashOptions::perceptual`. Files are decoded in batches, in parallel
+/// when the `parallel` feature is enabled, while hashing happens on the GPU. Files that can't be
+/// decoded are skipped, the results are sorted by path.
+#[cfg(feature = "opengl")]
+pub fn hash_dir_gpu(
+    gl: &texture::glow::Context,
+    hasher: &GpuHasher,
+    path: impl AsRef<Path>,
+    options: &HashOptions,
+) -> Result<Vec<(PathBuf, Hash)>, Error> {
+    let mut files = Vec::new();
+    options.collect_files(path.as_ref(), &mut files)?;
+    files.sort();
+
+    let mut hashes = Vec::with_capacity(files.len());
+    for batch in files.chunks(GPU_BATCH) {
+        #[cfg(feature = "parallel")]
+        let batch = batch.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let batch = batch.iter();
+
+        let (paths, images): (Vec<PathBuf>, Vec<Image<u8, Rgb>>) = batch
+            .filter_map(|path| Some((path.clone(), options.open_file(path).ok()?)))
+            .unzip();
+        hashes.extend(paths.into_iter().zip(hasher.hash_many(gl, &images)?));
+    }
+    Ok(hashes)
+}
+
+/// Group images with hashes that differ by at most `max_distance` bits, only groups with more
+/// than one image are returned. Images are grouped transitively, so two images in the same group
+/// may differ by more than `max_distance` if they're connected through other images.
+pub fn group_duplicates<P: Clone>(hashes: &[(P, Hash)], max_distance: usize) -> Vec<Vec<P>> {
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    // Union-find over the indices of `hashes`
+    let mut parent: Vec<usize> = (0..hashes.len()).collect();
+
+    for i in 0..hashes.len() {
+        for j in i + 1..hashes.len() {
+            if hashes[i].1.diff(&hashes[j].1) <= max_distance {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[b] = a;
+            }
+        }
+    }
+
+    let mut groups: std::collections::BTreeMap<usize, Vec<P>> = Default::default();
+    for (i, (path, _)) in hashes.iter().enumerate() {
+        let r = root(&mut parent, i);
+        groups.entry(r).or_default().push(path.clone());
+    }
+
+    groups.into_values().filter(|g| g.len() > 1).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_group_duplicates() {
+        let mut a = Image::<f32, Gray>::new((32, 32));
+        a.for_each(|pt, mut px| px[0] = if pt.x < 16 { 0.1 } else { 0.9 });
+        let mut b = a.clone();
+        b.set((0, 0), [0.2]);
+        let mut c = a.new_like();
+        c.for_each(|pt, mut px| px[0] = if pt.y < 16 { 0.1 } else { 0.9 });
+
+        let hashes = vec![("a", a.hash()), ("c", c.hash()), ("b", b.hash())];
+        let groups = hash::group_duplicates(&hashes, 4);
+        assert_eq!(groups, vec![vec!["a", "b"]]);
+        assert!(hash::group_duplicates(&hashes, 256).len() == 1);
+    }
+
+    #[test]
+    fn test_phash() {
+        let mut rng = Seed(3).rng();
+        let blobs: Vec<_> = (0..12)
+            .map(|_| {
+                (
+                    rng.range(0.0, 120.0),
+                    rng.range(0.0, 90.0),
+                    rng.range(6.0, 20.0),
+                )
+            })
+            .collect();
+        let mut a = Image::<f32, Rgb>::new((120, 90));
+        a.for_each(|pt, mut px| {
+            let (x, y) = (pt.x as f64, pt.y as f64);
+            let v: f64 = blobs
+                .iter()
+                .map(|(cx, cy, r)| (-((x - cx).powi(2) + (y - cy).powi(2)) / (2.0 * r * r)).exp())
+                .sum();
+            let v = v.min(1.0) as f32;
+            px.copy_from_slice([v, v * 0.5, 1.0 - v]);
+        });
+        let hash = a.phash();
+        assert_eq!(Vec::<bool>::from(hash.clone()).len(), 64);
+
+        // Scaling, type conversion and small edits barely change the hash
+        assert!(hash.diff(&a.downscale((60, 45)).phash()) <= 2);
+        assert!(hash.diff(&a.convert::<u8, Rgb>().phash()) <= 2);
+        let mut b = a.clone();
+        b.set((10, 10), [1.0, 1.0, 1.0]);
+        assert!(hash.diff(&b.phash()) <= 2);
+
+        // Different content produces a different hash
+        let mut c = a.new_like();
+        c.for_each(|pt, mut px| {
+            let v = ((pt.x / 15 + pt.y / 15) % 2) as f32;
+            px.copy_from_slice([v, v, v]);
+        });
+        assert!(hash.diff(&c.phash()) > 16);
+    }
+}
@@ -0,0 +1,292 @@
+use crate::*;
+
+/// Number of discrete levels in a response curve
+pub const LEVELS: usize = 256;
+
+/// Exposure time relative to the reference exposure for an exposure offset in stops
+pub fn exposure_time(ev: f64) -> f64 {
+    2f64.powf(ev)
+}
+
+/// Level used to look up `value`, which should be between 0 and 1
+pub fn level(value: f64) -> usize {
+    (value.clamp(0.0, 1.0) * (LEVELS - 1) as f64).round() as usize
+}
+
+/// Confidence in a level, pixels close to black or white carry little information
+fn weight(z: usize) -> f64 {
+    z.min(LEVELS - 1 - z) as f64
+}
+
+/// Camera response, mapping each pixel level to the log of the exposure that produced it for
+/// every RGB channel
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Response {
+    /// Log exposure for every level, one curve per channel
+    pub curves: [Vec<f64>; 3],
+}
+
+impl Default for Response {
+    fn default() -> Self {
+        Response::linear()
+    }
+}
+
+impl Response {
+    /// Response of linear data, where pixel values are proportional to the exposure
+    pub fn linear() -> Response {
+        let curve: Vec<f64> = (0..LEVELS)
+            .map(|z| (z.max(1) as f64 / (LEVELS - 1) as f64).ln())
+            .collect();
+        Response {
+            curves: [curve.clone(), curve.clone(), curve],
+        }
+    }
+
+    /// Recover the response curve from a set of differently exposed images of a static scene
+    /// (Debevec and Malik 1997). Each image is paired with its relative exposure time, `samples`
+    /// pixel positions are used for the fit and `smoothness` controls how strongly the curve is
+    /// regularized, values around 10-100 work well. The curves are normalized so the middle
+    /// level maps to an exposure of 1.
+    pub fn recover<T: Type, C: Color>(
+        images: &[(&Image<T, C>, f64)],
+        samples: usize,
+        smoothness: f64,
+    ) -> Result<Response, Error> {
+        let size = check(images)?;
+        let samples = samples.clamp(1, size.width * size.height);
+
+        // Sample positions on an evenly spaced grid
+        let columns = ((samples as f64 * size.width as f64 / size.height as f64)
+            .sqrt()
+            .ceil() as usize)
+            .clamp(1, size.width);
+        let rows = samples.div_ceil(columns).min(size.height);
+        let mut points = Vec::with_capacity(columns * rows);
+        for j in 0..rows {
+            for i in 0..columns {
+                points.push(Point::new(
+                    (2 * i + 1) * size.width / (2 * columns),
+                    (2 * j + 1) * size.height / (2 * rows),
+                ));
+            }
+        }
+
+        let levels: Vec<Vec<Pixel<Rgb>>> = images
+            .iter()
+            .map(|(image, _)| {
+                points
+                    .iter()
+                    .map(|pt| image.get_pixel(*pt).convert::<Rgb>())
+                    .collect()
+            })
+            .collect();
+
+        let mut curves: [Vec<f64>; 3] = Default::default();
+        for (c, curve) in curves.iter_mut().enumerate() {
+            // Least squares over the curve values followed by the log radiance of each sample,
+            // accumulated directly into the normal equations
+            let n = LEVELS + points.len();
+            let mut ata = vec![0.0; n * n];
+            let mut atb = vec![0.0; n];
+            let mut add_row = |row: &[(usize, f64)], rhs: f64| {
+                for (i, a) in row {
+                    for (j, b) in row {
+                        ata[i * n + j] += a * b;
+                    }
+                    atb[*i] += a * rhs;
+                }
+            };
+
+            for ((_, time), pixels) in images.iter().zip(&levels) {
+                for (i, px) in pixels.iter().enumerate() {
+                    let z = level(px[c]);
+                    let w = weight(z);
+                    add_row(&[(z, w), (LEVELS + i, -w)], w * time.ln());
+                }
+            }
+
+            add_row(&[(LEVELS / 2, 1.0)], 0.0);
+            for z in 1..LEVELS - 1 {
+                let w = smoothness * weight(z);
+                add_row(&[(z - 1, w), (z, -2.0 * w), (z + 1, w)], 0.0);
+            }
+
+            let x = solve_dense(ata, atb, n)
+                .ok_or_else(|| Error::Message("Unable to recover camera response".to_string()))?;
+            *curve = x[..LEVELS].to_vec();
+        }
+
+        Ok(Response { curves })
+    }
+
+    /// Log exposure for a level of the given channel
+    pub fn log_exposure(&self, channel: usize, z: usize) -> f64 {
+        self.curves[channel][z.min(LEVELS - 1)]
+    }
+}
+
+/// Ensure all images have the same size and valid exposure times
+fn check<T: Type, C: Color>(images: &[(&Image<T, C>, f64)]) -> Result<Size, Error> {
+    let (first, _) = images
+        .first()
+        .ok_or_else(|| Error::Message("No images to merge".to_string()))?;
+    let size = first.size();
+    for (image, time) in images {
+        if image.size() != size {
+            return Err(Error::InvalidDimensions(
+                image.width(),
+                image.height(),
+                C::CHANNELS,
+            ));
+        }
+        if !(*time > 0.0 && time.is_finite()) {
+            return Err(Error::Message(format!("Invalid exposure time: {}", time)));
+        }
+    }
+    Ok(size)
+}
+
+/// Solve a dense symmetric system using Gaussian elimination with partial pivoting
+fn solve_dense(mut a: Vec<f64>, mut b: Vec<f64>, n: usize) -> Option<Vec<f64>> {
+    for i in 0..n {
+        a[i * n + i] += 1e-9;
+    }
+
+    for col in 0..n {
+        let pivot =
+            (col..n).max_by(|x, y| a[x * n + col].abs().total_cmp(&a[y * n + col].abs()))?;
+        if a[pivot * n + col].abs() < 1e-15 {
+            return None;
+        }
+        if pivot != col {
+            for k in 0..n {
+                a.swap(pivot * n + k, col * n + k);
+            }
+            b.swap(pivot, col);
+        }
+
+        for row in col + 1..n {
+            let f = a[row * n + col] / a[col * n + col];
+            if f == 0.0 {
+                continue;
+            }
+            for k in col..n {
+                a[row * n + k] -= f * a[col * n + k];
+            }
+            b[row] -= f * b[col];
+        }
+    }
+
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| a[row * n + k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row * n + row];
+    }
+    Some(x)
+}
+
+/// Merge a set of differently exposed images of a static scene into a single radiance image.
+/// Each image is paired with its relative exposure time, `exposure_time` converts exposure
+/// offsets in stops. Pixel values are converted to `Rgb` and mapped to exposures through
+/// `response`, then combined with more weight given to well exposed pixels. Pixels that are
+/// clipped in every image are taken from the shortest exposure when bright and from the longest
+/// exposure when dark. The result is scene-referred linear data, ready for tone mapping.
+pub fn merge<T: Type, C: Color>(
+    images: &[(&Image<T, C>, f64)],
+    response: &Response,
+) -> Result<Image<f32, Rgb>, Error> {
+    let size = check(images)?;
+    let log_times: Vec<f64> = images.iter().map(|(_, t)| t.ln()).collect();
+    let shortest = (0..images.len())
+        .min_by(|a, b| log_times[*a].total_cmp(&log_times[*b]))
+        .unwrap_or_default();
+    let longest = (0..images.len())
+        .max_by(|a, b| log_times[*a].total_cmp(&log_times[*b]))
+        .unwrap_or_default();
+
+    let mut dest = Image::<f32, Rgb>::new(size);
+    dest.each_pixel_mut(|pt, mut px| {
+        let pixels: Vec<Pixel<Rgb>> = images
+            .iter()
+            .map(|(image, _)| image.get_pixel(pt).convert::<Rgb>())
+            .collect();
+
+        for c in 0..3 {
+            let (mut sum, mut total) = (0.0, 0.0);
+            for (k, p) in pixels.iter().enumerate() {
+                let z = level(p[c]);
+                let w = weight(z);
+                sum += w * (response.log_exposure(c, z) - log_times[k]);
+                total += w;
+            }
+
+            px[c] = if total > 0.0 {
+                (sum / total).exp()
+            } else {
+                let k = if level(pixels[0][c]) > LEVELS / 2 {
+                    shortest
+                } else {
+                    longest
+                };
+                (response.log_exposure(c, level(pixels[k][c])) - log_times[k]).exp()
+            };
+        }
+    });
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    /// Radiance increases exponentially from left to right
+    fn radiance(x: usize) -> f64 {
+        0.01 * 100f64.powf(x as f64 / 63.0)
+    }
+
+    fn bracket(ev: f64, curve: fn(f64) -> f64) -> Image<u8, Rgb> {
+        let mut image = Image::<u8, Rgb>::new((64, 8));
+        image.each_pixel_mut(|pt, px| {
+            let v = curve(radiance(pt.x) * hdr::exposure_time(ev));
+            px.iter_mut().for_each(|x| *x = v.clamp(0.0, 1.0));
+        });
+        image
+    }
+
+    #[test]
+    fn test_hdr_merge() {
+        let images: Vec<_> = [-3.0, 0.0, 3.0]
+            .iter()
+            .map(|ev| (bracket(*ev, |x| x), hdr::exposure_time(*ev)))
+            .collect();
+        let refs: Vec<_> = images.iter().map(|(im, t)| (im, *t)).collect();
+
+        let merged = hdr::merge(&refs, &hdr::Response::linear()).unwrap();
+        for x in [0, 20, 40, 63] {
+            let value = merged.get((x, 4))[1] as f64;
+            assert!((value / radiance(x) - 1.0).abs() < 0.1, "{} {}", x, value);
+        }
+
+        // Recovered response from gamma encoded images, correct up to scale
+        let images: Vec<_> = [-3.0, 0.0, 3.0]
+            .iter()
+            .map(|ev| (bracket(*ev, |x| x.powf(1.0 / 2.2)), hdr::exposure_time(*ev)))
+            .collect();
+        let refs: Vec<_> = images.iter().map(|(im, t)| (im, *t)).collect();
+        let response = hdr::Response::recover(&refs, 64, 20.0).unwrap();
+        let merged = hdr::merge(&refs, &response).unwrap();
+        let ratio = merged.get((50, 4))[0] as f64 / merged.get((10, 4))[0] as f64;
+        let expected = radiance(50) / radiance(10);
+        assert!(
+            (ratio / expected - 1.0).abs() < 0.2,
+            "{} {}",
+            ratio,
+            expected
+        );
+
+        let small = Image::<u8, Rgb>::new((4, 4));
+        assert!(hdr::merge(&[(&small, 1.0), (&images[0].0, 2.0)], &response).is_err());
+    }
+}
@@ -52,6 +52,23 @@ impl Histogram {
         hist
     }
 
+    /// Add the counts of another histogram with the same number of bins
+    pub fn merge(&mut self, other: &Histogram) {
+        for (a, b) in self.bins.iter_mut().zip(other.bins.iter()) {
+            *a += b;
+        }
+        self.total += other.total;
+    }
+
+    /// Remove the counts of another histogram with the same number of bins, which should have
+    /// been merged into this one before
+    pub fn remove(&mut self, other: &Histogram) {
+        for (a, b) in self.bins.iter_mut().zip(other.bins.iter()) {
+            *a = a.saturating_sub(*b);
+        }
+        self.total = self.total.saturating_sub(other.total);
+    }
+
     /// Add a value to the histogram
     pub fn add_value<T: Type>(&mut self, value: T) {
         let x = value.to_norm() * (self.bins.len() - 1) as f64;
@@ -128,6 +145,56 @@ impl Histogram {
     pub fn sum(&self) -> usize {
         self.total
     }
+
+    /// Get the normalized value below which `p` (between 0 and 1) of the values fall
+    pub fn percentile(&self, p: f64) -> f64 {
+        let n = self.bins.len();
+        if n < 2 || self.total == 0 {
+            return p.clamp(0.0, 1.0);
+        }
+
+        let target = p.clamp(0.0, 1.0) * self.total as f64;
+        let mut sum = 0;
+        for (i, x) in self.bins() {
+            sum += x;
+            if x > 0 && sum as f64 >= target {
+                return i as f64 / (n - 1) as f64;
+            }
+        }
+        1.0
+    }
+
+    /// Find the threshold that best separates the histogram into two classes using Otsu's
+    /// method, the result is a normalized value between the two classes
+    pub fn otsu(&self) -> f64 {
+        let n = self.bins.len();
+        if n < 2 || self.total == 0 {
+            return 0.5;
+        }
+
+        let total = self.total as f64;
+        let sum: f64 = self.bins().map(|(i, x)| (i * x) as f64).sum();
+
+        let (mut weight, mut sum0) = (0.0, 0.0);
+        let (mut best, mut best_variance) = (0, -1.0);
+        for (i, x) in self.bins().take(n - 1) {
+            weight += x as f64;
+            sum0 += (i * x) as f64;
+            if weight == 0.0 || weight == total {
+                continue;
+            }
+
+            let mean0 = sum0 / weight;
+            let mean1 = (sum - sum0) / (total - weight);
+            let variance = weight * (total - weight) * (mean0 - mean1) * (mean0 - mean1);
+            if variance > best_variance {
+                best_variance = variance;
+                best = i;
+            }
+        }
+
+        (best as f64 + 0.5) / (n - 1) as f64
+    }
 }
 
 #[cfg(test)]
@@ -147,4 +214,27 @@ mod tests {
             assert!(h.distribution().into_iter().skip(1).sum::<f64>() == 0.0);
         }
     }
+
+    #[test]
+    fn test_histogram_percentile() {
+        let mut hist = Histogram::new(11);
+        for i in 0..=10 {
+            hist.add_value(i as f64 / 10.0);
+        }
+        assert_eq!(hist.percentile(0.0), 0.0);
+        assert_eq!(hist.percentile(0.5), 0.5);
+        assert_eq!(hist.percentile(1.0), 1.0);
+    }
+
+    #[test]
+    fn test_histogram_otsu() {
+        let mut hist = Histogram::new(256);
+        for i in 0..100 {
+            hist.add_value(0.2 + (i % 10) as f64 * 0.005);
+            hist.add_value(0.7 + (i % 10) as f64 * 0.005);
+        }
+
+        let level = hist.otsu();
+        assert!(level > 0.245 && level < 0.7, "{}", level);
+    }
 }
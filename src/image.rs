@@ -382,12 +382,36 @@ impl<T: Type, C: Color> Image<T, C> {
         io::read(path)
     }
 
+    /// Read an image from disk using `OpenOptions` to control how the file is converted
+    pub fn open_with(
+        path: impl AsRef<std::path::Path>,
+        options: io::OpenOptions,
+    ) -> Result<Image<T, C>, Error> {
+        options.open(path)
+    }
+
+    /// Read an untrusted image from disk, files that exceed `Limits::strict` are rejected
+    /// before any pixel data is decoded
+    pub fn open_untrusted(path: impl AsRef<std::path::Path>) -> Result<Image<T, C>, Error> {
+        io::OpenOptions::new()
+            .limits(io::Limits::strict())
+            .open(path)
+    }
+
+    /// Read an image from disk along with information about the data stored in the file, this
+    /// can be used to detect precision loss when converting to `T` and `C`
+    pub fn open_with_info(
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<(Image<T, C>, io::SourceInfo), Error> {
+        io::OpenOptions::new().open_with_info(path)
+    }
+
     /// Write an image to disk
     pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), Error> {
         io::write(path, self)
     }
 
-    /// Iterate over part of an image with mutable data access
+    /// Iterate over part of an image with mutable data access, points are in image coordinates
     #[cfg(feature = "parallel")]
     pub fn iter_region_mut(
         &mut self,
@@ -400,11 +424,11 @@ impl<T: Type, C: Color> Image<T, C> {
                     .take(roi.width())
                     .map(DataMut::new)
                     .enumerate()
-                    .map(move |(x, d)| (Point::new(x, y), d))
+                    .map(move |(x, d)| (Point::new(x + roi.origin.x, y), d))
             })
     }
 
-    /// Iterate over part of an image with mutable data access
+    /// Iterate over part of an image with mutable data access, points are in image coordinates
     #[cfg(not(feature = "parallel"))]
     pub fn iter_region_mut(
         &mut self,
@@ -417,11 +441,11 @@ impl<T: Type, C: Color> Image<T, C> {
                     .take(roi.width())
                     .map(DataMut::new)
                     .enumerate()
-                    .map(move |(x, d)| (Point::new(x, y), d))
+                    .map(move |(x, d)| (Point::new(x + roi.origin.x, y), d))
             })
     }
 
-    /// Iterate over part of an image
+    /// Iterate over part of an image, points are in image coordinates
     #[cfg(feature = "parallel")]
     pub fn iter_region(
         &self,
@@ -434,11 +458,11 @@ impl<T: Type, C: Color> Image<T, C> {
                     .take(roi.width())
                     .map(Data::new)
                     .enumerate()
-                    .map(move |(x, d)| (Point::new(x, y), d))
+                    .map(move |(x, d)| (Point::new(x + roi.origin.x, y), d))
             })
     }
 
-    /// Iterate over part of an image
+    /// Iterate over part of an image, points are in image coordinates
     #[cfg(not(feature = "parallel"))]
     pub fn iter_region(&self, roi: Region) -> impl std::iter::Iterator<Item = (Point, Data<T, C>)> {
         self.row_range(roi.origin.y, roi.height())
@@ -448,7 +472,7 @@ impl<T: Type, C: Color> Image<T, C> {
                     .take(roi.width())
                     .map(Data::new)
                     .enumerate()
-                    .map(move |(x, d)| (Point::new(x, y), d))
+                    .map(move |(x, d)| (Point::new(x + roi.origin.x, y), d))
             })
     }
 
@@ -517,6 +541,14 @@ impl<T: Type, C: Color> Image<T, C> {
         self.iter_region_mut(roi).for_each(|(pt, px)| f(pt, px))
     }
 
+    /// Iterate over the pixels covered by a `Roi` applying `f` to every pixel
+    pub fn for_each_roi<F: FnMut(Point, DataMut<T, C>)>(&mut self, roi: &Roi, mut f: F) {
+        let mask = roi.to_mask(self.size());
+        for pt in mask.points() {
+            f(pt, self.get_mut(pt));
+        }
+    }
+
     /// Iterate over each pixel of two images at once
     #[cfg(feature = "parallel")]
     pub fn for_each2<F: Sync + Send + Fn(Point, DataMut<T, C>, Data<T, C>)>(
@@ -722,6 +754,11 @@ impl<T: Type, C: Color> Image<T, C> {
         self.run(filter::convert(), None)
     }
 
+    /// Convert image type/color using the given alpha policy, see `filter::AlphaMode`
+    pub fn convert_with_alpha<U: Type, D: Color>(&self, alpha: filter::AlphaMode) -> Image<U, D> {
+        self.run(filter::convert_with_alpha(alpha), None)
+    }
+
     /// Convert image type/color
     pub fn convert_to<U: Type, D: Color>(&self, dest: &mut Image<U, D>) {
         dest.apply(filter::convert(), &[self]);
@@ -819,6 +856,33 @@ impl<T: Type, C: Color> Image<T, C> {
         self.run(filter::resize(self.size(), size), Some(Meta::new(size)))
     }
 
+    /// Shrink an image using area averaging, each output pixel is the average of the input pixels
+    /// it covers. Unlike `resize`, which point-samples, this doesn't alias on large reductions.
+    /// Axes that are being enlarged use `Interpolation::Bilinear`.
+    pub fn downscale(&self, size: impl Into<Size>) -> Image<T, C> {
+        let size = size.into();
+        self.run(
+            filter::Resize::new(size).with_interpolation(Interpolation::Bilinear),
+            Some(Meta::new(size)),
+        )
+    }
+
+    /// Shrink an image to fit within `max_size` pixels in each direction, preserving the aspect
+    /// ratio, see `downscale`. Images that already fit are copied.
+    pub fn thumbnail(&self, max_size: usize) -> Image<T, C> {
+        let (width, height) = (self.width(), self.height());
+        let longest = width.max(height);
+        if longest <= max_size {
+            return self.clone();
+        }
+
+        let scale = max_size as f64 / longest as f64;
+        self.downscale((
+            ((width as f64 * scale).round() as usize).max(1),
+            ((height as f64 * scale).round() as usize).max(1),
+        ))
+    }
+
     /// Scale an image
     pub fn scale(&self, width: f64, height: f64) -> Image<T, C> {
         self.run(
@@ -0,0 +1,93 @@
+use std::path::Path;
+
+use crate::*;
+
+/// `SourceInfo` describes the data stored in an image file, before any conversion to the
+/// requested `Type` and `Color` is applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceInfo {
+    /// Image size
+    pub size: Size,
+
+    /// Channel names, for example `["R", "G", "B", "A"]`
+    pub channels: Vec<String>,
+
+    /// Native data type
+    pub base_type: io::BaseType,
+
+    /// Number of bits per sample stored in the file, this may be smaller than the size of
+    /// `base_type`
+    pub bits_per_sample: usize,
+
+    /// Compression method, if reported by the file format
+    pub compression: Option<String>,
+}
+
+impl SourceInfo {
+    /// Read the header of an image file without decoding the pixel data
+    pub fn read(path: impl AsRef<Path>) -> Result<SourceInfo, Error> {
+        io::source_info(path)
+    }
+
+    /// Number of channels
+    pub fn num_channels(&self) -> Channel {
+        self.channels.len()
+    }
+
+    /// Returns true when loading the file as `T` would lose precision
+    pub fn precision_loss<T: Type>(&self) -> bool {
+        let bits = T::BASE.bits();
+        if T::is_float() {
+            // Floating point types are able to represent integer data with up to
+            // (mantissa + 1) bits exactly
+            let exact = match bits {
+                16 => 11,
+                32 => 24,
+                _ => 53,
+            };
+            self.base_type.is_float() && self.base_type.bits() > bits
+                || !self.base_type.is_float() && self.bits_per_sample > exact
+        } else {
+            self.base_type.is_float() || self.bits_per_sample > bits
+        }
+    }
+
+    /// Returns true when loading the file as `C` would drop channels
+    pub fn channel_loss<C: Color>(&self) -> bool {
+        self.num_channels() > C::CHANNELS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn info(base_type: io::BaseType, bits_per_sample: usize) -> SourceInfo {
+        SourceInfo {
+            size: Size::new(1, 1),
+            channels: vec!["R".into(), "G".into(), "B".into(), "A".into()],
+            base_type,
+            bits_per_sample,
+            compression: None,
+        }
+    }
+
+    #[test]
+    fn test_source_info() {
+        let a = info(io::BaseType::UInt16, 16);
+        assert!(a.precision_loss::<u8>());
+        assert!(!a.precision_loss::<u16>());
+        assert!(!a.precision_loss::<f32>());
+        assert!(a.channel_loss::<Rgb>());
+        assert!(!a.channel_loss::<Rgba>());
+
+        let b = info(io::BaseType::UInt16, 10);
+        assert!(!b.precision_loss::<f16>());
+        assert!(info(io::BaseType::UInt16, 12).precision_loss::<f16>());
+
+        let c = info(io::BaseType::Float, 32);
+        assert!(c.precision_loss::<u16>());
+        assert!(c.precision_loss::<f16>());
+        assert!(!c.precision_loss::<f32>());
+    }
+}
@@ -0,0 +1,167 @@
+use crate::*;
+
+/// Identifies which decode limit was exceeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Limit {
+    /// Image width
+    Width,
+
+    /// Image height
+    Height,
+
+    /// Total number of pixels
+    Pixels,
+
+    /// Estimated number of bytes needed to decode the image
+    Memory,
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s = match self {
+            Limit::Width => "width",
+            Limit::Height => "height",
+            Limit::Pixels => "pixels",
+            Limit::Memory => "memory",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// `Limits` are checked against the file header before any pixel data is decoded, this makes it
+/// possible to safely load untrusted images without the risk of decompression bombs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Limits {
+    /// Maximum image width
+    pub max_width: Option<usize>,
+
+    /// Maximum image height
+    pub max_height: Option<usize>,
+
+    /// Maximum number of pixels
+    pub max_pixels: Option<usize>,
+
+    /// Maximum number of bytes allocated while decoding
+    pub max_memory: Option<usize>,
+}
+
+impl Limits {
+    /// No limits
+    pub fn none() -> Limits {
+        Limits::default()
+    }
+
+    /// Conservative limits suitable for decoding untrusted input: 16384x16384, 64 megapixels
+    /// and 1GB of memory
+    pub fn strict() -> Limits {
+        Limits {
+            max_width: Some(16384),
+            max_height: Some(16384),
+            max_pixels: Some(64 * 1024 * 1024),
+            max_memory: Some(1024 * 1024 * 1024),
+        }
+    }
+
+    /// Set maximum image dimensions
+    pub fn with_max_dimensions(mut self, width: usize, height: usize) -> Limits {
+        self.max_width = Some(width);
+        self.max_height = Some(height);
+        self
+    }
+
+    /// Set maximum number of pixels
+    pub fn with_max_pixels(mut self, pixels: usize) -> Limits {
+        self.max_pixels = Some(pixels);
+        self
+    }
+
+    /// Set maximum number of bytes allocated while decoding
+    pub fn with_max_memory(mut self, bytes: usize) -> Limits {
+        self.max_memory = Some(bytes);
+        self
+    }
+
+    /// Returns true when no limits are set
+    pub fn is_none(&self) -> bool {
+        *self == Limits::default()
+    }
+
+    /// Estimate the number of bytes needed to decode a file into an `Image<T, C>`, including
+    /// the intermediate buffer used when the file has a different number of channels
+    pub fn estimate_memory<T: Type, C: Color>(info: &SourceInfo) -> usize {
+        let pixels = info.size.width.saturating_mul(info.size.height);
+        let dest = pixels.saturating_mul(C::CHANNELS * std::mem::size_of::<T>());
+        if info.num_channels() == C::CHANNELS {
+            return dest;
+        }
+
+        let tmp = pixels.saturating_mul(info.num_channels() * std::mem::size_of::<f32>());
+        dest.saturating_add(tmp)
+    }
+
+    /// Check an image header against the limits
+    pub fn check<T: Type, C: Color>(&self, info: &SourceInfo) -> Result<(), Error> {
+        let check = |limit, value: usize, max: Option<usize>| match max {
+            Some(max) if value > max => Err(Error::LimitExceeded(limit, value, max)),
+            _ => Ok(()),
+        };
+
+        check(Limit::Width, info.size.width, self.max_width)?;
+        check(Limit::Height, info.size.height, self.max_height)?;
+        check(
+            Limit::Pixels,
+            info.size.width.saturating_mul(info.size.height),
+            self.max_pixels,
+        )?;
+        check(
+            Limit::Memory,
+            Self::estimate_memory::<T, C>(info),
+            self.max_memory,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_limits() {
+        let info = SourceInfo {
+            size: Size::new(1000, 500),
+            channels: vec!["R".into(), "G".into(), "B".into()],
+            base_type: io::BaseType::UInt8,
+            bits_per_sample: 8,
+            compression: None,
+        };
+
+        assert!(io::Limits::none().check::<u8, Rgb>(&info).is_ok());
+        assert!(io::Limits::strict().check::<f32, Rgba>(&info).is_ok());
+
+        match io::Limits::none()
+            .with_max_dimensions(800, 800)
+            .check::<u8, Rgb>(&info)
+        {
+            Err(Error::LimitExceeded(io::Limit::Width, 1000, 800)) => (),
+            x => panic!("unexpected result: {:?}", x),
+        }
+
+        match io::Limits::none()
+            .with_max_pixels(100_000)
+            .check::<u8, Rgb>(&info)
+        {
+            Err(Error::LimitExceeded(io::Limit::Pixels, 500_000, 100_000)) => (),
+            x => panic!("unexpected result: {:?}", x),
+        }
+
+        // 500,000 pixels * 3 channels * 4 bytes
+        let limits = io::Limits::none().with_max_memory(6_000_000);
+        assert!(limits.check::<f32, Rgb>(&info).is_ok());
+
+        // Converting to Rgba needs an additional f32 Rgb buffer
+        match limits.check::<f32, Rgba>(&info) {
+            Err(Error::LimitExceeded(io::Limit::Memory, 14_000_000, 6_000_000)) => (),
+            x => panic!("unexpected result: {:?}", x),
+        }
+    }
+}
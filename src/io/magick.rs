@@ -4,7 +4,7 @@ use std::path::Path;
 use std::process::{Command, Stdio};
 use std::usize;
 
-use crate::{Color, Image, Rgb, Type};
+use crate::{io::BaseType, Color, Image, Rgb, Size, SourceInfo, Type};
 
 /// Magick I/O errors
 #[derive(Debug, thiserror::Error)]
@@ -55,6 +55,39 @@ fn depth<T: Type, C: Color>(cmd: &mut Command) {
     }
 }
 
+/// Get channel names from the `%[channels]` identify property
+fn channel_names(channels: &str) -> Result<Vec<String>, Error> {
+    // ImageMagick 7 reports the colorspace followed by the channel count (`srgba 4.0`),
+    // older versions and GraphicsMagick only report the colorspace (`srgba`)
+    let name = channels
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let name = name.strip_prefix('s').unwrap_or(&name);
+    let (base, alpha) = match name.strip_suffix('a') {
+        Some(base) => (base, true),
+        None => (name, false),
+    };
+
+    let mut names = match base {
+        "gray" => vec!["Y"],
+        "rgb" => vec!["R", "G", "B"],
+        "yuv" => vec!["Y", "U", "V"],
+        "lab" => vec!["L", "a", "b"],
+        "xyz" => vec!["X", "Y", "Z"],
+        "hsv" => vec!["H", "S", "V"],
+        "cmyk" => vec!["C", "M", "Y", "K"],
+        _ => return Err(Error::InvalidColor),
+    };
+
+    if alpha {
+        names.push("A");
+    }
+
+    Ok(names.into_iter().map(String::from).collect())
+}
+
 /// ImageMagick
 pub const IM: Magick = Magick {
     identify: &["identify"],
@@ -114,6 +147,89 @@ impl Magick {
         }
     }
 
+    /// Read image file header using identify command
+    pub fn get_image_info<P: AsRef<Path>>(&self, path: P) -> Result<SourceInfo, Error> {
+        let identify = Command::new(self.identify[0])
+            .args(self.identify[1..].iter())
+            .args(&["-format", "%w\n%h\n%[channels]\n%z\n%C\n"])
+            .arg(path.as_ref())
+            .output();
+
+        let info = match identify {
+            Ok(info) => info,
+            Err(_) => return Err(Error::UnableToExecuteCommand),
+        };
+
+        let info = match String::from_utf8(info.stdout) {
+            Ok(info) => info,
+            Err(_) => return Err(Error::InvalidImageData),
+        };
+
+        let lines: Vec<&str> = info.lines().map(|x| x.trim()).collect();
+        if lines.len() < 4 {
+            return Err(Error::InvalidImageShape);
+        }
+
+        let (width, height) = match (lines[0].parse(), lines[1].parse()) {
+            (Ok(w), Ok(h)) => (w, h),
+            _ => return Err(Error::InvalidImageShape),
+        };
+
+        let bits_per_sample = lines[3].parse().unwrap_or(8);
+        let base_type = match bits_per_sample {
+            0..=8 => BaseType::UInt8,
+            9..=16 => BaseType::UInt16,
+            17..=32 => BaseType::Float,
+            _ => BaseType::Double,
+        };
+
+        let compression = lines
+            .get(4)
+            .filter(|c| !c.is_empty() && !c.eq_ignore_ascii_case("none"))
+            .map(|c| c.to_ascii_lowercase());
+
+        Ok(SourceInfo {
+            size: Size::new(width, height),
+            channels: channel_names(lines[2])?,
+            base_type,
+            bits_per_sample,
+            compression,
+        })
+    }
+
+    /// Read image file header and metadata using identify command, metadata is limited to EXIF
+    /// properties
+    pub fn get_image_probe<P: AsRef<Path>>(&self, path: P) -> Result<crate::io::Probe, Error> {
+        let info = self.get_image_info(path.as_ref())?;
+        let identify = Command::new(self.identify[0])
+            .args(self.identify[1..].iter())
+            .args(&["-format", "%m\n%[exif:*]"])
+            .arg(path.as_ref())
+            .output();
+
+        let output = match identify {
+            Ok(output) => output,
+            Err(_) => return Err(Error::UnableToExecuteCommand),
+        };
+
+        let output = String::from_utf8_lossy(&output.stdout);
+        let mut lines = output.lines().map(|x| x.trim());
+        let format = lines
+            .next()
+            .filter(|x| !x.is_empty())
+            .map(|x| x.to_ascii_lowercase());
+        let metadata = lines
+            .filter_map(|line| line.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        Ok(crate::io::Probe {
+            info,
+            format,
+            metadata,
+        })
+    }
+
     /// Read image from disk using ImageMagick/GraphicsMagick
     pub fn read<P: AsRef<Path>, T: Type, C: Color>(&self, path: P) -> Result<Image<T, C>, Error> {
         if !ALLOWED_COLORS.contains(&C::NAME) {
@@ -244,6 +360,18 @@ pub fn read<P: AsRef<Path>, T: Type, C: Color>(path: P) -> Result<Image<T, C>, c
     Ok(x)
 }
 
+/// Read image file header
+pub(crate) fn source_info<P: AsRef<Path>>(path: P) -> Result<SourceInfo, crate::Error> {
+    let x = unsafe { DEFAULT.get_image_info(path)? };
+    Ok(x)
+}
+
+/// Read image file header and metadata
+pub(crate) fn probe_header<P: AsRef<Path>>(path: P) -> Result<crate::io::Probe, crate::Error> {
+    let x = unsafe { DEFAULT.get_image_probe(path)? };
+    Ok(x)
+}
+
 /// Write image to disk
 pub fn write<P: AsRef<Path>, T: Type, C: Color>(
     path: P,
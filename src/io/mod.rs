@@ -1,3 +1,15 @@
+use crate::*;
+
+mod info;
+mod limits;
+mod options;
+mod probe;
+
+pub use info::SourceInfo;
+pub use limits::{Limit, Limits};
+pub use options::OpenOptions;
+pub use probe::{probe, Probe};
+
 #[cfg(feature = "magick")]
 /// ImageMagick/GraphicsMagick based I/O
 ///
@@ -63,6 +75,25 @@ pub enum BaseType {
     Last,
 }
 
+impl BaseType {
+    /// Number of bits used to store a single value, or 0 for non-numeric types
+    pub fn bits(&self) -> usize {
+        use BaseType::*;
+        match self {
+            UInt8 | Int8 => 8,
+            UInt16 | Int16 | Half => 16,
+            UInt32 | Int32 | Float => 32,
+            UInt64 | Int64 | Double => 64,
+            Unknown | None | String | Ptr | Last => 0,
+        }
+    }
+
+    /// Returns true for floating point types
+    pub fn is_float(&self) -> bool {
+        matches!(self, BaseType::Half | BaseType::Float | BaseType::Double)
+    }
+}
+
 #[cfg(all(feature = "oiio", not(feature = "docs-rs")))]
 /// OpenImageIO bindings
 pub mod oiio;
@@ -70,11 +101,55 @@ pub mod oiio;
 #[cfg(all(feature = "oiio", not(feature = "docs-rs")))]
 pub use oiio::{read, write};
 
+#[cfg(all(feature = "oiio", not(feature = "docs-rs")))]
+pub(crate) use oiio::{probe_header, source_info};
+
 #[cfg(feature = "magick")]
 pub use magick::{read, write};
 
+#[cfg(feature = "magick")]
+pub(crate) use magick::{probe_header, source_info};
+
+/// Read an image in bands of `rows` scanlines, `f` is called with the first row of each band and
+/// the band itself. Reading stops early when `f` returns false.
+///
+/// When using OpenImageIO the bands are decoded incrementally, other backends decode the whole
+/// image up front.
+pub fn read_bands<P: AsRef<std::path::Path>, T: Type, C: Color>(
+    path: P,
+    rows: usize,
+    f: impl FnMut(usize, Image<T, C>) -> bool,
+) -> Result<(), Error> {
+    #[cfg(all(feature = "oiio", not(feature = "docs-rs")))]
+    {
+        oiio::ImageInput::open(path, None)?.read_bands(rows, f)
+    }
+
+    #[cfg(not(all(feature = "oiio", not(feature = "docs-rs"))))]
+    {
+        let mut f = f;
+        let image: Image<T, C> = read(path)?;
+        let rows = rows.max(1);
+        let mut y = 0;
+        while y < image.height() {
+            let n = rows.min(image.height() - y);
+            if !f(
+                y,
+                image.crop(Region::new(Point::new(0, y), Size::new(image.width(), n))),
+            ) {
+                break;
+            }
+            y += n;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(all(not(feature = "magick"), not(feature = "oiio")))]
 mod stub;
 
 #[cfg(all(not(feature = "magick"), not(feature = "oiio")))]
 pub use stub::{read, write};
+
+#[cfg(all(not(feature = "magick"), not(feature = "oiio")))]
+pub(crate) use stub::{probe_header, source_info};
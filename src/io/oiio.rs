@@ -229,6 +229,22 @@ impl ImageInput {
         &self.path
     }
 
+    /// Get the name of the file format, for example `png` or `openexr`
+    pub fn format_name(&self) -> String {
+        let image_input = self.image_input;
+        let mut len = 0;
+        let len_ptr = &mut len;
+        let x = unsafe {
+            cpp!([image_input as "std::unique_ptr<ImageInput>", len_ptr as "size_t*"] -> *const u8 as "const char*" {
+                const char *name = image_input->format_name();
+                *len_ptr = strlen(name);
+                return name;
+            })
+        };
+
+        unsafe { String::from_utf8_lossy(std::slice::from_raw_parts(x, len)).into_owned() }
+    }
+
     /// Open image for reading
     pub fn open(
         path: impl AsRef<std::path::Path>,
@@ -318,6 +334,100 @@ impl ImageInput {
         Ok(())
     }
 
+    /// Read scanlines starting at row `y` into an existing image, the number of rows read is
+    /// determined by the height of `image`
+    pub fn read_scanlines_into<T: Type, C: Color>(
+        &self,
+        y: usize,
+        image: &mut Image<T, C>,
+    ) -> Result<(), Error> {
+        let data = image.data.as_mut_ptr();
+
+        let channels = C::CHANNELS;
+
+        let input = self.image_input;
+        let index = self.subimage;
+        let miplevel = self.miplevel;
+        let spec = &self.spec;
+        let fmt = T::BASE;
+        let ybegin = y;
+        let yend = y + image.height();
+
+        if spec.nchannels() < C::CHANNELS || spec.width() != image.width() || yend > spec.height() {
+            return Err(Error::InvalidDimensions(
+                spec.width(),
+                spec.height(),
+                spec.nchannels(),
+            ));
+        }
+
+        let res = unsafe {
+            cpp!([input as "std::unique_ptr<ImageInput>",
+              index as "size_t",
+              miplevel as "size_t",
+              ybegin as "size_t",
+              yend as "size_t",
+              channels as "size_t",
+              fmt as "TypeDesc::BASETYPE",
+              data as "void *"
+            ] ->  bool as "bool" {
+                int y0 = input->spec().y;
+                return input->read_scanlines(index, miplevel, y0 + ybegin, y0 + yend, 0, 0, channels, fmt, data);
+            })
+        };
+
+        if !res {
+            return Err(Error::CannotReadImage(
+                self.path.to_string_lossy().to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Read an image in bands of `rows` scanlines, `f` is called with the first row of each
+    /// band and the band itself. Reading stops early when `f` returns false.
+    pub fn read_bands<T: Type, C: Color>(
+        &self,
+        rows: usize,
+        mut f: impl FnMut(usize, Image<T, C>) -> bool,
+    ) -> Result<(), Error> {
+        let nchannels = self.spec.nchannels();
+
+        // Bands are converted using the same rules as `read`
+        if C::CHANNELS != nchannels {
+            if nchannels == 1 {
+                self.read_bands_exact::<f32, Gray>(rows, |y, band| f(y, band.convert()))
+            } else if nchannels == 4 {
+                self.read_bands_exact::<f32, Rgba>(rows, |y, band| f(y, band.convert()))
+            } else {
+                self.read_bands_exact::<f32, Rgb>(rows, |y, band| f(y, band.convert()))
+            }
+        } else {
+            self.read_bands_exact(rows, f)
+        }
+    }
+
+    fn read_bands_exact<T: Type, C: Color>(
+        &self,
+        rows: usize,
+        mut f: impl FnMut(usize, Image<T, C>) -> bool,
+    ) -> Result<(), Error> {
+        let (width, height) = (self.spec.width(), self.spec.height());
+        let rows = rows.max(1);
+        let mut y = 0;
+        while y < height {
+            let n = rows.min(height - y);
+            let mut band = Image::new((width, n));
+            self.read_scanlines_into(y, &mut band)?;
+            if !f(y, band) {
+                break;
+            }
+            y += n;
+        }
+        Ok(())
+    }
+
     /// Read to new image
     ///
     /// Note: the `convert` method may be called if the requested color doesn't match
@@ -442,6 +552,31 @@ impl ImageSpec {
         }
     }
 
+    /// Get the name of the channel at `index`
+    pub fn channel_name(&self, index: Channel) -> Option<&str> {
+        let mut len = 0;
+        let len_ptr = &mut len;
+        let x = unsafe {
+            cpp!([self as "const ImageSpec*", index as "size_t", len_ptr as "size_t*"] -> *const u8 as "const char*" {
+                if (index >= self->channelnames.size()) {
+                    return nullptr;
+                }
+                auto &s = self->channelnames[index];
+                *len_ptr = s.size();
+                return s.c_str();
+            })
+        };
+
+        if x.is_null() {
+            return None;
+        }
+
+        unsafe {
+            let x = std::slice::from_raw_parts(x, len);
+            std::str::from_utf8(x).ok()
+        }
+    }
+
     /// Get an attribute
     pub fn get_attr(&self, key: impl AsRef<str>) -> Option<Attr> {
         let key_str = std::ffi::CString::new(key.as_ref().as_bytes().to_vec()).unwrap();
@@ -679,6 +814,58 @@ pub fn read<P: AsRef<std::path::Path>, T: Type, C: Color>(path: P) -> Result<Ima
     ImageInput::open(path, None)?.read()
 }
 
+/// Read image file header
+pub(crate) fn source_info<P: AsRef<std::path::Path>>(path: P) -> Result<SourceInfo, Error> {
+    let input = ImageInput::open(path, None)?;
+    Ok(spec_source_info(input.spec()))
+}
+
+/// Read image file header and metadata
+pub(crate) fn probe_header<P: AsRef<std::path::Path>>(path: P) -> Result<io::Probe, Error> {
+    let input = ImageInput::open(path, None)?;
+    let metadata = input
+        .spec()
+        .attrs()
+        .into_iter()
+        .map(|(k, v)| {
+            let v = match v {
+                Attr::Int(i) => i.to_string(),
+                Attr::Float(f) => f.to_string(),
+                Attr::String(s) => s.to_string(),
+            };
+            (k.to_string(), v)
+        })
+        .collect();
+
+    Ok(io::Probe {
+        info: spec_source_info(input.spec()),
+        format: Some(input.format_name()),
+        metadata,
+    })
+}
+
+fn spec_source_info(spec: &ImageSpec) -> SourceInfo {
+    let channels = (0..spec.nchannels())
+        .map(|i| spec.channel_name(i).unwrap_or_default().to_string())
+        .collect();
+    let bits_per_sample = match spec.get_attr("oiio:BitsPerSample") {
+        Some(Attr::Int(bits)) if bits > 0 => bits as usize,
+        _ => spec.format().bits(),
+    };
+    let compression = match spec.get_attr("compression") {
+        Some(Attr::String(s)) => Some(s.to_string()),
+        _ => None,
+    };
+
+    SourceInfo {
+        size: Size::new(spec.width(), spec.height()),
+        channels,
+        base_type: spec.format(),
+        bits_per_sample,
+        compression,
+    }
+}
+
 /// Write image to disk
 pub fn write<P: AsRef<std::path::Path>, T: Type, C: Color>(
     path: P,
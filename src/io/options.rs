@@ -0,0 +1,176 @@
+use std::path::Path;
+
+use crate::*;
+
+/// `OpenOptions` controls how image files are converted when they're loaded
+///
+/// By default the data stored in a file is converted to the requested `Type` and `Color`
+/// without any checks, `OpenOptions` can be used to make that conversion explicit:
+///
+/// ```rust,no_run
+/// use image2::*;
+///
+/// fn main() -> Result<(), Error> {
+///     let image: Image<f32, Rgba> = Image::open_with(
+///         "images/A.exr",
+///         OpenOptions::new()
+///             .expect_color::<Rgba>()
+///             .max_size((1024, 1024))
+///             .premultiply(true),
+///     )?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpenOptions {
+    channels: Option<Channel>,
+    max_size: Option<Size>,
+    premultiply: bool,
+    limits: io::Limits,
+}
+
+impl OpenOptions {
+    /// Create new `OpenOptions`
+    pub fn new() -> OpenOptions {
+        OpenOptions::default()
+    }
+
+    /// Require the file to contain the same number of channels as `C`, instead of converting
+    /// from whatever is stored on disk
+    pub fn expect_color<C: Color>(self) -> OpenOptions {
+        self.expect_channels(C::CHANNELS)
+    }
+
+    /// Require the file to contain exactly `channels` channels
+    pub fn expect_channels(mut self, channels: Channel) -> OpenOptions {
+        self.channels = Some(channels);
+        self
+    }
+
+    /// Downscale images that are larger than `size` to fit, preserving the aspect ratio. Images
+    /// are shrunk using area averaging, see `Image::downscale`.
+    pub fn max_size(mut self, size: impl Into<Size>) -> OpenOptions {
+        self.max_size = Some(size.into());
+        self
+    }
+
+    /// Multiply color channels by alpha after loading, this has no effect on colors without an
+    /// alpha channel
+    pub fn premultiply(mut self, premultiply: bool) -> OpenOptions {
+        self.premultiply = premultiply;
+        self
+    }
+
+    /// Reject files that exceed `limits` before decoding any pixel data, this should be used
+    /// when loading untrusted images
+    pub fn limits(mut self, limits: io::Limits) -> OpenOptions {
+        self.limits = limits;
+        self
+    }
+
+    /// Read an image from disk using the configured options
+    pub fn open<T: Type, C: Color>(&self, path: impl AsRef<Path>) -> Result<Image<T, C>, Error> {
+        let path = path.as_ref();
+
+        if self.channels.is_some() || !self.limits.is_none() {
+            self.check::<T, C>(&io::source_info(path)?)?;
+        }
+
+        self.read(path)
+    }
+
+    /// Read an image from disk using the configured options, along with information about the
+    /// data stored in the file
+    pub fn open_with_info<T: Type, C: Color>(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<(Image<T, C>, SourceInfo), Error> {
+        let path = path.as_ref();
+        let info = io::source_info(path)?;
+        self.check::<T, C>(&info)?;
+        Ok((self.read(path)?, info))
+    }
+
+    fn check<T: Type, C: Color>(&self, info: &SourceInfo) -> Result<(), Error> {
+        if let Some(expected) = self.channels {
+            if expected != info.num_channels() {
+                return Err(Error::UnexpectedChannels(expected, info.num_channels()));
+            }
+        }
+
+        self.limits.check::<T, C>(info)
+    }
+
+    fn read<T: Type, C: Color>(&self, path: &Path) -> Result<Image<T, C>, Error> {
+        let mut image = io::read(path)?;
+
+        if let Some(size) = self.max_size {
+            if let Some(size) = self.fit(image.size(), size) {
+                image = image.downscale(size);
+            }
+        }
+
+        if self.premultiply {
+            premultiply(&mut image);
+        }
+
+        Ok(image)
+    }
+
+    /// Get the size an image should be resized to, or `None` if it already fits
+    fn fit(&self, size: Size, max: Size) -> Option<Size> {
+        if size.width <= max.width && size.height <= max.height {
+            return None;
+        }
+
+        let scale =
+            (max.width as f64 / size.width as f64).min(max.height as f64 / size.height as f64);
+        Some(Size::new(
+            ((size.width as f64 * scale).round() as usize).clamp(1, max.width.max(1)),
+            ((size.height as f64 * scale).round() as usize).clamp(1, max.height.max(1)),
+        ))
+    }
+}
+
+fn premultiply<T: Type, C: Color>(image: &mut Image<T, C>) {
+    let alpha = match C::ALPHA {
+        Some(alpha) => alpha,
+        None => return,
+    };
+
+    image.for_each(|_, mut data| {
+        let mut px = data.to_pixel();
+        let a = px[alpha];
+        for c in (0..C::CHANNELS).filter(|c| *c != alpha) {
+            px[c] *= a;
+        }
+        px.copy_to_slice(&mut data);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_open_options_fit() {
+        let options = OpenOptions::new().max_size((100, 100));
+        assert_eq!(
+            options.fit(Size::new(400, 200), Size::new(100, 100)),
+            Some(Size::new(100, 50))
+        );
+        assert_eq!(options.fit(Size::new(50, 80), Size::new(100, 100)), None);
+        assert_eq!(
+            options.fit(Size::new(10, 1000), Size::new(100, 100)),
+            Some(Size::new(1, 100))
+        );
+    }
+
+    #[test]
+    fn test_premultiply() {
+        let mut image = Image::<f32, Rgba>::new((2, 2));
+        image.set((0, 0), [1.0, 0.5, 0.25, 0.5]);
+        super::premultiply(&mut image);
+        assert_eq!(image.get_pixel((0, 0)).as_ref(), &[0.5, 0.25, 0.125, 0.5]);
+    }
+}
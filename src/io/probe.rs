@@ -0,0 +1,51 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::*;
+
+/// Image header returned by `io::probe`, unlike `Meta` this doesn't depend on a `Type` or
+/// `Color` so it can be used to inspect files before deciding how to load them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Probe {
+    /// Size, channels and data type
+    pub info: SourceInfo,
+
+    /// File format name, for example `png` or `openexr`
+    pub format: Option<String>,
+
+    /// Metadata attributes stored in the file header, values are converted to strings
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl Probe {
+    /// Image size
+    pub fn size(&self) -> Size {
+        self.info.size
+    }
+
+    /// Number of channels
+    pub fn num_channels(&self) -> Channel {
+        self.info.num_channels()
+    }
+
+    /// Number of bits per sample stored in the file
+    pub fn bits_per_sample(&self) -> usize {
+        self.info.bits_per_sample
+    }
+
+    /// Get a metadata attribute
+    pub fn get(&self, key: impl AsRef<str>) -> Option<&str> {
+        self.metadata.get(key.as_ref()).map(|x| x.as_str())
+    }
+
+    /// Get the `Meta` of the image that would be created by loading the file as `T` and `C`
+    pub fn meta<T: Type, C: Color>(&self) -> Meta<T, C> {
+        Meta::new(self.info.size)
+    }
+}
+
+/// Read the header of an image file, including dimensions, channels, bit depth and metadata,
+/// without decoding the pixel data
+pub fn probe(path: impl AsRef<Path>) -> Result<Probe, Error> {
+    io::probe_header(path)
+}
@@ -8,6 +8,16 @@ pub fn read<P: AsRef<Path>, T: Type, C: Color>(_path: P) -> Result<Image<T, C>,
     unimplemented!()
 }
 
+/// Read image file header, this implementation is a stub and always returns an error
+pub(crate) fn source_info<P: AsRef<Path>>(_path: P) -> Result<io::SourceInfo, crate::Error> {
+    Err(Error::Message("no I/O backend enabled".into()))
+}
+
+/// Read image file header and metadata, this implementation is a stub and always fails
+pub(crate) fn probe_header<P: AsRef<Path>>(_path: P) -> Result<io::Probe, crate::Error> {
+    Err(Error::Message("no I/O backend enabled".into()))
+}
+
 /// Write image to disk, this implementation is a stub, to enable I/O use the `oiio` trait to use the
 /// OpenImageIO backend, or `magick` to use the ImageMagick backend
 pub fn write<P: AsRef<Path>, T: Type, C: Color>(
@@ -16,3 +26,25 @@ pub fn write<P: AsRef<Path>, T: Type, C: Color>(
 ) -> Result<(), crate::Error> {
     unimplemented!()
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_stub_source_info() {
+        assert!(SourceInfo::read("images/A.exr").is_err());
+    }
+
+    #[test]
+    fn test_stub_probe() {
+        assert!(io::probe("images/A.exr").is_err());
+    }
+
+    #[test]
+    fn test_stub_limits() {
+        // Checking limits reads the header first, which must fail cleanly without a backend
+        let options = io::OpenOptions::new().limits(io::Limits::strict());
+        assert!(options.open::<u8, Rgb>("images/A.exr").is_err());
+    }
+}
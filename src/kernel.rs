@@ -3,6 +3,64 @@ use std::ops;
 
 use crate::*;
 
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Determines how pixels outside of the image are handled during convolution
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Border {
+    /// Use the nearest edge pixel
+    #[default]
+    Clamp,
+
+    /// Reflect around the edge pixel, the edge pixel itself is not repeated
+    Mirror,
+
+    /// Wrap around to the opposite edge
+    Wrap,
+
+    /// Use a constant, normalized value
+    Constant(f64),
+}
+
+impl Border {
+    /// Map a possibly out of bounds coordinate into `0..len`, returns `None` when the constant
+    /// value should be used instead
+    pub fn index(&self, i: isize, len: usize) -> Option<usize> {
+        if len == 0 {
+            return None;
+        }
+
+        if i >= 0 && (i as usize) < len {
+            return Some(i as usize);
+        }
+
+        let n = len as isize;
+        match self {
+            Border::Clamp => Some(i.clamp(0, n - 1) as usize),
+            Border::Mirror => {
+                if n == 1 {
+                    return Some(0);
+                }
+                let period = 2 * n - 2;
+                let i = i.rem_euclid(period);
+                Some(if i >= n { period - i } else { i } as usize)
+            }
+            Border::Wrap => Some(i.rem_euclid(n) as usize),
+            Border::Constant(_) => None,
+        }
+    }
+
+    /// Value used for pixels outside of the image
+    pub fn value(&self) -> f64 {
+        match self {
+            Border::Constant(x) => *x,
+            _ => 0.0,
+        }
+    }
+}
+
 /// 2-dimensional convolution kernel
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -10,13 +68,20 @@ pub struct Kernel {
     rows: usize,
     cols: usize,
     data: Vec<Vec<f64>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    border: Border,
 }
 
 impl From<Vec<Vec<f64>>> for Kernel {
     fn from(data: Vec<Vec<f64>>) -> Kernel {
         let rows = data.len();
         let cols = data[0].len();
-        Kernel { data, rows, cols }
+        Kernel {
+            data,
+            rows,
+            cols,
+            border: Border::default(),
+        }
     }
 }
 
@@ -32,6 +97,7 @@ impl<'a> From<&'a [&'a [f64]]> for Kernel {
             data: v,
             rows,
             cols,
+            border: Border::default(),
         }
     }
 }
@@ -43,6 +109,7 @@ impl<const N: usize> From<[[f64; N]; N]> for Kernel {
             data,
             rows: N,
             cols: N,
+            border: Border::default(),
         }
     }
 }
@@ -55,15 +122,20 @@ impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Kernel {
     fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
         let r2 = (self.rows / 2) as isize;
         let c2 = (self.cols / 2) as isize;
+        let size = input.images()[0].size();
         let mut f = input.new_pixel();
         let mut x: f64;
         for ky in -r2..=r2 {
             let kr = &self.data[(ky + r2) as usize];
-            let pty = (pt.y as isize + ky) as usize;
+            let pty = self.border.index(pt.y as isize + ky, size.height);
             for kx in -c2..=c2 {
                 let krc = kr[(kx + c2) as usize];
+                let ptx = self.border.index(pt.x as isize + kx, size.width);
                 for c in 0..f.len() {
-                    x = input.get_f(((pt.x as isize + kx) as usize, pty), c, Some(0));
+                    x = match (ptx, pty) {
+                        (Some(ptx), Some(pty)) => input.get_f((ptx, pty), c, Some(0)),
+                        _ => self.border.value(),
+                    };
                     f[c] += x * krc;
                 }
             }
@@ -76,7 +148,23 @@ impl Kernel {
     /// Create a new kernel with the given number of rows and columns
     pub fn new(rows: usize, cols: usize) -> Kernel {
         let data = vec![vec![0.0; cols]; rows];
-        Kernel { data, rows, cols }
+        Kernel {
+            data,
+            rows,
+            cols,
+            border: Border::default(),
+        }
+    }
+
+    /// Set the border mode used for pixels near the edge of the image
+    pub fn with_border(mut self, border: Border) -> Kernel {
+        self.border = border;
+        self
+    }
+
+    /// Get the border mode
+    pub fn border(&self) -> Border {
+        self.border
     }
 
     /// Create a new, square kernel
@@ -145,28 +233,12 @@ impl Kernel {
 
     /// Sobel X
     pub fn sobel_x() -> Kernel {
-        Kernel {
-            rows: 3,
-            cols: 3,
-            data: vec![
-                vec![1.0, 0.0, -1.0],
-                vec![2.0, 0.0, -2.0],
-                vec![1.0, 0.0, -1.0],
-            ],
-        }
+        Kernel::from([[1.0, 0.0, -1.0], [2.0, 0.0, -2.0], [1.0, 0.0, -1.0]])
     }
 
     /// Sobel Y
     pub fn sobel_y() -> Kernel {
-        Kernel {
-            rows: 3,
-            cols: 3,
-            data: vec![
-                vec![1.0, 2.0, 1.0],
-                vec![0.0, 0.0, 0.0],
-                vec![-1.0, -2.0, -1.0],
-            ],
-        }
+        Kernel::from([[1.0, 2.0, 1.0], [0.0, 0.0, 0.0], [-1.0, -2.0, -1.0]])
     }
 
     /// Laplacian
@@ -178,6 +250,252 @@ impl Kernel {
     pub fn sobel() -> Kernel {
         Kernel::sobel_x() + Kernel::sobel_y()
     }
+
+    /// Laplacian of Gaussian with a radius of `ceil(3 * sigma)`, scaled by `sigma^2` so
+    /// responses can be compared across scales. The kernel sums to zero, bright blobs with a
+    /// radius of about `sigma * sqrt(2)` produce the strongest negative response and edges show
+    /// up as zero crossings, see `filter::ZeroCrossing`.
+    pub fn laplacian_of_gaussian(sigma: f64) -> Kernel {
+        let sigma = sigma.max(0.1);
+        let r = (3.0 * sigma).ceil().max(1.0) as usize;
+        let s2 = sigma * sigma;
+        let mut k = Kernel::create(2 * r + 1, 2 * r + 1, |i, j| {
+            let (x, y) = (i as f64 - r as f64, j as f64 - r as f64);
+            let d = (x * x + y * y) / (2.0 * s2);
+            (d - 1.0) * (-d).exp() / (f64::consts::PI * s2)
+        });
+        k.remove_mean();
+        k
+    }
+
+    /// Difference of Gaussians, the gaussian with standard deviation `sigma2` subtracted from
+    /// the one with `sigma1`. With `sigma2` around `1.6 * sigma1` this approximates a negated
+    /// `laplacian_of_gaussian` and is commonly used for blob detection.
+    pub fn difference_of_gaussians(sigma1: f64, sigma2: f64) -> Kernel {
+        let (sigma1, sigma2) = (sigma1.max(0.1), sigma2.max(0.1));
+        let r = (3.0 * sigma1.max(sigma2)).ceil().max(1.0) as usize;
+        let gaussian = |sigma: f64| {
+            let mut k = Kernel::create(2 * r + 1, 2 * r + 1, |i, j| {
+                let (x, y) = (i as f64 - r as f64, j as f64 - r as f64);
+                (-(x * x + y * y) / (2.0 * sigma * sigma)).exp()
+            });
+            k.normalize();
+            k
+        };
+        let mut k = gaussian(sigma1) - gaussian(sigma2);
+        k.remove_mean();
+        k
+    }
+
+    /// Shift all values so the kernel sums to zero, removing any response to flat areas
+    fn remove_mean(&mut self) {
+        let n = (self.rows * self.cols) as f64;
+        let mean = self.data.iter().flatten().sum::<f64>() / n;
+        self.data.iter_mut().flatten().for_each(|x| *x -= mean);
+    }
+
+    /// Sum of all values
+    pub fn sum(&self) -> f64 {
+        self.data.iter().flatten().sum()
+    }
+
+    /// Split the kernel into a horizontal and vertical pass, returns `None` if the kernel isn't
+    /// separable
+    pub fn separable(&self) -> Option<SeparableKernel> {
+        let (mut row, mut col) = (0, 0);
+        for j in 0..self.rows {
+            for i in 0..self.cols {
+                if self.data[j][i].abs() > self.data[row][col].abs() {
+                    row = j;
+                    col = i;
+                }
+            }
+        }
+
+        let pivot = self.data[row][col];
+        if pivot == 0.0 {
+            return None;
+        }
+
+        let horizontal: Vec<f64> = self.data[row].iter().map(|x| x / pivot).collect();
+        let vertical: Vec<f64> = self.data.iter().map(|r| r[col]).collect();
+
+        let eps = pivot.abs() * 1e-9;
+        for (row, v) in self.data.iter().zip(&vertical) {
+            for (x, h) in row.iter().zip(&horizontal) {
+                if (x - v * h).abs() > eps {
+                    return None;
+                }
+            }
+        }
+
+        Some(SeparableKernel::new(horizontal, vertical).with_border(self.border))
+    }
+}
+
+/// Generate a normalized gaussian kernel with a radius of `ceil(3 * sigma)`, values of `sigma`
+/// that are not positive produce the identity kernel
+pub fn gaussian(sigma: f64) -> SeparableKernel {
+    if sigma <= 0.0 {
+        return SeparableKernel::symmetric([1.0]);
+    }
+    let radius = (3.0 * sigma).ceil().max(1.0) as usize;
+    SeparableKernel::gaussian(2 * radius + 1, sigma)
+}
+
+/// Convolution kernel that can be split into a horizontal and a vertical 1-dimensional pass,
+/// this is much faster than `Kernel` for large kernels
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SeparableKernel {
+    horizontal: Vec<f64>,
+    vertical: Vec<f64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    border: Border,
+}
+
+impl SeparableKernel {
+    /// Create a new separable kernel from horizontal and vertical weights
+    pub fn new(horizontal: impl Into<Vec<f64>>, vertical: impl Into<Vec<f64>>) -> SeparableKernel {
+        SeparableKernel {
+            horizontal: horizontal.into(),
+            vertical: vertical.into(),
+            border: Border::default(),
+        }
+    }
+
+    /// Set the border mode used for pixels near the edge of the image
+    pub fn with_border(mut self, border: Border) -> SeparableKernel {
+        self.border = border;
+        self
+    }
+
+    /// Get the border mode
+    pub fn border(&self) -> Border {
+        self.border
+    }
+
+    /// Create a kernel that uses the same weights in both directions
+    pub fn symmetric(weights: impl Into<Vec<f64>>) -> SeparableKernel {
+        let weights = weights.into();
+        SeparableKernel::new(weights.clone(), weights)
+    }
+
+    /// Generate gaussian blur kernel, when `std` is not positive only the center weight is set
+    pub fn gaussian(n: usize, std: f64) -> SeparableKernel {
+        assert!(n % 2 == 1);
+        let center = (n / 2) as f64;
+        let mut weights: Vec<f64> = (0..n)
+            .map(|i| {
+                let x = i as f64 - center;
+                if std <= 0.0 {
+                    return if x == 0.0 { 1.0 } else { 0.0 };
+                }
+                (-(x * x) / (2.0 * std * std)).exp()
+            })
+            .collect();
+        let sum: f64 = weights.iter().sum();
+        weights.iter_mut().for_each(|w| *w /= sum);
+        SeparableKernel::symmetric(weights)
+    }
+
+    /// Generate box blur kernel
+    pub fn box_blur(n: usize) -> SeparableKernel {
+        assert!(n % 2 == 1);
+        SeparableKernel::symmetric(vec![1.0 / n as f64; n])
+    }
+
+    /// Horizontal weights
+    pub fn horizontal(&self) -> &[f64] {
+        &self.horizontal
+    }
+
+    /// Vertical weights
+    pub fn vertical(&self) -> &[f64] {
+        &self.vertical
+    }
+
+    /// Convert to a 2-dimensional `Kernel`
+    pub fn to_kernel(&self) -> Kernel {
+        Kernel::create(self.vertical.len(), self.horizontal.len(), |i, j| {
+            self.vertical[j] * self.horizontal[i]
+        })
+        .with_border(self.border)
+    }
+}
+
+impl From<SeparableKernel> for Kernel {
+    fn from(k: SeparableKernel) -> Kernel {
+        k.to_kernel()
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for SeparableKernel {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn prepare(
+        &self,
+        input: &Input<T, C>,
+    ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+        // Horizontal pass, the vertical pass is performed by `compute_at`
+        let image = input.images()[0];
+        let (width, channels) = (image.width(), C::CHANNELS);
+        let c2 = (self.horizontal.len() / 2) as isize;
+        let mut data = vec![0.0; width * image.height() * channels];
+
+        #[cfg(feature = "parallel")]
+        let rows = data.par_chunks_mut(width * channels);
+        #[cfg(not(feature = "parallel"))]
+        let rows = data.chunks_mut(width * channels);
+
+        rows.enumerate().for_each(|(y, row)| {
+            for x in 0..width {
+                for (k, w) in self.horizontal.iter().enumerate() {
+                    let i = self.border.index(x as isize + k as isize - c2, width);
+                    for c in 0..channels {
+                        row[x * channels + c] += match i {
+                            Some(i) => image.get_f((i, y), c),
+                            None => self.border.value(),
+                        } * w;
+                    }
+                }
+            }
+        });
+
+        Some(std::sync::Arc::new(data))
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let data = match input.prepared::<Vec<f64>>() {
+            Some(data) => data,
+            None => return,
+        };
+
+        let image = input.images()[0];
+        let (width, height) = (image.width(), image.height());
+        if pt.x >= width {
+            return;
+        }
+
+        // Rows outside of the image are constant after the horizontal pass
+        let outside = self.border.value() * self.horizontal.iter().sum::<f64>();
+
+        let r2 = (self.vertical.len() / 2) as isize;
+        let mut f = input.new_pixel();
+        f.fill(0.0);
+        for (k, w) in self.vertical.iter().enumerate() {
+            let j = self.border.index(pt.y as isize + k as isize - r2, height);
+            for c in 0..f.len() {
+                f[c] += match j {
+                    Some(j) => data[(j * width + pt.x) * C::CHANNELS + c],
+                    None => outside,
+                } * w;
+            }
+        }
+        f.copy_to_slice(dest);
+    }
 }
 
 impl ops::Add for Kernel {
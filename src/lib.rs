@@ -34,17 +34,30 @@
 /// 16-bit float
 pub use half::f16;
 
+#[macro_use]
+mod trace;
+
+mod bitmap;
 mod color;
+mod colorize;
 mod data;
+mod draw;
 mod error;
+mod fill;
 mod filters;
 mod geom;
-mod hash;
 mod histogram;
 mod image;
 mod image_data;
+mod math;
 mod meta;
+mod mipmap;
+mod normal_map;
 mod pixel;
+mod rng;
+mod seamless;
+mod swizzle;
+mod tiling;
 mod r#type;
 
 /// OpenGL interop
@@ -75,22 +88,85 @@ pub mod kernel;
 /// Image transforms
 pub mod transform;
 
+/// Contour tracing
+pub mod contour;
+
+/// Raster to vector tracing
+pub mod vectorize;
+
+/// Foreground segmentation
+pub mod segment;
+
+/// Morphological operations
+pub mod morphology;
+
+/// Image analysis
+pub mod analysis;
+
+/// Content-based image hashing
+pub mod hash;
+
+/// GPU-compressed texture formats
+pub mod compress;
+
+/// Environment map processing for image based lighting
+pub mod envmap;
+
+/// Sparse linear solvers over image grids
+pub mod solver;
+
+/// Color lookup tables
+pub mod lut;
+
+/// Edit propagation from brush strokes
+pub mod propagate;
+
+/// Clone stamp and healing brushes
+pub mod retouch;
+
+/// High dynamic range merging of bracketed exposures
+pub mod hdr;
+
+/// Color quantization to a limited palette
+pub mod quantize;
+
+/// Image annotations with SVG and PDF export
+pub mod annotate;
+
+/// Filter parameter sweeps rendered as image strips or animations
+pub mod sweep;
+
+/// Random transforms for data augmentation
+pub mod augment;
+
+/// Evaluation metrics for segmentation masks and label maps
+pub mod metrics;
+
+/// Image registration and stacking for noise reduction
+pub mod stack;
+
 pub use crate::meta::Meta;
-pub use color::{Channel, Cmyk, Color, Gray, Hsv, Rgb, Rgba, Srgb, Srgba, Xyz, Yuv};
+pub use bitmap::{BitImage, Rle};
+pub use color::{Channel, Channels, Cmyk, Color, Gray, Hsv, Lab, Rgb, Rgba, Srgb, Srgba, Xyz, Yuv};
 pub use data::{Data, DataMut};
 pub use error::Error;
 pub use filters::{
-    filter, AsyncFilter, AsyncMode, AsyncPipeline, Filter, FilterExt, Input, Pipeline, Schedule,
+    filter, temporal, AsyncFilter, AsyncMode, AsyncPipeline, Expr, Filter, FilterExt, Input,
+    Pipeline, Sampling, Schedule, TemporalFilter, TileCache,
 };
-pub use geom::{Point, Region, Size};
+pub use geom::{NamedRoi, Point, Polygon, Region, Roi, Size};
 pub use hash::Hash;
 pub use histogram::Histogram;
 pub use image::Image;
 pub use image_data::ImageData;
-pub use kernel::Kernel;
+pub use io::{OpenOptions, SourceInfo};
+pub use kernel::{Border, Kernel, SeparableKernel};
+pub use mipmap::MipFilter;
 pub use pixel::Pixel;
 pub use r#type::Type;
-pub use transform::Transform;
+pub use rng::{Rng, Seed, Seeded};
+pub use seamless::CloneMode;
+pub use transform::{Interpolation, Transform, TransformExt};
 
 #[cfg(feature = "mmap")]
 pub use image_data::mmap::Mmap;
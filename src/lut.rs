@@ -0,0 +1,398 @@
+use std::path::Path;
+
+use crate::*;
+
+/// Interpolation used to look up values between LUT entries
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LutInterpolation {
+    /// Blend the 8 surrounding entries
+    #[default]
+    Trilinear,
+
+    /// Blend the 4 entries of the tetrahedron containing the value, this is more accurate for
+    /// neutral colors and is used by most color grading applications
+    Tetrahedral,
+}
+
+/// 3D color lookup table. The first three channels of each pixel are used as coordinates and
+/// replaced with the interpolated table value, any other channels (typically alpha) are copied
+/// unchanged. Values are looked up without any color conversion, so the image should use the
+/// encoding the LUT was created for, which is usually `Srgb`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lut3d {
+    /// Title
+    pub title: Option<String>,
+
+    /// Number of entries along each axis
+    pub size: usize,
+
+    /// Input value mapped to the first entry of each axis
+    pub domain_min: [f64; 3],
+
+    /// Input value mapped to the last entry of each axis
+    pub domain_max: [f64; 3],
+
+    /// Table entries, red changes fastest followed by green and blue
+    pub data: Vec<[f64; 3]>,
+
+    /// Interpolation method
+    pub interpolation: LutInterpolation,
+}
+
+impl Lut3d {
+    /// Largest `LUT_3D_SIZE` accepted by `Lut3d::parse`
+    pub const MAX_SIZE: usize = 256;
+
+    /// Create a LUT that maps every color to itself
+    pub fn identity(size: usize) -> Lut3d {
+        let size = size.max(2);
+        let scale = 1.0 / (size - 1) as f64;
+        let mut data = Vec::with_capacity(size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.push([r as f64 * scale, g as f64 * scale, b as f64 * scale]);
+                }
+            }
+        }
+        Lut3d {
+            title: None,
+            size,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+            data,
+            interpolation: LutInterpolation::default(),
+        }
+    }
+
+    /// Set interpolation method
+    pub fn with_interpolation(mut self, interpolation: LutInterpolation) -> Lut3d {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Load an Adobe/Resolve `.cube` file
+    pub fn open(path: impl AsRef<Path>) -> Result<Lut3d, Error> {
+        let s = std::fs::read_to_string(path)?;
+        Lut3d::parse(&s)
+    }
+
+    /// Parse the contents of a `.cube` file
+    pub fn parse(s: &str) -> Result<Lut3d, Error> {
+        let invalid =
+            |line: usize, msg: &str| Error::Message(format!("Invalid LUT line {}: {}", line, msg));
+        let floats = |line: usize, values: &[&str]| -> Result<[f64; 3], Error> {
+            if values.len() != 3 {
+                return Err(invalid(line, "expected 3 values"));
+            }
+            let mut out = [0.0; 3];
+            for (x, v) in out.iter_mut().zip(values) {
+                *x = v.parse().map_err(|_| invalid(line, "invalid number"))?;
+            }
+            Ok(out)
+        };
+
+        let mut lut = Lut3d {
+            title: None,
+            size: 0,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+            data: Vec::new(),
+            interpolation: LutInterpolation::default(),
+        };
+
+        for (i, line) in s.lines().enumerate() {
+            let n = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let keyword = parts.next().unwrap_or_default();
+            let values: Vec<&str> = parts.collect();
+            match keyword {
+                "TITLE" => {
+                    let title = line["TITLE".len()..].trim().trim_matches('"');
+                    lut.title = Some(title.to_string());
+                }
+                "LUT_3D_SIZE" => {
+                    lut.size = values
+                        .first()
+                        .and_then(|x| x.parse().ok())
+                        .filter(|x| *x >= 2)
+                        .ok_or_else(|| invalid(n, "invalid size"))?;
+                    if lut.size > Lut3d::MAX_SIZE {
+                        return Err(invalid(n, "size too large"));
+                    }
+                }
+                "LUT_1D_SIZE" => return Err(invalid(n, "1D LUTs are not supported")),
+                "DOMAIN_MIN" => lut.domain_min = floats(n, &values)?,
+                "DOMAIN_MAX" => lut.domain_max = floats(n, &values)?,
+                "LUT_3D_INPUT_RANGE" | "LUT_IN_VIDEO_RANGE" | "LUT_OUT_VIDEO_RANGE" => (),
+                _ if keyword.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.') => {
+                    let mut row = vec![keyword];
+                    row.extend(values);
+                    lut.data.push(floats(n, &row)?);
+                }
+                _ => return Err(invalid(n, "unknown keyword")),
+            }
+        }
+
+        if lut.size == 0 {
+            return Err(Error::Message("Missing LUT_3D_SIZE".into()));
+        }
+
+        let expected = lut
+            .size
+            .checked_mul(lut.size)
+            .and_then(|x| x.checked_mul(lut.size))
+            .ok_or_else(|| Error::Message("LUT_3D_SIZE is too large".into()))?;
+        if lut.data.len() != expected {
+            return Err(Error::Message(format!(
+                "Expected {} LUT entries, found {}",
+                expected,
+                lut.data.len()
+            )));
+        }
+
+        Ok(lut)
+    }
+
+    /// Encode as a `.cube` file
+    pub fn to_cube(&self) -> String {
+        let mut s = String::new();
+        if let Some(title) = &self.title {
+            s.push_str(&format!("TITLE \"{}\"\n", title));
+        }
+        s.push_str(&format!("LUT_3D_SIZE {}\n", self.size));
+        let [r, g, b] = self.domain_min;
+        s.push_str(&format!("DOMAIN_MIN {} {} {}\n", r, g, b));
+        let [r, g, b] = self.domain_max;
+        s.push_str(&format!("DOMAIN_MAX {} {} {}\n", r, g, b));
+        for [r, g, b] in &self.data {
+            s.push_str(&format!("{:.6} {:.6} {:.6}\n", r, g, b));
+        }
+        s
+    }
+
+    /// Write a `.cube` file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        std::fs::write(path, self.to_cube())?;
+        Ok(())
+    }
+
+    /// Create a LUT from a HALD CLUT image. A level `L` HALD image is `L^3` pixels square and
+    /// contains a `L^2` sized cube, with pixels in the same order as `.cube` entries.
+    pub fn from_hald<T: Type, C: Color>(image: &Image<T, C>) -> Result<Lut3d, Error> {
+        let side = image.width();
+        let level = (1..=16).find(|l| l * l * l == side);
+        let level = match level {
+            Some(level) if image.height() == side && level >= 2 => level,
+            _ => {
+                return Err(Error::InvalidDimensions(
+                    image.width(),
+                    image.height(),
+                    C::CHANNELS,
+                ))
+            }
+        };
+
+        let mut lut = Lut3d::identity(level * level);
+        image.each_pixel(|pt, px| {
+            let value = if C::CHANNELS >= 3 {
+                [px[0], px[1], px[2]]
+            } else {
+                [px[0]; 3]
+            };
+            lut.data[pt.y * side + pt.x] = value;
+        });
+        Ok(lut)
+    }
+
+    /// Load a HALD CLUT image
+    pub fn open_hald(path: impl AsRef<Path>) -> Result<Lut3d, Error> {
+        let image = Image::<f32, Rgb>::open(path)?;
+        Lut3d::from_hald(&image)
+    }
+
+    /// Encode as a HALD CLUT image, returns `None` if the size of the LUT isn't a square number
+    /// or the domain isn't the default range of 0 to 1. Values are stored without any color
+    /// conversion. Use `Lut3d::identity(level * level).to_hald()` to create an identity image
+    /// which can be edited to create a new look.
+    pub fn to_hald<T: Type, C: Color>(&self) -> Option<Image<T, C>> {
+        let level = (2..=16).find(|l| l * l == self.size)?;
+        if self.domain_min != [0.0; 3] || self.domain_max != [1.0; 3] {
+            return None;
+        }
+
+        let side = level * level * level;
+        let mut image = Image::<T, C>::new((side, side));
+        image.each_pixel_mut(|pt, mut px| {
+            let value = self.data[pt.y * side + pt.x];
+            for c in 0..C::CHANNELS.min(3) {
+                px[c] = value[c];
+            }
+            if let Some(a) = C::ALPHA {
+                px[a] = 1.0;
+            }
+        });
+        Some(image)
+    }
+
+    fn entry(&self, r: usize, g: usize, b: usize) -> [f64; 3] {
+        self.data[(b * self.size + g) * self.size + r]
+    }
+
+    /// Look up a color
+    pub fn lookup(&self, rgb: [f64; 3]) -> [f64; 3] {
+        let max = (self.size - 1) as f64;
+        let mut index = [0; 3];
+        let mut frac = [0.0; 3];
+        for c in 0..3 {
+            let range = self.domain_max[c] - self.domain_min[c];
+            let t = if range != 0.0 {
+                (rgb[c] - self.domain_min[c]) / range
+            } else {
+                0.0
+            };
+            let x = (t * max).clamp(0.0, max);
+            index[c] = (x.floor() as usize).min(self.size - 2);
+            frac[c] = x - index[c] as f64;
+        }
+
+        let [r, g, b] = index;
+        let corner = |dr: usize, dg: usize, db: usize| self.entry(r + dr, g + dg, b + db);
+        let [fr, fg, fb] = frac;
+
+        match self.interpolation {
+            LutInterpolation::Trilinear => {
+                let mut out = [0.0; 3];
+                for (db, wb) in [(0, 1.0 - fb), (1, fb)] {
+                    for (dg, wg) in [(0, 1.0 - fg), (1, fg)] {
+                        for (dr, wr) in [(0, 1.0 - fr), (1, fr)] {
+                            let v = corner(dr, dg, db);
+                            let w = wr * wg * wb;
+                            (0..3).for_each(|c| out[c] += v[c] * w);
+                        }
+                    }
+                }
+                out
+            }
+            LutInterpolation::Tetrahedral => {
+                // Walk from the first to the last corner along the edges of the tetrahedron
+                let (c000, c111) = (corner(0, 0, 0), corner(1, 1, 1));
+                let (steps, mid) = if fr > fg {
+                    if fg > fb {
+                        ([fr, fg, fb], [corner(1, 0, 0), corner(1, 1, 0)])
+                    } else if fr > fb {
+                        ([fr, fb, fg], [corner(1, 0, 0), corner(1, 0, 1)])
+                    } else {
+                        ([fb, fr, fg], [corner(0, 0, 1), corner(1, 0, 1)])
+                    }
+                } else if fb > fg {
+                    ([fb, fg, fr], [corner(0, 0, 1), corner(0, 1, 1)])
+                } else if fb > fr {
+                    ([fg, fb, fr], [corner(0, 1, 0), corner(0, 1, 1)])
+                } else {
+                    ([fg, fr, fb], [corner(0, 1, 0), corner(1, 1, 0)])
+                };
+
+                [0, 1, 2].map(|c| {
+                    c000[c]
+                        + steps[0] * (mid[0][c] - c000[c])
+                        + steps[1] * (mid[1][c] - mid[0][c])
+                        + steps[2] * (c111[c] - mid[1][c])
+                })
+            }
+        }
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Lut3d {
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let mut px = input.get_pixel(pt, None);
+        if C::CHANNELS >= 3 {
+            let out = self.lookup([px[0], px[1], px[2]]);
+            (0..3).for_each(|c| px[c] = out[c]);
+        } else {
+            let out = self.lookup([px[0]; 3]);
+            px[0] = out[0];
+        }
+        px.copy_to_slice(dest);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_lut_parse() {
+        let cube = "# comment\nTITLE \"Invert\"\nLUT_3D_SIZE 2\n\n1 1 1\n0 1 1\n1 0 1\n0 0 1\n1 1 0\n0 1 0\n1 0 0\n0 0 0\n";
+        let lut = lut::Lut3d::parse(cube).unwrap();
+        assert_eq!(lut.title.as_deref(), Some("Invert"));
+        assert_eq!(lut.size, 2);
+
+        let inverted = lut.lookup([0.25, 0.5, 1.0]);
+        assert!((inverted[0] - 0.75).abs() < 1e-9);
+        assert!((inverted[1] - 0.5).abs() < 1e-9);
+        assert!(inverted[2].abs() < 1e-9);
+
+        let roundtrip = lut::Lut3d::parse(&lut.to_cube()).unwrap();
+        assert_eq!(roundtrip, lut);
+
+        assert!(lut::Lut3d::parse("LUT_3D_SIZE 2\n0 0 0\n").is_err());
+        assert!(lut::Lut3d::parse("LUT_1D_SIZE 2\n0 0 0\n1 1 1\n").is_err());
+        assert!(lut::Lut3d::parse("LUT_3D_SIZE 10000000\n0 0 0\n").is_err());
+        assert!(lut::Lut3d::parse("LUT_3D_SIZE 257\n0 0 0\n").is_err());
+    }
+
+    #[test]
+    fn test_lut_hald() {
+        let identity = lut::Lut3d::identity(4);
+        let hald: Image<u16, Rgb> = identity.to_hald().unwrap();
+        assert_eq!(hald.size(), Size::new(8, 8));
+        assert_eq!(hald.get((1, 0)).as_slice(), &[21845, 0, 0]);
+        assert_eq!(hald.get((7, 7)).as_slice(), &[65535, 65535, 65535]);
+
+        let lut = lut::Lut3d::from_hald(&hald).unwrap();
+        assert_eq!(lut.size, 4);
+        for (a, b) in lut.data.iter().zip(&identity.data) {
+            assert!((0..3).all(|c| (a[c] - b[c]).abs() < 1e-4));
+        }
+
+        assert!(lut::Lut3d::identity(5).to_hald::<u8, Rgb>().is_none());
+        assert!(lut::Lut3d::from_hald(&Image::<u8, Rgb>::new((9, 9))).is_err());
+    }
+
+    #[test]
+    fn test_lut_apply() {
+        let mut image = Image::<f32, Rgba>::new((2, 1));
+        image.set((0, 0), [0.1, 0.6, 0.3, 0.5]);
+        image.set((1, 0), [0.9, 0.2, 0.7, 1.0]);
+
+        for interpolation in [
+            lut::LutInterpolation::Trilinear,
+            lut::LutInterpolation::Tetrahedral,
+        ] {
+            let lut = lut::Lut3d::identity(5).with_interpolation(interpolation);
+            let mut dest = image.new_like();
+            lut.eval(&[&image], &mut dest);
+            for x in 0..2 {
+                for c in 0..4 {
+                    assert!((dest.get_f((x, 0), c) - image.get_f((x, 0), c)).abs() < 1e-6);
+                }
+            }
+        }
+
+        // Tetrahedral interpolation keeps neutral colors neutral
+        let mut lut =
+            lut::Lut3d::identity(2).with_interpolation(lut::LutInterpolation::Tetrahedral);
+        lut.data[7] = [0.5, 0.5, 0.5];
+        let out = lut.lookup([0.4, 0.4, 0.4]);
+        assert!((out[0] - out[1]).abs() < 1e-9 && (out[1] - out[2]).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::*;
+
+/// Intersection over union of two binary masks, pixels with a normalized value >= 0.5 are
+/// foreground. Returns 0 when both masks are empty.
+pub fn iou<T: Type, U: Type>(a: &Image<T, Gray>, b: &Image<U, Gray>) -> f64 {
+    assert_eq!(a.size(), b.size(), "mask sizes must match");
+    let (a, b) = (BitImage::from_image(a), BitImage::from_image(b));
+    let union = (&a | &b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    (&a & &b).count() as f64 / union as f64
+}
+
+/// Dice coefficient (F1 score) of two binary masks, pixels with a normalized value >= 0.5 are
+/// foreground. Returns 0 when both masks are empty.
+pub fn dice<T: Type, U: Type>(a: &Image<T, Gray>, b: &Image<U, Gray>) -> f64 {
+    assert_eq!(a.size(), b.size(), "mask sizes must match");
+    let (a, b) = (BitImage::from_image(a), BitImage::from_image(b));
+    let total = a.count() + b.count();
+    if total == 0 {
+        return 0.0;
+    }
+    2.0 * (&a & &b).count() as f64 / total as f64
+}
+
+/// Fraction of pixels where two binary masks agree
+pub fn pixel_accuracy<T: Type, U: Type>(a: &Image<T, Gray>, b: &Image<U, Gray>) -> f64 {
+    assert_eq!(a.size(), b.size(), "mask sizes must match");
+    let (a, b) = (BitImage::from_image(a), BitImage::from_image(b));
+    let total = a.width() * a.height();
+    if total == 0 {
+        return 0.0;
+    }
+    1.0 - (&a ^ &b).count() as f64 / total as f64
+}
+
+/// Confusion matrix for evaluating label maps with a fixed number of classes. Counts can be
+/// accumulated over many images to compute metrics for a whole dataset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfusionMatrix {
+    /// Number of classes
+    pub classes: usize,
+
+    /// Number of pixels for each pair of classes, indexed by `truth * classes + predicted`
+    pub counts: Vec<u64>,
+}
+
+impl ConfusionMatrix {
+    /// Create an empty matrix
+    pub fn new(classes: usize) -> ConfusionMatrix {
+        ConfusionMatrix {
+            classes,
+            counts: vec![0; classes * classes],
+        }
+    }
+
+    /// Create a matrix from a single pair of label maps, see `ConfusionMatrix::add`
+    pub fn from_labels<T: Type, U: Type>(
+        classes: usize,
+        truth: &Image<T, Gray>,
+        predicted: &Image<U, Gray>,
+    ) -> ConfusionMatrix {
+        let mut matrix = ConfusionMatrix::new(classes);
+        matrix.add(truth, predicted);
+        matrix
+    }
+
+    /// Add the pixels of a pair of label maps, where each pixel value is a class index. Pixels
+    /// with a value outside of the class range in either image are ignored, which allows an
+    /// "ignore" label such as 255 to be used.
+    pub fn add<T: Type, U: Type>(&mut self, truth: &Image<T, Gray>, predicted: &Image<U, Gray>) {
+        assert_eq!(truth.size(), predicted.size(), "label map sizes must match");
+        for (t, p) in truth.data().iter().zip(predicted.data()) {
+            let (t, p) = (t.to_f64() as usize, p.to_f64() as usize);
+            if t < self.classes && p < self.classes {
+                self.counts[t * self.classes + p] += 1;
+            }
+        }
+    }
+
+    /// Number of pixels of class `truth` that were predicted as `predicted`
+    pub fn get(&self, truth: usize, predicted: usize) -> u64 {
+        self.counts[truth * self.classes + predicted]
+    }
+
+    /// Total number of pixels counted
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Fraction of pixels that were predicted correctly
+    pub fn pixel_accuracy(&self) -> f64 {
+        let total = self.total();
+        if total == 0 {
+            return 0.0;
+        }
+        let correct: u64 = (0..self.classes).map(|c| self.get(c, c)).sum();
+        correct as f64 / total as f64
+    }
+
+    /// True positives, false positives and false negatives for a class
+    fn outcomes(&self, class: usize) -> (u64, u64, u64) {
+        let tp = self.get(class, class);
+        let predicted: u64 = (0..self.classes).map(|t| self.get(t, class)).sum();
+        let actual: u64 = (0..self.classes).map(|p| self.get(class, p)).sum();
+        (tp, predicted - tp, actual - tp)
+    }
+
+    /// Intersection over union of a single class, `None` when the class appears in neither the
+    /// ground truth nor the prediction
+    pub fn iou(&self, class: usize) -> Option<f64> {
+        let (tp, fp, fn_) = self.outcomes(class);
+        let union = tp + fp + fn_;
+        (union > 0).then(|| tp as f64 / union as f64)
+    }
+
+    /// Dice coefficient of a single class, `None` when the class appears in neither the ground
+    /// truth nor the prediction
+    pub fn dice(&self, class: usize) -> Option<f64> {
+        let (tp, fp, fn_) = self.outcomes(class);
+        let total = 2 * tp + fp + fn_;
+        (total > 0).then(|| 2.0 * tp as f64 / total as f64)
+    }
+
+    /// Mean intersection over union of the classes that appear in the ground truth or the
+    /// prediction
+    pub fn mean_iou(&self) -> f64 {
+        let values: Vec<f64> = (0..self.classes).filter_map(|c| self.iou(c)).collect();
+        if values.is_empty() {
+            return 0.0;
+        }
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use metrics::ConfusionMatrix;
+
+    #[test]
+    fn test_mask_metrics() {
+        let mut a = Image::<u8, Gray>::new((10, 10));
+        let mut b = Image::<f32, Gray>::new((10, 10));
+        a.fill_rect(
+            Region::new(Point::new(0, 0), Size::new(6, 10)),
+            &Pixel::from(vec![1.0]),
+        );
+        b.fill_rect(
+            Region::new(Point::new(3, 0), Size::new(6, 10)),
+            &Pixel::from(vec![1.0]),
+        );
+
+        assert!((metrics::iou(&a, &b) - 30.0 / 90.0).abs() < 1e-12);
+        assert!((metrics::dice(&a, &b) - 60.0 / 120.0).abs() < 1e-12);
+        assert!((metrics::pixel_accuracy(&a, &b) - 0.4).abs() < 1e-12);
+        assert_eq!(metrics::iou(&a, &a), 1.0);
+    }
+
+    #[test]
+    fn test_confusion_matrix() {
+        let truth =
+            Image::<u8, Gray>::new_with_data((4, 2), vec![0, 0, 1, 1, 2, 2, 255, 1]).unwrap();
+        let predicted =
+            Image::<u8, Gray>::new_with_data((4, 2), vec![0, 1, 1, 1, 2, 0, 2, 1]).unwrap();
+
+        let matrix = ConfusionMatrix::from_labels(3, &truth, &predicted);
+        assert_eq!(matrix.total(), 7);
+        assert_eq!(matrix.get(0, 1), 1);
+        assert_eq!(matrix.get(2, 0), 1);
+        assert!((matrix.pixel_accuracy() - 5.0 / 7.0).abs() < 1e-12);
+        assert_eq!(matrix.iou(1), Some(0.75));
+        assert_eq!(matrix.dice(2), Some(2.0 / 3.0));
+        assert!((matrix.mean_iou() - (1.0 / 3.0 + 0.75 + 0.5) / 3.0).abs() < 1e-12);
+        assert_eq!(ConfusionMatrix::new(2).iou(0), None);
+    }
+}
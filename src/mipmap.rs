@@ -0,0 +1,236 @@
+use crate::*;
+
+use crate::math::sinc;
+
+/// Filter used to downsample mipmap levels
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MipFilter {
+    /// Area average
+    #[default]
+    Box,
+
+    /// Kaiser-windowed sinc, sharper than `Box` with less aliasing. `width` is the radius in
+    /// output pixels and `alpha` controls the shape of the window
+    Kaiser {
+        /// Filter radius, in output pixels
+        width: f64,
+
+        /// Window shape, larger values reduce ringing
+        alpha: f64,
+    },
+}
+
+impl MipFilter {
+    /// Kaiser filter with the default width and alpha
+    pub fn kaiser() -> MipFilter {
+        MipFilter::Kaiser {
+            width: 3.0,
+            alpha: 4.0,
+        }
+    }
+
+    /// Weights of the source pixels contributing to each output pixel when reducing `src`
+    /// pixels to `dest` pixels
+    fn weights(&self, src: usize, dest: usize) -> Vec<Vec<(usize, f64)>> {
+        let scale = src as f64 / dest as f64;
+        (0..dest)
+            .map(|i| {
+                let mut w: Vec<(usize, f64)> = match *self {
+                    MipFilter::Box => {
+                        let (start, end) = (i as f64 * scale, (i + 1) as f64 * scale);
+                        (start.floor() as usize..(end.ceil() as usize).min(src))
+                            .map(|j| {
+                                let overlap = end.min(j as f64 + 1.0) - start.max(j as f64);
+                                (j, overlap.max(0.0))
+                            })
+                            .collect()
+                    }
+                    MipFilter::Kaiser { width, alpha } => {
+                        let center = (i as f64 + 0.5) * scale;
+                        let radius = (width * scale).ceil() as isize;
+                        let c = center.floor() as isize;
+                        (c - radius..=c + radius)
+                            .map(|j| {
+                                let d = (j as f64 + 0.5 - center) / scale;
+                                let w = sinc(d) * kaiser(d / width, alpha);
+                                (j.clamp(0, src as isize - 1) as usize, w)
+                            })
+                            .collect()
+                    }
+                };
+
+                let sum: f64 = w.iter().map(|x| x.1).sum();
+                if sum.abs() > f64::EPSILON {
+                    w.iter_mut().for_each(|x| x.1 /= sum);
+                }
+                w
+            })
+            .collect()
+    }
+}
+
+/// Modified Bessel function of the first kind, order zero
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    for k in 1..32 {
+        term *= (x / (2.0 * k as f64)).powi(2);
+        sum += term;
+        if term < sum * 1e-12 {
+            break;
+        }
+    }
+    sum
+}
+
+fn kaiser(x: f64, alpha: f64) -> f64 {
+    if x.abs() > 1.0 {
+        return 0.0;
+    }
+    bessel_i0(alpha * (1.0 - x * x).sqrt()) / bessel_i0(alpha)
+}
+
+/// Linear, premultiplied RGBA pixels
+struct Level {
+    size: Size,
+    data: Vec<[f64; 4]>,
+}
+
+impl Level {
+    fn from_image<T: Type, C: Color>(image: &Image<T, C>) -> Level {
+        let mut data = Vec::with_capacity(image.width() * image.height());
+        image.each_pixel(|_, px| {
+            let mut px = px.clone();
+            let alpha = match C::ALPHA {
+                Some(a) => std::mem::replace(&mut px[a], 1.0).clamp(0.0, 1.0),
+                None => 1.0,
+            };
+            let rgb: Pixel<Rgb> = px.convert();
+            data.push([rgb[0] * alpha, rgb[1] * alpha, rgb[2] * alpha, alpha]);
+        });
+        Level {
+            size: image.size(),
+            data,
+        }
+    }
+
+    fn to_image<T: Type, C: Color>(&self) -> Image<T, C> {
+        let mut image = Image::new(self.size);
+        let mut rgb = Pixel::<Rgb>::new();
+        image.each_pixel_mut(|pt, mut px| {
+            let [r, g, b, alpha] = self.data[pt.y * self.size.width + pt.x];
+            let scale = if alpha > 0.0 { 1.0 / alpha } else { 0.0 };
+            rgb.copy_from_slice([r * scale, g * scale, b * scale]);
+            rgb.convert_to(px);
+            if let Some(a) = C::ALPHA {
+                px[a] = alpha;
+            }
+        });
+        image
+    }
+
+    /// Reduce to half size in each direction, dimensions are rounded down and never less than 1
+    fn reduce(&self, filter: MipFilter) -> Level {
+        let (width, height) = (self.size.width, self.size.height);
+        let size = Size::new((width / 2).max(1), (height / 2).max(1));
+
+        let wx = filter.weights(width, size.width);
+        let mut tmp = vec![[0.0; 4]; size.width * height];
+        for y in 0..height {
+            for (x, weights) in wx.iter().enumerate() {
+                let d = &mut tmp[y * size.width + x];
+                for (j, w) in weights {
+                    let s = self.data[y * width + j];
+                    (0..4).for_each(|c| d[c] += s[c] * w);
+                }
+            }
+        }
+
+        let wy = filter.weights(height, size.height);
+        let mut data = vec![[0.0; 4]; size.width * size.height];
+        for (y, weights) in wy.iter().enumerate() {
+            for x in 0..size.width {
+                let d = &mut data[y * size.width + x];
+                for (j, w) in weights {
+                    let s = tmp[j * size.width + x];
+                    (0..4).for_each(|c| d[c] += s[c] * w);
+                }
+                // Sharpening filters can produce negative values
+                d.iter_mut().for_each(|v| *v = v.max(0.0));
+                d[3] = d[3].min(1.0);
+            }
+        }
+
+        Level { size, data }
+    }
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Generate a full mipmap chain using `MipFilter::Box`, see `mipmaps_with`
+    pub fn mipmaps(&self) -> Vec<Image<T, C>> {
+        self.mipmaps_with(MipFilter::Box)
+    }
+
+    /// Generate a full mipmap chain, starting with a copy of the image and ending with a 1x1
+    /// image. Each level is half the size of the previous one, filtering is done in linear
+    /// light with premultiplied alpha so bright details and transparent edges are preserved.
+    pub fn mipmaps_with(&self, filter: MipFilter) -> Vec<Image<T, C>> {
+        let mut levels = vec![self.clone()];
+        if self.width() == 0 || self.height() == 0 {
+            return levels;
+        }
+
+        let mut level = Level::from_image(self);
+        while level.size.width > 1 || level.size.height > 1 {
+            level = level.reduce(filter);
+            levels.push(level.to_image());
+        }
+        levels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_mipmaps() {
+        let mut image = Image::<f32, Srgb>::new((8, 4));
+        image.for_each(|pt, mut px| {
+            let v = ((pt.x + pt.y) % 2) as f32;
+            px.copy_from_slice([v, v, v]);
+        });
+
+        let levels = image.mipmaps();
+        let sizes: Vec<Size> = levels.iter().map(|x| x.size()).collect();
+        assert_eq!(
+            sizes,
+            vec![
+                Size::new(8, 4),
+                Size::new(4, 2),
+                Size::new(2, 1),
+                Size::new(1, 1)
+            ]
+        );
+
+        // Averaging black and white in linear light is brighter than 0.5 in sRGB
+        let expected: Pixel<Srgb> = Pixel::<Rgb>::from(vec![0.5, 0.5, 0.5]).convert();
+        for level in &levels[1..] {
+            assert!((level.get_f((0, 0), 0) - expected[0]).abs() < 1e-3);
+        }
+
+        let levels = image.mipmaps_with(MipFilter::kaiser());
+        assert_eq!(levels.len(), 4);
+        assert!((levels[1].get_f((1, 0), 1) - expected[1]).abs() < 0.05);
+
+        // Transparent pixels don't bleed into the color of opaque pixels
+        let mut image = Image::<f32, Rgba>::new((2, 2));
+        image.set((0, 0), [1.0, 0.0, 0.0, 1.0]);
+        image.set((1, 0), [0.0, 1.0, 0.0, 0.0]);
+        let levels = image.mipmaps();
+        assert!((levels[1].get_f((0, 0), 0) - 1.0).abs() < 1e-6);
+        assert!(levels[1].get_f((0, 0), 1).abs() < 1e-6);
+        assert!((levels[1].get_f((0, 0), 3) - 0.25).abs() < 1e-6);
+    }
+}
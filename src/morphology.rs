@@ -0,0 +1,416 @@
+use crate::*;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Shape used by morphological operations, stored as a list of offsets from the center pixel
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructuringElement {
+    offsets: Vec<(isize, isize)>,
+}
+
+impl StructuringElement {
+    /// Create a structuring element from offsets relative to the center pixel
+    pub fn new(offsets: impl Into<Vec<(isize, isize)>>) -> StructuringElement {
+        StructuringElement {
+            offsets: offsets.into(),
+        }
+    }
+
+    /// Create a `width` x `height` structuring element containing the offsets for which `f`
+    /// returns true, `f` is called with the column and row
+    pub fn create(width: usize, height: usize, f: impl Fn(usize, usize) -> bool) -> Self {
+        let (cx, cy) = ((width / 2) as isize, (height / 2) as isize);
+        let mut offsets = Vec::new();
+        for j in 0..height {
+            for i in 0..width {
+                if f(i, j) {
+                    offsets.push((i as isize - cx, j as isize - cy));
+                }
+            }
+        }
+        StructuringElement { offsets }
+    }
+
+    /// Rectangle
+    pub fn rect(width: usize, height: usize) -> StructuringElement {
+        Self::create(width, height, |_, _| true)
+    }
+
+    /// Ellipse inscribed in a `width` x `height` rectangle
+    pub fn ellipse(width: usize, height: usize) -> StructuringElement {
+        let (rx, ry) = (width as f64 / 2.0, height as f64 / 2.0);
+        Self::create(width, height, |i, j| {
+            let x = (i as f64 + 0.5 - rx) / rx;
+            let y = (j as f64 + 0.5 - ry) / ry;
+            x * x + y * y <= 1.0
+        })
+    }
+
+    /// Cross made of the center row and column of a `width` x `height` rectangle
+    pub fn cross(width: usize, height: usize) -> StructuringElement {
+        Self::create(width, height, |i, j| i == width / 2 || j == height / 2)
+    }
+
+    /// Offsets from the center pixel
+    pub fn offsets(&self) -> &[(isize, isize)] {
+        &self.offsets
+    }
+
+    /// Element mirrored around the center pixel
+    pub fn reflect(&self) -> StructuringElement {
+        StructuringElement {
+            offsets: self.offsets.iter().map(|(x, y)| (-x, -y)).collect(),
+        }
+    }
+}
+
+/// Morphological operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Operation {
+    /// Minimum over the structuring element
+    Erode,
+
+    /// Maximum over the structuring element
+    Dilate,
+
+    /// Erode followed by dilate, removes small bright features
+    Open,
+
+    /// Dilate followed by erode, fills small dark features
+    Close,
+
+    /// Difference between dilation and erosion
+    Gradient,
+
+    /// Difference between the input and its opening
+    TopHat,
+
+    /// Difference between the closing and the input
+    BlackHat,
+}
+
+/// Morphology filter, each channel is processed independently. Pixels outside of the image are
+/// ignored.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Morphology {
+    /// Operation
+    pub operation: Operation,
+
+    /// Structuring element
+    pub element: StructuringElement,
+}
+
+impl Morphology {
+    /// Create a new morphology filter
+    pub fn new(operation: Operation, element: StructuringElement) -> Morphology {
+        Morphology { operation, element }
+    }
+
+    fn apply(&self, data: &[f64], size: Size, channels: usize, dilate: bool) -> Vec<f64> {
+        let element = if dilate {
+            self.element.reflect()
+        } else {
+            self.element.clone()
+        };
+
+        let mut output = vec![0.0; data.len()];
+        let row_len = size.width * channels;
+        if row_len == 0 {
+            return output;
+        }
+
+        #[cfg(feature = "parallel")]
+        let rows = output.par_chunks_mut(row_len);
+        #[cfg(not(feature = "parallel"))]
+        let rows = output.chunks_mut(row_len);
+
+        rows.enumerate().for_each(|(y, row)| {
+            for x in 0..size.width {
+                for c in 0..channels {
+                    let mut value = if dilate {
+                        f64::NEG_INFINITY
+                    } else {
+                        f64::INFINITY
+                    };
+                    for (dx, dy) in element.offsets() {
+                        let (i, j) = (x as isize + dx, y as isize + dy);
+                        if i < 0 || j < 0 || i >= size.width as isize || j >= size.height as isize {
+                            continue;
+                        }
+                        let v = data[(j as usize * size.width + i as usize) * channels + c];
+                        value = if dilate { value.max(v) } else { value.min(v) };
+                    }
+                    if value.is_infinite() {
+                        value = data[(y * size.width + x) * channels + c];
+                    }
+                    row[x * channels + c] = value;
+                }
+            }
+        });
+
+        output
+    }
+
+    /// Run the operation on normalized, interleaved pixel data
+    fn run(&self, data: &[f64], size: Size, channels: usize) -> Vec<f64> {
+        let erode = |data: &[f64]| self.apply(data, size, channels, false);
+        let dilate = |data: &[f64]| self.apply(data, size, channels, true);
+        let sub = |a: Vec<f64>, b: &[f64]| a.iter().zip(b).map(|(a, b)| a - b).collect();
+
+        match self.operation {
+            Operation::Erode => erode(data),
+            Operation::Dilate => dilate(data),
+            Operation::Open => dilate(&erode(data)),
+            Operation::Close => erode(&dilate(data)),
+            Operation::Gradient => sub(dilate(data), &erode(data)),
+            Operation::TopHat => sub(data.to_vec(), &dilate(&erode(data))),
+            Operation::BlackHat => sub(erode(&dilate(data)), data),
+        }
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Morphology {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn prepare(
+        &self,
+        input: &Input<T, C>,
+    ) -> Option<std::sync::Arc<dyn std::any::Any + Send + Sync>> {
+        let image = input.images()[0];
+        let data: Vec<f64> = image.data().iter().map(|x| x.to_norm()).collect();
+        Some(std::sync::Arc::new(self.run(
+            &data,
+            image.size(),
+            C::CHANNELS,
+        )))
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, dest: &mut DataMut<U, D>) {
+        let width = input.images()[0].width();
+        let index = (pt.y * width + pt.x) * C::CHANNELS;
+        match input.prepared::<Vec<f64>>() {
+            Some(data) if pt.x < width && index + C::CHANNELS <= data.len() => {
+                Pixel::<C>::from(&data[index..index + C::CHANNELS]).convert_to_data(dest)
+            }
+            _ => input.get_pixel(pt, None).convert_to_data(dest),
+        }
+    }
+}
+
+/// Erode using the given structuring element
+pub fn erode(element: StructuringElement) -> Morphology {
+    Morphology::new(Operation::Erode, element)
+}
+
+/// Dilate using the given structuring element
+pub fn dilate(element: StructuringElement) -> Morphology {
+    Morphology::new(Operation::Dilate, element)
+}
+
+/// Morphological opening, erode followed by dilate
+pub fn open(element: StructuringElement) -> Morphology {
+    Morphology::new(Operation::Open, element)
+}
+
+/// Morphological closing, dilate followed by erode
+pub fn close(element: StructuringElement) -> Morphology {
+    Morphology::new(Operation::Close, element)
+}
+
+/// Morphological gradient, the difference between dilation and erosion
+pub fn gradient(element: StructuringElement) -> Morphology {
+    Morphology::new(Operation::Gradient, element)
+}
+
+/// Top-hat transform, the difference between the input and its opening
+pub fn top_hat(element: StructuringElement) -> Morphology {
+    Morphology::new(Operation::TopHat, element)
+}
+
+/// Black-hat transform, the difference between the closing and the input
+pub fn black_hat(element: StructuringElement) -> Morphology {
+    Morphology::new(Operation::BlackHat, element)
+}
+
+/// One dimensional squared Euclidean distance transform of a sampled function (Felzenszwalb and
+/// Huttenlocher 2012), writes the distance and the index of the nearest sample for every
+/// position. Samples that are not finite are ignored.
+fn distance_1d(f: &[f64], dist: &mut [f64], nearest: &mut [usize]) {
+    let mut v: Vec<usize> = Vec::with_capacity(f.len());
+    let mut z: Vec<f64> = Vec::with_capacity(f.len());
+    for (q, fq) in f.iter().enumerate().filter(|(_, x)| x.is_finite()) {
+        let qf = q as f64;
+        while let Some(&p) = v.last() {
+            let pf = p as f64;
+            let s = ((fq + qf * qf) - (f[p] + pf * pf)) / (2.0 * qf - 2.0 * pf);
+            if s <= z[z.len() - 1] {
+                v.pop();
+                z.pop();
+                continue;
+            }
+            z.push(s);
+            break;
+        }
+        if v.is_empty() {
+            z.push(f64::NEG_INFINITY);
+        }
+        v.push(q);
+    }
+
+    if v.is_empty() {
+        dist.fill(f64::INFINITY);
+        nearest.fill(usize::MAX);
+        return;
+    }
+
+    let mut j = 0;
+    for (q, (d, n)) in dist.iter_mut().zip(nearest.iter_mut()).enumerate() {
+        while j + 1 < v.len() && z[j + 1] < q as f64 {
+            j += 1;
+        }
+        let dq = q as f64 - v[j] as f64;
+        *d = dq * dq + f[v[j]];
+        *n = v[j];
+    }
+}
+
+/// Exact squared distances and the position of the nearest background pixel for every pixel
+fn distance_2d<T: Type>(mask: &Image<T, Gray>) -> (Vec<f64>, Vec<Option<Point>>) {
+    let (width, height) = (mask.width(), mask.height());
+    let mut columns = vec![0.0; width * height];
+    let mut rows_of = vec![0; width * height];
+
+    // Distances along each column, stored transposed
+    let mut f = vec![0.0; height];
+    for x in 0..width {
+        for (y, v) in f.iter_mut().enumerate() {
+            *v = if mask.get_f((x, y), 0) >= 0.5 {
+                f64::INFINITY
+            } else {
+                0.0
+            };
+        }
+        let range = x * height..(x + 1) * height;
+        distance_1d(&f, &mut columns[range.clone()], &mut rows_of[range]);
+    }
+
+    let mut dist = vec![0.0; width * height];
+    let mut nearest = vec![None; width * height];
+    let mut f = vec![0.0; width];
+    let mut d = vec![0.0; width];
+    let mut n = vec![0; width];
+    for y in 0..height {
+        for (x, v) in f.iter_mut().enumerate() {
+            *v = columns[x * height + y];
+        }
+        distance_1d(&f, &mut d, &mut n);
+        for x in 0..width {
+            let i = y * width + x;
+            dist[i] = d[x];
+            if n[x] != usize::MAX {
+                nearest[i] = Some(Point::new(n[x], rows_of[n[x] * height + y]));
+            }
+        }
+    }
+    (dist, nearest)
+}
+
+/// Exact Euclidean distance transform, computes the distance from every pixel to the closest
+/// background pixel. Pixels with a normalized value >= 0.5 are foreground, background pixels
+/// have a distance of 0. When the mask has no background every distance is infinite.
+pub fn distance_transform<T: Type>(mask: &Image<T, Gray>) -> Image<f32, Gray> {
+    let (dist, _) = distance_2d(mask);
+    let mut dest = Image::new(mask.size());
+    for (d, x) in dest.data_mut().iter_mut().zip(dist) {
+        *d = x.sqrt() as f32;
+    }
+    dest
+}
+
+/// Position of the closest background pixel for every pixel in raster order, `None` when the
+/// mask has no background. Coloring each pixel using its closest background pixel produces a
+/// Voronoi diagram of the background.
+pub fn feature_transform<T: Type>(mask: &Image<T, Gray>) -> Vec<Option<Point>> {
+    distance_2d(mask).1
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::morphology::*;
+
+    fn count(image: &Image<u8, Gray>) -> usize {
+        image.data().iter().filter(|x| **x > 127).count()
+    }
+
+    #[test]
+    fn test_morphology() {
+        assert_eq!(StructuringElement::cross(3, 3).offsets().len(), 5);
+        assert_eq!(StructuringElement::rect(3, 3).offsets().len(), 9);
+        assert_eq!(StructuringElement::ellipse(5, 5).offsets().len(), 21);
+
+        let mut image = Image::<u8, Gray>::new((16, 16));
+        image.for_each_region(
+            Region::new(Point::new(4, 4), Size::new(6, 6)),
+            |_, mut px| {
+                px[0] = 255;
+            },
+        );
+        image.set((7, 7), [0]);
+        image.set((13, 2), [255]);
+
+        let mut dest = image.new_like();
+        dilate(StructuringElement::cross(3, 3)).eval(&[&image], &mut dest);
+        assert_eq!(dest.get_f((13, 1), 0), 1.0);
+        assert_eq!(dest.get_f((12, 1), 0), 0.0);
+
+        // Opening removes the isolated pixel, closing fills the hole
+        open(StructuringElement::rect(3, 3)).eval(&[&image], &mut dest);
+        assert_eq!(dest.get_f((13, 2), 0), 0.0);
+
+        close(StructuringElement::rect(3, 3)).eval(&[&image], &mut dest);
+        assert_eq!(dest.get_f((7, 7), 0), 1.0);
+        assert_eq!(count(&dest), 37);
+
+        erode(StructuringElement::rect(3, 3)).eval(&[&image], &mut dest);
+        assert_eq!(count(&dest), 7);
+
+        gradient(StructuringElement::rect(3, 3)).eval(&[&image], &mut dest);
+        assert_eq!(dest.get_f((4, 4), 0), 1.0);
+        assert_eq!(dest.get_f((0, 0), 0), 0.0);
+    }
+
+    #[test]
+    fn test_distance_transform() {
+        let mut mask = Image::<u8, Gray>::new((20, 12));
+        mask.for_each(|_, mut px| px[0] = 255);
+        mask.set((3, 4), [0]);
+        mask.set((15, 8), [0]);
+
+        let dist = distance_transform(&mask);
+        let nearest = feature_transform(&mask);
+        for y in 0..12 {
+            for x in 0..20 {
+                let d = |px: usize, py: usize| {
+                    ((x as f64 - px as f64).powi(2) + (y as f64 - py as f64).powi(2)).sqrt()
+                };
+                let expected = d(3, 4).min(d(15, 8));
+                assert!((dist.get((x, y))[0] as f64 - expected).abs() < 1e-4);
+
+                let p = nearest[y * 20 + x].unwrap();
+                assert!((d(p.x, p.y) - expected).abs() < 1e-9);
+            }
+        }
+        assert_eq!(dist.get((3, 4))[0], 0.0);
+
+        let mut full = Image::<u8, Gray>::new((4, 4));
+        full.data_mut().fill(255);
+        assert!(distance_transform(&full).get((1, 1))[0].is_infinite());
+        assert!(feature_transform(&full).iter().all(Option::is_none));
+    }
+}
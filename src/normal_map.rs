@@ -0,0 +1,106 @@
+use crate::*;
+
+/// Decode a normal stored in the range 0..1
+fn decode(px: &[f32]) -> [f64; 3] {
+    [0, 1, 2].map(|c| px[c] as f64 * 2.0 - 1.0)
+}
+
+/// Normalize `n` and encode it in the range 0..1
+fn encode(n: [f64; 3], px: &mut [f32]) {
+    let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+    let n = if len > 0.0 {
+        n.map(|x| x / len)
+    } else {
+        [0.0, 0.0, 1.0]
+    };
+    for c in 0..3 {
+        px[c] = (n[c] * 0.5 + 0.5) as f32;
+    }
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Generate a tangent-space normal map from a height map, the luminance of the image is used
+    /// as the height. Slopes are computed using the Sobel operator and scaled by `strength`, edge
+    /// pixels are clamped. The result uses the OpenGL convention with green pointing up, see
+    /// `flip_normal_y` to convert to the DirectX convention.
+    pub fn height_to_normal(&self, strength: f64) -> Image<f32, Rgb> {
+        let gray: Image<f32, Gray> = self.convert();
+        let (w, h) = (gray.width(), gray.height());
+        let data = gray.data();
+        let at = |x: isize, y: isize| {
+            let x = x.clamp(0, w as isize - 1) as usize;
+            let y = y.clamp(0, h as isize - 1) as usize;
+            data[y * w + x] as f64
+        };
+
+        let mut dest = Image::<f32, Rgb>::new(gray.size());
+        for (i, px) in dest.data_mut().chunks_exact_mut(3).enumerate() {
+            let (x, y) = ((i % w) as isize, (i / w) as isize);
+            let dx = (at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2.0 * at(x - 1, y)
+                - at(x - 1, y + 1))
+                / 8.0;
+            let dy = (at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2.0 * at(x, y - 1)
+                - at(x + 1, y - 1))
+                / 8.0;
+
+            // Image rows increase downwards while the normal's y axis points up
+            encode([-dx * strength, dy * strength, 1.0], px);
+        }
+        dest
+    }
+}
+
+impl Image<f32, Rgb> {
+    /// Rescale every normal in a normal map to unit length, this should be done after resizing,
+    /// blurring or blending normal maps
+    pub fn renormalize_normals(&mut self) {
+        for px in self.data_mut().chunks_exact_mut(3) {
+            encode(decode(px), px);
+        }
+    }
+
+    /// Invert the green channel of a normal map, converting between the OpenGL and DirectX
+    /// conventions
+    pub fn flip_normal_y(&mut self) {
+        for px in self.data_mut().chunks_exact_mut(3) {
+            px[1] = 1.0 - px[1];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_height_to_normal() {
+        // Height increases to the right
+        let mut height = Image::<f32, Gray>::new((8, 8));
+        height.for_each(|pt, mut px| px[0] = pt.x as f32 * 0.1);
+
+        let mut normals = height.height_to_normal(2.0);
+        let n = normals.get_pixel((4, 4));
+        assert!(n[0] < 0.5);
+        assert!((n[1] - 0.5).abs() < 1e-6);
+        assert!(n[2] > 0.5);
+
+        let len = |px: &Pixel<Rgb>| {
+            let v: Vec<f64> = px.iter().map(|x| x * 2.0 - 1.0).collect();
+            (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+        };
+        assert!((len(&n) - 1.0).abs() < 1e-5);
+
+        normals.set((0, 0), [0.5, 0.5, 0.6]);
+        normals.renormalize_normals();
+        assert!((len(&normals.get_pixel((0, 0))) - 1.0).abs() < 1e-5);
+        assert!((normals.get_f((0, 0), 2) - 1.0).abs() < 1e-6);
+
+        normals.set((1, 1), [0.5, 0.2, 0.5]);
+        normals.flip_normal_y();
+        assert!((normals.get_f((1, 1), 1) - 0.8).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,216 @@
+use crate::solver::PoissonSystem;
+use crate::*;
+
+/// Brush stroke carrying a set of adjustment parameters
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stroke {
+    /// Points along the stroke, consecutive points are connected
+    pub points: Vec<Point>,
+
+    /// Brush radius in pixels
+    pub radius: f64,
+
+    /// Adjustment parameters, for example the exposure change in stops
+    pub params: Vec<f64>,
+}
+
+impl Stroke {
+    /// Create a new stroke
+    pub fn new(points: impl Into<Vec<Point>>, radius: f64, params: impl Into<Vec<f64>>) -> Stroke {
+        Stroke {
+            points: points.into(),
+            radius,
+            params: params.into(),
+        }
+    }
+
+    /// Returns true when `pt` is covered by the stroke
+    pub fn contains(&self, pt: Point) -> bool {
+        match self.points.len() {
+            0 => false,
+            1 => crate::geom::distance(pt, self.points[0]) <= self.radius,
+            _ => self
+                .points
+                .windows(2)
+                .any(|w| crate::geom::segment_distance(pt, w[0], w[1]) <= self.radius),
+        }
+    }
+}
+
+/// Spread sparse edits across an image. Pixels covered by a stroke take the parameters of that
+/// stroke and the remaining pixels receive a blend of the nearby strokes, weighted by color
+/// similarity so edits stop at image edges. Away from any stroke the parameters fade towards
+/// zero, strokes with zero parameters can be used to protect areas from an edit.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EditPropagation {
+    /// Strokes, later strokes take precedence where they overlap
+    pub strokes: Vec<Stroke>,
+
+    /// Color difference at which the affinity between neighboring pixels falls to ~60%
+    pub sigma: f64,
+
+    /// How quickly edits fade with distance from the strokes, 0 disables fading
+    pub falloff: f64,
+}
+
+impl Default for EditPropagation {
+    fn default() -> Self {
+        EditPropagation {
+            strokes: Vec::new(),
+            sigma: 0.05,
+            falloff: 1e-3,
+        }
+    }
+}
+
+impl EditPropagation {
+    /// Create an empty edit propagation
+    pub fn new() -> EditPropagation {
+        EditPropagation::default()
+    }
+
+    /// Add a stroke
+    pub fn add_stroke(&mut self, stroke: Stroke) -> &mut Self {
+        self.strokes.push(stroke);
+        self
+    }
+
+    /// Set color affinity
+    pub fn with_sigma(mut self, sigma: f64) -> EditPropagation {
+        self.sigma = sigma;
+        self
+    }
+
+    /// Set falloff
+    pub fn with_falloff(mut self, falloff: f64) -> EditPropagation {
+        self.falloff = falloff;
+        self
+    }
+
+    /// Number of parameters carried by the strokes
+    pub fn num_params(&self) -> usize {
+        self.strokes
+            .iter()
+            .map(|s| s.params.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Compute one map per parameter with the propagated value at every pixel of `image`
+    pub fn propagate<T: Type, C: Color>(&self, image: &Image<T, C>) -> Vec<Image<f32, Gray>> {
+        let size = image.size();
+        let (width, height) = (size.width, size.height);
+
+        // Index of the stroke covering each pixel
+        let mut owner = vec![None; width * height];
+        for (i, stroke) in self.strokes.iter().enumerate() {
+            let r = stroke.radius.max(0.0).ceil() as usize;
+            if stroke.points.is_empty() {
+                continue;
+            }
+            let (mut x0, mut y0, mut x1, mut y1) = (usize::MAX, usize::MAX, 0, 0);
+            for p in &stroke.points {
+                (x0, y0) = (x0.min(p.x), y0.min(p.y));
+                (x1, y1) = (x1.max(p.x), y1.max(p.y));
+            }
+
+            for y in y0.saturating_sub(r)..(y1 + r + 1).min(height) {
+                for x in x0.saturating_sub(r)..(x1 + r + 1).min(width) {
+                    if stroke.contains(Point::new(x, y)) {
+                        owner[y * width + x] = Some(i);
+                    }
+                }
+            }
+        }
+
+        let channels: Vec<Channel> = (0..C::CHANNELS).filter(|c| Some(*c) != C::ALPHA).collect();
+        let sigma2 = 2.0 * self.sigma * self.sigma;
+        let system = PoissonSystem::new(size, |pt| owner[pt.y * width + pt.x].is_none())
+            .with_weights(|a, b| {
+                let d: f64 = channels
+                    .iter()
+                    .map(|c| (image.get_f(a, *c) - image.get_f(b, *c)).powi(2))
+                    .sum();
+                (-d / sigma2).exp().max(1e-4)
+            })
+            .with_regularization(self.falloff);
+
+        let param = |pt: Point, n: usize| {
+            owner[pt.y * width + pt.x]
+                .and_then(|i| self.strokes[i].params.get(n).copied())
+                .unwrap_or(0.0)
+        };
+
+        (0..self.num_params())
+            .map(|n| {
+                let mut dest = Image::<f32, Gray>::new(size);
+                dest.for_each(|pt, mut px| px[0] = param(pt, n) as f32);
+
+                if !system.unknowns().is_empty() {
+                    let zeros = vec![0.0; system.unknowns().len()];
+                    let result =
+                        system.solve(&zeros, |pt| param(pt, n), &zeros, width * height, 1e-6);
+                    for (pt, value) in system.unknowns().iter().zip(result) {
+                        dest.set_f(*pt, 0, value);
+                    }
+                }
+                dest
+            })
+            .collect()
+    }
+}
+
+/// Dodge and burn, adjust the exposure of each pixel in linear light by the number of stops in
+/// `stops`, which is typically created using `EditPropagation::propagate`. Alpha is not affected.
+pub fn dodge_burn<T: Type, C: Color>(image: &Image<T, C>, stops: &Image<f32, Gray>) -> Image<T, C> {
+    let mut dest = image.clone();
+    dest.each_pixel_mut(|pt, mut px| {
+        let scale = 2f64.powf(stops.get_f(pt, 0));
+        let alpha = C::ALPHA.map(|a| std::mem::replace(&mut px[a], 1.0));
+        let mut rgb: Pixel<Rgb> = px.convert();
+        rgb.iter_mut().for_each(|x| *x *= scale);
+        rgb.convert_to(px);
+        if let (Some(alpha), Some(a)) = (alpha, C::ALPHA) {
+            px[a] = alpha;
+        }
+    });
+    dest
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_edit_propagation() {
+        // Dark left half and bright right half
+        let mut image = Image::<f32, Rgb>::new((20, 10));
+        image.for_each(|pt, mut px| {
+            let v = if pt.x < 10 { 0.2 } else { 0.8 };
+            px.copy_from_slice([v, v, v]);
+        });
+
+        let mut edits = propagate::EditPropagation::new();
+        edits.add_stroke(propagate::Stroke::new(
+            vec![Point::new(2, 2), Point::new(2, 7)],
+            1.0,
+            vec![1.0, -0.5],
+        ));
+        assert_eq!(edits.num_params(), 2);
+
+        let maps = edits.propagate(&image);
+        assert_eq!(maps.len(), 2);
+        assert_eq!(maps[0].get((2, 5))[0], 1.0);
+        assert_eq!(maps[1].get((3, 5))[0], -0.5);
+
+        // The edit spreads across the dark region but stops at the edge
+        assert!(maps[0].get((8, 5))[0] > 0.5);
+        assert!(maps[0].get((12, 5))[0] < 0.1);
+
+        let result = propagate::dodge_burn(&image, &maps[0]);
+        assert!((result.get((2, 5))[0] - 0.4).abs() < 1e-6);
+        assert!((result.get((18, 5))[0] - 0.8).abs() < 0.05);
+    }
+}
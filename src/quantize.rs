@@ -0,0 +1,786 @@
+use crate::*;
+
+use crate::math::dist2;
+
+/// Maximum number of palette entries, indices are stored as bytes
+pub const MAX_COLORS: usize = 256;
+
+/// Algorithm used to build a palette
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QuantizeMethod {
+    /// Recursively split the box of colors with the largest range at the median
+    #[default]
+    MedianCut,
+
+    /// Build an octree of colors and merge the least common leaves
+    Octree,
+}
+
+/// List of colors
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Palette {
+    /// Palette colors
+    pub colors: Vec<Pixel<Rgb>>,
+}
+
+impl Palette {
+    /// Create a palette from a list of colors
+    pub fn new(colors: impl Into<Vec<Pixel<Rgb>>>) -> Palette {
+        Palette {
+            colors: colors.into(),
+        }
+    }
+
+    /// Number of colors
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    /// Returns true when the palette has no colors
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// Index of the color closest to `color`
+    pub fn nearest(&self, color: &[f64]) -> usize {
+        self.colors
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (i, dist2(c.as_ref(), color)))
+            .fold((0, f64::MAX), |acc, x| if x.1 < acc.1 { x } else { acc })
+            .0
+    }
+
+    /// Create a palette of `n` distinct colors for displaying label maps, such as the output of
+    /// segmentation or connected component labeling. The first color is black so label 0 can be
+    /// used for the background.
+    pub fn classes(n: usize) -> Palette {
+        let colors = (0..n)
+            .map(|i| {
+                if i == 0 {
+                    return Pixel::new();
+                }
+
+                // Golden angle hue steps keep neighboring labels far apart
+                let hue = (i as f64 * 0.618_033_988_75).fract() * 6.0;
+                let value = if i % 2 == 0 { 0.75 } else { 1.0 };
+                let x = 1.0 - (hue % 2.0 - 1.0).abs();
+                let (r, g, b) = match hue as usize {
+                    0 => (1.0, x, 0.0),
+                    1 => (x, 1.0, 0.0),
+                    2 => (0.0, 1.0, x),
+                    3 => (0.0, x, 1.0),
+                    4 => (x, 0.0, 1.0),
+                    _ => (1.0, 0.0, x),
+                };
+                Pixel::from(vec![r * value, g * value, b * value])
+            })
+            .collect::<Vec<_>>();
+        Palette::new(colors)
+    }
+
+    /// Map every pixel of `image` to the index of the closest palette color, without dithering.
+    /// Use `u16` indices for palettes with more than 256 colors.
+    pub fn map<I: Type, T: Type, C: Color>(&self, image: &Image<T, C>) -> Indexed<I> {
+        let mut indices = Vec::with_capacity(image.width() * image.height());
+        image.each_pixel(|_, px| {
+            let px = px.convert::<Rgb>();
+            let index = if self.is_empty() {
+                0
+            } else {
+                self.nearest(px.as_ref())
+            };
+            indices.push(I::from_f64(index as f64));
+        });
+        Indexed {
+            size: image.size(),
+            palette: self.clone(),
+            indices,
+        }
+    }
+}
+
+/// Image stored as indices into a palette. Indices are bytes by default, `u16` indices can be
+/// used for label maps with more than 256 classes.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Indexed<I: Type = u8> {
+    /// Image size
+    pub size: Size,
+
+    /// Palette
+    pub palette: Palette,
+
+    /// Palette index of each pixel in raster order
+    pub indices: Vec<I>,
+}
+
+impl<I: Type> Indexed<I> {
+    /// Create from a label map, each pixel value is used as an index into `palette`
+    pub fn from_labels(labels: &Image<I, Gray>, palette: Palette) -> Indexed<I> {
+        Indexed {
+            size: labels.size(),
+            palette,
+            indices: labels.data().to_vec(),
+        }
+    }
+
+    /// Palette index of the pixel at `pt`
+    pub fn index(&self, pt: impl Into<Point>) -> usize {
+        let pt = pt.into();
+        self.indices[pt.y * self.size.width + pt.x].to_f64() as usize
+    }
+
+    /// Get the indices as a label map
+    pub fn labels(&self) -> Image<I, Gray> {
+        let mut image = Image::new(self.size);
+        image.data_mut().copy_from_slice(&self.indices);
+        image
+    }
+
+    /// Replace every index with its palette color, indices past the end of the palette wrap
+    /// around so a short class color map can be used for any number of labels
+    pub fn to_image<T: Type, C: Color>(&self) -> Image<T, C> {
+        let mut image = Image::new(self.size);
+        if self.palette.is_empty() {
+            return image;
+        }
+        image.each_pixel_mut(|pt, px| {
+            let index = self.index(pt) % self.palette.len();
+            self.palette.colors[index].convert_to(px);
+        });
+        image
+    }
+}
+
+/// Reduce an image to a limited number of colors. Colors are converted to `Rgb` and alpha is
+/// ignored.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quantizer {
+    /// Maximum number of colors, at most `MAX_COLORS`
+    pub colors: usize,
+
+    /// Palette algorithm
+    pub method: QuantizeMethod,
+
+    /// Use Floyd-Steinberg error diffusion when mapping pixels to the palette
+    pub dither: bool,
+
+    /// Maximum number of pixels sampled to build the palette, 0 uses every pixel
+    pub max_samples: usize,
+}
+
+impl Quantizer {
+    /// Create a median cut quantizer without dithering
+    pub fn new(colors: usize) -> Quantizer {
+        Quantizer {
+            colors: colors.clamp(1, MAX_COLORS),
+            method: QuantizeMethod::MedianCut,
+            dither: false,
+            max_samples: 65536,
+        }
+    }
+
+    /// Set the palette algorithm
+    pub fn with_method(mut self, method: QuantizeMethod) -> Quantizer {
+        self.method = method;
+        self
+    }
+
+    /// Enable or disable dithering
+    pub fn with_dither(mut self, dither: bool) -> Quantizer {
+        self.dither = dither;
+        self
+    }
+
+    /// Set the maximum number of sampled pixels
+    pub fn with_max_samples(mut self, n: usize) -> Quantizer {
+        self.max_samples = n;
+        self
+    }
+
+    /// Build a palette for `image`
+    pub fn palette<T: Type, C: Color>(&self, image: &Image<T, C>) -> Palette {
+        let samples: Vec<[f64; 3]> = analysis::sample_pixels(image, self.max_samples)
+            .iter()
+            .map(|px| [px[0], px[1], px[2]])
+            .collect();
+        if samples.is_empty() {
+            return Palette::default();
+        }
+
+        let colors = self.colors.clamp(1, MAX_COLORS);
+        let colors = match self.method {
+            QuantizeMethod::MedianCut => median_cut(samples, colors),
+            QuantizeMethod::Octree => octree(&samples, colors),
+        };
+        Palette::new(
+            colors
+                .into_iter()
+                .map(|c| Pixel::from(c.to_vec()))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Build a palette and map every pixel to it
+    pub fn quantize<T: Type, C: Color>(&self, image: &Image<T, C>) -> Indexed {
+        let palette = self.palette(image);
+        self.remap(image, palette)
+    }
+
+    /// Map every pixel of `image` to the closest color in an existing palette
+    pub fn remap<T: Type, C: Color>(&self, image: &Image<T, C>, palette: Palette) -> Indexed {
+        let (width, height) = (image.width(), image.height());
+        let mut indices = vec![0u8; width * height];
+        if palette.is_empty() {
+            return Indexed {
+                size: image.size(),
+                palette,
+                indices,
+            };
+        }
+
+        let mut colors: Vec<[f64; 3]> = Vec::with_capacity(width * height);
+        image.each_pixel(|_, px| {
+            let px = px.convert::<Rgb>();
+            colors.push([px[0], px[1], px[2]]);
+        });
+
+        for y in 0..height {
+            // Serpentine scan avoids directional artifacts when dithering
+            let reverse = self.dither && y % 2 == 1;
+            for i in 0..width {
+                let x = if reverse { width - 1 - i } else { i };
+                let color = colors[y * width + x];
+                let index = palette.nearest(&color);
+                indices[y * width + x] = index as u8;
+                if !self.dither {
+                    continue;
+                }
+
+                let chosen = &palette.colors[index];
+                let error = [
+                    color[0] - chosen[0],
+                    color[1] - chosen[1],
+                    color[2] - chosen[2],
+                ];
+                let ahead = if reverse {
+                    x as isize - 1
+                } else {
+                    x as isize + 1
+                };
+                let behind = if reverse {
+                    x as isize + 1
+                } else {
+                    x as isize - 1
+                };
+                for (nx, ny, w) in [
+                    (ahead, y, 7.0 / 16.0),
+                    (behind, y + 1, 3.0 / 16.0),
+                    (x as isize, y + 1, 5.0 / 16.0),
+                    (ahead, y + 1, 1.0 / 16.0),
+                ] {
+                    if nx < 0 || nx as usize >= width || ny >= height {
+                        continue;
+                    }
+                    let n = &mut colors[ny * width + nx as usize];
+                    for c in 0..3 {
+                        n[c] += error[c] * w;
+                    }
+                }
+            }
+        }
+
+        Indexed {
+            size: image.size(),
+            palette,
+            indices,
+        }
+    }
+}
+
+/// Mean of a set of colors
+fn mean(colors: &[[f64; 3]]) -> [f64; 3] {
+    let mut sum = [0.0; 3];
+    for color in colors {
+        for c in 0..3 {
+            sum[c] += color[c];
+        }
+    }
+    let n = colors.len().max(1) as f64;
+    [sum[0] / n, sum[1] / n, sum[2] / n]
+}
+
+/// Channel with the largest range and its range
+fn widest_channel(colors: &[[f64; 3]]) -> (usize, f64) {
+    (0..3)
+        .map(|c| {
+            let (min, max) = colors
+                .iter()
+                .fold((f64::MAX, f64::MIN), |(a, b), x| (a.min(x[c]), b.max(x[c])));
+            (c, max - min)
+        })
+        .fold((0, -1.0), |acc, x| if x.1 > acc.1 { x } else { acc })
+}
+
+/// Median cut (Heckbert 1982)
+fn median_cut(samples: Vec<[f64; 3]>, colors: usize) -> Vec<[f64; 3]> {
+    let mut boxes = vec![samples];
+    while boxes.len() < colors {
+        // Split the box with the largest range, weighted by the number of colors it contains
+        let candidate = boxes
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (i, widest_channel(b).1 * (b.len() as f64).sqrt()))
+            .filter(|(_, score)| *score > 0.0)
+            .fold(None, |acc: Option<(usize, f64)>, x| match acc {
+                Some(a) if a.1 >= x.1 => Some(a),
+                _ => Some(x),
+            });
+        let index = match candidate {
+            Some((i, _)) => i,
+            None => break,
+        };
+
+        let mut b = boxes.swap_remove(index);
+        let (channel, _) = widest_channel(&b);
+        b.sort_by(|x, y| x[channel].total_cmp(&y[channel]));
+        let upper = b.split_off(b.len() / 2);
+        boxes.push(b);
+        boxes.push(upper);
+    }
+    boxes.iter().map(|b| mean(b)).collect()
+}
+
+/// Octree node, children are indices into the node list
+#[derive(Default, Clone)]
+struct Node {
+    children: [Option<usize>; 8],
+    sum: [f64; 3],
+    count: usize,
+    leaf: bool,
+}
+
+/// Number of octree levels below the root
+const OCTREE_DEPTH: usize = 8;
+
+/// Octree quantization (Gervautz and Purgathofer 1988)
+fn octree(samples: &[[f64; 3]], colors: usize) -> Vec<[f64; 3]> {
+    let mut nodes = vec![Node::default()];
+    let mut levels: Vec<Vec<usize>> = vec![Vec::new(); OCTREE_DEPTH];
+    let mut leaves = 0;
+
+    for color in samples {
+        let bytes = color.map(|x| (x.clamp(0.0, 1.0) * 255.0).round() as u8);
+        let mut node = 0;
+        for (level, parents) in levels.iter_mut().enumerate() {
+            let bit = 7 - level;
+            let child = ((bytes[0] >> bit) & 1) << 2
+                | ((bytes[1] >> bit) & 1) << 1
+                | ((bytes[2] >> bit) & 1);
+            node = match nodes[node].children[child as usize] {
+                Some(n) => n,
+                None => {
+                    let n = nodes.len();
+                    nodes.push(Node::default());
+                    nodes[node].children[child as usize] = Some(n);
+                    parents.push(node);
+                    n
+                }
+            };
+        }
+
+        let leaf = &mut nodes[node];
+        if !leaf.leaf {
+            leaf.leaf = true;
+            leaves += 1;
+        }
+        leaf.count += 1;
+        for (s, x) in leaf.sum.iter_mut().zip(color) {
+            *s += x;
+        }
+    }
+
+    // Merge the children of the deepest internal nodes, least common first
+    for level in (0..OCTREE_DEPTH).rev() {
+        if leaves <= colors {
+            break;
+        }
+
+        let mut parents = std::mem::take(&mut levels[level]);
+        parents.sort_unstable();
+        parents.dedup();
+        let mut parents: Vec<(usize, usize)> = parents
+            .into_iter()
+            .map(|p| {
+                let count = nodes[p]
+                    .children
+                    .iter()
+                    .flatten()
+                    .map(|c| nodes[*c].count)
+                    .sum();
+                (p, count)
+            })
+            .collect();
+        parents.sort_by_key(|(_, count)| *count);
+
+        for (p, _) in parents {
+            if leaves <= colors {
+                break;
+            }
+            let mut merged = Node {
+                leaf: true,
+                ..Node::default()
+            };
+            let mut removed = 0;
+            for c in nodes[p].children.iter().flatten() {
+                merged.count += nodes[*c].count;
+                for i in 0..3 {
+                    merged.sum[i] += nodes[*c].sum[i];
+                }
+                removed += 1;
+            }
+            nodes[p] = merged;
+            leaves = leaves + 1 - removed;
+        }
+    }
+
+    // Collect the remaining leaves
+    let mut result = Vec::with_capacity(leaves);
+    let mut stack = vec![0];
+    while let Some(n) = stack.pop() {
+        let node = &nodes[n];
+        if node.leaf {
+            let count = node.count.max(1) as f64;
+            result.push(node.sum.map(|x| x / count));
+        } else {
+            stack.extend(node.children.iter().flatten());
+        }
+    }
+    result
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Reduce the image to at most `colors` colors using median cut, returning the palette and
+    /// the index of every pixel
+    pub fn quantize(&self, colors: usize) -> Indexed {
+        Quantizer::new(colors).quantize(self)
+    }
+}
+
+/// Compress palette indices using the variable length LZW coding used by GIF, the output is split
+/// into data sub-blocks
+fn lzw(indices: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    let mut next = end + 1;
+    let mut size = min_code_size as u32 + 1;
+    let mut table: std::collections::HashMap<(u16, u8), u16> = std::collections::HashMap::new();
+
+    let mut bytes = Vec::new();
+    let (mut acc, mut bits) = (0u32, 0u32);
+    let mut write = |code: u16, size: u32, bytes: &mut Vec<u8>| {
+        acc |= (code as u32) << bits;
+        bits += size;
+        while bits >= 8 {
+            bytes.push(acc as u8);
+            acc >>= 8;
+            bits -= 8;
+        }
+    };
+
+    write(clear, size, &mut bytes);
+    if let Some((first, rest)) = indices.split_first() {
+        let mut prefix = *first as u16;
+        for k in rest {
+            if let Some(code) = table.get(&(prefix, *k)) {
+                prefix = *code;
+                continue;
+            }
+
+            write(prefix, size, &mut bytes);
+            if next >= 1 << size && size < 12 {
+                size += 1;
+            }
+            if next < 4096 {
+                table.insert((prefix, *k), next);
+                next += 1;
+            } else {
+                write(clear, size, &mut bytes);
+                table.clear();
+                next = end + 1;
+                size = min_code_size as u32 + 1;
+            }
+            prefix = *k as u16;
+        }
+        write(prefix, size, &mut bytes);
+        if next >= 1 << size && size < 12 {
+            size += 1;
+        }
+    }
+    write(end, size, &mut bytes);
+    write(0, 7, &mut bytes);
+
+    let mut out = Vec::with_capacity(bytes.len() + bytes.len() / 255 + 2);
+    for block in bytes.chunks(255) {
+        out.push(block.len() as u8);
+        out.extend_from_slice(block);
+    }
+    out.push(0);
+    out
+}
+
+/// Encode indexed frames as an animated GIF, each frame uses its own palette and is displayed
+/// for `delay`. When `repeat` is set the animation loops forever.
+pub fn encode_gif(frames: &[Indexed], delay: std::time::Duration, repeat: bool) -> Vec<u8> {
+    let width = frames
+        .iter()
+        .map(|f| f.size.width)
+        .max()
+        .unwrap_or(0)
+        .min(65535);
+    let height = frames
+        .iter()
+        .map(|f| f.size.height)
+        .max()
+        .unwrap_or(0)
+        .min(65535);
+
+    let mut gif = b"GIF89a".to_vec();
+    gif.extend_from_slice(&(width as u16).to_le_bytes());
+    gif.extend_from_slice(&(height as u16).to_le_bytes());
+    gif.extend_from_slice(&[0, 0, 0]);
+    if repeat {
+        gif.extend_from_slice(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+    }
+
+    let delay = (delay.as_millis() / 10).min(65535) as u16;
+    for frame in frames {
+        let (w, h) = (frame.size.width.min(width), frame.size.height.min(height));
+
+        // Graphic control extension
+        gif.extend_from_slice(&[0x21, 0xf9, 4, 0]);
+        gif.extend_from_slice(&delay.to_le_bytes());
+        gif.extend_from_slice(&[0, 0]);
+
+        // Image descriptor with a local color table
+        let bits = (frame
+            .palette
+            .len()
+            .max(2)
+            .next_power_of_two()
+            .trailing_zeros() as u8)
+            .max(1);
+        gif.push(0x2c);
+        gif.extend_from_slice(&[0, 0, 0, 0]);
+        gif.extend_from_slice(&(w as u16).to_le_bytes());
+        gif.extend_from_slice(&(h as u16).to_le_bytes());
+        gif.push(0x80 | (bits - 1));
+        for i in 0..1usize << bits {
+            match frame.palette.colors.get(i) {
+                Some(c) => gif.extend((0..3).map(|j| (c[j].clamp(0.0, 1.0) * 255.0).round() as u8)),
+                None => gif.extend_from_slice(&[0, 0, 0]),
+            }
+        }
+
+        let min_code_size = bits.max(2);
+        let mut indices = Vec::with_capacity(w * h);
+        for y in 0..h {
+            indices
+                .extend_from_slice(&frame.indices[y * frame.size.width..y * frame.size.width + w]);
+        }
+        gif.push(min_code_size);
+        gif.extend(lzw(&indices, min_code_size));
+    }
+
+    gif.push(0x3b);
+    gif
+}
+
+/// Quantize each frame to 256 colors and write them to an animated GIF file
+pub fn save_gif<T: Type, C: Color>(
+    path: impl AsRef<std::path::Path>,
+    frames: &[Image<T, C>],
+    delay: std::time::Duration,
+) -> Result<(), Error> {
+    let quantizer = Quantizer::new(MAX_COLORS);
+    let frames: Vec<Indexed> = frames.iter().map(|f| quantizer.quantize(f)).collect();
+    std::fs::write(path, encode_gif(&frames, delay, true))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use quantize::{Indexed, Palette, QuantizeMethod, Quantizer};
+
+    #[test]
+    fn test_quantize() {
+        // Four flat quadrants
+        let colors = [
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [1.0, 1.0, 0.0],
+        ];
+        let mut image = Image::<u8, Rgb>::new((16, 16));
+        image.each_pixel_mut(|pt, px| {
+            let color = colors[(pt.y / 8) * 2 + pt.x / 8];
+            px.copy_from_slice(color);
+        });
+
+        for method in [QuantizeMethod::MedianCut, QuantizeMethod::Octree] {
+            let indexed = Quantizer::new(4).with_method(method).quantize(&image);
+            assert_eq!(indexed.palette.len(), 4);
+            assert_eq!(indexed.indices.len(), 256);
+            assert!(indexed.to_image::<u8, Rgb>() == image);
+
+            // Octree reduction merges whole subtrees, so it can end up with fewer colors
+            let indexed = Quantizer::new(2).with_method(method).quantize(&image);
+            assert!(!indexed.palette.is_empty() && indexed.palette.len() <= 2);
+        }
+
+        // Dithering preserves the average of a gradient
+        let mut gradient = Image::<f32, Rgb>::new((64, 8));
+        gradient.each_pixel_mut(|pt, px| {
+            px.fill(pt.x as f32 / 63.0);
+        });
+        let indexed = Quantizer::new(2).with_dither(true).quantize(&gradient);
+        let result = indexed.to_image::<f32, Rgb>();
+        let (mut sum, mut expected) = (0.0, 0.0);
+        for x in 16..32 {
+            for y in 0..8 {
+                sum += result.get_f((x, y), 0);
+                expected += gradient.get_f((x, y), 0);
+            }
+        }
+        assert!((sum - expected).abs() / 128.0 < 0.1, "{} {}", sum, expected);
+    }
+
+    /// Minimal GIF decoder for the output of `encode_gif`, returns the indices of each frame
+    fn decode_gif(gif: &[u8]) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+        let mut i = 13;
+        while gif[i] != 0x3b {
+            match gif[i] {
+                0x21 => {
+                    i += 2;
+                    while gif[i] != 0 {
+                        i += gif[i] as usize + 1;
+                    }
+                    i += 1;
+                }
+                0x2c => {
+                    let packed = gif[i + 9];
+                    i += 10 + 3 * (1 << ((packed & 7) + 1));
+                    let min = gif[i] as u32;
+                    i += 1;
+                    let mut data = Vec::new();
+                    while gif[i] != 0 {
+                        data.extend_from_slice(&gif[i + 1..i + 1 + gif[i] as usize]);
+                        i += gif[i] as usize + 1;
+                    }
+                    i += 1;
+
+                    let (clear, end) = (1u32 << min, (1u32 << min) + 1);
+                    let mut table: Vec<Vec<u8>> = Vec::new();
+                    let mut size = min + 1;
+                    let mut prev: Option<Vec<u8>> = None;
+                    let mut out = Vec::new();
+                    let mut pos = 0;
+                    loop {
+                        let mut code = 0;
+                        for b in 0..size {
+                            let bit = (data[(pos + b) as usize / 8] >> ((pos + b) % 8)) & 1;
+                            code |= (bit as u32) << b;
+                        }
+                        pos += size;
+                        if code == clear {
+                            table = (0..clear).map(|x| vec![x as u8]).collect();
+                            table.push(Vec::new());
+                            table.push(Vec::new());
+                            size = min + 1;
+                            prev = None;
+                            continue;
+                        }
+                        if code == end {
+                            break;
+                        }
+                        let entry = match (table.get(code as usize), &prev) {
+                            (Some(e), _) => e.clone(),
+                            (None, Some(p)) => {
+                                let mut e = p.clone();
+                                e.push(p[0]);
+                                e
+                            }
+                            (None, None) => panic!("invalid code"),
+                        };
+                        out.extend_from_slice(&entry);
+                        if let Some(mut p) = prev {
+                            p.push(entry[0]);
+                            table.push(p);
+                        }
+                        if table.len() == 1 << size && size < 12 {
+                            size += 1;
+                        }
+                        prev = Some(entry);
+                    }
+                    frames.push(out);
+                }
+                _ => panic!("unexpected block"),
+            }
+        }
+        frames
+    }
+
+    #[test]
+    fn test_encode_gif() {
+        let mut image = Image::<f32, Rgb>::new((37, 29));
+        image.each_pixel_mut(|pt, mut px| {
+            px[0] = ((pt.x * 7 + pt.y * 3) % 17) as f64 / 16.0;
+            px[1] = (pt.y % 5) as f64 / 4.0;
+            px[2] = ((pt.x / 3) % 2) as f64;
+        });
+        let frames = vec![
+            Quantizer::new(256).quantize(&image),
+            Quantizer::new(3).quantize(&image),
+        ];
+
+        let gif = quantize::encode_gif(&frames, std::time::Duration::from_millis(100), true);
+        assert!(gif.starts_with(b"GIF89a"));
+        let decoded = decode_gif(&gif);
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0], frames[0].indices);
+        assert_eq!(decoded[1], frames[1].indices);
+    }
+
+    #[test]
+    fn test_label_map() {
+        let mut labels = Image::<u16, Gray>::new((40, 4));
+        labels.for_each(|pt, mut px| px[0] = pt.x as u16 * 10);
+
+        let palette = Palette::classes(300);
+        assert_eq!(palette.len(), 300);
+        assert_eq!(palette.colors[0], Pixel::new());
+        for i in 1..20 {
+            assert!(palette.colors[i] != palette.colors[i + 1]);
+        }
+
+        let indexed = Indexed::from_labels(&labels, palette.clone());
+        assert_eq!(indexed.index((39, 2)), 390);
+        assert!(indexed.labels() == labels);
+
+        // Indices past the end of the palette wrap around
+        let display = indexed.to_image::<f64, Rgb>();
+        assert_eq!(display.get_pixel((39, 0)), palette.colors[90]);
+        assert_eq!(display.get_pixel((0, 0)), Pixel::new());
+
+        // Mapping the colors back recovers the labels within the palette
+        let mapped: Indexed<u16> = palette.map(&display);
+        assert_eq!(mapped.index((12, 1)), 120);
+        assert_eq!(mapped.index((35, 1)), 50);
+    }
+}
@@ -0,0 +1,301 @@
+use crate::*;
+
+/// Determines how a retouching brush transfers pixels from the source area
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RetouchMode {
+    /// Copy source pixels as-is, like a clone stamp
+    #[default]
+    Clone,
+
+    /// Reproduce the texture of the source while matching the color and brightness of the
+    /// destination, like a healing brush
+    Heal,
+}
+
+/// Clone stamp and healing brush. Each dab copies a disc of pixels from the source position,
+/// found by adding `offset` to the destination position, and the bounding box of every changed
+/// pixel is accumulated so callers only need to update the affected area, for example using
+/// `Window::draw_region`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RetouchBrush {
+    /// Brush mode
+    pub mode: RetouchMode,
+
+    /// Brush radius in pixels
+    pub radius: f64,
+
+    /// Fraction of the radius painted at full strength, the remainder fades out smoothly
+    pub hardness: f64,
+
+    /// Position of the source relative to the destination
+    pub offset: (isize, isize),
+
+    dirty: Option<Region>,
+}
+
+impl RetouchBrush {
+    /// Create a new brush
+    pub fn new(mode: RetouchMode, radius: f64) -> RetouchBrush {
+        RetouchBrush {
+            mode,
+            radius: radius.max(0.5),
+            hardness: 0.5,
+            offset: (0, 0),
+            dirty: None,
+        }
+    }
+
+    /// Create a clone stamp brush
+    pub fn clone_stamp(radius: f64) -> RetouchBrush {
+        RetouchBrush::new(RetouchMode::Clone, radius)
+    }
+
+    /// Create a healing brush
+    pub fn healing(radius: f64) -> RetouchBrush {
+        RetouchBrush::new(RetouchMode::Heal, radius)
+    }
+
+    /// Set hardness
+    pub fn with_hardness(mut self, hardness: f64) -> RetouchBrush {
+        self.hardness = hardness.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set source offset
+    pub fn with_offset(mut self, dx: isize, dy: isize) -> RetouchBrush {
+        self.offset = (dx, dy);
+        self
+    }
+
+    /// Set the offset so that painting at `dest` copies pixels from `source`
+    pub fn set_source(&mut self, source: impl Into<Point>, dest: impl Into<Point>) {
+        let (source, dest) = (source.into(), dest.into());
+        self.offset = (
+            source.x as isize - dest.x as isize,
+            source.y as isize - dest.y as isize,
+        );
+    }
+
+    /// Brush opacity at the given distance from the center
+    pub fn weight(&self, distance: f64) -> f64 {
+        let inner = self.radius * self.hardness;
+        if distance <= inner {
+            1.0
+        } else if distance >= self.radius {
+            0.0
+        } else {
+            let t = 1.0 - (distance - inner) / (self.radius - inner);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+
+    /// Area modified since the last call to `take_dirty`
+    pub fn dirty(&self) -> Option<Region> {
+        self.dirty
+    }
+
+    /// Return the modified area and reset it
+    pub fn take_dirty(&mut self) -> Option<Region> {
+        self.dirty.take()
+    }
+
+    /// Bounding box of a dab at `center`, expanded by `margin` and clipped to `size`
+    fn bounds(&self, center: Point, margin: isize, size: Size) -> Option<Region> {
+        let r = self.radius.ceil() as isize + margin;
+        let (cx, cy) = (center.x as isize, center.y as isize);
+        let x0 = (cx - r).max(0) as usize;
+        let y0 = (cy - r).max(0) as usize;
+        let x1 = ((cx + r + 1).max(0) as usize).min(size.width);
+        let y1 = ((cy + r + 1).max(0) as usize).min(size.height);
+        if x0 >= x1 || y0 >= y1 {
+            return None;
+        }
+        Some(Region::new(Point::new(x0, y0), Size::new(x1 - x0, y1 - y0)))
+    }
+
+    /// Paint a single dab at `center` using pixels from the image itself, returns the modified
+    /// area
+    pub fn dab<T: Type, C: Color>(
+        &mut self,
+        image: &mut Image<T, C>,
+        center: impl Into<Point>,
+    ) -> Option<Region> {
+        let center = center.into();
+        let roi = self.bounds(center, 1, image.size())?;
+
+        // Read from a copy of the source area so overlapping source and destination areas
+        // don't feed back into each other
+        let (dx, dy) = self.offset;
+        let x0 = (roi.origin.x as isize + dx).max(0) as usize;
+        let y0 = (roi.origin.y as isize + dy).max(0) as usize;
+        let x1 = ((roi.max_x() as isize + dx).max(0) as usize).min(image.width());
+        let y1 = ((roi.max_y() as isize + dy).max(0) as usize).min(image.height());
+        if x0 >= x1 || y0 >= y1 {
+            return None;
+        }
+        let origin = Point::new(x0, y0);
+        let copy = image.crop(Region::new(origin, Size::new(x1 - x0, y1 - y0)));
+
+        let offset = self.offset;
+        self.paint(image, center, roi, |pt, c| {
+            let pt = source_point(pt, offset, origin, copy.size())?;
+            Some(copy.get_f(pt, c))
+        })
+    }
+
+    /// Paint a single dab at `center` using pixels from `source`, returns the modified area
+    pub fn dab_from<T: Type, C: Color>(
+        &mut self,
+        image: &mut Image<T, C>,
+        source: &Image<T, C>,
+        center: impl Into<Point>,
+    ) -> Option<Region> {
+        let center = center.into();
+        let roi = self.bounds(center, 1, image.size())?;
+        let offset = self.offset;
+        self.paint(image, center, roi, |pt, c| {
+            let pt = source_point(pt, offset, Point::zero(), source.size())?;
+            Some(source.get_f(pt, c))
+        })
+    }
+
+    /// Paint dabs along the line from `a` to `b` using pixels from the image itself, returns the
+    /// area modified by the stroke
+    pub fn stroke<T: Type, C: Color>(
+        &mut self,
+        image: &mut Image<T, C>,
+        a: impl Into<Point>,
+        b: impl Into<Point>,
+    ) -> Option<Region> {
+        let (a, b) = (a.into(), b.into());
+        let spacing = (self.radius / 4.0).max(1.0);
+        let steps = (crate::geom::distance(a, b) / spacing).ceil().max(1.0) as usize;
+        let mut modified: Option<Region> = None;
+        for i in 0..=steps {
+            let t = i as f64 / steps as f64;
+            let pt = Point::new(
+                (a.x as f64 + (b.x as f64 - a.x as f64) * t).round() as usize,
+                (a.y as f64 + (b.y as f64 - a.y as f64) * t).round() as usize,
+            );
+            if let Some(roi) = self.dab(image, pt) {
+                modified = Some(modified.map_or(roi, |r| r.union(&roi)));
+            }
+        }
+        modified
+    }
+
+    /// Transfer pixels inside of `roi` from `source`, which maps destination positions to source
+    /// values and returns `None` for positions without a source pixel
+    fn paint<T: Type, C: Color>(
+        &mut self,
+        image: &mut Image<T, C>,
+        center: Point,
+        roi: Region,
+        source: impl Fn(Point, usize) -> Option<f64>,
+    ) -> Option<Region> {
+        // Source pixels for the dab area and the pixels that will be painted, the outermost
+        // pixels are never painted so they can act as the boundary when healing
+        let mut patch = Image::<T, C>::new(roi.size);
+        let mut mask = Image::<u8, Gray>::new(roi.size);
+        let mut changed: Option<Region> = None;
+        for y in 0..roi.size.height {
+            for x in 0..roi.size.width {
+                let pt = Point::new(roi.origin.x + x, roi.origin.y + y);
+                if source(pt, 0).is_none() {
+                    continue;
+                }
+                for c in 0..C::CHANNELS {
+                    patch.set_f((x, y), c, source(pt, c).unwrap_or_default());
+                }
+
+                let border =
+                    x == 0 || y == 0 || x + 1 == roi.size.width || y + 1 == roi.size.height;
+                if !border && self.weight(crate::geom::distance(pt, center)) > 0.0 {
+                    mask.set_f((x, y), 0, 1.0);
+                    let px = Region::new(pt, Size::new(1, 1));
+                    changed = Some(changed.map_or(px, |r| r.union(&px)));
+                }
+            }
+        }
+
+        let changed = changed?;
+        let result = match self.mode {
+            RetouchMode::Clone => patch,
+            RetouchMode::Heal => {
+                let mut healed = image.crop(roi);
+                healed.seamless_clone(&patch, &mask, Point::zero());
+                healed
+            }
+        };
+
+        mask.each_pixel(|pt, px| {
+            if px[0] < 0.5 {
+                return;
+            }
+            let dest = Point::new(roi.origin.x + pt.x, roi.origin.y + pt.y);
+            let w = self.weight(crate::geom::distance(dest, center));
+            for c in 0..C::CHANNELS {
+                let value = image.get_f(dest, c);
+                image.set_f(dest, c, value + (result.get_f(pt, c) - value) * w);
+            }
+        });
+
+        self.dirty = Some(self.dirty.map_or(changed, |d| d.union(&changed)));
+        Some(changed)
+    }
+}
+
+/// Maps `pt` to a position in a source image covering `size` pixels starting at `origin`
+fn source_point(pt: Point, offset: (isize, isize), origin: Point, size: Size) -> Option<Point> {
+    let x = pt.x as isize + offset.0 - origin.x as isize;
+    let y = pt.y as isize + offset.1 - origin.y as isize;
+    if x < 0 || y < 0 || x as usize >= size.width || y as usize >= size.height {
+        return None;
+    }
+    Some(Point::new(x as usize, y as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use retouch::RetouchBrush;
+
+    #[test]
+    fn test_retouch_brush() {
+        let mut image = Image::<f32, Gray>::new((40, 20));
+        image.for_each(|pt, mut px| px[0] = if pt.x < 20 { 0.2 } else { 0.8 });
+
+        // Clone stamp copies the source area
+        let mut brush = RetouchBrush::clone_stamp(4.0).with_hardness(1.0);
+        brush.set_source((30, 10), (10, 10));
+        let mut cloned = image.clone();
+        let roi = brush.dab(&mut cloned, (10, 10)).unwrap();
+        assert!((cloned.get((10, 10))[0] - 0.8).abs() < 1e-6);
+        assert!((cloned.get((2, 10))[0] - 0.2).abs() < 1e-6);
+        assert!(roi.contains(Point::new(10, 10)));
+        assert!(!roi.contains(Point::new(2, 10)));
+
+        // Dirty area accumulates until taken
+        brush.stroke(&mut cloned, (5, 5), (8, 14));
+        let dirty = brush.take_dirty().unwrap();
+        assert!(dirty.contains(Point::new(10, 10)) && dirty.contains(Point::new(5, 5)));
+        assert!(brush.dirty().is_none());
+
+        // Healing keeps the texture of the source but matches the destination brightness
+        let mut texture = image.clone();
+        texture.for_each(|pt, mut px| {
+            if pt.x >= 20 && (pt.x + pt.y) % 2 == 0 {
+                px[0] = 0.9;
+            }
+        });
+        let mut healed = texture.clone();
+        let mut brush = RetouchBrush::healing(4.0).with_offset(20, 0);
+        brush.dab(&mut healed, (10, 10));
+        let (a, b) = (healed.get((10, 10))[0], healed.get((11, 10))[0]);
+        assert!((a - b).abs() > 0.05);
+        assert!(a.min(b) < 0.5);
+        assert!(brush.dirty().is_some());
+    }
+}
@@ -0,0 +1,92 @@
+/// Small, deterministic xorshift random number generator used by algorithms that need
+/// randomized initialization
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// Create a new generator, the same seed always produces the same sequence
+    pub fn new(seed: u64) -> Rng {
+        // Mix the seed so small seeds still produce well distributed output
+        Rng(mix(seed).max(1))
+    }
+
+    /// Random 64-bit value
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    /// Random value in 0..1
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Random value in 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_f64() * n as f64) as usize % n.max(1)
+    }
+
+    /// Random value in min..max
+    pub fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+
+    /// Normally distributed value with a mean of 0 and a standard deviation of 1
+    pub fn normal(&mut self) -> f64 {
+        let u = 1.0 - self.next_f64();
+        let v = self.next_f64();
+        (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos()
+    }
+}
+
+/// SplitMix64 finalizer
+fn mix(x: u64) -> u64 {
+    let mut x = x ^ 0x9e37_79b9_7f4a_7c15;
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Seed for operations with random behavior, using the same seed always produces the same
+/// output regardless of the number of threads used
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Seed(pub u64);
+
+impl From<u64> for Seed {
+    fn from(seed: u64) -> Seed {
+        Seed(seed)
+    }
+}
+
+impl Seed {
+    /// Create a random number generator
+    pub fn rng(&self) -> Rng {
+        Rng::new(self.0)
+    }
+
+    /// Derive an independent seed, for example one per image when processing a batch
+    pub fn derive(&self, stream: u64) -> Seed {
+        Seed(mix(self.0 ^ mix(stream)))
+    }
+
+    /// Create a random number generator for a single position, this allows pixels to be
+    /// processed independently and in any order
+    pub fn at(&self, x: usize, y: usize) -> Rng {
+        Rng::new(self.derive(((y as u64) << 32) ^ x as u64).0)
+    }
+}
+
+/// Implemented by operations with random behavior so the seed can be set generically, for
+/// example by a dataset generation pipeline
+pub trait Seeded {
+    /// Get the seed
+    fn seed(&self) -> Seed;
+
+    /// Set the seed
+    fn set_seed(&mut self, seed: Seed);
+}
@@ -0,0 +1,158 @@
+use crate::solver::PoissonSystem;
+use crate::*;
+
+/// Determines which gradients `Image::seamless_clone_with` reproduces inside of the mask
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CloneMode {
+    /// Gradients of the source, the pasted content replaces the destination
+    #[default]
+    Normal,
+
+    /// The stronger of the source and destination gradients between each pair of pixels, which
+    /// keeps destination texture showing through flat parts of the source. Useful for pasting
+    /// objects with holes or text onto textured backgrounds.
+    Mixed,
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Paste the pixels of `source` selected by `mask` into this image at `offset` so that the
+    /// result blends without visible seams. Instead of copying values directly the gradients of
+    /// `source` are reproduced while the border of the masked region matches this image, by
+    /// solving the Poisson equation over the masked pixels. `mask` must be the same size as
+    /// `source`, pixels with a mask value of at least 0.5 are pasted. Masked pixels that would
+    /// land outside of this image are ignored.
+    pub fn seamless_clone<U: Type>(
+        &mut self,
+        source: &Image<T, C>,
+        mask: &Image<U, Gray>,
+        offset: impl Into<Point>,
+    ) {
+        self.seamless_clone_with(source, mask, offset, CloneMode::Normal)
+    }
+
+    /// Paste the pixels of `source` selected by `mask` into this image at `offset` by solving the
+    /// Poisson equation over the masked pixels, using the gradients selected by `mode`. See
+    /// `Image::seamless_clone`.
+    pub fn seamless_clone_with<U: Type>(
+        &mut self,
+        source: &Image<T, C>,
+        mask: &Image<U, Gray>,
+        offset: impl Into<Point>,
+        mode: CloneMode,
+    ) {
+        let offset = offset.into();
+        let source_size = source.size();
+        let to_source = |pt: Point| {
+            if pt.x < offset.x || pt.y < offset.y {
+                return None;
+            }
+            let src = Point::new(pt.x - offset.x, pt.y - offset.y);
+            if src.x < source_size.width && src.y < source_size.height {
+                Some(src)
+            } else {
+                None
+            }
+        };
+
+        let system = PoissonSystem::new(self.size(), |pt| match to_source(pt) {
+            Some(src) if src.x < mask.width() && src.y < mask.height() => mask.get_f(src, 0) >= 0.5,
+            _ => false,
+        });
+
+        if system.unknowns().is_empty() {
+            return;
+        }
+
+        for c in 0..C::CHANNELS {
+            let mut guidance = Vec::with_capacity(system.unknowns().len());
+            let mut initial = Vec::with_capacity(system.unknowns().len());
+            for pt in system.unknowns() {
+                let src = to_source(*pt).unwrap();
+                let value = source.get_f(src, c);
+                let current = self.get_f(*pt, c);
+                let mut sum = 0.0;
+                for q in system.neighbors(*pt) {
+                    // Neighbors outside of the source image have no gradient
+                    let gradient = match to_source(q) {
+                        Some(q) => value - source.get_f(q, c),
+                        None => 0.0,
+                    };
+                    sum += match mode {
+                        CloneMode::Normal => gradient,
+                        CloneMode::Mixed => {
+                            let existing = current - self.get_f(q, c);
+                            if existing.abs() > gradient.abs() {
+                                existing
+                            } else {
+                                gradient
+                            }
+                        }
+                    };
+                }
+                guidance.push(sum);
+                initial.push(current);
+            }
+
+            let result = system.solve(&guidance, |pt| self.get_f(pt, c), &initial, 2000, 1e-6);
+            for (pt, value) in system.unknowns().iter().zip(result) {
+                self.set_f(*pt, c, value.clamp(0.0, 1.0));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_seamless_clone() {
+        let mut dest = Image::<f32, Gray>::new((20, 16));
+        dest.for_each(|pt, mut px| px[0] = 0.3 + pt.x as f32 * 0.01);
+
+        let mut source = Image::<f32, Gray>::new((10, 8));
+        source.for_each(|pt, mut px| px[0] = pt.x as f32 * 0.01);
+
+        let mut mask = Image::<u8, Gray>::new((10, 8));
+        mask.for_each(|pt, mut px| {
+            if (2..8).contains(&pt.x) && (2..6).contains(&pt.y) {
+                px[0] = 255;
+            }
+        });
+
+        // The gradients of the source match the destination, so the result is unchanged
+        let mut result = dest.clone();
+        result.seamless_clone(&source, &mask, (5, 4));
+        for y in 0..16 {
+            for x in 0..20 {
+                assert!((result.get((x, y))[0] - dest.get((x, y))[0]).abs() < 1e-4);
+            }
+        }
+
+        // A constant source takes on the surrounding values
+        let mut constant = source.new_like();
+        constant.for_each(|_, mut px| px[0] = 1.0);
+        let mut result = dest.clone();
+        result.seamless_clone(&constant, &mask, (5, 4));
+        let inside = result.get((10, 7))[0];
+        assert!(inside > 0.3 && inside < 0.6);
+
+        // Mixed gradients keep the destination texture under a flat source
+        let mut stripes = dest.clone();
+        stripes.for_each(|pt, mut px| px[0] = if pt.x % 2 == 0 { 0.2 } else { 0.6 });
+        let mut normal = stripes.clone();
+        normal.seamless_clone(&constant, &mask, (5, 4));
+        let mut mixed = stripes.clone();
+        mixed.seamless_clone_with(&constant, &mask, (5, 4), CloneMode::Mixed);
+        let contrast =
+            |image: &Image<f32, Gray>| (image.get_f((10, 8), 0) - image.get_f((11, 8), 0)).abs();
+        assert!(contrast(&normal) < 0.1);
+        assert!((contrast(&mixed) - 0.4).abs() < 1e-3);
+
+        // Masked pixels outside of the destination are ignored
+        let mut result = dest.clone();
+        result.seamless_clone(&constant, &mask, (15, 12));
+        assert!((result.get((19, 15))[0] - dest.get((19, 15))[0]).abs() < 0.05);
+    }
+}
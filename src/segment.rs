@@ -0,0 +1,607 @@
+use crate::*;
+
+use crate::math::{det3, dist2, invert3};
+
+const COMPONENTS: usize = 5;
+const GAMMA: f64 = 50.0;
+const EPSILON: f64 = 1e-9;
+
+/// Per-pixel label used to seed and refine `GrabCut` segmentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Label {
+    /// Definitely background, never changed by the solver
+    Background,
+
+    /// Definitely foreground, never changed by the solver
+    Foreground,
+
+    /// Likely background, may be changed by the solver
+    ProbablyBackground,
+
+    /// Likely foreground, may be changed by the solver
+    ProbablyForeground,
+}
+
+impl Label {
+    /// Returns true for `Foreground` and `ProbablyForeground`
+    pub fn is_foreground(&self) -> bool {
+        matches!(self, Label::Foreground | Label::ProbablyForeground)
+    }
+
+    /// Returns true when the label was set by the user and is fixed
+    pub fn is_fixed(&self) -> bool {
+        matches!(self, Label::Foreground | Label::Background)
+    }
+}
+
+#[derive(Clone, Default)]
+struct Component {
+    weight: f64,
+    mean: [f64; 3],
+    inv_cov: [[f64; 3]; 3],
+    norm: f64,
+}
+
+/// Gaussian mixture model over RGB values
+#[derive(Clone, Default)]
+struct Gmm {
+    components: Vec<Component>,
+}
+
+impl Gmm {
+    fn component_likelihood(c: &Component, x: &[f64; 3]) -> f64 {
+        if c.weight <= 0.0 {
+            return 0.0;
+        }
+
+        let d = [x[0] - c.mean[0], x[1] - c.mean[1], x[2] - c.mean[2]];
+        let mut m = 0.0;
+        for i in 0..3 {
+            for j in 0..3 {
+                m += d[i] * c.inv_cov[i][j] * d[j];
+            }
+        }
+        c.norm * (-0.5 * m).exp()
+    }
+
+    fn likelihood(&self, x: &[f64; 3]) -> f64 {
+        self.components
+            .iter()
+            .map(|c| c.weight * Self::component_likelihood(c, x))
+            .sum()
+    }
+
+    fn most_likely(&self, x: &[f64; 3]) -> usize {
+        let mut best = 0;
+        let mut max = f64::MIN;
+        for (i, c) in self.components.iter().enumerate() {
+            let p = Self::component_likelihood(c, x);
+            if p > max {
+                max = p;
+                best = i;
+            }
+        }
+        best
+    }
+
+    /// Estimate model parameters from samples and their component assignment
+    fn learn(samples: &[[f64; 3]], assignment: &[usize]) -> Gmm {
+        let mut sums = [[0.0; 3]; COMPONENTS];
+        let mut prods = [[[0.0; 3]; 3]; COMPONENTS];
+        let mut counts = [0usize; COMPONENTS];
+
+        for (x, k) in samples.iter().zip(assignment) {
+            counts[*k] += 1;
+            for i in 0..3 {
+                sums[*k][i] += x[i];
+                for j in 0..3 {
+                    prods[*k][i][j] += x[i] * x[j];
+                }
+            }
+        }
+
+        let total = samples.len().max(1) as f64;
+        let components = (0..COMPONENTS)
+            .map(|k| {
+                let n = counts[k] as f64;
+                if counts[k] == 0 {
+                    return Component::default();
+                }
+
+                let mean = [sums[k][0] / n, sums[k][1] / n, sums[k][2] / n];
+                let mut cov = [[0.0; 3]; 3];
+                for i in 0..3 {
+                    for j in 0..3 {
+                        cov[i][j] = prods[k][i][j] / n - mean[i] * mean[j];
+                    }
+                    // Avoid singular matrices for flat regions
+                    cov[i][i] += 0.01;
+                }
+
+                let det = det3(&cov).max(EPSILON);
+                let inv_cov = invert3(&cov);
+                Component {
+                    weight: n / total,
+                    mean,
+                    inv_cov,
+                    norm: 1.0 / ((2.0 * std::f64::consts::PI).powi(3) * det).sqrt(),
+                }
+            })
+            .collect();
+
+        Gmm { components }
+    }
+
+    /// Initialize component assignment using a few rounds of k-means
+    fn initial_assignment(samples: &[[f64; 3]]) -> Vec<usize> {
+        let mut assignment = vec![0; samples.len()];
+        if samples.is_empty() {
+            return assignment;
+        }
+
+        let mut centers: Vec<[f64; 3]> = (0..COMPONENTS)
+            .map(|k| samples[k * (samples.len() - 1) / (COMPONENTS - 1).max(1)])
+            .collect();
+
+        for _ in 0..10 {
+            for (x, a) in samples.iter().zip(assignment.iter_mut()) {
+                *a = (0..COMPONENTS)
+                    .min_by(|i, j| dist2(x, &centers[*i]).total_cmp(&dist2(x, &centers[*j])))
+                    .unwrap_or(0);
+            }
+
+            let mut sums = [[0.0; 3]; COMPONENTS];
+            let mut counts = [0usize; COMPONENTS];
+            for (x, a) in samples.iter().zip(&assignment) {
+                counts[*a] += 1;
+                for i in 0..3 {
+                    sums[*a][i] += x[i];
+                }
+            }
+
+            for k in 0..COMPONENTS {
+                if counts[k] > 0 {
+                    let n = counts[k] as f64;
+                    centers[k] = [sums[k][0] / n, sums[k][1] / n, sums[k][2] / n];
+                }
+            }
+        }
+
+        assignment
+    }
+}
+
+/// Flow network solved using Dinic's algorithm
+pub(crate) struct FlowGraph {
+    head: Vec<usize>,
+    next: Vec<usize>,
+    to: Vec<usize>,
+    cap: Vec<f64>,
+}
+
+impl FlowGraph {
+    const NONE: usize = usize::MAX;
+
+    pub fn new(nodes: usize) -> FlowGraph {
+        FlowGraph {
+            head: vec![Self::NONE; nodes],
+            next: Vec::new(),
+            to: Vec::new(),
+            cap: Vec::new(),
+        }
+    }
+
+    /// Add an edge from `a` to `b` with capacity `ab` and a reverse edge with capacity `ba`
+    pub fn add_edge(&mut self, a: usize, b: usize, ab: f64, ba: f64) {
+        for (from, to, cap) in [(a, b, ab), (b, a, ba)] {
+            self.next.push(self.head[from]);
+            self.to.push(to);
+            self.cap.push(cap);
+            self.head[from] = self.to.len() - 1;
+        }
+    }
+
+    fn levels(&self, s: usize, t: usize) -> Option<Vec<usize>> {
+        let mut level = vec![Self::NONE; self.head.len()];
+        let mut queue = std::collections::VecDeque::new();
+        level[s] = 0;
+        queue.push_back(s);
+        while let Some(u) = queue.pop_front() {
+            let mut e = self.head[u];
+            while e != Self::NONE {
+                let v = self.to[e];
+                if self.cap[e] > EPSILON && level[v] == Self::NONE {
+                    level[v] = level[u] + 1;
+                    queue.push_back(v);
+                }
+                e = self.next[e];
+            }
+        }
+
+        if level[t] == Self::NONE {
+            None
+        } else {
+            Some(level)
+        }
+    }
+
+    /// Compute the maximum flow from `s` to `t`
+    pub fn max_flow(&mut self, s: usize, t: usize) -> f64 {
+        let mut total = 0.0;
+        while let Some(mut level) = self.levels(s, t) {
+            let mut it = self.head.clone();
+            let mut path: Vec<usize> = Vec::new();
+            let mut u = s;
+            loop {
+                if u == t {
+                    let f = path
+                        .iter()
+                        .map(|e| self.cap[*e])
+                        .fold(f64::INFINITY, f64::min);
+                    for e in &path {
+                        self.cap[*e] -= f;
+                        self.cap[*e ^ 1] += f;
+                    }
+                    total += f;
+                    path.clear();
+                    u = s;
+                    continue;
+                }
+
+                let mut advanced = false;
+                while it[u] != Self::NONE {
+                    let e = it[u];
+                    let v = self.to[e];
+                    if self.cap[e] > EPSILON && level[v] == level[u].wrapping_add(1) {
+                        path.push(e);
+                        u = v;
+                        advanced = true;
+                        break;
+                    }
+                    it[u] = self.next[e];
+                }
+
+                if !advanced {
+                    if u == s {
+                        break;
+                    }
+
+                    // Dead end, remove the node from the level graph and retreat
+                    level[u] = Self::NONE;
+                    let e = path.pop().unwrap();
+                    u = self.to[e ^ 1];
+                    it[u] = self.next[it[u]];
+                }
+            }
+        }
+        total
+    }
+
+    /// Nodes reachable from `s` in the residual graph, after `max_flow` these make up the source
+    /// side of the minimum cut
+    pub fn source_side(&self, s: usize) -> Vec<bool> {
+        let mut visited = vec![false; self.head.len()];
+        let mut stack = vec![s];
+        visited[s] = true;
+        while let Some(u) = stack.pop() {
+            let mut e = self.head[u];
+            while e != Self::NONE {
+                let v = self.to[e];
+                if self.cap[e] > EPSILON && !visited[v] {
+                    visited[v] = true;
+                    stack.push(v);
+                }
+                e = self.next[e];
+            }
+        }
+        visited
+    }
+}
+
+/// Iterative graph-cut foreground extraction
+///
+/// Color models for the foreground and background are estimated from the labeled pixels and the
+/// segmentation is refined by computing a minimum cut each iteration. Labels can be updated
+/// between iterations to correct the result interactively.
+pub struct GrabCut {
+    size: Size,
+    pixels: Vec<[f64; 3]>,
+    labels: Vec<Label>,
+    beta: f64,
+}
+
+impl GrabCut {
+    fn init<T: Type, C: Color>(image: &Image<T, C>, label: Label) -> GrabCut {
+        let mut pixels = vec![[0.0; 3]; image.width() * image.height()];
+        image.each_pixel(|pt, px| {
+            let rgb: Pixel<Rgb> = px.convert();
+            pixels[pt.y * image.width() + pt.x] = [rgb[0] * 255., rgb[1] * 255., rgb[2] * 255.];
+        });
+
+        let mut grabcut = GrabCut {
+            size: image.size(),
+            labels: vec![label; pixels.len()],
+            pixels,
+            beta: 0.0,
+        };
+        grabcut.beta = grabcut.compute_beta();
+        grabcut
+    }
+
+    /// Create a new `GrabCut` where everything outside of `rect` is background and everything
+    /// inside is probably foreground
+    pub fn new_with_rect<T: Type, C: Color>(image: &Image<T, C>, rect: Region) -> GrabCut {
+        let mut grabcut = Self::init(image, Label::Background);
+        grabcut.mark_region(rect, Label::ProbablyForeground);
+        grabcut
+    }
+
+    /// Create a new `GrabCut` where every pixel is probably background, scribbles should be added
+    /// using `mark`, `mark_region` or `mark_mask` before calling `iterate`
+    pub fn new<T: Type, C: Color>(image: &Image<T, C>) -> GrabCut {
+        Self::init(image, Label::ProbablyBackground)
+    }
+
+    /// Image size
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// Get label at the given point
+    pub fn label(&self, pt: impl Into<Point>) -> Label {
+        let pt = pt.into();
+        self.labels[pt.y * self.size.width + pt.x]
+    }
+
+    /// Set the label of every pixel in a disc with the given radius, this can be used to add
+    /// brush strokes
+    pub fn mark(&mut self, center: impl Into<Point>, radius: usize, label: Label) {
+        let c = center.into();
+        let r = radius as isize;
+        for dy in -r..=r {
+            for dx in -r..=r {
+                if dx * dx + dy * dy > r * r {
+                    continue;
+                }
+
+                let (x, y) = (c.x as isize + dx, c.y as isize + dy);
+                if x >= 0
+                    && y >= 0
+                    && (x as usize) < self.size.width
+                    && (y as usize) < self.size.height
+                {
+                    self.labels[y as usize * self.size.width + x as usize] = label;
+                }
+            }
+        }
+    }
+
+    /// Set the label for every pixel in a region
+    pub fn mark_region(&mut self, region: Region, label: Label) {
+        let x1 = region.max_x().min(self.size.width);
+        let y1 = region.max_y().min(self.size.height);
+        for y in region.min_y()..y1 {
+            for x in region.min_x()..x1 {
+                self.labels[y * self.size.width + x] = label;
+            }
+        }
+    }
+
+    /// Set the label for every pixel that is set in a binary mask
+    pub fn mark_mask<T: Type>(&mut self, mask: &Image<T, Gray>, label: Label) {
+        mask.each_pixel(|pt, px| {
+            if px[0] >= 0.5 && pt.x < self.size.width && pt.y < self.size.height {
+                self.labels[pt.y * self.size.width + pt.x] = label;
+            }
+        });
+    }
+
+    fn neighbors(&self) -> impl '_ + Iterator<Item = (usize, usize, f64)> {
+        let (w, h) = (self.size.width, self.size.height);
+        let offsets: [(isize, isize, f64); 4] = [
+            (1, 0, 1.0),
+            (0, 1, 1.0),
+            (1, 1, std::f64::consts::SQRT_2),
+            (-1, 1, std::f64::consts::SQRT_2),
+        ];
+        (0..w * h).flat_map(move |i| {
+            let (x, y) = ((i % w) as isize, (i / w) as isize);
+            offsets.into_iter().filter_map(move |(dx, dy, d)| {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || nx as usize >= w || ny as usize >= h {
+                    return None;
+                }
+                Some((i, ny as usize * w + nx as usize, d))
+            })
+        })
+    }
+
+    fn compute_beta(&self) -> f64 {
+        let mut sum = 0.0;
+        let mut count = 0usize;
+        for (a, b, _) in self.neighbors() {
+            sum += dist2(&self.pixels[a], &self.pixels[b]);
+            count += 1;
+        }
+
+        if sum <= EPSILON {
+            0.0
+        } else {
+            1.0 / (2.0 * sum / count as f64)
+        }
+    }
+
+    fn learn_models(&self) -> (Gmm, Gmm) {
+        let (fg, bg): (Vec<_>, Vec<_>) = self
+            .pixels
+            .iter()
+            .zip(&self.labels)
+            .partition(|(_, l)| l.is_foreground());
+        let fg: Vec<[f64; 3]> = fg.into_iter().map(|(p, _)| *p).collect();
+        let bg: Vec<[f64; 3]> = bg.into_iter().map(|(p, _)| *p).collect();
+
+        let learn = |samples: &[[f64; 3]]| {
+            let mut sorted = samples.to_vec();
+            sorted.sort_by(|a, b| (a[0] + a[1] + a[2]).total_cmp(&(b[0] + b[1] + b[2])));
+            let assignment = Gmm::initial_assignment(&sorted);
+            let gmm = Gmm::learn(&sorted, &assignment);
+
+            // Refine the initial model using maximum likelihood component assignment
+            let assignment: Vec<usize> = sorted.iter().map(|x| gmm.most_likely(x)).collect();
+            Gmm::learn(&sorted, &assignment)
+        };
+
+        (learn(&fg), learn(&bg))
+    }
+
+    /// Run `n` iterations of model estimation and graph cut, returns the number of pixels with
+    /// changed labels in the last iteration
+    pub fn iterate(&mut self, n: usize) -> usize {
+        let mut changed = 0;
+        for _ in 0..n {
+            changed = self.step();
+            if changed == 0 {
+                break;
+            }
+        }
+        changed
+    }
+
+    fn step(&mut self) -> usize {
+        let (fg, bg) = self.learn_models();
+        let count = self.pixels.len();
+        let (s, t) = (count, count + 1);
+        let mut graph = FlowGraph::new(count + 2);
+
+        let mut neighbor_sum = vec![0.0; count];
+        let mut edges = Vec::with_capacity(count * 4);
+        for (a, b, d) in self.neighbors() {
+            let w = GAMMA / d * (-self.beta * dist2(&self.pixels[a], &self.pixels[b])).exp();
+            neighbor_sum[a] += w;
+            neighbor_sum[b] += w;
+            edges.push((a, b, w));
+        }
+        let max_weight = 1.0 + neighbor_sum.iter().copied().fold(0.0, f64::max);
+
+        for i in 0..count {
+            let (to_source, to_sink) = match self.labels[i] {
+                Label::Foreground => (max_weight, 0.0),
+                Label::Background => (0.0, max_weight),
+                _ => {
+                    let x = &self.pixels[i];
+                    let fg = -(fg.likelihood(x).max(f64::MIN_POSITIVE)).ln();
+                    let bg = -(bg.likelihood(x).max(f64::MIN_POSITIVE)).ln();
+                    (bg, fg)
+                }
+            };
+            graph.add_edge(s, i, to_source, 0.0);
+            graph.add_edge(i, t, to_sink, 0.0);
+        }
+
+        for (a, b, w) in edges {
+            graph.add_edge(a, b, w, w);
+        }
+
+        graph.max_flow(s, t);
+        let source = graph.source_side(s);
+
+        let mut changed = 0;
+        for (i, label) in self.labels.iter_mut().enumerate() {
+            if label.is_fixed() {
+                continue;
+            }
+
+            let new = if source[i] {
+                Label::ProbablyForeground
+            } else {
+                Label::ProbablyBackground
+            };
+
+            if new != *label {
+                *label = new;
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    /// Binary foreground mask
+    pub fn mask(&self) -> Image<u8, Gray> {
+        let mut dest = Image::new(self.size);
+        dest.for_each(|pt, mut px| {
+            if self.labels[pt.y * self.size.width + pt.x].is_foreground() {
+                px[0] = u8::MAX;
+            }
+        });
+        dest
+    }
+
+    /// Alpha matte, the hard segmentation edge is softened using a box filter with the given
+    /// radius, a radius of 0 returns the binary mask as a float image
+    pub fn matte(&self, radius: usize) -> Image<f32, Gray> {
+        let (w, h) = (self.size.width, self.size.height);
+        let mask: Vec<f64> = self
+            .labels
+            .iter()
+            .map(|l| if l.is_foreground() { 1.0 } else { 0.0 })
+            .collect();
+
+        let r = radius as isize;
+        let mut dest = Image::new(self.size);
+        dest.for_each(|pt, mut px| {
+            let mut sum = 0.0;
+            let mut n = 0.0;
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    let (x, y) = (pt.x as isize + dx, pt.y as isize + dy);
+                    if x >= 0 && y >= 0 && (x as usize) < w && (y as usize) < h {
+                        sum += mask[y as usize * w + x as usize];
+                        n += 1.0;
+                    }
+                }
+            }
+            px[0] = (sum / n) as f32;
+        });
+        dest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use segment::*;
+
+    #[test]
+    fn test_grabcut_rect() {
+        let mut image = Image::<f32, Rgb>::new((40, 30));
+        image.for_each(|pt, mut px| {
+            let inside = pt.x >= 12 && pt.x < 28 && pt.y >= 8 && pt.y < 22;
+            let v = ((pt.x * 7 + pt.y * 13) % 5) as f32 * 0.02;
+            if inside {
+                px[0] = 0.9 - v;
+                px[1] = 0.2 + v;
+                px[2] = 0.1;
+            } else {
+                px[0] = 0.1 + v;
+                px[1] = 0.3;
+                px[2] = 0.8 - v;
+            }
+        });
+
+        let mut grabcut =
+            GrabCut::new_with_rect(&image, Region::new(Point::new(5, 3), Size::new(30, 24)));
+        grabcut.iterate(5);
+
+        assert!(grabcut.label((20, 15)).is_foreground());
+        assert!(!grabcut.label((7, 5)).is_foreground());
+        assert!(!grabcut.label((0, 0)).is_foreground());
+
+        let mask = grabcut.mask();
+        let regions = contour::find_regions(&mask);
+        assert_eq!(
+            regions,
+            vec![Region::new(Point::new(12, 8), Size::new(16, 14))]
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use crate::*;
+
+/// Marks a pixel that isn't part of the system
+const FIXED: usize = usize::MAX;
+
+/// Discrete Poisson equation over a subset of the pixels in a grid. Each unknown pixel is
+/// coupled to its 4-connected neighbors, neighbors outside of the grid are ignored and neighbors
+/// that aren't unknowns act as fixed boundary values.
+///
+/// For every unknown pixel `p` with neighbors `N(p)` the solution `f` satisfies
+/// `sum(w(p, q) * (f(p) - f(q)) for q in N(p)) = guidance(p)`, which is the Laplace equation when
+/// the guidance is zero. Edge weights `w` default to 1 and can be set using `with_weights` to
+/// make the solution follow image edges. `with_regularization` adds `lambda * f(p)` to the left
+/// side, which pulls the solution towards zero far away from the boundary.
+#[derive(Debug, Clone)]
+pub struct PoissonSystem {
+    size: Size,
+    index: Vec<usize>,
+    unknowns: Vec<Point>,
+
+    /// Weights of the edges to the right and bottom neighbors of each pixel
+    weights: Option<(Vec<f64>, Vec<f64>)>,
+
+    regularization: f64,
+}
+
+impl PoissonSystem {
+    /// Create a system where `unknown` selects the pixels to solve for
+    pub fn new(size: Size, unknown: impl Fn(Point) -> bool) -> PoissonSystem {
+        let mut index = vec![FIXED; size.width * size.height];
+        let mut unknowns = Vec::new();
+        for y in 0..size.height {
+            for x in 0..size.width {
+                let pt = Point::new(x, y);
+                if unknown(pt) {
+                    index[y * size.width + x] = unknowns.len();
+                    unknowns.push(pt);
+                }
+            }
+        }
+        PoissonSystem {
+            size,
+            index,
+            unknowns,
+            weights: None,
+            regularization: 0.0,
+        }
+    }
+
+    /// Set the weight of the edge between every pair of neighboring pixels, weights should be
+    /// positive and symmetric
+    pub fn with_weights(mut self, weight: impl Fn(Point, Point) -> f64) -> PoissonSystem {
+        let (w, h) = (self.size.width, self.size.height);
+        let mut right = vec![0.0; w * h];
+        let mut down = vec![0.0; w * h];
+        for y in 0..h {
+            for x in 0..w {
+                let pt = Point::new(x, y);
+                if x + 1 < w {
+                    right[y * w + x] = weight(pt, Point::new(x + 1, y));
+                }
+                if y + 1 < h {
+                    down[y * w + x] = weight(pt, Point::new(x, y + 1));
+                }
+            }
+        }
+        self.weights = Some((right, down));
+        self
+    }
+
+    /// Add a regularization term with the given weight, this also makes the system solvable when
+    /// there are no fixed pixels
+    pub fn with_regularization(mut self, lambda: f64) -> PoissonSystem {
+        self.regularization = lambda.max(0.0);
+        self
+    }
+
+    /// Weight of the edge between two neighboring pixels
+    pub fn weight(&self, a: Point, b: Point) -> f64 {
+        match &self.weights {
+            None => 1.0,
+            Some((right, _)) if a.y == b.y => right[a.y * self.size.width + a.x.min(b.x)],
+            Some((_, down)) => down[a.y.min(b.y) * self.size.width + a.x],
+        }
+    }
+
+    /// Grid size
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// Unknown pixels, in the order used by `solve`
+    pub fn unknowns(&self) -> &[Point] {
+        &self.unknowns
+    }
+
+    /// In-bounds 4-connected neighbors of a pixel
+    pub fn neighbors(&self, pt: Point) -> impl Iterator<Item = Point> {
+        let (w, h) = (self.size.width, self.size.height);
+        [
+            (pt.x > 0).then(|| Point::new(pt.x - 1, pt.y)),
+            (pt.x + 1 < w).then(|| Point::new(pt.x + 1, pt.y)),
+            (pt.y > 0).then(|| Point::new(pt.x, pt.y - 1)),
+            (pt.y + 1 < h).then(|| Point::new(pt.x, pt.y + 1)),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    fn index(&self, pt: Point) -> usize {
+        self.index[pt.y * self.size.width + pt.x]
+    }
+
+    /// Multiply by the system matrix, the diagonal is the sum of the edge weights plus the
+    /// regularization weight and every unknown neighbor contributes its negated edge weight
+    fn apply(&self, x: &[f64], out: &mut [f64]) {
+        for (i, pt) in self.unknowns.iter().enumerate() {
+            let mut sum = self.regularization * x[i];
+            for q in self.neighbors(*pt) {
+                let w = self.weight(*pt, q);
+                sum += w * x[i];
+                let j = self.index(q);
+                if j != FIXED {
+                    sum -= w * x[j];
+                }
+            }
+            out[i] = sum;
+        }
+    }
+
+    /// Solve for the unknowns using conjugate gradients. `guidance[i]` is the sum of the guidance
+    /// gradients `g(p) - g(q)` over the neighbors `q` of unknown `i`, `boundary` provides the
+    /// values of fixed pixels and `initial` is the starting estimate.
+    pub fn solve(
+        &self,
+        guidance: &[f64],
+        boundary: impl Fn(Point) -> f64,
+        initial: &[f64],
+        max_iterations: usize,
+        tolerance: f64,
+    ) -> Vec<f64> {
+        let n = self.unknowns.len();
+        let mut b = guidance.to_vec();
+        for (i, pt) in self.unknowns.iter().enumerate() {
+            for q in self.neighbors(*pt) {
+                if self.index(q) == FIXED {
+                    b[i] += self.weight(*pt, q) * boundary(q);
+                }
+            }
+        }
+
+        let mut x = initial.to_vec();
+        let mut ax = vec![0.0; n];
+        self.apply(&x, &mut ax);
+        let mut r: Vec<f64> = b.iter().zip(&ax).map(|(b, ax)| b - ax).collect();
+        let mut p = r.clone();
+        let mut rr: f64 = r.iter().map(|x| x * x).sum();
+        let threshold = tolerance * tolerance * b.iter().map(|x| x * x).sum::<f64>().max(1e-12);
+
+        let mut ap = vec![0.0; n];
+        for _ in 0..max_iterations {
+            if rr <= threshold {
+                break;
+            }
+
+            self.apply(&p, &mut ap);
+            let pap: f64 = p.iter().zip(&ap).map(|(a, b)| a * b).sum();
+            if pap.abs() < f64::MIN_POSITIVE {
+                break;
+            }
+
+            let alpha = rr / pap;
+            for i in 0..n {
+                x[i] += alpha * p[i];
+                r[i] -= alpha * ap[i];
+            }
+
+            let next: f64 = r.iter().map(|x| x * x).sum();
+            let beta = next / rr;
+            rr = next;
+            for i in 0..n {
+                p[i] = r[i] + beta * p[i];
+            }
+        }
+
+        x
+    }
+}
+
+/// Smoothly interpolate sparse values over an image. Pixels where `mask` is at least 0.5 keep
+/// their value from `values` and every other pixel is filled with the solution of the Laplace
+/// equation, the smoothest surface passing through the constraints. `mask` must be the same size
+/// as `values`.
+pub fn interpolate<T: Type, C: Color, U: Type>(
+    values: &Image<T, C>,
+    mask: &Image<U, Gray>,
+) -> Image<f32, C> {
+    let system = PoissonSystem::new(values.size(), |pt| mask.get_f(pt, 0) < 0.5);
+    let mut dest = values.convert::<f32, C>();
+    if system.unknowns().is_empty() {
+        return dest;
+    }
+
+    let guidance = vec![0.0; system.unknowns().len()];
+    for c in 0..C::CHANNELS {
+        // Start from the mean of the constraints to speed up convergence
+        let (mut sum, mut count) = (0.0, 0usize);
+        values.each_pixel(|pt, px| {
+            if mask.get_f(pt, 0) >= 0.5 {
+                sum += px[c];
+                count += 1;
+            }
+        });
+        let initial = vec![sum / count.max(1) as f64; system.unknowns().len()];
+
+        let result = system.solve(
+            &guidance,
+            |pt| values.get_f(pt, c),
+            &initial,
+            values.width() * values.height(),
+            1e-6,
+        );
+        for (pt, value) in system.unknowns().iter().zip(result) {
+            dest.set_f(*pt, c, value);
+        }
+    }
+    dest
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_solver_interpolate() {
+        let mut values = Image::<f32, Gray>::new((16, 4));
+        let mut mask = Image::<u8, Gray>::new((16, 4));
+        for y in 0..4 {
+            values.set((0, y), [0.0]);
+            values.set((15, y), [1.0]);
+            mask.set((0, y), [255]);
+            mask.set((15, y), [255]);
+        }
+
+        // A linear ramp between the two constrained columns
+        let result = solver::interpolate(&values, &mask);
+        for y in 0..4 {
+            for x in 0..16 {
+                assert!((result.get((x, y))[0] - x as f32 / 15.0).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_solver_poisson() {
+        // Constant guidance of -2 in 1D produces a parabola
+        let system = solver::PoissonSystem::new(Size::new(9, 1), |pt| pt.x > 0 && pt.x < 8);
+        assert_eq!(system.unknowns().len(), 7);
+        let guidance = vec![-2.0; 7];
+        let result = system.solve(&guidance, |_| 0.0, &[0.0; 7], 100, 1e-9);
+        for (pt, value) in system.unknowns().iter().zip(result) {
+            let x = pt.x as f64;
+            assert!((value - x * (x - 8.0)).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,463 @@
+use crate::*;
+
+/// Motion model used to register images in a stack
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Alignment {
+    /// Images are already aligned
+    None,
+
+    /// Translation only, estimated using phase correlation
+    #[default]
+    Translation,
+
+    /// Affine transform, the phase correlation estimate is refined by minimizing the intensity
+    /// difference using Gauss-Newton iterations
+    Affine,
+}
+
+/// Method used to combine the values of each pixel across a stack
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Combine {
+    /// Average of all values
+    #[default]
+    Mean,
+
+    /// Median value, robust to outliers such as satellite trails or hot pixels
+    Median,
+
+    /// Average after repeatedly discarding values more than `sigma` standard deviations from
+    /// the mean
+    SigmaClip {
+        /// Rejection threshold in standard deviations
+        sigma: f64,
+
+        /// Maximum number of rejection passes
+        iterations: usize,
+    },
+}
+
+impl Combine {
+    /// Combine a set of values, `values` is reordered
+    pub fn apply(&self, values: &mut [f64]) -> f64 {
+        if values.is_empty() {
+            return 0.0;
+        }
+
+        let mean = |v: &[f64]| v.iter().sum::<f64>() / v.len() as f64;
+        match *self {
+            Combine::Mean => mean(values),
+            Combine::Median => {
+                values.sort_by(|a, b| a.total_cmp(b));
+                let n = values.len();
+                if n % 2 == 1 {
+                    values[n / 2]
+                } else {
+                    (values[n / 2 - 1] + values[n / 2]) / 2.0
+                }
+            }
+            Combine::SigmaClip { sigma, iterations } => {
+                let mut len = values.len();
+                for _ in 0..iterations {
+                    let m = mean(&values[..len]);
+                    let std_dev = (values[..len].iter().map(|v| (v - m) * (v - m)).sum::<f64>()
+                        / len as f64)
+                        .sqrt();
+                    let limit = sigma * std_dev;
+
+                    // Move the values that are kept to the front
+                    let mut kept = 0;
+                    for i in 0..len {
+                        if (values[i] - m).abs() <= limit {
+                            values.swap(kept, i);
+                            kept += 1;
+                        }
+                    }
+                    if kept == len || kept == 0 {
+                        break;
+                    }
+                    len = kept;
+                }
+                mean(&values[..len])
+            }
+        }
+    }
+}
+
+/// Registers a set of images against a reference frame and combines them into a single image
+/// with reduced noise, for example for astrophotography or low-light bursts
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stack {
+    /// Motion model
+    pub alignment: Alignment,
+
+    /// Combination method
+    pub combine: Combine,
+
+    /// Index of the image the others are aligned to
+    pub reference: usize,
+
+    /// Interpolation used to resample aligned images
+    pub interpolation: Interpolation,
+}
+
+impl Default for Stack {
+    fn default() -> Stack {
+        Stack {
+            alignment: Alignment::default(),
+            combine: Combine::default(),
+            reference: 0,
+            interpolation: Interpolation::Bilinear,
+        }
+    }
+}
+
+impl Stack {
+    /// Create a new `Stack` using translation alignment and mean combination
+    pub fn new() -> Stack {
+        Stack::default()
+    }
+
+    /// Set the motion model
+    pub fn with_alignment(mut self, alignment: Alignment) -> Stack {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Set the combination method
+    pub fn with_combine(mut self, combine: Combine) -> Stack {
+        self.combine = combine;
+        self
+    }
+
+    /// Set the reference image
+    pub fn with_reference(mut self, reference: usize) -> Stack {
+        self.reference = reference;
+        self
+    }
+
+    /// Set the interpolation method
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Stack {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Estimate the transform of each image, mapping coordinates in the reference image to
+    /// coordinates in that image
+    pub fn align<T: Type, C: Color>(&self, images: &[Image<T, C>]) -> Vec<Transform> {
+        let reference = match images.get(self.reference) {
+            Some(image) => image,
+            None => return vec![Transform::identity(); images.len()],
+        };
+
+        // Stacked frames are usually noisy, smoothing them gives more reliable gradients for the
+        // refinement step
+        let smooth = |image: &Image<T, C>| {
+            image
+                .convert::<f64, Gray>()
+                .run::<f64, Gray>(filter::gaussian_blur(1.0), None)
+        };
+        let gray = smooth(reference);
+
+        images
+            .iter()
+            .enumerate()
+            .map(|(i, image)| {
+                if i == self.reference || self.alignment == Alignment::None {
+                    return Transform::identity();
+                }
+                let (dx, dy, _) = analysis::phase_correlate(reference, image);
+                let affine = self.alignment == Alignment::Affine;
+                refine(
+                    &gray,
+                    &smooth(image),
+                    Transform::translation(dx, dy),
+                    affine,
+                )
+            })
+            .collect()
+    }
+
+    /// Combine images using known transforms, as returned by `Stack::align`. The output has
+    /// the size of the reference image, pixels that fall outside of an image are excluded.
+    pub fn combine<T: Type, C: Color>(
+        &self,
+        images: &[Image<T, C>],
+        transforms: &[Transform],
+    ) -> Image<T, C> {
+        let size = match images.get(self.reference).or(images.first()) {
+            Some(image) => image.size(),
+            None => return Image::new((0, 0)),
+        };
+
+        let mut dest = Image::new(size);
+        dest.for_each(|pt, mut px| {
+            let mut samples: Vec<Pixel<C>> = Vec::with_capacity(images.len());
+            for (image, transform) in images.iter().zip(transforms) {
+                let p = transform.transform_point(euclid::Point2D::new(pt.x as f64, pt.y as f64));
+                let (w, h) = (image.width() as f64, image.height() as f64);
+                if p.x >= -0.5 && p.y >= -0.5 && p.x <= w - 0.5 && p.y <= h - 0.5 {
+                    samples.push(image.sample(p.x, p.y, self.interpolation));
+                }
+            }
+            if samples.is_empty() {
+                return;
+            }
+
+            let mut values = vec![0.0; samples.len()];
+            let mut out = Pixel::<C>::new();
+            for c in 0..C::CHANNELS {
+                for (v, s) in values.iter_mut().zip(&samples) {
+                    *v = s[c];
+                }
+                out[c] = self.combine.apply(&mut values);
+            }
+            out.copy_to_slice(&mut px);
+        });
+        dest
+    }
+
+    /// Align and combine a set of images
+    pub fn run<T: Type, C: Color>(&self, images: &[Image<T, C>]) -> Image<T, C> {
+        let transforms = self.align(images);
+        self.combine(images, &transforms)
+    }
+}
+
+/// Solve a small dense linear system using Gaussian elimination with partial pivoting, `a` is
+/// an `n` x `n` matrix in row-major order
+fn solve(mut a: Vec<f64>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot =
+            (col..n).max_by(|i, j| a[i * n + col].abs().total_cmp(&a[j * n + col].abs()))?;
+        if a[pivot * n + col].abs() < 1e-12 {
+            return None;
+        }
+        for k in 0..n {
+            a.swap(col * n + k, pivot * n + k);
+        }
+        b.swap(col, pivot);
+        for row in col + 1..n {
+            let f = a[row * n + col] / a[col * n + col];
+            for k in col..n {
+                a[row * n + k] -= f * a[col * n + k];
+            }
+            b[row] -= f * b[col];
+        }
+    }
+
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| a[row * n + k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row * n + row];
+    }
+    Some(x)
+}
+
+/// Refine an initial transform mapping `reference` coordinates to `image` coordinates using
+/// forward additive Gauss-Newton (Lucas-Kanade) image alignment. Only the translation is
+/// updated unless `affine` is true.
+fn refine(
+    reference: &Image<f64, Gray>,
+    image: &Image<f64, Gray>,
+    initial: Transform,
+    affine: bool,
+) -> Transform {
+    let (w, h) = (image.width(), image.height());
+    let data = image.data();
+    let at = |x: usize, y: usize| data[y * w + x];
+
+    // Central difference gradients of the moving image
+    let mut gx = Image::<f64, Gray>::new(image.size());
+    let mut gy = Image::<f64, Gray>::new(image.size());
+    for y in 0..h {
+        for x in 0..w {
+            let i = y * w + x;
+            gx.data_mut()[i] = (at((x + 1).min(w - 1), y) - at(x.saturating_sub(1), y)) / 2.0;
+            gy.data_mut()[i] = (at(x, (y + 1).min(h - 1)) - at(x, y.saturating_sub(1))) / 2.0;
+        }
+    }
+
+    // Parameters are relative to the image center for better conditioning:
+    // x' = (1 + p0) u + p1 v + p4, y' = p2 u + (1 + p3) v + p5
+    let (cx, cy) = (
+        reference.width() as f64 / 2.0,
+        reference.height() as f64 / 2.0,
+    );
+    let origin = initial.transform_point(euclid::Point2D::new(cx, cy));
+    let mut p = [
+        initial.m11 - 1.0,
+        initial.m21,
+        initial.m12,
+        initial.m22 - 1.0,
+        origin.x,
+        origin.y,
+    ];
+    let params: &[usize] = if affine { &[0, 1, 2, 3, 4, 5] } else { &[4, 5] };
+    let n = params.len();
+
+    let step = ((reference.width() * reference.height()) as f64 / 20000.0)
+        .sqrt()
+        .max(1.0) as usize;
+    let sample =
+        |image: &Image<f64, Gray>, x: f64, y: f64| image.sample(x, y, Interpolation::Bilinear)[0];
+
+    for _ in 0..30 {
+        let mut hessian = vec![0.0; n * n];
+        let mut b = vec![0.0; n];
+        for y in (0..reference.height()).step_by(step) {
+            for x in (0..reference.width()).step_by(step) {
+                let (u, v) = (x as f64 - cx, y as f64 - cy);
+                let wx = (1.0 + p[0]) * u + p[1] * v + p[4];
+                let wy = p[2] * u + (1.0 + p[3]) * v + p[5];
+                if wx < 0.0 || wy < 0.0 || wx > (w - 1) as f64 || wy > (h - 1) as f64 {
+                    continue;
+                }
+
+                let (ix, iy) = (sample(&gx, wx, wy), sample(&gy, wx, wy));
+                let error = reference.data()[y * reference.width() + x] - sample(image, wx, wy);
+                let sd = [ix * u, ix * v, iy * u, iy * v, ix, iy];
+                for (i, pi) in params.iter().enumerate() {
+                    b[i] += sd[*pi] * error;
+                    for (j, pj) in params.iter().enumerate() {
+                        hessian[i * n + j] += sd[*pi] * sd[*pj];
+                    }
+                }
+            }
+        }
+
+        let delta = match solve(hessian, b) {
+            Some(delta) => delta,
+            None => break,
+        };
+        for (i, d) in params.iter().zip(&delta) {
+            p[*i] += d;
+        }
+        if delta.iter().all(|d| d.abs() < 1e-4) {
+            break;
+        }
+    }
+
+    // Undo the centering: x' = A (x - c) + t
+    let (a11, a12, a21, a22) = (1.0 + p[0], p[1], p[2], 1.0 + p[3]);
+    Transform::new(
+        a11,
+        a21,
+        a12,
+        a22,
+        p[4] - a11 * cx - a12 * cy,
+        p[5] - a21 * cx - a22 * cy,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use stack::{Alignment, Combine, Stack};
+
+    fn scene(transform: Transform, noise: f64, seed: u64) -> Image<f32, Gray> {
+        let mut rng = Seed(99).rng();
+        let blobs: Vec<_> = (0..24)
+            .map(|_| {
+                let (x, y) = (rng.range(4.0, 60.0), rng.range(4.0, 60.0));
+                (x, y, rng.range(1.5, 3.0), rng.range(0.3, 0.8))
+            })
+            .collect();
+
+        let mut image = Image::<f32, Gray>::new((64, 64));
+        image.for_each(|pt, mut px| {
+            let p = transform.transform_point(euclid::Point2D::new(pt.x as f64, pt.y as f64));
+            let v: f64 = blobs
+                .iter()
+                .map(|(cx, cy, r, a)| {
+                    a * (-((p.x - cx).powi(2) + (p.y - cy).powi(2)) / (2.0 * r * r)).exp()
+                })
+                .sum();
+            let n = Seed(seed).at(pt.x, pt.y).normal() * noise;
+            px[0] = (v + n) as f32;
+        });
+        image
+    }
+
+    fn error(a: &Image<f32, Gray>, b: &Image<f32, Gray>) -> f64 {
+        let mut sum = 0.0;
+        for y in 12..52 {
+            for x in 12..52 {
+                sum += (a.get_f((x, y), 0) - b.get_f((x, y), 0)).abs();
+            }
+        }
+        sum / (40.0 * 40.0)
+    }
+
+    #[test]
+    fn test_stack() {
+        let clean = scene(Transform::identity(), 0.0, 0);
+        let offsets = [
+            (0.0, 0.0),
+            (2.5, -1.0),
+            (-3.0, 2.0),
+            (1.0, 3.5),
+            (-1.5, -2.5),
+        ];
+        let frames: Vec<_> = offsets
+            .iter()
+            .enumerate()
+            .map(|(i, (dx, dy))| scene(Transform::translation(-dx, -dy), 0.05, i as u64))
+            .collect();
+
+        let stack = Stack::new();
+        let transforms = stack.align(&frames);
+        for (t, (dx, dy)) in transforms.iter().zip(&offsets) {
+            assert!(
+                (t.m31 - dx).abs() < 0.15 && (t.m32 - dy).abs() < 0.15,
+                "{:?}",
+                t
+            );
+        }
+
+        let single = error(&frames[0], &clean);
+        let mean = stack.combine(&frames, &transforms);
+        assert!(error(&mean, &clean) < single * 0.7);
+
+        // A frame with a bright artifact is rejected by the robust methods
+        let mut frames = frames;
+        frames[3].for_each(|_, mut px| px[0] += 1.0);
+        let mean = Stack::new().run(&frames);
+        for combine in [
+            Combine::Median,
+            Combine::SigmaClip {
+                sigma: 1.5,
+                iterations: 3,
+            },
+        ] {
+            let robust = Stack::new().with_combine(combine).run(&frames);
+            assert!(error(&robust, &clean) < error(&mean, &clean) * 0.5);
+        }
+    }
+
+    #[test]
+    fn test_stack_affine() {
+        let reference = scene(Transform::identity(), 0.0, 0);
+        let rotation = Transform::translation(-32.0, -32.0)
+            .then_rotate(euclid::Angle::degrees(3.0))
+            .then_scale(1.02, 1.02)
+            .then_translate(euclid::Vector2D::new(33.0, 31.0));
+        let moved = scene(rotation.inverse().unwrap(), 0.0, 0);
+
+        let stack = Stack::new().with_alignment(Alignment::Affine);
+        let transforms = stack.align(&[reference.clone(), moved]);
+        let t = transforms[1];
+        for (a, b) in [
+            (t.m11, rotation.m11),
+            (t.m12, rotation.m12),
+            (t.m21, rotation.m21),
+            (t.m22, rotation.m22),
+        ] {
+            assert!((a - b).abs() < 0.01, "{:?} {:?}", t, rotation);
+        }
+        assert!((t.m31 - rotation.m31).abs() < 0.3 && (t.m32 - rotation.m32).abs() < 0.3);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::*;
+
+/// Evenly spaced parameter values used to preview the effect of a filter, for example a blur
+/// radius from 0 to 10. `make` arguments build the filter for a single value, like
+/// `|sigma| filter::gaussian_blur(sigma)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sweep {
+    /// First value
+    pub start: f64,
+
+    /// Last value
+    pub end: f64,
+
+    /// Number of values, including `start` and `end`
+    pub steps: usize,
+}
+
+impl Sweep {
+    /// Create a new sweep from `start` to `end` with `steps` values
+    pub fn new(start: f64, end: f64, steps: usize) -> Sweep {
+        Sweep {
+            start,
+            end,
+            steps: steps.max(1),
+        }
+    }
+
+    /// Parameter values in order
+    pub fn values(&self) -> Vec<f64> {
+        if self.steps <= 1 {
+            return vec![self.start];
+        }
+        (0..self.steps)
+            .map(|i| self.start + (self.end - self.start) * i as f64 / (self.steps - 1) as f64)
+            .collect()
+    }
+
+    /// Run the filter once for every value
+    pub fn render<T: Type, C: Color, F: Filter<T, C>>(
+        &self,
+        image: &Image<T, C>,
+        make: impl Fn(f64) -> F,
+    ) -> Vec<Image<T, C>> {
+        self.values()
+            .into_iter()
+            .map(|v| image.run(make(v), None))
+            .collect()
+    }
+
+    /// Arrange frames in a grid with `columns` columns, leaving `label_height` pixels below
+    /// each frame for `label`
+    fn build_strip<T: Type, C: Color>(
+        &self,
+        frames: &[Image<T, C>],
+        columns: usize,
+        label_height: usize,
+        mut label: impl FnMut(&mut Image<T, C>, f64, Point),
+    ) -> Image<T, C> {
+        let columns = columns.clamp(1, frames.len().max(1));
+        let rows = frames.len().div_ceil(columns);
+        let cell = frames.iter().fold(Size::zero(), |size, f| {
+            Size::new(size.width.max(f.width()), size.height.max(f.height()))
+        });
+        let cell = Size::new(cell.width, cell.height + label_height);
+
+        let mut strip = Image::new((cell.width * columns, cell.height * rows));
+        for (i, (frame, value)) in frames.iter().zip(self.values()).enumerate() {
+            let origin = Point::new((i % columns) * cell.width, (i / columns) * cell.height);
+            strip.copy_from_region(Point::zero(), frame, Region::new(origin, frame.size()));
+            if label_height > 0 {
+                let pos = Point::new(origin.x, origin.y + cell.height - label_height);
+                label(&mut strip, value, pos);
+            }
+        }
+        strip
+    }
+
+    /// Render the sweep into a single image, with frames ordered left to right and top to
+    /// bottom in a grid of `columns` columns
+    pub fn strip<T: Type, C: Color, F: Filter<T, C>>(
+        &self,
+        image: &Image<T, C>,
+        columns: usize,
+        make: impl Fn(f64) -> F,
+    ) -> Image<T, C> {
+        let frames = self.render(image, make);
+        self.build_strip(&frames, columns, 0, |_, _, _| ())
+    }
+
+    /// Render the sweep into a single image like `Sweep::strip`, each frame is labeled with its
+    /// parameter value
+    #[cfg(feature = "text")]
+    pub fn strip_with_labels<T: Type, C: Color, F: Filter<T, C>>(
+        &self,
+        image: &Image<T, C>,
+        columns: usize,
+        font: &crate::text::Font,
+        size: f32,
+        make: impl Fn(f64) -> F,
+    ) -> Image<T, C> {
+        let frames = self.render(image, make);
+        let mut color = Pixel::new();
+        color.fill(1.0);
+        self.build_strip(
+            &frames,
+            columns,
+            size.ceil() as usize,
+            |strip, value, pos| strip.draw_text(format!("{:.2}", value), font, size, pos, &color),
+        )
+    }
+
+    /// Render the sweep and write it to an animated GIF, showing each frame for `delay`
+    pub fn save_animation<T: Type, C: Color, F: Filter<T, C>>(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        image: &Image<T, C>,
+        delay: std::time::Duration,
+        make: impl Fn(f64) -> F,
+    ) -> Result<(), Error> {
+        let frames = self.render(image, make);
+        quantize::save_gif(path, &frames, delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use sweep::Sweep;
+
+    #[test]
+    fn test_sweep() {
+        let sweep = Sweep::new(0.0, 1.0, 5);
+        assert_eq!(sweep.values(), vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(Sweep::new(2.0, 3.0, 1).values(), vec![2.0]);
+
+        let mut image = Image::<f32, Gray>::new((8, 6));
+        image.for_each(|_, mut px| px[0] = 0.2);
+
+        let strip = sweep.strip(&image, 3, filter::brightness);
+        assert_eq!(strip.size(), Size::new(24, 12));
+        assert!((strip.get((1, 1))[0] - 0.0).abs() < 1e-6);
+        assert!((strip.get((9, 1))[0] - 0.05).abs() < 1e-6);
+        assert!((strip.get((17, 1))[0] - 0.1).abs() < 1e-6);
+        assert!((strip.get((9, 7))[0] - 0.2).abs() < 1e-6);
+
+        // Unused cells stay empty
+        assert_eq!(strip.get((20, 10))[0], 0.0);
+
+        let path = std::env::temp_dir().join("image2-sweep-test.gif");
+        sweep
+            .save_animation(
+                &path,
+                &image,
+                std::time::Duration::from_millis(200),
+                filter::brightness,
+            )
+            .unwrap();
+        let gif = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(gif.starts_with(b"GIF89a") && gif.ends_with(&[0x3b]));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::*;
+
+/// Source of a single output channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Swizzle {
+    Channel(Channel),
+    Constant(f64),
+}
+
+fn parse_swizzle<C: Color>(pattern: &str, channels: Channel) -> Result<Vec<Swizzle>, Error> {
+    if pattern.chars().count() != channels {
+        return Err(Error::UnexpectedChannels(channels, pattern.chars().count()));
+    }
+
+    pattern
+        .chars()
+        .map(|ch| {
+            let c = match ch.to_ascii_lowercase() {
+                'r' | 'x' => 0,
+                'g' | 'y' => 1,
+                'b' | 'z' => 2,
+                'a' | 'w' => match C::ALPHA {
+                    Some(a) => a,
+                    None => return Ok(Swizzle::Constant(1.0)),
+                },
+                '0' => return Ok(Swizzle::Constant(0.0)),
+                '1' => return Ok(Swizzle::Constant(1.0)),
+                _ => return Err(Error::Message(format!("Invalid swizzle channel: {}", ch))),
+            };
+
+            if c >= C::CHANNELS {
+                return Err(Error::Message(format!(
+                    "Swizzle channel {} is out of range for {}",
+                    ch,
+                    C::NAME
+                )));
+            }
+            Ok(Swizzle::Channel(c))
+        })
+        .collect()
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Reorder channels using a pattern like `"bgra"`, each character selects the source channel
+    /// for the matching output channel. `r`, `g`, `b`, `a` (or `x`, `y`, `z`, `w`) select a
+    /// channel by position, `a` uses the alpha channel of `C` and is `1` when the image has no alpha
+    /// channel, `0` and `1` fill the channel with a constant. The
+    /// pattern must have one character per channel of `D`.
+    pub fn swizzle<D: Color>(&self, pattern: &str) -> Result<Image<T, D>, Error> {
+        let swizzle = parse_swizzle::<C>(pattern, D::CHANNELS)?;
+        let mut dest = self.new_like_with_color::<D>();
+        dest.for_each(|pt, mut px| {
+            let src = self.get(pt);
+            for (c, s) in swizzle.iter().enumerate() {
+                px[c] = match s {
+                    Swizzle::Channel(i) => src[*i],
+                    Swizzle::Constant(f) => T::from_norm(*f),
+                };
+            }
+        });
+        Ok(dest)
+    }
+
+    /// Build an image by taking one channel from each source image, `sources[i]` provides output
+    /// channel `i`. This is useful for packing several grayscale masks into a single texture,
+    /// for example occlusion, roughness and metalness into the channels of an ORM map. Every
+    /// source must have the same size.
+    pub fn pack_channels<U: Type, D: Color>(
+        sources: &[(&Image<U, D>, Channel)],
+    ) -> Result<Image<T, C>, Error> {
+        if sources.len() != C::CHANNELS {
+            return Err(Error::UnexpectedChannels(C::CHANNELS, sources.len()));
+        }
+
+        let size = match sources.first() {
+            Some((image, _)) => image.size(),
+            None => Size::new(0, 0),
+        };
+
+        for (image, c) in sources {
+            if image.size() != size {
+                return Err(Error::InvalidDimensions(
+                    image.width(),
+                    image.height(),
+                    image.channels(),
+                ));
+            }
+
+            if *c >= D::CHANNELS {
+                return Err(Error::Message(format!(
+                    "Channel {} is out of range for {}",
+                    c,
+                    D::NAME
+                )));
+            }
+        }
+
+        let mut dest = Image::new(size);
+        dest.for_each(|pt, mut px| {
+            for (i, (image, c)) in sources.iter().enumerate() {
+                px[i] = T::from_norm(image.get(pt)[*c].to_norm());
+            }
+        });
+        Ok(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_swizzle() {
+        let mut image = Image::<u8, Rgba>::new((2, 2));
+        image.set((1, 1), [10, 20, 30, 40]);
+
+        let bgra: Image<u8, Rgba> = image.swizzle("bgra").unwrap();
+        assert_eq!(bgra.get((1, 1)).as_slice(), &[30, 20, 10, 40]);
+
+        let rgb: Image<u8, Rgb> = image.swizzle("a01").unwrap();
+        assert_eq!(rgb.get((1, 1)).as_slice(), &[40, 0, 255]);
+
+        assert!(image.swizzle::<Rgb>("rgba").is_err());
+        assert!(image.swizzle::<Rgb>("rgq").is_err());
+        assert!(rgb.swizzle::<Rgb>("rg?").is_err());
+    }
+
+    #[test]
+    fn test_pack_channels() {
+        let mut ao = Image::<f32, Gray>::new((3, 2));
+        let mut rough = ao.new_like();
+        let mut metal = ao.new_like();
+        ao.set((2, 1), [1.0]);
+        rough.set((2, 1), [0.5]);
+        metal.set((2, 1), [0.0]);
+
+        let orm = Image::<u8, Rgb>::pack_channels(&[(&ao, 0), (&rough, 0), (&metal, 0)]).unwrap();
+        assert_eq!(orm.size(), Size::new(3, 2));
+        assert_eq!(orm.get((2, 1)).as_slice(), &[255, 127, 0]);
+
+        let small = Image::<f32, Gray>::new((1, 1));
+        assert!(Image::<u8, Rgb>::pack_channels(&[(&ao, 0), (&small, 0), (&metal, 0)]).is_err());
+        assert!(Image::<u8, Rgb>::pack_channels(&[(&ao, 0), (&rough, 1), (&metal, 0)]).is_err());
+        assert!(Image::<u8, Rgb>::pack_channels(&[(&ao, 0)]).is_err());
+    }
+}
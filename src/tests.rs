@@ -100,6 +100,27 @@ fn test_hash() {
     println!("{}", a.hash().diff(&b.hash()));
 }
 
+#[test]
+fn test_iter_region() {
+    let mut image: Image<f32, Gray> = Image::new((8, 6));
+    image.for_each(|pt, mut px| px[0] = (pt.y * 8 + pt.x) as f32 / 48.0);
+
+    let roi = Region::new(Point::new(3, 2), Size::new(4, 3));
+    let points: Vec<Point> = image.iter_region(roi).map(|(pt, _)| pt).collect();
+    assert_eq!(points.len(), 12);
+    assert!(points.iter().all(|pt| roi.contains(*pt)));
+    image
+        .iter_region(roi)
+        .for_each(|(pt, px)| assert_eq!(px[0], image.get(pt)[0]));
+
+    let mut dest = image.new_like();
+    invert().eval_partial(roi, &[&image], &mut dest);
+    for pt in [Point::new(3, 2), Point::new(6, 4)] {
+        assert!((dest.get(pt)[0] - (1.0 - image.get(pt)[0])).abs() < 1e-6);
+    }
+    assert_eq!(dest.get((2, 2))[0], 0.0);
+}
+
 #[test]
 fn test_kernel() {
     let image: Image<f32, Rgb> = Image::open("images/A.exr").unwrap();
@@ -198,6 +219,35 @@ fn test_brightness_contrast() {
     assert!(dest.save("images/test-contrast-brightness-2.jpg").is_ok());
 }
 
+#[test]
+fn test_pipeline_prepare() {
+    let mut image: Image<f32, Rgb> = Image::new((16, 16));
+    image.for_each(|pt, mut px| {
+        let v = if pt.x < 8 { 0.2 } else { 0.6 };
+        px.copy_from_slice([v, v * 0.5, v]);
+    });
+
+    // Filters that prepare data see the output of the filters before them
+    let mut brightened = image.new_like();
+    brightness(1.5).eval(&[&image], &mut brightened);
+    let mut expected = image.new_like();
+    gaussian_blur(2.0).eval(&[&brightened], &mut expected);
+
+    let mut dest = image.new_like();
+    Pipeline::new()
+        .then(brightness(1.5))
+        .then(gaussian_blur(2.0))
+        .execute(&[&image], &mut dest);
+    assert!(dest == expected);
+
+    AutoWhiteBalance::gray_world().eval(&[&brightened], &mut expected);
+    Pipeline::new()
+        .then(brightness(1.5))
+        .then(AutoWhiteBalance::gray_world())
+        .execute(&[&image], &mut dest);
+    assert!(dest == expected);
+}
+
 #[test]
 fn test_saturation() {
     let mut image: Image<f32, Rgb> = Image::open("images/A.exr").unwrap();
@@ -249,6 +299,106 @@ fn test_metadata() {
     assert!(input2.spec().get_attr("testing") == Some(Attr::String("123")));
 }
 
+#[test]
+fn test_per_channel() {
+    let mut image: Image<f32, Rgb> = Image::new((4, 4));
+    image.for_each(|_, mut px| px.copy_from_slice([0.25, 0.125, 0.75]));
+
+    let mut dest = image.new_like();
+    per_channel(invert(), Channels::only(1)).eval(&[&image], &mut dest);
+    assert_eq!(dest.get_pixel((1, 1)).to_vec(), vec![0.25, 0.875, 0.75]);
+
+    per_channel(invert(), [0, 2]).eval(&[&image], &mut dest);
+    assert_eq!(dest.get_pixel((2, 3)).to_vec(), vec![0.75, 0.125, 0.25]);
+}
+
+#[test]
+fn test_channels() {
+    let channels = Channels::from([0, 2]);
+    assert!(channels.contains(0) && !channels.contains(1) && channels.contains(2));
+    assert!(!channels.without(2).contains(2));
+
+    // Channels past the end of the set are ignored
+    assert_eq!(channels.with(64), channels);
+    assert_eq!(channels.with(usize::MAX), channels);
+    assert_eq!(Channels::all().without(100), Channels::all());
+    assert!(!Channels::all().contains(64));
+}
+
+#[test]
+fn test_wrapped_prepare() {
+    let mut image: Image<f32, Rgb> = Image::new((16, 16));
+    image.for_each(|pt, mut px| {
+        let v = if pt.x < 8 { 0.0 } else { 1.0 };
+        px.copy_from_slice([v, v, v]);
+    });
+
+    let mut blurred = image.new_like();
+    gaussian_blur(2.0).eval(&[&image], &mut blurred);
+
+    let mut dest = image.new_like();
+    per_channel(gaussian_blur(2.0), Channels::only(1)).eval(&[&image], &mut dest);
+    let px = dest.get_pixel((7, 4));
+    assert_eq!(px[0], 0.0);
+    assert_eq!(px[2], 0.0);
+    assert!(px[1] > 0.0);
+    assert_eq!(px[1], blurred.get_pixel((7, 4))[1]);
+
+    if_then_else(|_, _| true, gaussian_blur(2.0), noop()).eval(&[&image], &mut dest);
+    assert!(dest == blurred);
+
+    if_then_else(|pt: Point, _| pt.y < 8, noop(), gaussian_blur(2.0)).eval(&[&image], &mut dest);
+    assert_eq!(dest.get_pixel((7, 4)).to_vec(), vec![0.0; 3]);
+    assert_eq!(dest.get_pixel((7, 12)), blurred.get_pixel((7, 12)));
+}
+
+#[test]
+fn test_gray() {
+    // The luma weights sum to one, so white stays white
+    let white: Pixel<Gray> = Pixel::<Rgb>::from(vec![1.0, 1.0, 1.0]).convert();
+    assert!((white[0] - 1.0).abs() < 1e-9);
+
+    let blue: Pixel<Gray> = Pixel::<Rgb>::from(vec![0.0, 0.0, 1.0]).convert();
+    assert!((blue[0] - 0.07).abs() < 1e-9);
+}
+
+#[test]
+fn test_yuv() {
+    // Neutral colors have no chroma
+    let white: Pixel<Yuv> = Pixel::<Rgb>::from(vec![1.0, 1.0, 1.0]).convert();
+    assert!((white[0] - 1.0).abs() < 1e-4);
+    assert!(white[1].abs() < 1e-4 && white[2].abs() < 1e-4);
+
+    let red: Pixel<Yuv> = Pixel::<Rgb>::from(vec![1.0, 0.0, 0.0]).convert();
+    assert!((red[1] + 0.14713).abs() < 1e-9 && (red[2] - 0.615).abs() < 1e-9);
+
+    // Converting back gives the original color
+    for rgb in [[0.2, 0.5, 0.9], [0.7, 0.1, 0.3], [0.0, 1.0, 0.5]] {
+        let yuv: Pixel<Yuv> = Pixel::<Rgb>::from(rgb.to_vec()).convert();
+        let back: Pixel<Rgb> = yuv.convert();
+        assert!((0..3).all(|c| (back[c] - rgb[c]).abs() < 1e-3));
+    }
+}
+
+#[test]
+fn test_luma_chroma() {
+    let mut image: Image<f32, Rgb> = Image::new((8, 8));
+    image.for_each(|pt, mut px| {
+        px.copy_from_slice([pt.x as f32 / 8.0, 0.5, pt.y as f32 / 8.0]);
+    });
+
+    let mut dest = image.new_like();
+    luma_chroma(noop(), noop()).eval(&[&image], &mut dest);
+    for (a, b) in image.data().iter().zip(dest.data()) {
+        assert!((a - b).abs() < 1e-3);
+    }
+
+    luma_chroma(normalize(0.0, 1.0, 0.5, 0.5), noop()).eval(&[&image], &mut dest);
+    let yuv: Image<f32, Yuv> = dest.convert();
+    assert!((yuv.get_f((3, 5), 0) - 0.5).abs() < 1e-3);
+    assert!((yuv.get_f((7, 0), 0) - 0.5).abs() < 1e-3);
+}
+
 #[test]
 fn test_type_and_color_name() {
     assert!(f32::type_name() != f64::type_name());
@@ -281,3 +431,737 @@ fn test_mmap() {
     assert!(image == image1);
     image1.save("images/test-mmap.png").unwrap();
 }
+
+#[test]
+fn test_convert_fast() {
+    let mut a = Image::<u8, Rgba>::new((5, 3));
+    a.for_each(|pt, mut px| {
+        px.copy_from_slice([pt.x as u8 * 40, pt.y as u8 * 90, 17, 51 * pt.x as u8]);
+    });
+
+    // `convert` premultiplies and makes alpha opaque, like `Pixel::convert`
+    let b: Image<f32, Rgba> = a.convert();
+    assert_eq!(b.get_f((2, 1), 3), 1.0);
+    assert!((b.get_f((2, 1), 0) - 80.0 / 255.0 * 102.0 / 255.0).abs() < 1e-6);
+    a.each_pixel(|pt, px| {
+        let expected: Pixel<Rgba> = px.convert();
+        assert!((0..4).all(|c| (b.get_f(pt, c) - expected[c]).abs() < 1e-6));
+    });
+
+    // Straight alpha is kept when both colors have it, and type conversion round-trips
+    let b: Image<f32, Rgba> = a.convert_with_alpha(filter::AlphaMode::Straight);
+    assert!((b.get_f((2, 1), 3) - 102.0 / 255.0).abs() < 1e-6);
+    assert!((b.get_f((2, 1), 0) - 80.0 / 255.0).abs() < 1e-6);
+    assert!(b.convert_with_alpha::<u8, Rgba>(filter::AlphaMode::Straight) == a);
+
+    // Removing alpha premultiplies unless requested otherwise
+    let rgb: Image<f32, Rgb> = a.convert();
+    let straight: Image<f32, Rgb> = a.convert_with_alpha(filter::AlphaMode::Straight);
+    assert!((rgb.get_f((4, 0), 0) - 160.0 / 255.0 * 204.0 / 255.0).abs() < 1e-6);
+    assert!((straight.get_f((4, 0), 0) - 160.0 / 255.0).abs() < 1e-6);
+
+    // Adding alpha
+    let rgba: Image<u8, Rgba> = straight.convert_with_alpha(filter::AlphaMode::Fill(0.0));
+    assert_eq!(rgba.get_pixel((4, 0))[3], 0.0);
+    assert_eq!(straight.convert::<u8, Rgba>().get_pixel((4, 0))[3], 1.0);
+
+    // Fast paths match converting one pixel at a time
+    let gray: Image<f32, Gray> = straight.convert();
+    let back: Image<f32, Rgb> = gray.convert();
+    straight.each_pixel(|pt, px| {
+        let expected: Pixel<Gray> = px.convert();
+        assert!((gray.get_f(pt, 0) - expected[0]).abs() < 1e-6);
+        assert_eq!(back.get_f(pt, 1), gray.get_f(pt, 0));
+    });
+
+    // Colors without a fast path use the generic conversion
+    let hsv: Image<f32, Hsv> = straight.convert();
+    let expected: Pixel<Hsv> = straight.get_pixel((3, 2)).convert();
+    assert!((hsv.get_f((3, 2), 0) - expected[0]).abs() < 1e-6);
+}
+
+#[test]
+fn test_resize_filter() {
+    // Alternating columns average to gray when shrinking by half
+    let mut a = Image::<f32, Gray>::new((8, 4));
+    a.for_each(|pt, mut px| px[0] = (pt.x % 2) as f32);
+
+    let mut dest = Image::<f32, Gray>::new((4, 2));
+    Resize::new((4, 2)).eval(&[&a], &mut dest);
+    dest.each_pixel(|_, px| assert!((px[0] - 0.5).abs() < 1e-6));
+
+    // Point sampling keeps the aliasing
+    let mut dest = Image::<f32, Gray>::new((4, 2));
+    Resize::new((4, 2))
+        .with_interpolation(Interpolation::Nearest)
+        .eval(&[&a], &mut dest);
+    assert!(dest.get_f((1, 1), 0) == 0.0 || dest.get_f((1, 1), 0) == 1.0);
+
+    // Enlarging a constant image leaves it unchanged
+    let mut b = Image::<f32, Rgb>::new((3, 3));
+    b.for_each(|_, mut px| px.copy_from_slice([0.25, 0.5, 0.75]));
+    for interpolation in [
+        Interpolation::Bilinear,
+        Interpolation::Bicubic,
+        Interpolation::Lanczos3,
+    ] {
+        let dest: Image<f32, Rgb> = b.run(
+            Resize::new((7, 5)).with_interpolation(interpolation),
+            Some(Meta::new((7, 5))),
+        );
+        assert_eq!(dest.size(), Size::new(7, 5));
+        dest.each_pixel(|_, px| assert!((px[1] - 0.5).abs() < 1e-6));
+    }
+}
+
+#[test]
+fn test_downscale() {
+    // A fine checkerboard averages to gray instead of producing moire
+    let mut a = Image::<f32, Gray>::new((300, 200));
+    a.for_each(|pt, mut px| px[0] = ((pt.x + pt.y) % 2) as f32);
+
+    let small = a.downscale((7, 5));
+    assert_eq!(small.size(), Size::new(7, 5));
+    small.each_pixel(|_, px| assert!((px[0] - 0.5).abs() < 0.01));
+
+    let thumb = a.thumbnail(30);
+    assert_eq!(thumb.size(), Size::new(30, 20));
+    thumb.each_pixel(|_, px| assert!((px[0] - 0.5).abs() < 1e-3));
+    assert!(small.thumbnail(10) == small);
+}
+
+#[test]
+fn test_separable_kernel() {
+    let mut image = Image::<f32, Rgb>::new((16, 12));
+    image.for_each(|pt, mut px| {
+        px[0] = ((pt.x * 7 + pt.y * 3) % 11) as f32 / 10.0;
+        px[1] = (pt.x % 2) as f32;
+        px[2] = (pt.y % 3) as f32 / 2.0;
+    });
+
+    let sep = SeparableKernel::gaussian(5, 1.2);
+    let k = sep.to_kernel();
+    assert!(k.separable().is_some());
+    assert!(Kernel::laplacian().separable().is_none());
+
+    let mut a = image.new_like();
+    let mut b = image.new_like();
+    sep.eval(&[&image], &mut a);
+    k.eval(&[&image], &mut b);
+    a.each_pixel(|pt, px| {
+        let other = b.get_pixel(pt);
+        for c in 0..3 {
+            assert!((px[c] - other[c]).abs() < 1e-5);
+        }
+    });
+}
+
+#[test]
+fn test_kernel_border() {
+    assert_eq!(Border::Clamp.index(-2, 5), Some(0));
+    assert_eq!(Border::Clamp.index(7, 5), Some(4));
+    assert_eq!(Border::Mirror.index(-2, 5), Some(2));
+    assert_eq!(Border::Mirror.index(6, 5), Some(2));
+    assert_eq!(Border::Wrap.index(-1, 5), Some(4));
+    assert_eq!(Border::Wrap.index(5, 5), Some(0));
+    assert_eq!(Border::Constant(0.5).index(-1, 5), None);
+
+    let mut image = Image::<f32, Gray>::new((6, 6));
+    image.for_each(|_, mut px| px[0] = 1.0);
+
+    // Averaging a constant image only changes at the edges when a constant border is used
+    let mut a = image.new_like();
+    let k = SeparableKernel::box_blur(3);
+    k.clone()
+        .with_border(Border::Mirror)
+        .eval(&[&image], &mut a);
+    assert!((a.get_f((0, 0), 0) - 1.0).abs() < 1e-6);
+
+    k.to_kernel()
+        .with_border(Border::Constant(0.0))
+        .eval(&[&image], &mut a);
+    assert!((a.get_f((0, 0), 0) - 4.0 / 9.0).abs() < 1e-6);
+    assert!((a.get_f((3, 3), 0) - 1.0).abs() < 1e-6);
+
+    k.with_border(Border::Constant(0.0)).eval(&[&image], &mut a);
+    assert!((a.get_f((0, 3), 0) - 6.0 / 9.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_gaussian_blur_sigma() {
+    assert_eq!(kernel::gaussian(2.0).horizontal().len(), 13);
+
+    let mut image = Image::<f32, Gray>::new((64, 64));
+    image.set((32, 32), [1.0]);
+
+    let mut small = image.new_like();
+    gaussian_blur(1.5).eval(&[&image], &mut small);
+    let sum: f64 = small.data().iter().map(|x| *x as f64).sum();
+    assert!((sum - 1.0).abs() < 1e-4);
+    assert!((small.get_f((30, 32), 0) - small.get_f((34, 32), 0)).abs() < 1e-6);
+
+    // The recursive approximation should be close to the exact kernel
+    let mut exact = image.new_like();
+    kernel::gaussian(10.0).eval(&[&image], &mut exact);
+    let mut approx = image.new_like();
+    gaussian_blur(10.0).eval(&[&image], &mut approx);
+    let peak = exact.get_f((32, 32), 0);
+    for x in [20, 32, 40] {
+        assert!((exact.get_f((x, 32), 0) - approx.get_f((x, 32), 0)).abs() < peak * 0.05);
+    }
+}
+
+#[test]
+fn test_gaussian_blur_zero() {
+    assert_eq!(kernel::gaussian(0.0).horizontal(), &[1.0]);
+    assert_eq!(
+        kernel::SeparableKernel::gaussian(3, 0.0).horizontal(),
+        &[0.0, 1.0, 0.0]
+    );
+
+    let mut image = Image::<f32, Gray>::new((8, 8));
+    image.for_each(|pt, mut px| px[0] = pt.x as f32 / 8.0);
+    let mut dest = image.new_like();
+    gaussian_blur(0.0).eval(&[&image], &mut dest);
+    assert!(dest == image);
+}
+
+#[test]
+fn test_gaussian_blur_edges() {
+    // Narrow images and large sigmas are dominated by the edges
+    for (width, sigma) in [(16, 20.0), (64, 20.0), (64, 8.5)] {
+        let mut image = Image::<f32, Gray>::new((width, 8));
+        image.for_each(|pt, mut px| px[0] = ((pt.x * 7 + pt.y * 3) % 11) as f32 / 10.0);
+
+        let mut exact = image.new_like();
+        kernel::gaussian(sigma).eval(&[&image], &mut exact);
+        let mut approx = image.new_like();
+        gaussian_blur(sigma).eval(&[&image], &mut approx);
+
+        let mean = |image: &Image<f32, Gray>| {
+            image.data().iter().map(|x| *x as f64).sum::<f64>() / image.data().len() as f64
+        };
+        assert!((mean(&exact) - mean(&approx)).abs() < 0.01);
+        for x in [0, 1, width / 2, width - 2, width - 1] {
+            for y in [0, 7] {
+                assert!((exact.get_f((x, y), 0) - approx.get_f((x, y), 0)).abs() < 0.03);
+            }
+        }
+    }
+
+    // Constant images are unchanged
+    let mut image = Image::<f32, Gray>::new((16, 4));
+    image.for_each(|_, mut px| px[0] = 0.5);
+    let mut dest = image.new_like();
+    gaussian_blur(20.0).eval(&[&image], &mut dest);
+    assert!(dest.data().iter().all(|x| (x - 0.5).abs() < 1e-4));
+}
+
+#[test]
+fn test_box_blur() {
+    let mut image = Image::<f32, Rgb>::new((20, 15));
+    image.for_each(|pt, mut px| {
+        px[0] = ((pt.x * 5 + pt.y * 3) % 7) as f32 / 6.0;
+        px[1] = (pt.y % 2) as f32;
+    });
+
+    let mut a = image.new_like();
+    let mut b = image.new_like();
+    box_blur(2).eval(&[&image], &mut a);
+    SeparableKernel::box_blur(5).eval(&[&image], &mut b);
+    a.each_pixel(|pt, px| {
+        let other = b.get_pixel(pt);
+        for c in 0..3 {
+            assert!((px[c] - other[c]).abs() < 1e-5);
+        }
+    });
+}
+
+#[test]
+fn test_threshold() {
+    let mut image = Image::<f32, Gray>::new((10, 10));
+    image.for_each(|pt, mut px| px[0] = if pt.x < 5 { 0.2 } else { 0.6 });
+
+    let mut dest = image.new_like();
+    Threshold::otsu().eval(&[&image], &mut dest);
+    assert_eq!(dest.get_f((2, 2), 0), 0.0);
+    assert_eq!(dest.get_f((7, 2), 0), 1.0);
+
+    let level = Threshold::otsu().level(&image);
+    assert!(level > 0.2 && level < 0.6);
+
+    Threshold::new(0.7).eval(&[&image], &mut dest);
+    assert_eq!(dest.get_f((7, 2), 0), 0.0);
+}
+
+#[test]
+fn test_adaptive_threshold() {
+    // Dark text on a background with a strong gradient, a global threshold can't separate them
+    let mut image = Image::<f32, Gray>::new((40, 10));
+    image.for_each(|pt, mut px| {
+        let background = 0.2 + 0.7 * pt.x as f32 / 40.0;
+        px[0] = if pt.x % 8 == 4 {
+            background - 0.15
+        } else {
+            background
+        };
+    });
+
+    let mut dest = image.new_like();
+    for method in [AdaptiveMethod::Mean, AdaptiveMethod::Gaussian] {
+        AdaptiveThreshold::new(method, 3, 0.05).eval(&[&image], &mut dest);
+        for x in 0..40 {
+            let expected = if x % 8 == 4 { 0.0 } else { 1.0 };
+            assert_eq!(dest.get_f((x, 5), 0), expected, "{:?} {}", method, x);
+        }
+    }
+}
+
+#[test]
+fn test_equalize_histogram() {
+    let mut image = Image::<f32, Gray>::new((64, 64));
+    image.for_each(|pt, mut px| px[0] = 0.4 + 0.2 * pt.x as f32 / 63.0);
+
+    let mut dest = image.new_like();
+    EqualizeHistogram::new().eval(&[&image], &mut dest);
+    assert!(dest.get_f((0, 0), 0) < 0.05);
+    assert!(dest.get_f((63, 0), 0) > 0.95);
+    assert!(dest.get_f((20, 0), 0) < dest.get_f((40, 0), 0));
+
+    Clahe::new(4.0)
+        .with_tiles((4, 4))
+        .eval(&[&image], &mut dest);
+    assert!(dest.get_f((0, 0), 0) < 0.4);
+    assert!(dest.get_f((63, 0), 0) > 0.6);
+
+    // Hue is preserved for color images
+    let mut color = Image::<f32, Rgb>::new((16, 16));
+    color.for_each(|pt, mut px| {
+        let v = 0.3 + 0.2 * pt.x as f32 / 15.0;
+        px.copy_from_slice([v, v * 0.5, v * 0.25]);
+    });
+    let mut dest = color.new_like();
+    EqualizeHistogram::new().eval(&[&color], &mut dest);
+    let a: Pixel<Hsv> = color.get_pixel((8, 8)).convert();
+    let b: Pixel<Hsv> = dest.get_pixel((8, 8)).convert();
+    assert!((a[0] - b[0]).abs() < 0.02, "{} {}", a[0], b[0]);
+}
+
+#[test]
+fn test_levels() {
+    let levels = Levels::new(0.2, 0.8, 1.0, 0.0, 1.0);
+    assert_eq!(levels.apply(0.1), 0.0);
+    assert!((levels.apply(0.5) - 0.5).abs() < 1e-9);
+    assert_eq!(levels.apply(0.9), 1.0);
+    assert!((Levels::new(0.0, 1.0, 2.0, 0.0, 1.0).apply(0.25) - 0.5).abs() < 1e-9);
+    assert!((Levels::new(0.0, 1.0, 1.0, 0.2, 0.4).apply(0.5) - 0.3).abs() < 1e-9);
+
+    let mut image = Image::<f32, Rgb>::new((10, 10));
+    image.for_each(|pt, mut px| {
+        let v = 0.3 + 0.04 * pt.x as f32;
+        px.copy_from_slice([v, v, 0.5]);
+    });
+
+    let auto = Levels::auto_levels(&image, 0.0);
+    assert!((auto.in_black - 0.3).abs() < 0.01);
+    assert!((auto.in_white - 0.66).abs() < 0.01);
+
+    let mut dest = image.new_like();
+    auto.eval(&[&image], &mut dest);
+    assert!(dest.get_f((0, 0), 0) < 0.01);
+    assert!(dest.get_f((9, 0), 1) > 0.99);
+
+    Levels::auto_levels_channel(&image, 0, 0.0).eval(&[&image], &mut dest);
+    assert!(dest.get_f((9, 0), 0) > 0.99);
+    assert!((dest.get_f((9, 0), 1) - 0.66).abs() < 1e-3);
+}
+
+#[test]
+fn test_curves() {
+    let curve = Curve::new([(1.0, 1.0), (0.0, 0.0), (0.5, 0.7)]);
+    assert!(curve.eval(0.0).abs() < 1e-6);
+    assert!((curve.eval(0.5) - 0.7).abs() < 1e-3);
+    assert!((curve.eval(1.0) - 1.0).abs() < 1e-6);
+
+    // Monotone between control points, even with a flat section
+    let curve = Curve::new([(0.0, 0.0), (0.3, 0.5), (0.6, 0.5), (1.0, 1.0)]);
+    let mut prev = 0.0;
+    for i in 0..=100 {
+        let y = curve.eval(i as f64 / 100.0);
+        assert!(y >= prev - 1e-9 && y <= 1.0);
+        prev = y;
+    }
+    assert!((curve.eval(0.45) - 0.5).abs() < 1e-3);
+
+    let mut image = Image::<f32, Rgb>::new((4, 4));
+    image.for_each(|_, mut px| px.copy_from_slice([0.5, 0.5, 0.5]));
+    let mut dest = image.new_like();
+    Curves::new()
+        .with_channel(0, [(0.0, 0.0), (0.5, 0.25), (1.0, 1.0)])
+        .with_master([(0.0, 0.0), (1.0, 0.5)])
+        .eval(&[&image], &mut dest);
+    assert!((dest.get_f((0, 0), 0) - 0.125).abs() < 1e-3);
+    assert!((dest.get_f((0, 0), 1) - 0.25).abs() < 1e-3);
+}
+
+#[test]
+fn test_transfer_functions() {
+    let mut image = Image::<f32, Rgba>::new((2, 1));
+    image.set((0, 0), [0.5, 0.02, 1.0, 0.5]);
+    image.set((1, 0), [0.0, 0.214, 0.8, 1.0]);
+
+    let mut linear = image.new_like();
+    SrgbToLinear.eval(&[&image], &mut linear);
+    assert!((linear.get_f((0, 0), 0) - 0.214).abs() < 1e-3);
+    assert!((linear.get_f((0, 0), 1) - 0.02 / 12.92).abs() < 1e-6);
+    assert_eq!(linear.get_f((0, 0), 3), 0.5);
+
+    let mut back = image.new_like();
+    LinearToSrgb.eval(&[&linear], &mut back);
+    for x in 0..2 {
+        for c in 0..4 {
+            assert!((back.get_f((x, 0), c) - image.get_f((x, 0), c)).abs() < 1e-5);
+        }
+    }
+
+    Gamma(2.0).eval(&[&image], &mut back);
+    assert!((back.get_f((1, 0), 2) - 0.8f64.sqrt()).abs() < 1e-6);
+    assert_eq!(back.get_f((0, 0), 3), 0.5);
+}
+
+#[test]
+fn test_exposure() {
+    let mut image = Image::<f32, Rgba>::new((1, 1));
+    image.set((0, 0), [0.25, 0.1, 0.0, 0.5]);
+
+    let mut dest = image.new_like();
+    filter::Exposure::new(1.0).eval(&[&image], &mut dest);
+    assert!((dest.get_f((0, 0), 0) - 0.5).abs() < 1e-6);
+    assert!((dest.get_f((0, 0), 1) - 0.2).abs() < 1e-6);
+    assert_eq!(dest.get_f((0, 0), 3), 0.5);
+
+    // Exposure is applied in linear light
+    let srgb: Image<f32, Srgb> = image.convert();
+    let mut out = srgb.new_like();
+    filter::Exposure::new(-1.0).eval(&[&srgb], &mut out);
+    let linear: Image<f32, Rgb> = out.convert();
+    assert!((linear.get_f((0, 0), 0) - 0.0625).abs() < 1e-4);
+
+    let rolloff = filter::Exposure::new(3.0).with_rolloff(0.8);
+    assert_eq!(rolloff.apply(0.05), 0.4);
+    assert!(rolloff.apply(0.25) < 1.0);
+    assert!(rolloff.apply(0.25) > rolloff.apply(0.2));
+}
+
+#[test]
+fn test_auto_white_balance() {
+    let mut image = Image::<f32, Rgb>::new((4, 4));
+    image.for_each(|pt, mut px| {
+        let v = (pt.x + pt.y * 4) as f32 / 15.0;
+        px.copy_from_slice([v * 0.8, v, v * 0.5]);
+    });
+
+    let gray_world = filter::AutoWhiteBalance::gray_world();
+    let gains = gray_world.gains(&image);
+    assert!((gains[0] - 1.25).abs() < 1e-6);
+    assert!((gains[1] - 1.0).abs() < 1e-6);
+    assert!((gains[2] - 2.0).abs() < 1e-6);
+
+    let mut dest = image.new_like();
+    gray_world.eval(&[&image], &mut dest);
+    let px = dest.get((3, 3));
+    assert!((px[0] - px[1]).abs() < 1e-5);
+    assert!((px[2] - px[1]).abs() < 1e-5);
+
+    let gains = filter::AutoWhiteBalance::white_patch(1.0).gains(&image);
+    assert!((gains[2] - 2.0).abs() < 0.01);
+}
+
+#[test]
+fn test_white_balance() {
+    let identity = filter::WhiteBalance::default().matrix();
+    for (i, row) in identity.iter().enumerate() {
+        for (j, x) in row.iter().enumerate() {
+            let expected = if i == j { 1.0 } else { 0.0 };
+            assert!((x - expected).abs() < 1e-6);
+        }
+    }
+
+    let mut image = Image::<f32, Rgb>::new((1, 1));
+    image.set((0, 0), [0.5, 0.5, 0.5]);
+    let mut dest = image.new_like();
+
+    // Correcting for tungsten light makes the image cooler
+    filter::WhiteBalance::new(3200.0, 0.0).eval(&[&image], &mut dest);
+    let px = dest.get((0, 0));
+    assert!(px[2] > px[1] && px[1] > px[0]);
+
+    filter::WhiteBalance::new(9000.0, 0.0).eval(&[&image], &mut dest);
+    let px = dest.get((0, 0));
+    assert!(px[0] > px[2]);
+
+    filter::WhiteBalance::new(6504.0, 50.0).eval(&[&image], &mut dest);
+    let px = dest.get((0, 0));
+    assert!(px[1] < px[0] && px[1] < px[2]);
+}
+
+#[test]
+fn test_hue_rotate() {
+    let mut image = Image::<f32, Rgba>::new((2, 1));
+    image.set((0, 0), [0.8, 0.1, 0.1, 0.5]);
+    image.set((1, 0), [0.3, 0.3, 0.3, 1.0]);
+
+    let mut dest = image.new_like();
+    filter::HueRotate(360.0).eval(&[&image], &mut dest);
+    for c in 0..4 {
+        assert!((dest.get_f((0, 0), c) - image.get_f((0, 0), c)).abs() < 1e-4);
+    }
+
+    filter::HueRotate(180.0).eval(&[&image], &mut dest);
+    let px = dest.get((0, 0));
+    assert!(px[1] > px[0] && px[2] > px[0]);
+    assert_eq!(px[3], 0.5);
+
+    // Neutral colors have no hue
+    for c in 0..3 {
+        assert!((dest.get_f((1, 0), c) - 0.3).abs() < 1e-3);
+    }
+
+    let lab = |image: &Image<f32, Rgba>| {
+        let mut px = image.get_pixel((0, 0));
+        px[3] = 1.0;
+        px.convert::<Lab>()[0]
+    };
+    assert!((lab(&dest) - lab(&image)).abs() < 0.02);
+}
+
+#[test]
+fn test_color_matrix() {
+    let mut image = Image::<f32, Rgba>::new((1, 1));
+    image.set((0, 0), [0.2, 0.4, 0.6, 0.5]);
+    let mut dest = image.new_like();
+
+    let swap = filter::ColorMatrix::new(3, [0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0]).unwrap();
+    swap.eval(&[&image], &mut dest);
+    assert_eq!(dest.get((0, 0)).as_slice(), &[0.6, 0.4, 0.2, 0.5]);
+    assert!(filter::ColorMatrix::new(3, [1.0; 4]).is_none());
+
+    filter::ColorMatrix::grayscale().eval(&[&image], &mut dest);
+    let px = dest.get((0, 0));
+    assert!((px[0] - px[1]).abs() < 1e-6 && (px[1] - px[2]).abs() < 1e-6);
+
+    let combined = swap.compose(&filter::ColorMatrix::identity(3).with_offset([0.1, 0.0, 0.0]));
+    let mut px = [0.2f64, 0.4, 0.6];
+    combined.apply(&mut px);
+    assert!((px[0] - 0.7).abs() < 1e-9 && (px[2] - 0.2).abs() < 1e-9);
+
+    // Gray is preserved by color blindness simulation
+    let mut px = [0.5f64, 0.5, 0.5];
+    filter::ColorMatrix::deuteranopia().apply(&mut px);
+    assert!(px.iter().all(|x| (x - 0.5).abs() < 1e-3));
+}
+
+#[test]
+fn test_dither() {
+    // Horizontal gradient reduced to black and white
+    let mut image = Image::<f32, Gray>::new((64, 16));
+    image.for_each(|pt, mut px| px[0] = pt.x as f32 / 63.0);
+    let mut dest = image.new_like();
+
+    let ordered = filter::OrderedDither::new(2);
+    let mut thresholds: Vec<f64> = (0..16).map(|i| ordered.threshold(i % 4, i / 4)).collect();
+    thresholds.sort_by(|a, b| a.total_cmp(b));
+    thresholds.dedup();
+    assert_eq!(thresholds.len(), 16);
+
+    for dither in [
+        Box::new(ordered) as Box<dyn Filter<f32, Gray>>,
+        Box::new(filter::FloydSteinberg::new(2)),
+    ] {
+        dither.eval(&[&image], &mut dest);
+        dest.each_pixel(|_, px| assert!(px[0] == 0.0 || px[0] == 1.0));
+
+        // Averages over each column band match the gradient
+        for band in 0..4 {
+            let (mut sum, mut expected) = (0.0, 0.0);
+            for y in 0..16 {
+                for x in band * 16..(band + 1) * 16 {
+                    sum += dest.get_f((x, y), 0);
+                    expected += image.get_f((x, y), 0);
+                }
+            }
+            assert!(
+                (sum - expected).abs() / 256.0 < 0.08,
+                "{} {}",
+                sum,
+                expected
+            );
+        }
+    }
+
+    assert_eq!(filter::FloydSteinberg::for_type::<u8>().levels, 256);
+    assert_eq!(filter::OrderedDither::with_bits(4).levels, 16);
+}
+
+#[test]
+fn test_posterize_solarize() {
+    let mut image = Image::<f32, Rgba>::new((16, 1));
+    image.for_each(|pt, mut px| {
+        for c in 0..3 {
+            px[c] = pt.x as f32 / 15.0;
+        }
+        px[3] = 0.5;
+    });
+    let mut dest = image.new_like();
+
+    filter::Posterize(3).eval(&[&image], &mut dest);
+    dest.each_pixel(|pt, px| {
+        let expected = (pt.x as f64 / 15.0 * 2.0).round() / 2.0;
+        assert!((px[0] - expected).abs() < 1e-6);
+        assert_eq!(px[3], 0.5);
+    });
+
+    filter::Solarize(0.5).eval(&[&image], &mut dest);
+    assert!((dest.get((3, 0))[1] - 0.2).abs() < 1e-6);
+    assert!((dest.get((12, 0))[1] - 0.2).abs() < 1e-6);
+    assert!(dest.get((15, 0))[2].abs() < 1e-6);
+    assert_eq!(dest.get((15, 0))[3], 0.5);
+}
+
+#[test]
+fn test_canny() {
+    let mut image = Image::<f32, Gray>::new((48, 48));
+    image.for_each(|pt, mut px| {
+        if (12..36).contains(&pt.x) && (12..36).contains(&pt.y) {
+            px[0] = 0.8;
+        }
+    });
+    let mut dest = image.new_like();
+    filter::Canny::new(0.1, 0.3).eval(&[&image], &mut dest);
+
+    // Edges are thin lines along the border of the square
+    for y in 16..32 {
+        let row: Vec<usize> = (0..48).filter(|x| dest.get((*x, y))[0] > 0.5).collect();
+        assert_eq!(row.len(), 2, "{:?}", row);
+        assert!(
+            row[0].abs_diff(12) <= 1 && row[1].abs_diff(35) <= 1,
+            "{:?}",
+            row
+        );
+    }
+    assert_eq!(dest.get((24, 24))[0], 0.0);
+    assert_eq!(dest.get((2, 2))[0], 0.0);
+
+    // Weak edges are dropped unless they are connected to a strong edge
+    let edges = filter::Canny::new(0.05, 0.95).edges(&image);
+    assert!(edges.iter().all(|e| !e));
+}
+
+#[test]
+fn test_expr() {
+    let mut image = Image::<f32, Rgb>::new((8, 4));
+    image.for_each(|pt, mut px| {
+        px[0] = 0.25;
+        px[1] = 0.5;
+        px[2] = pt.x as f32 / 7.0;
+    });
+    let mut dest = image.new_like();
+
+    let expr = Expr::new("1.0 - r, g * 0.5, b").unwrap();
+    expr.eval(&[&image], &mut dest);
+    let px = dest.get_pixel((7, 2));
+    assert!((px[0] - 0.75).abs() < 1e-6 && (px[1] - 0.25).abs() < 1e-6);
+    assert!((px[2] - 1.0).abs() < 1e-6);
+
+    // Positions, conditionals and functions
+    Expr::new("x < w / 2 ? 0 : clamp(max(r, g) * 2 ^ 2, 0, 0.9)")
+        .unwrap()
+        .eval(&[&image], &mut dest);
+    assert_eq!(dest.get_f((1, 0), 0), 0.0);
+    assert!((dest.get_f((6, 0), 1) - 0.9).abs() < 1e-6);
+
+    // Multiple inputs
+    let mut other = image.new_like();
+    other.for_each(|_, mut px| px[0] = 0.5);
+    let expr = Expr::new("mix(r, r1, u), 0, -b1 + 2 % 3 / 4").unwrap();
+    assert_eq!(expr.inputs(), 2);
+    expr.eval(&[&image, &other], &mut dest);
+    assert!((dest.get_f((7, 0), 0) - 0.5).abs() < 1e-6);
+    assert!((dest.get_f((0, 0), 0) - 0.25).abs() < 1e-6);
+    assert!((dest.get_f((0, 0), 2) - 0.5).abs() < 1e-6);
+
+    for source in ["r +", "foo", "sin(1, 2)", "r, g", "(r", "r $ g", "q1"] {
+        assert!(Expr::new(source).is_err(), "{}", source);
+    }
+}
+
+#[test]
+fn test_laplacian_of_gaussian() {
+    let log = Kernel::laplacian_of_gaussian(2.0);
+    let dog = Kernel::difference_of_gaussians(2.0, 3.2);
+    assert!(log.sum().abs() < 1e-9 && dog.sum().abs() < 1e-9);
+
+    let mut image = Image::<f32, Gray>::new((48, 48));
+    image.for_each(|pt, mut px| {
+        if (12..36).contains(&pt.x) && (12..36).contains(&pt.y) {
+            px[0] = 0.8;
+        }
+    });
+
+    for (kernel, sign) in [(log, -1.0), (dog, 1.0)] {
+        let mut response = image.new_like();
+        kernel.eval(&[&image], &mut response);
+        assert!(response.get_f((13, 24), 0) * sign > 0.0);
+        assert!(response.get_f((10, 24), 0) * sign < 0.0);
+
+        let mut edges = image.new_like();
+        filter::ZeroCrossing::new(0.01).eval(&[&response], &mut edges);
+        for y in 18..30 {
+            let row: Vec<usize> = (0..48).filter(|x| edges.get((*x, y))[0] > 0.5).collect();
+            assert!(!row.is_empty() && row.len() <= 4, "{:?}", row);
+            assert!(row
+                .iter()
+                .all(|x| x.abs_diff(12) <= 1 || x.abs_diff(35) <= 1));
+        }
+        assert_eq!(edges.get((24, 24))[0], 0.0);
+    }
+}
+
+#[test]
+fn test_seeded_noise() {
+    let mut image = Image::<f32, Rgb>::new((32, 32));
+    image.data_mut().iter_mut().for_each(|x| *x = 0.5);
+    let mut a = image.new_like();
+    let mut b = image.new_like();
+
+    let noise = filter::Noise::new(0.1).with_seed(42);
+    noise.eval(&[&image], &mut a);
+    noise.eval(&[&image], &mut b);
+    assert!(a == b);
+
+    let mut other = noise;
+    other.set_seed(Seed(42).derive(1));
+    other.eval(&[&image], &mut b);
+    assert!(a != b);
+
+    // Noise has the requested standard deviation
+    let values: Vec<f64> = a.data().iter().map(|x| *x as f64 - 0.5).collect();
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let std = (values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / values.len() as f64).sqrt();
+    assert!(
+        mean.abs() < 0.01 && (std - 0.1).abs() < 0.01,
+        "{} {}",
+        mean,
+        std
+    );
+
+    filter::Noise::new(0.1)
+        .with_monochrome(true)
+        .eval(&[&image], &mut b);
+    b.each_pixel(|_, px| assert!((px[0] - px[1]).abs() < 1e-6 && (px[1] - px[2]).abs() < 1e-6));
+
+    let mut rng = Seed(7).rng();
+    let first: Vec<u64> = (0..4).map(|_| rng.next_u64()).collect();
+    let mut rng = Rng::new(7);
+    assert_eq!(first, (0..4).map(|_| rng.next_u64()).collect::<Vec<_>>());
+}
@@ -9,6 +9,12 @@ pub struct ImageTexture<T: Type, C: Color> {
 
     /// Texture
     pub texture: Texture,
+
+    /// Size of the allocated texture storage
+    pub size: Size,
+
+    /// Number of mipmap levels, including the base level
+    pub levels: usize,
     _t: std::marker::PhantomData<(T, C)>,
 }
 
@@ -18,9 +24,168 @@ impl<T: Type, C: Color> ImageTexture<T, C> {
         ImageTexture {
             framebuffer,
             texture,
+            size: Size::zero(),
+            levels: 1,
             _t: std::marker::PhantomData,
         }
     }
+
+    /// Replace the contents of the texture with `image`, the existing texture object is reused
+    /// and its storage is only reallocated when the image size changes
+    pub fn replace<I: ToTexture<T, C>>(
+        &mut self,
+        gl: &glow::Context,
+        image: &I,
+    ) -> Result<(), Error> {
+        let meta = image.get_meta();
+        if meta.size() == self.size {
+            image.update_image_texture(gl, self, Region::new(Point::zero(), self.size))?;
+            self.clear_mipmaps(gl);
+            return Ok(());
+        }
+
+        unsafe {
+            gl.bind_texture(glow::TEXTURE_2D, Some(self.texture));
+            gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                image.internal()? as i32,
+                meta.width() as i32,
+                meta.height() as i32,
+                0,
+                I::COLOR,
+                I::KIND,
+                Some(image.get_data()),
+            );
+            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAX_LEVEL, 0);
+            gl.bind_texture(glow::TEXTURE_2D, None);
+        }
+        self.size = meta.size();
+        self.levels = 1;
+        Ok(())
+    }
+
+    /// Upload mipmap levels, `levels` should contain the chain starting with the first level
+    /// after the base level, for example `&image.mipmaps()[1..]`. Minified images are drawn
+    /// from the closest level to avoid aliasing.
+    pub fn set_mipmaps<I: ToTexture<T, C>>(
+        &mut self,
+        gl: &glow::Context,
+        levels: &[I],
+    ) -> Result<(), Error> {
+        unsafe {
+            gl.bind_texture(glow::TEXTURE_2D, Some(self.texture));
+            for (i, level) in levels.iter().enumerate() {
+                let meta = level.get_meta();
+                gl.tex_image_2d(
+                    glow::TEXTURE_2D,
+                    i as i32 + 1,
+                    level.internal()? as i32,
+                    meta.width() as i32,
+                    meta.height() as i32,
+                    0,
+                    I::COLOR,
+                    I::KIND,
+                    Some(level.get_data()),
+                );
+            }
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_MAX_LEVEL,
+                levels.len() as i32,
+            );
+            gl.bind_texture(glow::TEXTURE_2D, None);
+        }
+        self.levels = levels.len() + 1;
+        Ok(())
+    }
+
+    /// Regenerate every mipmap level from the base level on the GPU. This is much cheaper than
+    /// `set_mipmaps` after a small update, but uses the driver's filter instead of a `MipFilter`.
+    pub fn generate_mipmaps(&mut self, gl: &glow::Context) {
+        let levels = (self.size.width.max(self.size.height).max(1).ilog2() + 1) as usize;
+        unsafe {
+            gl.bind_texture(glow::TEXTURE_2D, Some(self.texture));
+            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAX_LEVEL, levels as i32 - 1);
+            gl.generate_mipmap(glow::TEXTURE_2D);
+            gl.bind_texture(glow::TEXTURE_2D, None);
+        }
+        self.levels = levels;
+    }
+
+    /// Drop any mipmap levels so only the base level is used, the levels uploaded by
+    /// `set_mipmaps` no longer match the base level once it has been replaced
+    pub fn clear_mipmaps(&mut self, gl: &glow::Context) {
+        unsafe {
+            gl.bind_texture(glow::TEXTURE_2D, Some(self.texture));
+            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAX_LEVEL, 0);
+            gl.bind_texture(glow::TEXTURE_2D, None);
+        }
+        self.levels = 1;
+    }
+
+    /// Get the mipmap level that should be used to display the texture at `display_size`, this
+    /// is the smallest level that is still at least as large as the display
+    pub fn mip_level(&self, display_size: Size) -> usize {
+        if display_size.width == 0 || display_size.height == 0 {
+            return 0;
+        }
+
+        let ratio = (self.size.width as f64 / display_size.width as f64)
+            .min(self.size.height as f64 / display_size.height as f64);
+        if ratio <= 1.0 {
+            return 0;
+        }
+        (ratio.log2().floor() as usize).min(self.levels - 1)
+    }
+
+    /// Returns `false` when the context has been lost or the texture and framebuffer no longer
+    /// exist in `gl`, in which case a new `ImageTexture` should be created
+    pub fn is_valid(&self, gl: &glow::Context) -> bool {
+        unsafe {
+            gl.get_error() != glow::CONTEXT_LOST
+                && gl.is_texture(self.texture)
+                && gl.is_framebuffer(self.framebuffer)
+        }
+    }
+
+    /// Read the base level of the texture into `dest`, which should have the same size
+    pub fn read(&self, gl: &glow::Context, dest: &mut Image<T, C>)
+    where
+        Image<T, C>: ToTexture<T, C>,
+    {
+        unsafe {
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(self.framebuffer));
+            gl.framebuffer_texture_2d(
+                glow::READ_FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::TEXTURE_2D,
+                Some(self.texture),
+                0,
+            );
+            gl.pixel_store_i32(glow::PACK_ALIGNMENT, 1);
+            gl.read_pixels(
+                0,
+                0,
+                dest.width() as i32,
+                dest.height() as i32,
+                <Image<T, C> as ToTexture<T, C>>::COLOR,
+                <Image<T, C> as ToTexture<T, C>>::KIND,
+                PixelPackData::Slice(dest.buffer_mut()),
+            );
+            gl.pixel_store_i32(glow::PACK_ALIGNMENT, 4);
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+        }
+    }
+
+    /// Delete the texture and framebuffer, `gl` should be the context they were created in
+    pub fn destroy(&mut self, gl: &glow::Context) {
+        unsafe {
+            gl.delete_framebuffer(self.framebuffer);
+            gl.delete_texture(self.texture);
+        }
+        self.size = Size::zero();
+    }
 }
 
 /// ToTexture is defined for image types that can be converted to OpenGL textures
@@ -73,7 +238,7 @@ pub trait ToTexture<T: Type, C: Color> {
                 .create_framebuffer()
                 .expect("Unable to create framebuffer");
             let texture = gl.create_texture().expect("Unable to create texture");
-            let image_texture = ImageTexture::<T, C>::new(framebuffer, texture);
+            let mut image_texture = ImageTexture::<T, C>::new(framebuffer, texture);
 
             // Texture
             gl.bind_texture(glow::TEXTURE_2D, Some(texture));
@@ -103,6 +268,7 @@ pub trait ToTexture<T: Type, C: Color> {
             );
 
             gl.bind_texture(glow::TEXTURE_2D, None);
+            image_texture.size = meta.size();
 
             // Framebuffer
             gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(image_texture.framebuffer));
@@ -118,8 +284,49 @@ pub trait ToTexture<T: Type, C: Color> {
         }
     }
 
-    /// Draw the texture on the framebuffer
-    fn draw_image_texture(
+    /// Upload part of the image to an existing texture
+    fn update_image_texture(
+        &self,
+        gl: &glow::Context,
+        image_texture: &ImageTexture<T, C>,
+        roi: Region,
+    ) -> Result<(), Error> {
+        let meta = self.get_meta();
+        let roi = match roi.intersection(&Region::new(Point::zero(), meta.size())) {
+            Some(roi) => roi,
+            None => return Ok(()),
+        };
+
+        unsafe {
+            gl.bind_texture(glow::TEXTURE_2D, Some(image_texture.texture));
+            gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
+            gl.pixel_store_i32(glow::UNPACK_ROW_LENGTH, meta.width() as i32);
+            gl.pixel_store_i32(glow::UNPACK_SKIP_PIXELS, roi.origin.x as i32);
+            gl.pixel_store_i32(glow::UNPACK_SKIP_ROWS, roi.origin.y as i32);
+
+            gl.tex_sub_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                roi.origin.x as i32,
+                roi.origin.y as i32,
+                roi.width() as i32,
+                roi.height() as i32,
+                Self::COLOR,
+                Self::KIND,
+                PixelUnpackData::Slice(self.get_data()),
+            );
+
+            gl.pixel_store_i32(glow::UNPACK_ROW_LENGTH, 0);
+            gl.pixel_store_i32(glow::UNPACK_SKIP_PIXELS, 0);
+            gl.pixel_store_i32(glow::UNPACK_SKIP_ROWS, 0);
+            gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 4);
+            gl.bind_texture(glow::TEXTURE_2D, None);
+        }
+        Ok(())
+    }
+
+    /// Draw an existing texture on the framebuffer without uploading any image data
+    fn blit_image_texture(
         &self,
         gl: &glow::Context,
         image_texture: &ImageTexture<T, C>,
@@ -130,7 +337,53 @@ pub trait ToTexture<T: Type, C: Color> {
         let y = offset.y;
         let display_width = display_size.width;
         let display_height = display_size.height;
+        let meta = self.get_meta();
+
+        // Minified images are read from the closest mipmap level
+        let level = image_texture.mip_level(display_size);
+        let width = (meta.width() >> level).max(1);
+        let height = (meta.height() >> level).max(1);
+        let filter = if level > 0 {
+            glow::LINEAR
+        } else {
+            glow::NEAREST
+        };
+
+        unsafe {
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(image_texture.framebuffer));
+            gl.framebuffer_texture_2d(
+                glow::READ_FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::TEXTURE_2D,
+                Some(image_texture.texture),
+                level as i32,
+            );
 
+            gl.blit_framebuffer(
+                0,
+                height as i32,
+                width as i32,
+                0,
+                x as i32,
+                y as i32,
+                x as i32 + display_width as i32,
+                y as i32 + display_height as i32,
+                glow::COLOR_BUFFER_BIT,
+                filter,
+            );
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+        }
+        Ok(())
+    }
+
+    /// Draw the texture on the framebuffer
+    fn draw_image_texture(
+        &self,
+        gl: &glow::Context,
+        image_texture: &ImageTexture<T, C>,
+        display_size: Size,
+        offset: Point,
+    ) -> Result<(), Error> {
         unsafe {
             // Texture
             gl.bind_texture(glow::TEXTURE_2D, Some(image_texture.texture));
@@ -149,32 +402,256 @@ pub trait ToTexture<T: Type, C: Color> {
             );
 
             gl.bind_texture(glow::TEXTURE_2D, None);
+        }
 
-            // Framebuffer
-            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(image_texture.framebuffer));
+        self.blit_image_texture(gl, image_texture, display_size, offset)
+    }
+}
+
+/// Number of values available to a `GlslFilter` through the `iParams` uniform
+pub const GLSL_PARAMS: usize = 16;
+
+/// Vertex shader drawing a single triangle that covers the whole viewport
+const GLSL_VERTEX: &str = "#version 330 core
+void main() {
+    vec2 pos = vec2(float((gl_VertexID << 1) & 2), float(gl_VertexID & 2));
+    gl_Position = vec4(pos * 2.0 - 1.0, 0.0, 1.0);
+}
+";
+
+/// Declarations available to `GlslFilter` sources
+const GLSL_HEADER: &str = "#version 330 core
+uniform sampler2D iChannel0;
+uniform vec3 iChannelResolution0;
+uniform vec3 iResolution;
+uniform float iTime;
+uniform float iParams[16];
+uniform vec2 image2_Offset;
+out vec4 image2_FragColor;
+#line 1
+";
+
+/// Entry point calling the Shadertoy style `mainImage` function
+const GLSL_FOOTER: &str = "
+void main() {
+    mainImage(image2_FragColor, gl_FragCoord.xy - image2_Offset);
+}
+";
+
+/// Fragment shader executed for every pixel of an image, using the same conventions as
+/// Shadertoy so effects can be prototyped without writing any Rust. The source should define
+/// `void mainImage(out vec4 fragColor, in vec2 fragCoord)` and can use the following uniforms:
+///
+/// - `iChannel0`: input image, `texture(iChannel0, fragCoord / iResolution.xy)` reads the pixel
+///   being computed
+/// - `iChannelResolution0`: input size in pixels
+/// - `iResolution`: output size in pixels
+/// - `iTime`: time in seconds, provided by the caller
+/// - `iParams`: up to `GLSL_PARAMS` user defined values
+///
+/// `fragCoord` is measured in pixels, with `y = 0` at the first row of the image. Output
+/// textures must be color-renderable, which is always the case for `Rgba` images.
+pub struct GlslFilter {
+    /// Linked shader program
+    pub program: Program,
+    vertex_array: VertexArray,
+}
+
+/// Compile a single shader stage
+unsafe fn compile_shader(gl: &glow::Context, kind: u32, source: &str) -> Result<Shader, Error> {
+    let shader = gl.create_shader(kind).map_err(Error::Message)?;
+    gl.shader_source(shader, source);
+    gl.compile_shader(shader);
+    if !gl.get_shader_compile_status(shader) {
+        let log = gl.get_shader_info_log(shader);
+        gl.delete_shader(shader);
+        return Err(Error::Message(format!("Unable to compile shader: {}", log)));
+    }
+    Ok(shader)
+}
+
+impl GlslFilter {
+    /// Compile a filter from the source of a `mainImage` function and any helpers it uses
+    pub fn new(gl: &glow::Context, source: &str) -> Result<GlslFilter, Error> {
+        unsafe {
+            let vertex = compile_shader(gl, glow::VERTEX_SHADER, GLSL_VERTEX)?;
+            let fragment = match compile_shader(
+                gl,
+                glow::FRAGMENT_SHADER,
+                &format!("{}{}{}", GLSL_HEADER, source, GLSL_FOOTER),
+            ) {
+                Ok(fragment) => fragment,
+                Err(e) => {
+                    gl.delete_shader(vertex);
+                    return Err(e);
+                }
+            };
+
+            let program = gl.create_program().map_err(Error::Message)?;
+            gl.attach_shader(program, vertex);
+            gl.attach_shader(program, fragment);
+            gl.link_program(program);
+            for shader in [vertex, fragment] {
+                gl.detach_shader(program, shader);
+                gl.delete_shader(shader);
+            }
+            if !gl.get_program_link_status(program) {
+                let log = gl.get_program_info_log(program);
+                gl.delete_program(program);
+                return Err(Error::Message(format!("Unable to link shader: {}", log)));
+            }
+
+            let vertex_array = match gl.create_vertex_array() {
+                Ok(vertex_array) => vertex_array,
+                Err(e) => {
+                    gl.delete_program(program);
+                    return Err(Error::Message(e));
+                }
+            };
+            Ok(GlslFilter {
+                program,
+                vertex_array,
+            })
+        }
+    }
+
+    /// Run the filter on the GPU, reading from `input` and writing every pixel of `output`.
+    /// Missing `params` are set to zero and extra values are ignored.
+    pub fn render<T: Type, C: Color, U: Type, D: Color>(
+        &self,
+        gl: &glow::Context,
+        input: &ImageTexture<T, C>,
+        output: &ImageTexture<U, D>,
+        time: f64,
+        params: &[f32],
+    ) -> Result<(), Error> {
+        let region = Region::new(Point::zero(), output.size);
+        self.render_region(gl, input, output, region, time, params)
+    }
+
+    /// Run the filter on the GPU, writing only the pixels of `output` inside `region`. The
+    /// filter sees the region as the whole output: `iResolution` is the region size and
+    /// `fragCoord` is relative to the region origin. This can be used to pack the results of
+    /// many small renders into a single texture.
+    pub fn render_region<T: Type, C: Color, U: Type, D: Color>(
+        &self,
+        gl: &glow::Context,
+        input: &ImageTexture<T, C>,
+        output: &ImageTexture<U, D>,
+        region: Region,
+        time: f64,
+        params: &[f32],
+    ) -> Result<(), Error> {
+        let mut values = [0.0; GLSL_PARAMS];
+        for (v, p) in values.iter_mut().zip(params) {
+            *v = *p;
+        }
+
+        unsafe {
+            let mut viewport = [0; 4];
+            gl.get_parameter_i32_slice(glow::VIEWPORT, &mut viewport);
+
+            gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(output.framebuffer));
             gl.framebuffer_texture_2d(
-                glow::READ_FRAMEBUFFER,
+                glow::DRAW_FRAMEBUFFER,
                 glow::COLOR_ATTACHMENT0,
                 glow::TEXTURE_2D,
-                Some(image_texture.texture),
+                Some(output.texture),
                 0,
             );
+            if gl.check_framebuffer_status(glow::DRAW_FRAMEBUFFER) != glow::FRAMEBUFFER_COMPLETE {
+                gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, None);
+                return Err(Error::Message(
+                    "Output texture is not color-renderable".to_string(),
+                ));
+            }
 
-            gl.blit_framebuffer(
-                0,
-                meta.height() as i32,
-                meta.width() as i32,
-                0,
-                x as i32,
-                y as i32,
-                x as i32 + display_width as i32,
-                y as i32 + display_height as i32,
-                glow::COLOR_BUFFER_BIT,
-                glow::NEAREST,
+            gl.viewport(
+                region.origin.x as i32,
+                region.origin.y as i32,
+                region.width() as i32,
+                region.height() as i32,
             );
-            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+            gl.use_program(Some(self.program));
+            gl.bind_vertex_array(Some(self.vertex_array));
+            gl.active_texture(glow::TEXTURE0);
+            gl.bind_texture(glow::TEXTURE_2D, Some(input.texture));
+
+            let uniform = |name| gl.get_uniform_location(self.program, name);
+            gl.uniform_1_i32(uniform("iChannel0").as_ref(), 0);
+            gl.uniform_3_f32(
+                uniform("iChannelResolution0").as_ref(),
+                input.size.width as f32,
+                input.size.height as f32,
+                1.0,
+            );
+            gl.uniform_3_f32(
+                uniform("iResolution").as_ref(),
+                region.width() as f32,
+                region.height() as f32,
+                1.0,
+            );
+            gl.uniform_2_f32(
+                uniform("image2_Offset").as_ref(),
+                region.origin.x as f32,
+                region.origin.y as f32,
+            );
+            gl.uniform_1_f32(uniform("iTime").as_ref(), time as f32);
+            gl.uniform_1_f32_slice(uniform("iParams").as_ref(), &values);
+
+            gl.draw_arrays(glow::TRIANGLES, 0, 3);
+
+            gl.bind_texture(glow::TEXTURE_2D, None);
+            gl.bind_vertex_array(None);
+            gl.use_program(None);
+            gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, None);
+            gl.viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+
+            match gl.get_error() {
+                glow::NO_ERROR => Ok(()),
+                e => Err(Error::Message(format!("OpenGL error: {:#x}", e))),
+            }
+        }
+    }
+
+    /// Run the filter on an image and read the result back, the output has the same size and
+    /// format as the input
+    pub fn apply<T: Type, C: Color>(
+        &self,
+        gl: &glow::Context,
+        image: &Image<T, C>,
+        time: f64,
+        params: &[f32],
+    ) -> Result<Image<T, C>, Error>
+    where
+        Image<T, C>: ToTexture<T, C>,
+    {
+        let mut input = image.create_image_texture(gl)?;
+        let mut output = match image.create_image_texture(gl) {
+            Ok(output) => output,
+            Err(e) => {
+                input.destroy(gl);
+                return Err(e);
+            }
+        };
+
+        let result = self.render(gl, &input, &output, time, params).map(|_| {
+            let mut dest = image.new_like();
+            output.read(gl, &mut dest);
+            dest
+        });
+
+        input.destroy(gl);
+        output.destroy(gl);
+        result
+    }
+
+    /// Delete the shader program, `gl` should be the context it was created in
+    pub fn destroy(&mut self, gl: &glow::Context) {
+        unsafe {
+            gl.delete_vertex_array(self.vertex_array);
+            gl.delete_program(self.program);
         }
-        Ok(())
     }
 }
 
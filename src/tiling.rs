@@ -0,0 +1,170 @@
+use crate::*;
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Make an image tile seamlessly. The image is cross-faded with a copy of itself offset by
+    /// half its size, which has no seams at the image borders. The offset copy is used within
+    /// `blend_width` pixels of the borders and fades into the original image towards the center,
+    /// `blend_width` is limited to half the image size.
+    pub fn make_tileable(&self, blend_width: usize) -> Image<T, C> {
+        let (width, height) = (self.width(), self.height());
+        let blend = blend_width.min(width / 2).min(height / 2).max(1) as f64;
+
+        let mut dest = self.new_like();
+        dest.for_each(|pt, mut px| {
+            let edge_x = pt.x.min(width - 1 - pt.x) as f64;
+            let edge_y = pt.y.min(height - 1 - pt.y) as f64;
+            let t = (edge_x.min(edge_y) / blend).min(1.0);
+
+            // Smoothstep to avoid visible creases at the start and end of the blend
+            let w = t * t * (3.0 - 2.0 * t);
+
+            let shifted = ((pt.x + width / 2) % width, (pt.y + height / 2) % height);
+            let mut out = self.get_pixel(pt) * w;
+            out += &(self.get_pixel(shifted) * (1.0 - w));
+            out.copy_to_slice(&mut px);
+        });
+        dest
+    }
+
+    /// Repeat the image `count` times in each direction, useful for previewing tileable textures
+    pub fn tile(&self, count: usize) -> Image<T, C> {
+        let (width, height) = (self.width(), self.height());
+        let mut dest = Image::new((width * count, height * count));
+        dest.for_each(|pt, mut px| {
+            px.copy_from_slice(self.get((pt.x % width, pt.y % height)));
+        });
+        dest
+    }
+
+    /// Split the image into patches of the given size, starting every `stride` pixels. The last
+    /// row and column of patches are aligned to the image edges so every pixel is covered, and
+    /// images smaller than a patch are extended by repeating their edge pixels. Each item
+    /// contains the position of the patch and a copy of its pixels.
+    pub fn extract_patches(
+        &self,
+        size: impl Into<Size>,
+        stride: usize,
+    ) -> impl '_ + Iterator<Item = (Point, Image<T, C>)> {
+        let size = size.into();
+        let xs = patch_positions(self.width(), size.width, stride);
+        let ys = patch_positions(self.height(), size.height, stride);
+        ys.into_iter()
+            .flat_map(move |y| xs.clone().into_iter().map(move |x| Point::new(x, y)))
+            .map(move |origin| {
+                let mut patch = Image::new(size);
+                patch.for_each(|pt, mut px| {
+                    let x = (origin.x + pt.x).min(self.width() - 1);
+                    let y = (origin.y + pt.y).min(self.height() - 1);
+                    px.copy_from_slice(self.get((x, y)));
+                });
+                (origin, patch)
+            })
+    }
+
+    /// Reassemble patches, for example those returned by `Image::extract_patches` after
+    /// processing, into an image of the given size. Where patches overlap they are blended
+    /// with weights that fall off towards the patch edges to avoid visible seams, parts of
+    /// patches outside the image are ignored.
+    pub fn from_patches(
+        size: impl Into<Size>,
+        patches: impl IntoIterator<Item = (Point, Image<T, C>)>,
+    ) -> Image<T, C> {
+        let size = size.into();
+        let (width, height) = (size.width, size.height);
+        let mut sum = vec![0.0; width * height * C::CHANNELS];
+        let mut weights = vec![0.0; width * height];
+
+        for (origin, patch) in patches {
+            let (pw, ph) = (patch.width(), patch.height());
+            for y in 0..ph.min(height.saturating_sub(origin.y)) {
+                for x in 0..pw.min(width.saturating_sub(origin.x)) {
+                    let w = (x + 1).min(pw - x).min(y + 1).min(ph - y) as f64;
+                    let i = (origin.y + y) * width + origin.x + x;
+                    weights[i] += w;
+                    let px = patch.get((x, y));
+                    for c in 0..C::CHANNELS {
+                        sum[i * C::CHANNELS + c] += px[c].to_norm() * w;
+                    }
+                }
+            }
+        }
+
+        let mut dest = Image::new(size);
+        for (i, px) in dest.data_mut().iter_mut().enumerate() {
+            let w = weights[i / C::CHANNELS];
+            if w > 0.0 {
+                *px = T::from_norm(sum[i] / w);
+            }
+        }
+        dest
+    }
+}
+
+/// Start positions of patches along one axis
+fn patch_positions(len: usize, size: usize, stride: usize) -> Vec<usize> {
+    if len <= size {
+        return vec![0];
+    }
+    let last = len - size;
+    let mut positions: Vec<usize> = (0..=last).step_by(stride.max(1)).collect();
+    if positions.last() != Some(&last) {
+        positions.push(last);
+    }
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_make_tileable() {
+        let mut image = Image::<f32, Gray>::new((32, 16));
+        image.for_each(|pt, mut px| px[0] = pt.x as f32 / 31.0);
+
+        let seam = |image: &Image<f32, Gray>| {
+            (0..16)
+                .map(|y| (image.get((0, y))[0] - image.get((31, y))[0]).abs())
+                .fold(0.0, f32::max)
+        };
+        assert!(seam(&image) > 0.9);
+
+        let tileable = image.make_tileable(4);
+        assert!(seam(&tileable) < 0.05);
+
+        // The center of the image is left unchanged
+        assert_eq!(tileable.get((16, 8))[0], image.get((16, 8))[0]);
+
+        let tiled = tileable.tile(3);
+        assert_eq!(tiled.size(), Size::new(96, 48));
+        assert_eq!(tiled.get((33, 17))[0], tileable.get((1, 1))[0]);
+    }
+
+    #[test]
+    fn test_patches() {
+        let mut image = Image::<f32, Rgb>::new((37, 21));
+        image.for_each(|pt, mut px| {
+            px[0] = pt.x as f32 / 36.0;
+            px[1] = pt.y as f32 / 20.0;
+            px[2] = 0.25;
+        });
+
+        let patches: Vec<_> = image.extract_patches((16, 16), 12).collect();
+        let origins: Vec<_> = patches.iter().map(|(pt, _)| (pt.x, pt.y)).collect();
+        assert_eq!(
+            origins,
+            vec![(0, 0), (12, 0), (21, 0), (0, 5), (12, 5), (21, 5)]
+        );
+        assert!(patches.iter().all(|(_, p)| p.size() == Size::new(16, 16)));
+        assert_eq!(patches[4].1.get((0, 0)), image.get((12, 5)));
+
+        let restored = Image::from_patches(image.size(), patches);
+        for (a, b) in restored.data().iter().zip(image.data().iter()) {
+            assert!((a - b).abs() < 1e-5);
+        }
+
+        // Small images are padded with edge pixels
+        let (_, patch) = image.extract_patches((64, 8), 8).next().unwrap();
+        assert_eq!(patch.get((50, 0)), image.get((36, 0)));
+    }
+}
@@ -0,0 +1,52 @@
+//! Optional instrumentation, when the `trace` feature is enabled filters and pipelines emit
+//! `tracing` spans that can be used to find slow stages
+
+/// Guard for a traced stage, the elapsed time is reported as an event when it's dropped
+#[cfg(feature = "trace")]
+pub(crate) struct Stage {
+    _span: tracing::span::EnteredSpan,
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "trace")]
+impl Stage {
+    pub(crate) fn new(span: tracing::Span) -> Stage {
+        Stage {
+            _span: span.entered(),
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "trace")]
+impl Drop for Stage {
+    fn drop(&mut self) {
+        tracing::debug!(elapsed_us = self.start.elapsed().as_micros() as u64, "done");
+    }
+}
+
+/// No-op guard used when the `trace` feature is disabled
+#[cfg(not(feature = "trace"))]
+pub(crate) struct Stage;
+
+/// Enter a new `debug` span, the span is closed when the returned guard is dropped
+macro_rules! stage {
+    ($name:expr $(, $($fields:tt)*)?) => {{
+        #[cfg(feature = "trace")]
+        {
+            $crate::trace::Stage::new(tracing::debug_span!($name $(, $($fields)*)?))
+        }
+        #[cfg(not(feature = "trace"))]
+        {
+            $crate::trace::Stage
+        }
+    }};
+}
+
+/// Emit a `debug` event inside the current span
+macro_rules! trace_event {
+    ($($args:tt)*) => {
+        #[cfg(feature = "trace")]
+        tracing::debug!($($args)*);
+    };
+}
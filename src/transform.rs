@@ -1,36 +1,583 @@
 use crate::*;
 
+use crate::math::sinc;
+
 type EPoint<T> = euclid::Point2D<T, f64>;
 
 /// Transform is used to perform pixel-level transformations on an image
 pub type Transform = euclid::Transform2D<f64, f64, f64>;
 
+/// Interpolation method used to sample images at non-integer coordinates
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Interpolation {
+    /// Nearest neighbor
+    Nearest,
+
+    /// Linear interpolation between the 4 nearest pixels
+    #[default]
+    Bilinear,
+
+    /// Cubic (Catmull-Rom) interpolation using the 16 nearest pixels
+    Bicubic,
+
+    /// Lanczos interpolation using a 3 pixel radius
+    Lanczos3,
+}
+
+impl Interpolation {
+    /// Number of pixels on each side of the sample point that contribute to the result
+    pub fn radius(&self) -> f64 {
+        match self {
+            Interpolation::Nearest => 0.5,
+            Interpolation::Bilinear => 1.0,
+            Interpolation::Bicubic => 2.0,
+            Interpolation::Lanczos3 => 3.0,
+        }
+    }
+
+    /// Kernel weight at distance `x` from the sample point
+    pub fn weight(&self, x: f64) -> f64 {
+        let x = x.abs();
+        match self {
+            Interpolation::Nearest => {
+                if x <= 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Interpolation::Bilinear => (1.0 - x).max(0.0),
+            Interpolation::Bicubic => {
+                // Catmull-Rom, a = -0.5
+                if x < 1.0 {
+                    1.5 * x * x * x - 2.5 * x * x + 1.0
+                } else if x < 2.0 {
+                    -0.5 * x * x * x + 2.5 * x * x - 4.0 * x + 2.0
+                } else {
+                    0.0
+                }
+            }
+            Interpolation::Lanczos3 => {
+                if x < 3.0 {
+                    sinc(x) * sinc(x / 3.0)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// Sample an image at the given coordinates, pixel centers are located at integer
+    /// coordinates. Points outside of the image return an empty pixel.
+    pub fn sample<T: Type, C: Color>(&self, image: &Image<T, C>, x: f64, y: f64) -> Pixel<C> {
+        let (width, height) = (image.width(), image.height());
+        if width == 0
+            || height == 0
+            || x < -0.5
+            || y < -0.5
+            || x > width as f64 - 0.5
+            || y > height as f64 - 0.5
+        {
+            return Pixel::new();
+        }
+
+        let clamp = |v: f64, max: usize| v.clamp(0.0, (max - 1) as f64) as usize;
+
+        if *self == Interpolation::Nearest {
+            return image.get_pixel((clamp(x.round(), width), clamp(y.round(), height)));
+        }
+
+        let r = self.radius();
+        let (x0, x1) = ((x - r).floor() as isize + 1, (x + r).floor() as isize);
+        let (y0, y1) = ((y - r).floor() as isize + 1, (y + r).floor() as isize);
+
+        let mut px = Pixel::new();
+        px.fill(0.0);
+        let mut total = 0.0;
+        for j in y0..=y1 {
+            let wy = self.weight(y - j as f64);
+            if wy == 0.0 {
+                continue;
+            }
+            for i in x0..=x1 {
+                let w = wy * self.weight(x - i as f64);
+                if w == 0.0 {
+                    continue;
+                }
+                let pt = (clamp(i as f64, width), clamp(j as f64, height));
+                px += &(image.get_pixel(pt) * w);
+                total += w;
+            }
+        }
+
+        if total != 0.0 {
+            px /= total;
+        }
+        px
+    }
+
+    /// Sample an image at the given coordinates, pixel centers are located at integer
+    /// coordinates. Unlike `Interpolation::sample`, points outside of the image are handled
+    /// using `border`, which also applies to the kernel taps near the edges.
+    pub fn sample_with_border<T: Type, C: Color>(
+        &self,
+        image: &Image<T, C>,
+        x: f64,
+        y: f64,
+        border: Border,
+    ) -> Pixel<C> {
+        let (width, height) = (image.width(), image.height());
+        let mut px = Pixel::new();
+        px.fill(0.0);
+
+        let mut constant = Pixel::new();
+        constant.fill(border.value());
+
+        let add = |i: isize, j: isize, w: f64, px: &mut Pixel<C>| match (
+            border.index(i, width),
+            border.index(j, height),
+        ) {
+            (Some(i), Some(j)) => *px += &(image.get_pixel((i, j)) * w),
+            _ => *px += &(&constant * w),
+        };
+
+        if *self == Interpolation::Nearest {
+            add(x.round() as isize, y.round() as isize, 1.0, &mut px);
+            return px;
+        }
+
+        let r = self.radius();
+        let (x0, x1) = ((x - r).floor() as isize + 1, (x + r).floor() as isize);
+        let (y0, y1) = ((y - r).floor() as isize + 1, (y + r).floor() as isize);
+
+        let mut total = 0.0;
+        for j in y0..=y1 {
+            let wy = self.weight(y - j as f64);
+            if wy == 0.0 {
+                continue;
+            }
+            for i in x0..=x1 {
+                let w = wy * self.weight(x - i as f64);
+                if w == 0.0 {
+                    continue;
+                }
+                add(i, j, w, &mut px);
+                total += w;
+            }
+        }
+
+        if total != 0.0 {
+            px /= total;
+        }
+        px
+    }
+}
+
+impl<T: Type, C: Color> Image<T, C> {
+    /// Sample the image at sub-pixel coordinates, pixel centers are located at integer
+    /// coordinates. Points outside of the image return an empty pixel, see
+    /// `Image::sample_with_border` to control how they are handled.
+    pub fn sample(&self, x: f64, y: f64, interpolation: Interpolation) -> Pixel<C> {
+        interpolation.sample(self, x, y)
+    }
+
+    /// Sample the image at sub-pixel coordinates, using `border` for pixels outside of the image
+    pub fn sample_with_border(
+        &self,
+        x: f64,
+        y: f64,
+        interpolation: Interpolation,
+        border: Border,
+    ) -> Pixel<C> {
+        interpolation.sample_with_border(self, x, y, border)
+    }
+}
+
+/// A `Transform` with an explicit interpolation method
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Interpolated {
+    /// Transform, maps output coordinates to input coordinates
+    pub transform: Transform,
+
+    /// Interpolation method
+    pub interpolation: Interpolation,
+}
+
+/// Extension methods for `Transform`
+pub trait TransformExt {
+    /// Use the given interpolation method when applying the transform as a filter
+    fn with_interpolation(self, interpolation: Interpolation) -> Interpolated;
+}
+
+impl TransformExt for Transform {
+    fn with_interpolation(self, interpolation: Interpolation) -> Interpolated {
+        Interpolated {
+            transform: self,
+            interpolation,
+        }
+    }
+}
+
+fn transform_output_size<T: Type, C: Color>(transform: &Transform, input: &Input<T, C>) -> Size {
+    let rect = transform.outer_transformed_rect(&euclid::Rect::new(
+        euclid::Point2D::new(0., 0.),
+        input.images()[0].size().to_f64(),
+    ));
+    rect.size.to_usize()
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Interpolated {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn output_size(&self, input: &Input<T, C>, _dest: &mut Image<U, D>) -> Size {
+        transform_output_size(&self.transform, input)
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, px: &mut DataMut<U, D>) {
+        let pt = EPoint::new(pt.x as f64, pt.y as f64);
+        let dest = self.transform.transform_point(pt);
+        input.images()[0]
+            .sample(dest.x, dest.y, self.interpolation)
+            .copy_to_slice(px);
+    }
+}
+
+/// Transforms use bilinear interpolation when used as a filter, see `TransformExt` to select a
+/// different interpolation method
 impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Transform {
     fn schedule(&self) -> Schedule {
         Schedule::Image
     }
 
     fn output_size(&self, input: &Input<T, C>, _dest: &mut Image<U, D>) -> Size {
-        let rect = self.outer_transformed_rect(&euclid::Rect::new(
-            euclid::Point2D::new(0., 0.),
-            input.images()[0].size().to_f64(),
-        ));
-        rect.size.to_usize()
+        transform_output_size(self, input)
     }
 
     fn compute_at(&self, pt: Point, input: &Input<T, C>, px: &mut DataMut<U, D>) {
         let pt = EPoint::new(pt.x as f64, pt.y as f64);
         let dest = self.transform_point(pt);
-        let px1 = input.get_pixel((dest.x.floor() as usize, dest.y.floor() as usize), None);
-        let px2 = input.get_pixel((dest.x.ceil() as usize, dest.y.ceil() as usize), None);
+        input.images()[0]
+            .sample(dest.x, dest.y, Interpolation::default())
+            .copy_to_slice(px);
+    }
+}
 
-        ((px1 + &px2) / 2.).copy_to_slice(px);
+/// Output of `stabilize`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stabilization {
+    /// Compensating transform for each frame, these map output coordinates to input coordinates
+    /// and can be used directly as a filter
+    pub transforms: Vec<Transform>,
+
+    /// Region that contains valid pixels in every stabilized frame
+    pub crop: Region,
+}
+
+// Similarity transform parameters: translation, rotation and log-scale
+#[derive(Debug, Clone, Copy, Default)]
+struct Motion {
+    x: f64,
+    y: f64,
+    angle: f64,
+    scale: f64,
+}
+
+impl Motion {
+    fn from_transform(t: &Transform) -> Motion {
+        Motion {
+            x: t.m31,
+            y: t.m32,
+            angle: t.m12.atan2(t.m11),
+            scale: t.m11.hypot(t.m12).max(f64::EPSILON).ln(),
+        }
+    }
+
+    fn add(self, other: Motion) -> Motion {
+        Motion {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            angle: self.angle + other.angle,
+            scale: self.scale + other.scale,
+        }
+    }
+
+    fn sub(self, other: Motion) -> Motion {
+        Motion {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            angle: self.angle - other.angle,
+            scale: self.scale - other.scale,
+        }
+    }
+
+    /// Forward transform, rotating and scaling around `center`
+    fn forward(&self, center: EPoint<f64>) -> Transform {
+        Transform::translation(-center.x, -center.y)
+            .then_rotate(euclid::Angle::radians(self.angle))
+            .then_scale(self.scale.exp(), self.scale.exp())
+            .then_translate(euclid::Vector2D::new(center.x + self.x, center.y + self.y))
+    }
+}
+
+/// Smooth a camera trajectory and compute compensating transforms for video stabilization
+///
+/// - `motion`: frame-to-frame motion for each frame, mapping points in the previous frame to
+///   points in the current one; the first entry is typically the identity. Only the
+///   translation, rotation and uniform scale of each transform are used.
+/// - `radius`: number of frames on either side used to smooth the trajectory
+/// - `size`: frame size
+pub fn stabilize(motion: &[Transform], radius: usize, size: Size) -> Stabilization {
+    let mut trajectory = Vec::with_capacity(motion.len());
+    let mut current = Motion::default();
+    for t in motion {
+        current = current.add(Motion::from_transform(t));
+        trajectory.push(current);
+    }
+
+    let n = trajectory.len();
+    let center = EPoint::new(size.width as f64 / 2.0, size.height as f64 / 2.0);
+    let (w, h) = (size.width as f64, size.height as f64);
+    let mut bounds = euclid::Box2D::new(EPoint::new(0.0, 0.0), EPoint::new(w, h));
+
+    let transforms = (0..n)
+        .map(|i| {
+            let window = &trajectory[i.saturating_sub(radius)..(i + radius + 1).min(n)];
+            let mut smoothed = window.iter().fold(Motion::default(), |acc, m| acc.add(*m));
+            let k = window.len() as f64;
+            smoothed.x /= k;
+            smoothed.y /= k;
+            smoothed.angle /= k;
+            smoothed.scale /= k;
+
+            let forward = smoothed.sub(trajectory[i]).forward(center);
+            let corners = [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)]
+                .map(|(x, y)| forward.transform_point(EPoint::new(x, y)));
+
+            // Axis-aligned rectangle inside of the transformed frame
+            bounds.min.x = bounds.min.x.max(corners[0].x).max(corners[3].x);
+            bounds.max.x = bounds.max.x.min(corners[1].x).min(corners[2].x);
+            bounds.min.y = bounds.min.y.max(corners[0].y).max(corners[1].y);
+            bounds.max.y = bounds.max.y.min(corners[2].y).min(corners[3].y);
+
+            forward.inverse().unwrap_or_else(Transform::identity)
+        })
+        .collect();
+
+    let min = EPoint::new(bounds.min.x.ceil().max(0.0), bounds.min.y.ceil().max(0.0));
+    let max = EPoint::new(
+        bounds.max.x.floor().max(min.x),
+        bounds.max.y.floor().max(min.y),
+    );
+    let crop = Region::new(
+        Point::new(min.x as usize, min.y as usize),
+        Size::new((max.x - min.x) as usize, (max.y - min.y) as usize),
+    );
+
+    Stabilization { transforms, crop }
+}
+
+/// Warp an image using a dense displacement field, each output pixel `p` is sampled from the
+/// input at `p + field(p)`. Pixels outside of the field use the nearest displacement.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Warp {
+    /// Displacement of each output pixel, in pixels
+    pub field: analysis::FlowField,
+
+    /// Interpolation method
+    pub interpolation: Interpolation,
+}
+
+impl Warp {
+    /// Create a new warp using bilinear interpolation
+    pub fn new(field: analysis::FlowField) -> Warp {
+        Warp {
+            field,
+            interpolation: Interpolation::default(),
+        }
+    }
+
+    /// Set interpolation method
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Warp {
+        self.interpolation = interpolation;
+        self
+    }
+}
+
+impl<T: Type, C: Color, U: Type, D: Color> Filter<T, C, U, D> for Warp {
+    fn schedule(&self) -> Schedule {
+        Schedule::Image
+    }
+
+    fn compute_at(&self, pt: Point, input: &Input<T, C>, px: &mut DataMut<U, D>) {
+        let (dx, dy) = self.field.sample(pt.x as f64, pt.y as f64);
+        input.images()[0]
+            .sample(pt.x as f64 + dx, pt.y as f64 + dy, self.interpolation)
+            .copy_to_slice(px);
+    }
+}
+
+/// Mesh based warp, the backend of a liquify tool. A regular grid of control points covers the
+/// image and each control point can be moved, pixels between control points follow the
+/// bicubic interpolation of the control point offsets. Image content near a control point moves
+/// along with it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MeshWarp {
+    size: Size,
+    cols: usize,
+    rows: usize,
+    offsets: Vec<(f64, f64)>,
+}
+
+impl MeshWarp {
+    /// Create a mesh for an image of the given size with `cols` by `rows` cells, the control
+    /// points are placed at the corners of the cells
+    pub fn new(size: impl Into<Size>, cols: usize, rows: usize) -> MeshWarp {
+        let (cols, rows) = (cols.max(1), rows.max(1));
+        MeshWarp {
+            size: size.into(),
+            cols,
+            rows,
+            offsets: vec![(0.0, 0.0); (cols + 1) * (rows + 1)],
+        }
+    }
+
+    /// Image size
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// Number of control points in each direction
+    pub fn grid_size(&self) -> Size {
+        Size::new(self.cols + 1, self.rows + 1)
+    }
+
+    fn cell_size(&self) -> (f64, f64) {
+        (
+            self.size.width.saturating_sub(1).max(1) as f64 / self.cols as f64,
+            self.size.height.saturating_sub(1).max(1) as f64 / self.rows as f64,
+        )
+    }
+
+    /// Original position of a control point
+    pub fn rest_position(&self, i: usize, j: usize) -> (f64, f64) {
+        let (cw, ch) = self.cell_size();
+        (i as f64 * cw, j as f64 * ch)
+    }
+
+    /// Current position of a control point
+    pub fn position(&self, i: usize, j: usize) -> (f64, f64) {
+        let (x, y) = self.rest_position(i, j);
+        let (dx, dy) = self.offsets[j * (self.cols + 1) + i];
+        (x + dx, y + dy)
+    }
+
+    /// Move a control point to a new position
+    pub fn set_position(&mut self, i: usize, j: usize, x: f64, y: f64) {
+        let (rx, ry) = self.rest_position(i, j);
+        self.offsets[j * (self.cols + 1) + i] = (x - rx, y - ry);
+    }
+
+    /// Move a control point by the given amount
+    pub fn move_point(&mut self, i: usize, j: usize, dx: f64, dy: f64) {
+        let offset = &mut self.offsets[j * (self.cols + 1) + i];
+        offset.0 += dx;
+        offset.1 += dy;
+    }
+
+    /// Find the control point closest to `(x, y)` within `max_distance`, useful for picking
+    /// points with the mouse
+    pub fn nearest_point(&self, x: f64, y: f64, max_distance: f64) -> Option<(usize, usize)> {
+        let mut best = None;
+        let mut best_distance = max_distance;
+        for j in 0..=self.rows {
+            for i in 0..=self.cols {
+                let (px, py) = self.position(i, j);
+                let d = (px - x).hypot(py - y);
+                if d <= best_distance {
+                    best = Some((i, j));
+                    best_distance = d;
+                }
+            }
+        }
+        best
+    }
+
+    /// Push every control point within `radius` of `(x, y)` by `(dx, dy)` with a smooth falloff,
+    /// this is the basic liquify brush
+    pub fn drag(&mut self, x: f64, y: f64, dx: f64, dy: f64, radius: f64) {
+        for j in 0..=self.rows {
+            for i in 0..=self.cols {
+                let (px, py) = self.position(i, j);
+                let t = (px - x).hypot(py - y) / radius.max(f64::EPSILON);
+                if t < 1.0 {
+                    let w = (1.0 - t * t).powi(2);
+                    self.move_point(i, j, dx * w, dy * w);
+                }
+            }
+        }
+    }
+
+    /// Reset every control point to its original position
+    pub fn reset(&mut self) {
+        self.offsets.iter_mut().for_each(|x| *x = (0.0, 0.0));
+    }
+
+    /// Interpolated control point offset at `(x, y)`, using Catmull-Rom splines
+    pub fn offset(&self, x: f64, y: f64) -> (f64, f64) {
+        let (cw, ch) = self.cell_size();
+        let (gx, gy) = (x / cw, y / ch);
+        let (i0, j0) = (gx.floor() as isize, gy.floor() as isize);
+        let (tx, ty) = (gx - i0 as f64, gy - j0 as f64);
+
+        let weights = |t: f64| {
+            let (t2, t3) = (t * t, t * t * t);
+            [
+                (-t3 + 2.0 * t2 - t) / 2.0,
+                (3.0 * t3 - 5.0 * t2 + 2.0) / 2.0,
+                (-3.0 * t3 + 4.0 * t2 + t) / 2.0,
+                (t3 - t2) / 2.0,
+            ]
+        };
+        let (wx, wy) = (weights(tx), weights(ty));
+
+        let (mut ox, mut oy) = (0.0, 0.0);
+        for (n, wy) in wy.iter().enumerate() {
+            let j = (j0 + n as isize - 1).clamp(0, self.rows as isize) as usize;
+            for (m, wx) in wx.iter().enumerate() {
+                let i = (i0 + m as isize - 1).clamp(0, self.cols as isize) as usize;
+                let (dx, dy) = self.offsets[j * (self.cols + 1) + i];
+                ox += dx * wx * wy;
+                oy += dy * wx * wy;
+            }
+        }
+        (ox, oy)
+    }
+
+    /// Build a `Warp` filter that renders the mesh. Each output pixel samples the input at its
+    /// position minus the interpolated offset, which approximates the inverse of the mesh
+    /// deformation.
+    pub fn warp(&self) -> Warp {
+        let mut field = analysis::FlowField::new(self.size);
+        for y in 0..self.size.height {
+            for x in 0..self.size.width {
+                let (dx, dy) = self.offset(x as f64, y as f64);
+                field.set((x, y), -dx, -dy);
+            }
+        }
+        Warp::new(field)
     }
 }
 
 #[cfg(test)]
 mod test {
     use crate::{filter::*, Filter, Image, Rgb};
+    use crate::{transform::stabilize, Point, Region, Size, Transform};
 
     #[test]
     fn test_rotate90() {
@@ -73,4 +620,120 @@ mod test {
         resize(a.size(), a.size() * 2).eval(&[&a], &mut dest1);
         assert!(dest0 == dest1);
     }
+
+    #[test]
+    fn test_interpolation() {
+        use crate::transform::{Interpolation, TransformExt};
+        use crate::Border;
+
+        let mut a = Image::<f32, crate::Gray>::new((8, 8));
+        a.for_each(|pt, mut px| px[0] = pt.x as f32 / 7.0);
+
+        // Half-pixel shift of a linear ramp
+        let t = Transform::translation(0.5, 0.0);
+        for interpolation in [
+            Interpolation::Bilinear,
+            Interpolation::Bicubic,
+            Interpolation::Lanczos3,
+        ] {
+            let mut dest = a.new_like();
+            t.with_interpolation(interpolation).eval(&[&a], &mut dest);
+            let expected = 3.5 / 7.0;
+            assert!(
+                (dest.get_f((3, 4), 0) - expected).abs() < 0.01,
+                "{:?}: {}",
+                interpolation,
+                dest.get_f((3, 4), 0)
+            );
+        }
+
+        let mut dest = a.new_like();
+        t.with_interpolation(Interpolation::Nearest)
+            .eval(&[&a], &mut dest);
+        assert!((dest.get_f((3, 4), 0) * 7.0 - 4.0).abs() < 1e-5);
+
+        // Points outside of the image are empty
+        assert_eq!(Interpolation::Bilinear.sample(&a, -2.0, 0.0)[0], 0.0);
+        assert_eq!(
+            a.sample(3.5, 2.0, Interpolation::Bicubic),
+            Interpolation::Bicubic.sample(&a, 3.5, 2.0)
+        );
+
+        // Borders apply to points outside of the image and to kernel taps near the edges
+        let left = a.sample_with_border(-2.0, 0.0, Interpolation::Bilinear, Border::Clamp);
+        assert_eq!(left[0], 0.0);
+        let right = a.sample_with_border(-1.0, 0.0, Interpolation::Bilinear, Border::Wrap);
+        assert_eq!(right[0], a.get_f((a.width() - 1, 0), 0));
+        let constant =
+            a.sample_with_border(-0.5, 1.0, Interpolation::Bilinear, Border::Constant(1.0));
+        assert!((constant[0] - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mesh_warp() {
+        use crate::transform::MeshWarp;
+
+        let mut a = Image::<f32, crate::Gray>::new((17, 17));
+        a.for_each(|pt, mut px| px[0] = pt.x as f32 / 16.0);
+
+        let mut mesh = MeshWarp::new(a.size(), 4, 4);
+        assert_eq!(mesh.grid_size(), Size::new(5, 5));
+        assert_eq!(mesh.position(2, 1), (8.0, 4.0));
+
+        // Unmodified mesh is the identity
+        let mut dest = a.new_like();
+        mesh.warp().eval(&[&a], &mut dest);
+        assert!(dest == a);
+
+        // Moving every control point shifts the whole image
+        for j in 0..5 {
+            for i in 0..5 {
+                mesh.move_point(i, j, 2.0, 0.0);
+            }
+        }
+        assert_eq!(mesh.offset(5.5, 7.25), (2.0, 0.0));
+        mesh.warp().eval(&[&a], &mut dest);
+        assert!((dest.get_f((10, 3), 0) - 8.0 / 16.0).abs() < 1e-6);
+
+        mesh.reset();
+        assert_eq!(mesh.nearest_point(9.0, 5.0, 2.0), Some((2, 1)));
+        assert_eq!(mesh.nearest_point(6.0, 6.0, 1.0), None);
+
+        mesh.drag(8.0, 8.0, 0.0, 3.0, 5.0);
+        assert_eq!(mesh.position(2, 2), (8.0, 11.0));
+        assert_eq!(mesh.position(0, 0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_stabilize() {
+        // Camera shake of +/- 2 pixels horizontally
+        let motion: Vec<Transform> = (0..9)
+            .map(|i| match i {
+                0 => Transform::identity(),
+                i if i % 2 == 1 => Transform::translation(2.0, 0.0),
+                _ => Transform::translation(-2.0, 0.0),
+            })
+            .collect();
+
+        let result = stabilize(&motion, 4, Size::new(100, 50));
+        assert_eq!(result.transforms.len(), 9);
+
+        // Frames that were shifted right should be sampled from the right
+        let p = result.transforms[5].transform_point(euclid::Point2D::new(50.0, 25.0));
+        assert!(p.x > 50.0 && (p.y - 25.0).abs() < 1e-9);
+
+        let p = result.transforms[4].transform_point(euclid::Point2D::new(50.0, 25.0));
+        assert!(p.x < 50.0);
+
+        assert!(result.crop.size.width < 100 && result.crop.size.width > 90);
+        assert_eq!(result.crop.size.height, 50);
+        assert!(result.crop.min_x() > 0);
+        assert_eq!(
+            result.crop,
+            result
+                .crop
+                .intersection(&Region::new(Point::zero(), Size::new(100, 50)))
+                .unwrap()
+        );
+    }
 }
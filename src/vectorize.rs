@@ -0,0 +1,357 @@
+use crate::contour::Mask;
+use crate::*;
+
+/// Unit steps for the directions east, south, west and north, with y pointing down
+const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+/// Position with sub-pixel precision
+pub type Position = (f64, f64);
+
+/// Segment of a traced path, each segment starts where the previous one ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Segment {
+    /// Straight line to the given end point
+    Line(Position),
+
+    /// Cubic Bezier curve with two control points followed by the end point
+    Cubic(Position, Position, Position),
+}
+
+impl Segment {
+    /// End point of the segment
+    pub fn end(&self) -> Position {
+        match self {
+            Segment::Line(end) => *end,
+            Segment::Cubic(_, _, end) => *end,
+        }
+    }
+}
+
+/// Closed vector path traced from the border of a region
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Path {
+    /// Start and end point
+    pub start: Position,
+
+    /// Segments in order, the last segment ends at `start`
+    pub segments: Vec<Segment>,
+
+    /// `true` when the path is the border of a hole inside of a region
+    pub hole: bool,
+}
+
+impl Path {
+    /// SVG path data, using absolute coordinates
+    pub fn to_svg_data(&self) -> String {
+        let mut data = format!("M{} {}", fmt(self.start.0), fmt(self.start.1));
+        for segment in &self.segments {
+            match segment {
+                Segment::Line(p) => data += &format!(" L{} {}", fmt(p.0), fmt(p.1)),
+                Segment::Cubic(a, b, p) => {
+                    data += &format!(
+                        " C{} {} {} {} {} {}",
+                        fmt(a.0),
+                        fmt(a.1),
+                        fmt(b.0),
+                        fmt(b.1),
+                        fmt(p.0),
+                        fmt(p.1)
+                    )
+                }
+            }
+        }
+        data + " Z"
+    }
+}
+
+/// Format a coordinate with up to three decimals
+fn fmt(x: f64) -> String {
+    let s = format!("{:.3}", x);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Tracing parameters
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TraceOptions {
+    /// Outlines enclosing an area smaller than this number of pixels are discarded, which
+    /// removes speckles
+    pub min_area: f64,
+
+    /// Maximum distance in pixels between the pixel outline and the fitted polygon
+    pub epsilon: f64,
+
+    /// Corner threshold, lower values produce more corners and higher values produce smoother
+    /// curves. 0 disables smoothing, values above 4/3 never produce corners.
+    pub smoothness: f64,
+}
+
+impl Default for TraceOptions {
+    fn default() -> Self {
+        TraceOptions {
+            min_area: 2.0,
+            epsilon: 1.0,
+            smoothness: 1.0,
+        }
+    }
+}
+
+/// Follow the pixel edges between foreground and background into closed outlines of corner
+/// positions. Edges are oriented so the foreground is on the right, which makes outer borders run
+/// clockwise and holes counter-clockwise. Diagonally touching pixels are connected, matching
+/// `contour::find_contours`.
+fn outlines(mask: &Mask) -> Vec<Vec<Point>> {
+    let stride = mask.width + 1;
+    let mut outgoing: Vec<Vec<usize>> = vec![Vec::new(); stride * (mask.height + 1)];
+    let mut start = Vec::new();
+    for y in 0..mask.height as isize {
+        for x in 0..mask.width as isize {
+            if !mask.get(x, y) {
+                continue;
+            }
+
+            // Corners of the pixel in clockwise order, each paired with the direction of the
+            // edge leaving it and the neighbor across that edge
+            let edges = [
+                ((x, y), 0, (x, y - 1)),
+                ((x + 1, y), 1, (x + 1, y)),
+                ((x + 1, y + 1), 2, (x, y + 1)),
+                ((x, y + 1), 3, (x - 1, y)),
+            ];
+            for ((cx, cy), dir, (nx, ny)) in edges {
+                if !mask.get(nx, ny) {
+                    let v = cy as usize * stride + cx as usize;
+                    outgoing[v].push(dir);
+                    start.push(v);
+                }
+            }
+        }
+    }
+
+    let mut result = Vec::new();
+    for first in start {
+        if outgoing[first].is_empty() {
+            continue;
+        }
+
+        let mut points = Vec::new();
+        let mut current = first;
+        let mut dir = outgoing[first][0];
+        loop {
+            // Prefer turning left at saddle points, which keeps diagonal pixels connected
+            let choice = [(dir + 3) % 4, dir, (dir + 1) % 4]
+                .into_iter()
+                .find(|d| outgoing[current].contains(d));
+            let next = match choice {
+                Some(d) => d,
+                None => break,
+            };
+            outgoing[current].retain(|d| *d != next);
+
+            if points.is_empty() || next != dir {
+                points.push(Point::new(current % stride, current / stride));
+            }
+            dir = next;
+            let (dx, dy) = DIRECTIONS[dir];
+            let x = (current % stride) as isize + dx;
+            let y = (current / stride) as isize + dy;
+            current = y as usize * stride + x as usize;
+        }
+
+        // The first vertex may not be a corner
+        if points.len() > 2 {
+            let (a, b, c) = (points[points.len() - 1], points[0], points[1]);
+            if (a.x == b.x && b.x == c.x) || (a.y == b.y && b.y == c.y) {
+                points.remove(0);
+            }
+        }
+        result.push(points);
+    }
+    result
+}
+
+/// Signed area of an outline, positive for clockwise outlines with y pointing down
+fn signed_area(points: &[Point]) -> f64 {
+    let n = points.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            a.x as f64 * b.y as f64 - b.x as f64 * a.y as f64
+        })
+        .sum::<f64>()
+        / 2.0
+}
+
+/// Point at `t` on the line from `a` to `b`
+fn lerp(t: f64, a: Position, b: Position) -> Position {
+    (a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1))
+}
+
+/// Replace polygon vertices with curves through the midpoints of the neighboring edges, or keep
+/// them as corners when the outline bends too sharply (Selinger 2003)
+fn smooth(vertices: &[Position], smoothness: f64) -> (Position, Vec<Segment>) {
+    let n = vertices.len();
+    let midpoint = |i: usize| lerp(0.5, vertices[i % n], vertices[(i + 1) % n]);
+    let start = midpoint(n - 1);
+    let mut segments = Vec::with_capacity(n);
+    for j in 0..n {
+        let (prev, vertex, next) = (
+            vertices[(j + n - 1) % n],
+            vertices[j],
+            vertices[(j + 1) % n],
+        );
+        let end = midpoint(j);
+
+        // Distance of the vertex from the line between its neighbors, relative to the size of
+        // a pixel along that line
+        let (dx, dy) = (next.0 - prev.0, next.1 - prev.1);
+        let denom = dx.abs() + dy.abs();
+        let alpha = if denom > 0.0 {
+            let cross = (vertex.0 - prev.0) * dy - (vertex.1 - prev.1) * dx;
+            let dd = cross.abs() / denom;
+            if dd > 1.0 {
+                (1.0 - 1.0 / dd) / 0.75
+            } else {
+                0.0
+            }
+        } else {
+            4.0 / 3.0
+        };
+
+        if alpha >= smoothness {
+            segments.push(Segment::Line(vertex));
+            segments.push(Segment::Line(end));
+        } else {
+            let alpha = alpha.clamp(0.55, 1.0);
+            segments.push(Segment::Cubic(
+                lerp(0.5 + 0.5 * alpha, prev, vertex),
+                lerp(0.5 + 0.5 * alpha, next, vertex),
+                end,
+            ));
+        }
+    }
+    (start, segments)
+}
+
+/// Trace the borders of the foreground regions of a binary mask into smooth vector paths, in
+/// the style of potrace. Pixels with a normalized value >= 0.5 are treated as foreground.
+///
+/// Outlines follow the pixel edges, so a single pixel becomes a unit square, and are simplified
+/// into polygons which are then converted to a mix of straight corners and cubic curves. Holes
+/// are returned as separate paths with `hole` set, use the even-odd fill rule to render them.
+pub fn trace<T: Type>(mask: &Image<T, Gray>, options: &TraceOptions) -> Vec<Path> {
+    let mask = Mask::new(mask);
+    outlines(&mask)
+        .into_iter()
+        .filter_map(|outline| {
+            let area = signed_area(&outline);
+            if area.abs() < options.min_area.max(f64::MIN_POSITIVE) {
+                return None;
+            }
+
+            let polygon = Polygon::new(outline).simplify(options.epsilon.max(0.0));
+            let vertices: Vec<Position> = polygon
+                .points
+                .iter()
+                .map(|p| (p.x as f64, p.y as f64))
+                .collect();
+            if vertices.len() < 3 {
+                return None;
+            }
+
+            let (start, segments) = smooth(&vertices, options.smoothness);
+            Some(Path {
+                start,
+                segments,
+                hole: area < 0.0,
+            })
+        })
+        .collect()
+}
+
+/// Render paths as an SVG document of the given size, filled with `color` using the even-odd
+/// rule
+pub fn to_svg(paths: &[Path], size: Size, color: &str) -> String {
+    let data: Vec<String> = paths.iter().map(Path::to_svg_data).collect();
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n  <path d=\"{2}\" fill=\"{3}\" fill-rule=\"evenodd\"/>\n</svg>\n",
+        size.width,
+        size.height,
+        data.join(" "),
+        color
+    )
+}
+
+/// Trace a mask and write the result to an SVG file
+pub fn save_svg<T: Type>(
+    path: impl AsRef<std::path::Path>,
+    mask: &Image<T, Gray>,
+    options: &TraceOptions,
+) -> Result<(), Error> {
+    let paths = trace(mask, options);
+    let svg = to_svg(&paths, mask.size(), "black");
+    std::fs::write(path.as_ref(), svg)
+        .map_err(|e| Error::UnableToWriteImage(format!("{}: {}", path.as_ref().display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use vectorize::{Segment, TraceOptions};
+
+    #[test]
+    fn test_vectorize() {
+        // Rectangle with a rectangular hole and a speck
+        let mut mask = Image::<u8, Gray>::new((64, 48));
+        mask.for_each_region(
+            Region::new(Point::new(4, 4), Size::new(40, 30)),
+            |_, mut px| {
+                px[0] = 255;
+            },
+        );
+        mask.for_each_region(
+            Region::new(Point::new(14, 14), Size::new(20, 10)),
+            |_, mut px| {
+                px[0] = 0;
+            },
+        );
+        mask.set((60, 44), [255]);
+
+        let paths = vectorize::trace(&mask, &TraceOptions::default());
+        assert_eq!(paths.len(), 2);
+        assert!(!paths[0].hole && paths[1].hole);
+
+        // Large rectangles keep their corners
+        assert!(paths[0]
+            .segments
+            .iter()
+            .all(|s| matches!(s, Segment::Line(_))));
+        let corners: Vec<_> = paths[0].segments.iter().map(Segment::end).collect();
+        assert!(corners.contains(&(4.0, 4.0)) && corners.contains(&(44.0, 34.0)));
+
+        // Disks become curves
+        let mut disk = Image::<u8, Gray>::new((40, 40));
+        disk.for_each(|pt, mut px| {
+            let (dx, dy) = (pt.x as f64 - 19.5, pt.y as f64 - 19.5);
+            if dx * dx + dy * dy < 15.0 * 15.0 {
+                px[0] = 255;
+            }
+        });
+        let paths = vectorize::trace(&disk, &TraceOptions::default());
+        assert_eq!(paths.len(), 1);
+        assert!(paths[0]
+            .segments
+            .iter()
+            .any(|s| matches!(s, Segment::Cubic(..))));
+        for segment in &paths[0].segments {
+            let (x, y) = segment.end();
+            let r = ((x - 20.0).powi(2) + (y - 20.0).powi(2)).sqrt();
+            assert!((r - 15.0).abs() < 1.5, "{}", r);
+        }
+
+        let svg = vectorize::to_svg(&paths, disk.size(), "black");
+        assert!(svg.starts_with("<svg") && svg.contains(" C"));
+    }
+}
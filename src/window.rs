@@ -3,6 +3,255 @@ use crate::*;
 
 use glfw::Context as GlfwContext;
 pub use glfw::{Action, Key, Modifiers, MouseButton, WindowEvent as Event, WindowId};
+use glow::HasContext;
+
+/// Band of scanlines decoded by a background thread, starting at the given row
+type Band<T, C> = Result<(usize, Image<T, C>), Error>;
+
+/// Receives bands from a background decoder
+struct Loading<T: Type, C: Color> {
+    rx: std::sync::mpsc::Receiver<Band<T, C>>,
+
+    /// Cleared when the window stops receiving bands, the decoder only wakes the event loop
+    /// while this is set so it never calls into GLFW after the window is gone
+    alive: std::sync::Arc<std::sync::Mutex<bool>>,
+}
+
+impl<T: Type, C: Color> Drop for Loading<T, C> {
+    fn drop(&mut self) {
+        if let Ok(mut alive) = self.alive.lock() {
+            *alive = false;
+        }
+    }
+}
+
+/// Determines how an image is placed in a window with a different aspect ratio
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Aspect {
+    /// Scale the image to fit inside of the window, preserving the aspect ratio
+    #[default]
+    Fit,
+
+    /// Scale the image to cover the whole window
+    Stretch,
+
+    /// Display the image at its original size in the center of the window, images that are
+    /// larger than the window are scaled down to fit
+    Center,
+}
+
+/// Background displayed behind transparent pixels
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlphaBackground {
+    /// Display color values as-is, ignoring alpha
+    None,
+
+    /// Solid color
+    Solid(Pixel<Rgb>),
+
+    /// Checkerboard pattern with squares of `size` image pixels
+    Checkerboard {
+        /// Size of each square
+        size: usize,
+
+        /// First color
+        light: Pixel<Rgb>,
+
+        /// Second color
+        dark: Pixel<Rgb>,
+    },
+}
+
+impl Default for AlphaBackground {
+    fn default() -> AlphaBackground {
+        AlphaBackground::Checkerboard {
+            size: 8,
+            light: Pixel::from(vec![0.8, 0.8, 0.8]),
+            dark: Pixel::from(vec![0.6, 0.6, 0.6]),
+        }
+    }
+}
+
+fn srgb_to_linear(x: f64) -> f64 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(x: f64) -> f64 {
+    if x <= 0.0031308 {
+        x * 12.92
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Color sampled using the color picker
+#[derive(Debug, Clone, PartialEq)]
+pub struct PickedColor<C: Color> {
+    /// Position in image coordinates
+    pub position: Point,
+
+    /// Normalized value in the image's color type
+    pub raw: Pixel<C>,
+
+    /// 8-bit sRGB
+    pub srgb: [u8; 3],
+
+    /// Linear RGB
+    pub linear: Pixel<Rgb>,
+
+    /// CIE-L*a*b*
+    pub lab: Pixel<Lab>,
+}
+
+impl<C: Color> PickedColor<C> {
+    /// Sample `image` at `position`, averaging a square of `2 * radius + 1` pixels
+    pub fn sample<T: Type>(image: &Image<T, C>, position: Point, radius: usize) -> Self {
+        let min = Point::new(
+            position.x.saturating_sub(radius),
+            position.y.saturating_sub(radius),
+        );
+        let region = Region::new(min, Size::new(2 * radius + 1, 2 * radius + 1))
+            .intersection(&Region::new(Point::zero(), image.size()));
+
+        let raw = match region {
+            Some(region) => image.crop(region).average_color(),
+            None => image.new_pixel(),
+        };
+
+        let srgb: Pixel<Srgb> = raw.convert();
+        PickedColor {
+            position,
+            srgb: [0, 1, 2].map(|c| (srgb[c].clamp(0.0, 1.0) * 255.0).round() as u8),
+            linear: raw.convert(),
+            lab: raw.convert(),
+            raw,
+        }
+    }
+}
+
+type ColorPickerCallback<C> = Box<dyn FnMut(&PickedColor<C>)>;
+
+/// Color picker tool state
+struct ColorPicker<C: Color> {
+    key: Key,
+    radius: usize,
+    active: bool,
+    callback: ColorPickerCallback<C>,
+}
+
+/// Guide lines drawn over the image in a `Window`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Guides {
+    /// Draw a grid between image pixels once each pixel covers at least this many screen
+    /// pixels, `None` disables the pixel grid
+    pub pixel_grid: Option<f64>,
+
+    /// Divide the image into thirds horizontally and vertically
+    pub rule_of_thirds: bool,
+
+    /// Horizontal guides, in image coordinates
+    pub horizontal: Vec<f64>,
+
+    /// Vertical guides, in image coordinates
+    pub vertical: Vec<f64>,
+
+    /// Line color
+    pub color: Pixel<Rgba>,
+}
+
+impl Default for Guides {
+    fn default() -> Guides {
+        Guides {
+            pixel_grid: Some(8.0),
+            rule_of_thirds: false,
+            horizontal: Vec::new(),
+            vertical: Vec::new(),
+            color: Pixel::from(vec![0.5, 0.5, 0.5, 1.0]),
+        }
+    }
+}
+
+/// Distance and angle between two points in image coordinates, reported by the measure tool
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    /// Start of the measurement
+    pub start: Point,
+
+    /// End of the measurement
+    pub end: Point,
+
+    /// Distance in pixels
+    pub distance: f64,
+
+    /// Angle in degrees, counter-clockwise from the positive x axis
+    pub angle: f64,
+
+    /// Distance in inches, when the window DPI is known
+    pub inches: Option<f64>,
+
+    /// `true` while the mouse button is still held down
+    pub in_progress: bool,
+}
+
+impl Measurement {
+    /// Measure from `start` to `end`, `dpi` is used to compute the physical distance
+    pub fn new(start: Point, end: Point, dpi: Option<f64>) -> Measurement {
+        let dx = end.x as f64 - start.x as f64;
+        let dy = end.y as f64 - start.y as f64;
+        let distance = dx.hypot(dy);
+        Measurement {
+            start,
+            end,
+            distance,
+            // Image y axis points down
+            angle: (-dy).atan2(dx).to_degrees(),
+            inches: dpi.filter(|dpi| *dpi > 0.0).map(|dpi| distance / dpi),
+            in_progress: false,
+        }
+    }
+
+    /// Distance in millimeters, when the window DPI is known
+    pub fn millimeters(&self) -> Option<f64> {
+        self.inches.map(|x| x * 25.4)
+    }
+}
+
+type MeasureCallback = Box<dyn FnMut(&Measurement)>;
+
+/// Measure tool state
+struct MeasureTool {
+    key: Key,
+    active: bool,
+    start: Option<Point>,
+    callback: MeasureCallback,
+}
+
+/// Statistics of a region selected using the statistics tool
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionStats {
+    /// Selected region, in image coordinates
+    pub region: Region,
+
+    /// Statistics for each channel
+    pub channels: Vec<analysis::ChannelStats>,
+
+    /// `true` while the mouse button is still held down
+    pub in_progress: bool,
+}
+
+type StatsCallback = Box<dyn FnMut(&RegionStats)>;
+
+/// Statistics tool state
+struct StatsTool {
+    key: Key,
+    active: bool,
+    start: Option<Point>,
+    callback: StatsCallback,
+}
 
 /// Multiple windows
 pub struct WindowSet<T: Type, C: Color> {
@@ -11,6 +260,9 @@ pub struct WindowSet<T: Type, C: Color> {
 
     /// Mapping from `WindowId` to `Window`
     pub windows: std::collections::BTreeMap<glfw::WindowId, Window<T, C>>,
+
+    /// Hidden window that owns the GL context shared by every window in the set
+    shared: std::cell::RefCell<Option<glfw::Window>>,
 }
 
 unsafe impl<T: Type, C: Color> Send for WindowSet<T, C> {}
@@ -20,6 +272,8 @@ unsafe impl<T: Type, C: Color> Sync for WindowSet<T, C> {}
 pub struct Window<T: Type, C: Color> {
     id: WindowId,
 
+    title: String,
+
     glfw: glfw::Glfw,
 
     /// GLFW Window
@@ -30,7 +284,12 @@ pub struct Window<T: Type, C: Color> {
 
     image: Image<T, C>,
 
-    image_texture: ImageTexture<T, C>,
+    /// Texture, shared with any views of this window
+    image_texture: std::rc::Rc<std::cell::RefCell<ImageTexture<T, C>>>,
+
+    /// Framebuffer used to display the texture of another window, framebuffers can't be shared
+    /// between contexts
+    framebuffer: Option<glow::Framebuffer>,
 
     /// Window's current size
     size: Size,
@@ -45,6 +304,45 @@ pub struct Window<T: Type, C: Color> {
     data: Option<Box<dyn std::any::Any>>,
 
     dirty: bool,
+
+    /// Image placement
+    aspect: Aspect,
+
+    /// Letterbox color
+    background: Pixel<Rgba>,
+
+    /// Displayed behind transparent pixels
+    alpha_background: AlphaBackground,
+
+    /// Mipmap filter, `None` when mipmaps are disabled
+    mipmaps: Option<MipFilter>,
+
+    /// Swap interval, `None` when the driver default is used
+    swap_interval: Option<u32>,
+
+    /// Bands received from a background decoder
+    loading: Option<Loading<T, C>>,
+
+    /// Color picker tool
+    color_picker: Option<ColorPicker<C>>,
+
+    /// Measure tool
+    measure: Option<MeasureTool>,
+
+    /// Statistics tool
+    stats: Option<StatsTool>,
+
+    /// Selected region, in image coordinates
+    selection: Option<Region>,
+
+    /// Dots per inch, used to report physical distances
+    dpi: Option<f64>,
+
+    /// Guide lines drawn over the image
+    guides: Guides,
+
+    /// `true` when guides are visible
+    show_guides: bool,
 }
 
 impl<T: Type, C: Color> WindowSet<T, C>
@@ -57,6 +355,7 @@ where
         Ok(WindowSet {
             glfw,
             windows: std::collections::BTreeMap::new(),
+            shared: std::cell::RefCell::new(None),
         })
     }
 
@@ -68,6 +367,7 @@ where
         Ok(WindowSet {
             glfw,
             windows: std::collections::BTreeMap::new(),
+            shared: std::cell::RefCell::new(None),
         })
     }
 
@@ -97,6 +397,65 @@ where
         self.add(window)
     }
 
+    /// Create a new window that displays an image file while it's being decoded, bands of
+    /// `rows` scanlines are drawn as soon as they're available
+    pub fn create_progressive(
+        &mut self,
+        title: impl AsRef<str>,
+        path: impl AsRef<std::path::Path>,
+        rows: usize,
+    ) -> Result<WindowId, Error>
+    where
+        C: 'static,
+    {
+        let window = Window::new_progressive(self, path, rows, title)?;
+        self.add(window)
+    }
+
+    /// Create a new window that displays the same texture as an existing window, no additional
+    /// GPU memory is used for the image
+    pub fn create_view(
+        &mut self,
+        title: impl AsRef<str>,
+        source: &WindowId,
+    ) -> Result<WindowId, Error> {
+        let source = match self.windows.get(source) {
+            Some(window) => window,
+            None => return Err(Error::Message("Cannot find window".into())),
+        };
+        let window = Window::new_view(self, source, title)?;
+        self.add(window)
+    }
+
+    /// Create a new GLFW window, all windows share a single GL context so textures can be used
+    /// from any window in the set
+    fn create_window(
+        &self,
+        size: Size,
+        title: &str,
+    ) -> Option<(glfw::Window, std::sync::mpsc::Receiver<(f64, Event)>)> {
+        let mut shared = self.shared.borrow_mut();
+        if shared.is_none() {
+            let mut glfw = self.glfw.borrow_mut();
+            glfw.window_hint(glfw::WindowHint::Visible(false));
+            *shared = glfw
+                .create_window(1, 1, "", glfw::WindowMode::Windowed)
+                .map(|(window, _)| window);
+            glfw.window_hint(glfw::WindowHint::Visible(true));
+        }
+
+        let (width, height) = (size.width as u32, size.height as u32);
+        match shared.as_ref() {
+            Some(root) => root.create_shared(width, height, title, glfw::WindowMode::Windowed),
+            None => self.glfw.borrow_mut().create_window(
+                width,
+                height,
+                title,
+                glfw::WindowMode::Windowed,
+            ),
+        }
+    }
+
     /// Get window by ID
     pub fn get(&self, window_id: &WindowId) -> Option<&Window<T, C>> {
         self.windows.get(window_id)
@@ -137,6 +496,71 @@ where
         self.windows.into_values().map(|x| x.into_image())
     }
 
+    /// Composite the images of all open windows into a grid with `columns` columns, `title`
+    /// is called with each cell and window title to draw labels below the images
+    fn build_contact_sheet(
+        &self,
+        columns: usize,
+        title_height: usize,
+        mut title: impl FnMut(&mut Image<T, C>, &str, Point),
+    ) -> Image<T, C> {
+        let windows: Vec<&Window<T, C>> = self.iter_windows().collect();
+        let columns = columns.max(1);
+        let rows = windows.len().div_ceil(columns);
+        let cell = windows.iter().fold(Size::zero(), |size, w| {
+            Size::new(
+                size.width.max(w.image.width()),
+                size.height.max(w.image.height()),
+            )
+        });
+        let cell = Size::new(cell.width, cell.height + title_height);
+
+        let mut sheet = Image::new((cell.width * columns.min(windows.len()), cell.height * rows));
+        for (i, window) in windows.iter().enumerate() {
+            let origin = Point::new((i % columns) * cell.width, (i / columns) * cell.height);
+            sheet.copy_from_region(
+                Point::zero(),
+                &window.image,
+                Region::new(origin, window.image.size()),
+            );
+            if title_height > 0 {
+                let pos = Point::new(origin.x, origin.y + cell.height - title_height);
+                title(&mut sheet, &window.title, pos);
+            }
+        }
+        sheet
+    }
+
+    /// Composite the images of all open windows into a single image with `columns` columns
+    pub fn contact_sheet(&self, columns: usize) -> Image<T, C> {
+        self.build_contact_sheet(columns, 0, |_, _, _| ())
+    }
+
+    /// Composite the images of all open windows into a single image with `columns` columns,
+    /// each image is labeled with its window title
+    #[cfg(feature = "text")]
+    pub fn contact_sheet_with_titles(
+        &self,
+        columns: usize,
+        font: &crate::text::Font,
+        size: f32,
+    ) -> Image<T, C> {
+        let mut color = Pixel::new();
+        color.fill(1.0);
+        self.build_contact_sheet(columns, size.ceil() as usize, |sheet, title, pos| {
+            sheet.draw_text(title, font, size, pos, &color)
+        })
+    }
+
+    /// Save a contact sheet of all open windows, see `WindowSet::contact_sheet`
+    pub fn export_contact_sheet(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        columns: usize,
+    ) -> Result<(), Error> {
+        self.contact_sheet(columns).save(path)
+    }
+
     /// Returns false when there are no more open windows
     pub fn step<F: FnMut(&mut Window<T, C>, Option<Event>) -> Result<(), Error>>(
         &mut self,
@@ -157,13 +581,19 @@ where
         self.glfw_context_mut().wait_events_timeout(timeout);
     }
 
-    /// Run the event loop until all windows are closed
+    /// Wake up the event loop from any thread
+    pub fn post_empty_event(&self) {
+        self.glfw_context().post_empty_event();
+    }
+
+    /// Run the event loop until all windows are closed, the loop sleeps until an event is
+    /// received, `Window::mark_as_dirty` and `WindowSet::post_empty_event` can be used to wake it
     pub fn run<F: FnMut(&mut Window<T, C>, Option<Event>) -> Result<(), Error>>(
         &mut self,
         mut event_handler: F,
     ) -> Result<(), Error> {
         while self.step(&mut event_handler)? {
-            self.wait_events(0.1)
+            self.glfw_context_mut().wait_events();
         }
         Ok(())
     }
@@ -179,12 +609,7 @@ where
         image: Image<T, C>,
         title: impl AsRef<str>,
     ) -> Result<Window<T, C>, Error> {
-        let (mut inner, events) = match context.glfw.borrow_mut().create_window(
-            image.width() as u32,
-            image.height() as u32,
-            title.as_ref(),
-            glfw::WindowMode::Windowed,
-        ) {
+        let (mut inner, events) = match context.create_window(image.size(), title.as_ref()) {
             Some(x) => x,
             None => return Err(Error::Message("Unable to open window".into())),
         };
@@ -204,6 +629,7 @@ where
 
         let mut window = Window {
             id,
+            title: title.as_ref().to_string(),
             glfw: context.glfw.borrow().clone(),
             inner,
             events,
@@ -211,23 +637,243 @@ where
             size,
             closed: false,
             data: None,
-            image_texture,
+            image_texture: std::rc::Rc::new(std::cell::RefCell::new(image_texture)),
+            framebuffer: None,
             image,
             dirty: false,
+            aspect: Aspect::default(),
+            background: Pixel::new(),
+            alpha_background: AlphaBackground::default(),
+            mipmaps: None,
+            swap_interval: None,
+            loading: None,
+            color_picker: None,
+            measure: None,
+            stats: None,
+            selection: None,
+            dpi: None,
+            guides: Guides::default(),
+            show_guides: false,
+        };
+
+        window.draw()?;
+        Ok(window)
+    }
+
+    /// Create a new window that displays the texture of `source`, the texture is only updated
+    /// by the source window. `source` must belong to `context`.
+    pub fn new_view(
+        context: &WindowSet<T, C>,
+        source: &Window<T, C>,
+        title: impl AsRef<str>,
+    ) -> Result<Window<T, C>, Error> {
+        let (mut inner, events) = match context.create_window(source.size, title.as_ref()) {
+            Some(x) => x,
+            None => return Err(Error::Message("Unable to open window".into())),
+        };
+        inner.set_all_polling(true);
+        inner.make_current();
+
+        let ctx = unsafe {
+            glow::Context::from_loader_function(|ptr| {
+                context.glfw.borrow().get_proc_address_raw(ptr)
+            })
+        };
+        let framebuffer = unsafe { ctx.create_framebuffer() }.map_err(Error::Message)?;
+
+        let (width, height) = inner.get_size();
+        let mut window = Window {
+            id: inner.window_id(),
+            title: title.as_ref().to_string(),
+            glfw: context.glfw.borrow().clone(),
+            inner,
+            events,
+            position: Point::default(),
+            size: Size::new(width as usize, height as usize),
+            closed: false,
+            data: None,
+            image_texture: source.image_texture.clone(),
+            framebuffer: Some(framebuffer),
+            image: source.image.clone(),
+            dirty: false,
+            aspect: Aspect::default(),
+            background: Pixel::new(),
+            alpha_background: AlphaBackground::default(),
+            mipmaps: None,
+            swap_interval: None,
+            loading: None,
+            color_picker: None,
+            measure: None,
+            stats: None,
+            selection: None,
+            dpi: None,
+            guides: Guides::default(),
+            show_guides: false,
         };
 
         window.draw()?;
         Ok(window)
     }
 
+    /// Returns true when the window displays the texture of another window
+    pub fn is_view(&self) -> bool {
+        self.framebuffer.is_some()
+    }
+
+    /// Create a new window that displays an image file while it's being decoded
+    ///
+    /// The file is decoded on a background thread in bands of `rows` scanlines, each band is
+    /// uploaded to the texture and drawn as soon as it arrives
+    pub fn new_progressive(
+        context: &WindowSet<T, C>,
+        path: impl AsRef<std::path::Path>,
+        rows: usize,
+        title: impl AsRef<str>,
+    ) -> Result<Window<T, C>, Error>
+    where
+        C: 'static,
+    {
+        let path = path.as_ref().to_path_buf();
+        let info = io::SourceInfo::read(&path)?;
+        let mut window = Window::new(context, Image::new(info.size), title)?;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let alive = std::sync::Arc::new(std::sync::Mutex::new(true));
+        let flag = alive.clone();
+        std::thread::spawn(move || {
+            // Wake up the event loop whenever a band is ready, the lock is held while posting
+            // the event so the window can't be dropped in between. Returns false once the
+            // window is gone.
+            let wake = || match flag.lock() {
+                Ok(alive) if *alive => {
+                    unsafe { glfw::ffi::glfwPostEmptyEvent() };
+                    true
+                }
+                _ => false,
+            };
+
+            // Decoding stops as soon as the window stops listening
+            let res = io::read_bands(path, rows, |y, band| {
+                tx.send(Ok((y, band))).is_ok() && wake()
+            });
+            if let Err(e) = res {
+                let _ = tx.send(Err(e));
+            }
+            drop(tx);
+            wake();
+        });
+
+        window.loading = Some(Loading { rx, alive });
+        Ok(window)
+    }
+
+    /// Returns true while a progressive window is still receiving image data
+    pub fn is_loading(&self) -> bool {
+        self.loading.is_some()
+    }
+
+    /// Copy any decoded bands into the image and draw them
+    fn poll_loading(&mut self) -> Result<(), Error> {
+        let rx = match &self.loading {
+            Some(loading) => &loading.rx,
+            None => return Ok(()),
+        };
+
+        let mut updated: Option<Region> = None;
+        let mut done = false;
+        loop {
+            match rx.try_recv() {
+                Ok(Ok((y, band))) => {
+                    let roi = Region::new(Point::new(0, y), band.size());
+                    self.image.copy_from_region(Point::zero(), &band, roi);
+                    updated = Some(match updated {
+                        Some(r) => r.union(&roi),
+                        None => roi,
+                    });
+                }
+                Ok(Err(e)) => {
+                    self.loading = None;
+                    return Err(e);
+                }
+                Err(std::sync::mpsc::TryRecvError::Empty) => break,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    done = true;
+                    break;
+                }
+            }
+        }
+
+        if done {
+            self.loading = None;
+        }
+
+        if let Some(roi) = updated {
+            self.draw_region(roi)?;
+        }
+
+        Ok(())
+    }
+
     /// Get window ID
     pub fn id(&self) -> WindowId {
         self.id
     }
 
-    /// Mark window as dirty, this will trigger a draw on the next iteration
+    /// Get window title
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Set window title
+    pub fn set_title(&mut self, title: impl Into<String>) {
+        self.title = title.into();
+        self.inner.set_title(&self.title);
+    }
+
+    /// Mark window as dirty, this will trigger a draw on the next iteration and wake up the
+    /// event loop if it's waiting for events
     pub fn mark_as_dirty(&mut self) {
         self.dirty = true;
+        self.glfw.post_empty_event();
+    }
+
+    /// Set the number of screen updates to wait for before swapping buffers, `0` disables vsync
+    pub fn set_swap_interval(&mut self, interval: u32) {
+        self.inner.make_current();
+        self.glfw.set_swap_interval(match interval {
+            0 => glfw::SwapInterval::None,
+            n => glfw::SwapInterval::Sync(n),
+        });
+        self.swap_interval = Some(interval);
+    }
+
+    /// Enable or disable vsync
+    pub fn set_vsync(&mut self, enabled: bool) {
+        self.set_swap_interval(enabled as u32)
+    }
+
+    /// Get the swap interval, returns `None` if it hasn't been set
+    pub fn swap_interval(&self) -> Option<u32> {
+        self.swap_interval
+    }
+
+    /// Generate mipmaps using `filter` whenever the texture is updated, so images that are
+    /// scaled down to fit the window don't shimmer. Partial updates from `draw_region` let the
+    /// GPU regenerate the levels instead, `filter` is used again on the next full draw. `None`
+    /// disables mipmaps.
+    pub fn set_mipmaps(&mut self, filter: Option<MipFilter>) {
+        self.mipmaps = filter;
+        if filter.is_none() && !self.is_view() {
+            if let Ok(ctx) = self.gl_context() {
+                self.image_texture.borrow_mut().clear_mipmaps(&ctx);
+            }
+        }
+        self.mark_as_dirty();
+    }
+
+    /// Get the mipmap filter, returns `None` when mipmaps are disabled
+    pub fn mipmaps(&self) -> Option<MipFilter> {
+        self.mipmaps
     }
 
     /// Check if window is dirty
@@ -261,17 +907,263 @@ where
         Ok(events)
     }
 
+    /// Enable the color picker tool, pressing `key` toggles picker mode. While picker mode is
+    /// active, clicking on the image samples the average color of a square of `2 * radius + 1`
+    /// pixels under the cursor and passes it to `callback`.
+    pub fn set_color_picker(
+        &mut self,
+        key: Key,
+        radius: usize,
+        callback: impl 'static + FnMut(&PickedColor<C>),
+    ) {
+        self.color_picker = Some(ColorPicker {
+            key,
+            radius,
+            active: false,
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Disable the color picker tool
+    pub fn remove_color_picker(&mut self) {
+        self.color_picker = None;
+    }
+
+    /// Returns true when picker mode is active
+    pub fn is_picking_color(&self) -> bool {
+        self.color_picker
+            .as_ref()
+            .map(|p| p.active)
+            .unwrap_or(false)
+    }
+
+    /// Sample the image at `pt`, averaging a square of `2 * radius + 1` pixels
+    pub fn pick_color(&self, pt: impl Into<Point>, radius: usize) -> PickedColor<C> {
+        PickedColor::sample(&self.image, pt.into(), radius)
+    }
+
+    fn handle_color_picker(&mut self, event: &Event) {
+        let position = self.position;
+        let picker = match &mut self.color_picker {
+            Some(picker) => picker,
+            None => return,
+        };
+
+        match event {
+            Event::Key(k, _, Action::Press, _) if *k == picker.key => {
+                picker.active = !picker.active;
+            }
+            Event::MouseButton(MouseButton::Button1, Action::Press, _) if picker.active => {
+                let color = PickedColor::sample(&self.image, position, picker.radius);
+                (picker.callback)(&color);
+            }
+            _ => (),
+        }
+    }
+
+    /// Set guide lines, this also makes them visible
+    pub fn set_guides(&mut self, guides: Guides) {
+        self.guides = guides;
+        self.show_guides(true);
+    }
+
+    /// Get guide lines
+    pub fn guides(&self) -> &Guides {
+        &self.guides
+    }
+
+    /// Get mutable guide lines, the window is redrawn on the next iteration
+    pub fn guides_mut(&mut self) -> &mut Guides {
+        self.mark_as_dirty();
+        &mut self.guides
+    }
+
+    /// Show or hide guide lines
+    pub fn show_guides(&mut self, show: bool) {
+        self.show_guides = show;
+        self.mark_as_dirty();
+    }
+
+    /// Toggle guide lines
+    pub fn toggle_guides(&mut self) {
+        self.show_guides(!self.show_guides);
+    }
+
+    /// Returns true when guide lines are visible
+    pub fn guides_visible(&self) -> bool {
+        self.show_guides
+    }
+
+    /// Set the image resolution in dots per inch, used to report physical distances
+    pub fn set_dpi(&mut self, dpi: Option<f64>) {
+        self.dpi = dpi;
+    }
+
+    /// Get the image resolution in dots per inch
+    pub fn dpi(&self) -> Option<f64> {
+        self.dpi
+    }
+
+    /// Enable the measure tool, pressing `key` toggles measure mode. While measure mode is
+    /// active, dragging with the left mouse button passes a `Measurement` to `callback` every
+    /// time the cursor moves and once more when the button is released.
+    pub fn set_measure_tool(&mut self, key: Key, callback: impl 'static + FnMut(&Measurement)) {
+        self.measure = Some(MeasureTool {
+            key,
+            active: false,
+            start: None,
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Disable the measure tool
+    pub fn remove_measure_tool(&mut self) {
+        self.measure = None;
+    }
+
+    /// Returns true when measure mode is active
+    pub fn is_measuring(&self) -> bool {
+        self.measure.as_ref().map(|m| m.active).unwrap_or(false)
+    }
+
+    fn handle_measure_tool(&mut self, event: &Event) {
+        let (position, dpi) = (self.position, self.dpi);
+        let tool = match &mut self.measure {
+            Some(tool) => tool,
+            None => return,
+        };
+
+        match event {
+            Event::Key(k, _, Action::Press, _) if *k == tool.key => {
+                tool.active = !tool.active;
+                tool.start = None;
+            }
+            Event::MouseButton(MouseButton::Button1, Action::Press, _) if tool.active => {
+                tool.start = Some(position);
+            }
+            Event::CursorPos(..) => {
+                if let Some(start) = tool.start {
+                    let mut m = Measurement::new(start, position, dpi);
+                    m.in_progress = true;
+                    (tool.callback)(&m);
+                }
+            }
+            Event::MouseButton(MouseButton::Button1, Action::Release, _) => {
+                if let Some(start) = tool.start.take() {
+                    (tool.callback)(&Measurement::new(start, position, dpi));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Enable the statistics tool, pressing `key` toggles selection mode. While selection mode
+    /// is active, dragging with the left mouse button selects a region and passes the minimum,
+    /// maximum, mean and standard deviation of each channel inside of it to `callback` every
+    /// time the cursor moves and once more when the button is released.
+    pub fn set_stats_tool(&mut self, key: Key, callback: impl 'static + FnMut(&RegionStats)) {
+        self.stats = Some(StatsTool {
+            key,
+            active: false,
+            start: None,
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Disable the statistics tool, this also clears the selection
+    pub fn remove_stats_tool(&mut self) {
+        self.stats = None;
+        self.set_selection(None);
+    }
+
+    /// Returns true when selection mode is active
+    pub fn is_selecting(&self) -> bool {
+        self.stats.as_ref().map(|s| s.active).unwrap_or(false)
+    }
+
+    /// Get the selected region
+    pub fn selection(&self) -> Option<Region> {
+        self.selection
+    }
+
+    /// Set the selected region, the selection is outlined using the guide color
+    pub fn set_selection(&mut self, region: Option<Region>) {
+        self.selection = region;
+        self.mark_as_dirty();
+    }
+
+    /// Compute statistics for the selected region
+    pub fn selection_stats(&self) -> Option<RegionStats> {
+        self.selection.map(|region| RegionStats {
+            region,
+            channels: self.image.region_stats(region),
+            in_progress: false,
+        })
+    }
+
+    fn handle_stats_tool(&mut self, event: &Event) {
+        let position = self.position;
+        let tool = match &mut self.stats {
+            Some(tool) => tool,
+            None => return,
+        };
+
+        let selection = |start: Point| {
+            let min = Point::new(start.x.min(position.x), start.y.min(position.y));
+            let size = Size::new(
+                start.x.max(position.x) - min.x + 1,
+                start.y.max(position.y) - min.y + 1,
+            );
+            Region::new(min, size)
+        };
+
+        let (region, in_progress) = match event {
+            Event::Key(k, _, Action::Press, _) if *k == tool.key => {
+                tool.active = !tool.active;
+                tool.start = None;
+                return;
+            }
+            Event::MouseButton(MouseButton::Button1, Action::Press, _) if tool.active => {
+                tool.start = Some(position);
+                (selection(position), true)
+            }
+            Event::CursorPos(..) => match tool.start {
+                Some(start) => (selection(start), true),
+                None => return,
+            },
+            Event::MouseButton(MouseButton::Button1, Action::Release, _) => {
+                match tool.start.take() {
+                    Some(start) => (selection(start), false),
+                    None => return,
+                }
+            }
+            _ => return,
+        };
+
+        let stats = RegionStats {
+            region,
+            channels: self.image.region_stats(region),
+            in_progress,
+        };
+        (tool.callback)(&stats);
+        self.set_selection(Some(region));
+    }
+
     /// Handle events using `event_handler`
     pub fn handle_events<F: FnMut(&mut Window<T, C>, Option<Event>) -> Result<(), Error>>(
         &mut self,
         mut event_handler: F,
     ) -> Result<(), Error> {
         let events = self.events()?;
+        self.poll_loading()?;
 
         if events.is_empty() {
             event_handler(self, None)?;
         } else {
             for event in events {
+                self.handle_color_picker(&event);
+                self.handle_measure_tool(&event);
+                self.handle_stats_tool(&event);
                 event_handler(self, Some(event))?;
             }
         }
@@ -306,47 +1198,89 @@ where
     /// Get mouse position  relative to image based on window mouse position
     pub fn fix_mouse_position(&self, pt: impl Into<Point>) -> Point {
         let pt = pt.into();
-        let ratio = (self.size.width as f64 / self.image.meta.width() as f64)
-            .min(self.size.height as f64 / self.image.meta.height() as f64);
-        let display_width = (self.image.meta.width() as f64 * ratio) as usize;
-        let display_height = (self.image.meta.height() as f64 * ratio) as usize;
-        let x = self.size.width.saturating_sub(display_width) / 2;
-        let y = self.size.height.saturating_sub(display_height) / 2;
+        let display = self.display_region();
+        if display.width() == 0 || display.height() == 0 {
+            return Point::zero();
+        }
+
+        let x =
+            pt.x.saturating_sub(display.origin.x)
+                .min(display.width() - 1);
+        let y =
+            pt.y.saturating_sub(display.origin.y)
+                .min(display.height() - 1);
 
-        self.scale_mouse_position(pt, x, y, display_width, display_height, ratio)
+        Point::new(
+            x * self.image.width() / display.width(),
+            y * self.image.height() / display.height(),
+        )
     }
 
-    fn scale_mouse_position(
-        &self,
-        pt: impl Into<Point>,
-        x: usize,
-        y: usize,
-        display_width: usize,
-        display_height: usize,
-        ratio: f64,
-    ) -> Point {
-        let mut pt = pt.into();
+    /// Set how the image is placed when the window aspect ratio doesn't match the image
+    pub fn set_aspect(&mut self, aspect: Aspect) {
+        self.aspect = aspect;
+        self.mark_as_dirty();
+    }
 
-        pt.x = pt.x.saturating_sub(x);
-        pt.y = pt.y.saturating_sub(y);
+    /// Get aspect mode
+    pub fn aspect(&self) -> Aspect {
+        self.aspect
+    }
 
-        if pt.x >= display_width {
-            pt.x = display_width.saturating_sub(1);
-        }
+    /// Set the color used for parts of the window that aren't covered by the image
+    pub fn set_background(&mut self, color: impl Into<Pixel<Rgba>>) {
+        self.background = color.into();
+        self.mark_as_dirty();
+    }
 
-        if pt.y >= display_height {
-            pt.y = display_height.saturating_sub(1);
-        }
+    /// Get background color
+    pub fn background(&self) -> &Pixel<Rgba> {
+        &self.background
+    }
 
-        Point::new(
-            (pt.x as f64 / ratio) as usize,
-            (pt.y as f64 / ratio) as usize,
-        )
+    /// Set the background displayed behind transparent pixels, this only affects images with an
+    /// alpha channel
+    pub fn set_alpha_background(&mut self, background: AlphaBackground) {
+        self.alpha_background = background;
+        self.mark_as_dirty();
+    }
+
+    /// Get the background displayed behind transparent pixels
+    pub fn alpha_background(&self) -> &AlphaBackground {
+        &self.alpha_background
+    }
+
+    /// Composite the image over the alpha background, returns `None` when the image can be
+    /// displayed directly. Values are treated as display-encoded and blended in linear light.
+    fn composite(&self) -> Option<Image<T, C>> {
+        let alpha = C::ALPHA?;
+        let (size, light, dark) = match &self.alpha_background {
+            AlphaBackground::None => return None,
+            AlphaBackground::Solid(color) => (usize::MAX, color, color),
+            AlphaBackground::Checkerboard { size, light, dark } => ((*size).max(1), light, dark),
+        };
+
+        let mut image = self.image.clone();
+        image.each_pixel_mut(|pt, px| {
+            let bg = if (pt.x / size + pt.y / size) % 2 == 0 {
+                light
+            } else {
+                dark
+            };
+            let t = px[alpha].clamp(0.0, 1.0);
+            for c in (0..C::CHANNELS).filter(|c| *c != alpha) {
+                let fg = srgb_to_linear(px[c]);
+                let bg = srgb_to_linear(bg[c.min(2)]);
+                px[c] = linear_to_srgb(fg * t + bg * (1.0 - t));
+            }
+            px[alpha] = 1.0;
+        });
+        Some(image)
     }
 
     /// Convert `Window` into `Image`
-    pub fn into_image(self) -> Image<T, C> {
-        self.image
+    pub fn into_image(mut self) -> Image<T, C> {
+        std::mem::replace(&mut self.image, Image::new(Size::zero()))
     }
 
     /// Get image
@@ -360,43 +1294,258 @@ where
         &mut self.image
     }
 
+    /// Replace the window's image, the existing texture is reused when the new image has the
+    /// same size. Returns the previous image.
+    pub fn set_image(&mut self, image: Image<T, C>) -> Result<Image<T, C>, Error> {
+        let ctx = self.gl_context()?;
+        let prev = std::mem::replace(&mut self.image, image);
+        self.upload(&ctx)?;
+        self.blit(&ctx)?;
+        self.inner.swap_buffers();
+        Ok(prev)
+    }
+
     /// Return true when the window is closed
     pub fn is_closed(&self) -> bool {
         self.closed || self.inner.should_close()
     }
 
-    /// Close a window
+    /// Close a window, GPU resources are released immediately and recreated if the window is
+    /// drawn again
     pub fn close(&mut self) {
         self.inner.set_should_close(true);
         self.inner.hide();
         self.closed = true;
+        self.destroy_texture();
     }
 
-    /// Update the texture with data from the window's image
-    pub fn draw(&mut self) -> Result<(), Error> {
+    /// Make the window's context current and return a handle to it. If the context was lost
+    /// (for example after a GPU reset, or when the window moved to a different GPU) the texture
+    /// is recreated from the window's image.
+    fn gl_context(&mut self) -> Result<glow::Context, Error> {
         self.inner.make_current();
+        let ctx = unsafe {
+            glow::Context::from_loader_function(|ptr| self.glfw.get_proc_address_raw(ptr))
+        };
+
+        if !self.is_view() && !self.image_texture.borrow().is_valid(&ctx) {
+            *self.image_texture.borrow_mut() = self.image.create_image_texture(&ctx)?;
+        }
+
+        Ok(ctx)
+    }
+
+    /// Upload the window's image to the texture, regenerating mipmaps when they're enabled
+    fn upload(&self, ctx: &glow::Context) -> Result<(), Error> {
+        if self.is_view() {
+            return Ok(());
+        }
+
+        let mut texture = self.image_texture.borrow_mut();
+        let composite = self.composite();
+        let image = composite.as_ref().unwrap_or(&self.image);
+        texture.replace(ctx, image)?;
+        if let Some(filter) = self.mipmaps {
+            texture.set_mipmaps(ctx, &image.mipmaps_with(filter)[1..])?;
+        }
+        Ok(())
+    }
+
+    fn blit(&self, ctx: &glow::Context) -> Result<(), Error> {
+        let display = self.display_region();
+        let bg = &self.background;
+        unsafe {
+            ctx.clear_color(bg[0] as f32, bg[1] as f32, bg[2] as f32, bg[3] as f32);
+            ctx.clear(glow::COLOR_BUFFER_BIT);
+        }
+
+        let texture = self.image_texture.borrow();
+        match self.framebuffer {
+            Some(framebuffer) => {
+                let mut view = ImageTexture::new(framebuffer, texture.texture);
+                view.size = texture.size;
+                view.levels = texture.levels;
+                self.image
+                    .blit_image_texture(ctx, &view, display.size, display.origin)?
+            }
+            None => self
+                .image
+                .blit_image_texture(ctx, &texture, display.size, display.origin)?,
+        }
+
+        if self.show_guides {
+            self.draw_guides(ctx, display);
+        }
+
+        if let Some(selection) = self.selection {
+            self.draw_selection(ctx, display, selection);
+        }
+        Ok(())
+    }
+
+    /// Outline the selected region using the guide color
+    fn draw_selection(&self, ctx: &glow::Context, display: Region, selection: Region) {
+        let (width, height) = (self.image.width() as f64, self.image.height() as f64);
+        if width == 0.0 || height == 0.0 {
+            return;
+        }
+
+        let sx = display.width() as f64 / width;
+        let sy = display.height() as f64 / height;
+        let x = display.origin.x as i32 + (selection.origin.x as f64 * sx) as i32;
+        let w = ((selection.width() as f64 * sx) as i32).max(1);
+        let h = ((selection.height() as f64 * sy) as i32).max(1);
+
+        // GL window coordinates start at the bottom
+        let top = self.size.height as i32
+            - display.origin.y as i32
+            - (selection.origin.y as f64 * sy) as i32;
+        let y = top - h;
+
+        let c = &self.guides.color;
+        unsafe {
+            ctx.enable(glow::SCISSOR_TEST);
+            ctx.clear_color(c[0] as f32, c[1] as f32, c[2] as f32, c[3] as f32);
+            for (x, y, w, h) in [
+                (x, y, w, 1),
+                (x, top - 1, w, 1),
+                (x, y, 1, h),
+                (x + w - 1, y, 1, h),
+            ] {
+                ctx.scissor(x, y, w, h);
+                ctx.clear(glow::COLOR_BUFFER_BIT);
+            }
+            ctx.disable(glow::SCISSOR_TEST);
+        }
+    }
+
+    /// Draw guide lines on top of the image, lines are drawn by clearing 1 pixel wide scissor
+    /// rectangles so no shaders are required
+    fn draw_guides(&self, ctx: &glow::Context, display: Region) {
+        let guides = &self.guides;
+        let (width, height) = (self.image.width() as f64, self.image.height() as f64);
+        if width == 0.0 || height == 0.0 {
+            return;
+        }
+
+        let sx = display.width() as f64 / width;
+        let sy = display.height() as f64 / height;
+        let mut xs = guides.vertical.clone();
+        let mut ys = guides.horizontal.clone();
+
+        if let Some(zoom) = guides.pixel_grid {
+            if sx >= zoom && sy >= zoom {
+                xs.extend((1..self.image.width()).map(|x| x as f64));
+                ys.extend((1..self.image.height()).map(|y| y as f64));
+            }
+        }
+
+        if guides.rule_of_thirds {
+            xs.extend([width / 3.0, width * 2.0 / 3.0]);
+            ys.extend([height / 3.0, height * 2.0 / 3.0]);
+        }
+
+        // GL window coordinates start at the bottom
+        let bottom = self.size.height as i32 - (display.origin.y + display.height()) as i32;
+        let c = &guides.color;
+        unsafe {
+            ctx.enable(glow::SCISSOR_TEST);
+            ctx.clear_color(c[0] as f32, c[1] as f32, c[2] as f32, c[3] as f32);
+            for x in xs.into_iter().filter(|x| *x >= 0.0 && *x <= width) {
+                let x = display.origin.x as i32 + (x * sx) as i32;
+                ctx.scissor(x, bottom, 1, display.height() as i32);
+                ctx.clear(glow::COLOR_BUFFER_BIT);
+            }
+            for y in ys.into_iter().filter(|y| *y >= 0.0 && *y <= height) {
+                let y = bottom + display.height() as i32 - (y * sy) as i32;
+                ctx.scissor(display.origin.x as i32, y, display.width() as i32, 1);
+                ctx.clear(glow::COLOR_BUFFER_BIT);
+            }
+            ctx.disable(glow::SCISSOR_TEST);
+        }
+    }
+
+    fn display_region(&self) -> Region {
         let meta = self.image.meta();
         let size = self.size;
-        let ratio = (size.width as f64 / meta.width() as f64)
+        if let Aspect::Stretch = self.aspect {
+            return Region::new(Point::zero(), size);
+        }
+
+        let mut ratio = (size.width as f64 / meta.width() as f64)
             .min(size.height as f64 / meta.height() as f64);
+        if let Aspect::Center = self.aspect {
+            ratio = ratio.min(1.0);
+        }
+
         let display_width = (meta.width() as f64 * ratio) as usize;
         let display_height = (meta.height() as f64 * ratio) as usize;
         let x = size.width.saturating_sub(display_width) / 2;
         let y = size.height.saturating_sub(display_height) / 2;
+        Region::new(Point::new(x, y), Size::new(display_width, display_height))
+    }
 
+    /// Update the texture with data from the window's image
+    pub fn draw(&mut self) -> Result<(), Error> {
+        let ctx = self.gl_context()?;
+        self.upload(&ctx)?;
+        self.blit(&ctx)?;
+        self.inner.swap_buffers();
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Update the part of the texture covered by `roi` and redraw, this avoids uploading the
+    /// whole image when only a small part has changed
+    pub fn draw_region(&mut self, roi: Region) -> Result<(), Error> {
+        let ctx = self.gl_context()?;
+
+        if !self.is_view() {
+            let mut texture = self.image_texture.borrow_mut();
+            let composite = self.composite();
+            let image = composite.as_ref().unwrap_or(&self.image);
+            image.update_image_texture(&ctx, &texture, roi)?;
+
+            // Rebuilding the filtered chain on the CPU would cost as much as a full upload, so
+            // the GPU regenerates the levels until the next full draw
+            if self.mipmaps.is_some() {
+                texture.generate_mipmaps(&ctx);
+            }
+        }
+        self.blit(&ctx)?;
+        self.inner.swap_buffers();
+        Ok(())
+    }
+}
+
+impl<T: Type, C: Color> Window<T, C> {
+    fn destroy_texture(&mut self) {
+        self.inner.make_current();
         let ctx = unsafe {
             glow::Context::from_loader_function(|ptr| self.glfw.get_proc_address_raw(ptr))
         };
 
-        self.image.draw_image_texture(
-            &ctx,
-            &self.image_texture,
-            (display_width, display_height).into(),
-            (x, y).into(),
-        )?;
-        self.inner.swap_buffers();
-        self.dirty = false;
-        Ok(())
+        // Shared textures are released by the last window using them
+        if std::rc::Rc::strong_count(&self.image_texture) == 1
+            && self.image_texture.borrow().size != Size::zero()
+        {
+            self.image_texture.borrow_mut().destroy(&ctx);
+        }
+    }
+}
+
+impl<T: Type, C: Color> Drop for Window<T, C> {
+    fn drop(&mut self) {
+        // Stop the background decoder from waking the event loop before GLFW can shut down
+        self.loading = None;
+        self.destroy_texture();
+
+        if let Some(framebuffer) = self.framebuffer.take() {
+            let ctx = unsafe {
+                glow::Context::from_loader_function(|ptr| self.glfw.get_proc_address_raw(ptr))
+            };
+            unsafe { ctx.delete_framebuffer(framebuffer) }
+        }
     }
 }
 
@@ -428,6 +1577,40 @@ where
     Err(Error::Message("Cannot find window".into()))
 }
 
+/// Show an image file while it's being decoded and exit when ESC is pressed, bands of `rows`
+/// scanlines are drawn as soon as they're available
+pub fn show_progressive<
+    T: Type,
+    C: 'static + Color,
+    F: FnMut(&mut Window<T, C>, Option<Event>) -> Result<(), Error>,
+>(
+    path: impl AsRef<std::path::Path>,
+    rows: usize,
+    mut f: F,
+) -> Result<Image<T, C>, Error>
+where
+    Image<T, C>: ToTexture<T, C>,
+{
+    let path = path.as_ref();
+    let mut windows = WindowSet::new()?;
+    let id = windows.create_progressive(path.to_string_lossy(), path, rows)?;
+
+    windows.run(|window, event| {
+        if let Some(Event::Key(k, _, action, _)) = event {
+            if k == Key::Escape && action == Action::Press {
+                window.close();
+            }
+        }
+        f(window, event)
+    })?;
+
+    if let Some(window) = windows.remove(&id) {
+        return Ok(window.into_image());
+    }
+
+    Err(Error::Message("Cannot find window".into()))
+}
+
 /// Show multiple images and exit when ESC is pressed
 pub fn show_all<
     T: Type,